@@ -8,35 +8,55 @@ use std::task::{Context, Poll};
 
 use actix_rt::net::TcpStream;
 use actix_service::{Service, ServiceFactory};
-use futures::future::{err, ok, BoxFuture, Either, FutureExt, Ready};
+use futures::future::{err, lazy, ok, BoxFuture, Either, FutureExt, Ready};
 
 use super::connect::{Address, Connect, Connection};
 use super::error::ConnectError;
 
 /// Tcp connector service factory
 #[derive(Debug)]
-pub struct TcpConnectorFactory<T>(PhantomData<T>);
+pub struct TcpConnectorFactory<T> {
+    fastopen: bool,
+    _t: PhantomData<T>,
+}
 
 impl<T> TcpConnectorFactory<T> {
     pub fn new() -> Self {
-        TcpConnectorFactory(PhantomData)
+        TcpConnectorFactory {
+            fastopen: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Send data of first write in SYN with TCP Fast Open.
+    ///
+    /// See `TcpConnector::fastopen()`.
+    pub fn fastopen(mut self, fastopen: bool) -> Self {
+        self.fastopen = fastopen;
+        self
     }
 
     /// Create tcp connector service
     pub fn service(&self) -> TcpConnector<T> {
-        TcpConnector(PhantomData)
+        TcpConnector {
+            fastopen: self.fastopen,
+            _t: PhantomData,
+        }
     }
 }
 
 impl<T> Default for TcpConnectorFactory<T> {
     fn default() -> Self {
-        TcpConnectorFactory(PhantomData)
+        TcpConnectorFactory::new()
     }
 }
 
 impl<T> Clone for TcpConnectorFactory<T> {
     fn clone(&self) -> Self {
-        TcpConnectorFactory(PhantomData)
+        TcpConnectorFactory {
+            fastopen: self.fastopen,
+            _t: PhantomData,
+        }
     }
 }
 
@@ -56,17 +76,37 @@ impl<T: Address> ServiceFactory for TcpConnectorFactory<T> {
 
 /// Tcp connector service
 #[derive(Default, Debug)]
-pub struct TcpConnector<T>(PhantomData<T>);
+pub struct TcpConnector<T> {
+    fastopen: bool,
+    _t: PhantomData<T>,
+}
 
 impl<T> TcpConnector<T> {
     pub fn new() -> Self {
-        TcpConnector(PhantomData)
+        TcpConnector {
+            fastopen: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Send data of first write in SYN with TCP Fast Open
+    /// (`TCP_FASTOPEN_CONNECT`).
+    ///
+    /// Connection is returned before handshake is completed, so connect
+    /// errors are returned by first write. Supported on linux only, option
+    /// is ignored on other platforms.
+    pub fn fastopen(mut self, fastopen: bool) -> Self {
+        self.fastopen = fastopen;
+        self
     }
 }
 
 impl<T> Clone for TcpConnector<T> {
     fn clone(&self) -> Self {
-        TcpConnector(PhantomData)
+        TcpConnector {
+            fastopen: self.fastopen,
+            _t: PhantomData,
+        }
     }
 }
 
@@ -85,7 +125,12 @@ impl<T: Address> Service for TcpConnector<T> {
         let Connect { req, addr, .. } = req;
 
         if let Some(addr) = addr {
-            Either::Left(TcpConnectorResponse::new(req, port, addr))
+            Either::Left(TcpConnectorResponse::with_fastopen(
+                req,
+                port,
+                addr,
+                self.fastopen,
+            ))
         } else {
             error!("TCP connector: got unresolved address");
             Either::Right(err(ConnectError::Unresolverd))
@@ -100,6 +145,7 @@ pub struct TcpConnectorResponse<T> {
     port: u16,
     addrs: Option<VecDeque<SocketAddr>>,
     stream: Option<BoxFuture<'static, Result<TcpStream, io::Error>>>,
+    fastopen: bool,
 }
 
 impl<T: Address> TcpConnectorResponse<T> {
@@ -107,6 +153,15 @@ impl<T: Address> TcpConnectorResponse<T> {
         req: T,
         port: u16,
         addr: either::Either<SocketAddr, VecDeque<SocketAddr>>,
+    ) -> TcpConnectorResponse<T> {
+        TcpConnectorResponse::with_fastopen(req, port, addr, false)
+    }
+
+    pub(crate) fn with_fastopen(
+        req: T,
+        port: u16,
+        addr: either::Either<SocketAddr, VecDeque<SocketAddr>>,
+        fastopen: bool,
     ) -> TcpConnectorResponse<T> {
         trace!(
             "TCP connector - connecting to {:?} port:{}",
@@ -119,13 +174,15 @@ impl<T: Address> TcpConnectorResponse<T> {
                 req: Some(req),
                 port,
                 addrs: None,
-                stream: Some(TcpStream::connect(addr).boxed()),
+                stream: Some(connect(addr, fastopen)),
+                fastopen,
             },
             either::Either::Right(addrs) => TcpConnectorResponse {
                 req: Some(req),
                 port,
                 addrs: Some(addrs),
                 stream: None,
+                fastopen,
             },
         }
     }
@@ -165,7 +222,49 @@ impl<T: Address> Future for TcpConnectorResponse<T> {
 
             // try to connect
             let addr = this.addrs.as_mut().unwrap().pop_front().unwrap();
-            this.stream = Some(TcpStream::connect(addr).boxed());
+            this.stream = Some(connect(addr, this.fastopen));
         }
     }
 }
+
+fn connect(
+    addr: SocketAddr,
+    fastopen: bool,
+) -> BoxFuture<'static, Result<TcpStream, io::Error>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if fastopen {
+            return lazy(move |_| connect_fastopen(addr)).boxed();
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = fastopen;
+
+    TcpStream::connect(addr).boxed()
+}
+
+/// Connect with `TCP_FASTOPEN_CONNECT` option, connect completes immediately
+/// and handshake is started by first write.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn connect_fastopen(addr: SocketAddr) -> Result<TcpStream, io::Error> {
+    use std::os::unix::io::AsRawFd;
+
+    let builder = match addr {
+        SocketAddr::V4(_) => net2::TcpBuilder::new_v4()?,
+        SocketAddr::V6(_) => net2::TcpBuilder::new_v6()?,
+    };
+    let val: libc::c_int = 1;
+    let res = unsafe {
+        libc::setsockopt(
+            builder.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN_CONNECT,
+            &val as *const _ as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    TcpStream::from_std(builder.connect(addr)?)
+}
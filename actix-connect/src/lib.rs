@@ -1,4 +1,4 @@
-//! Actix connect - tcp connector service
+//! Actix connect - tcp and unix domain socket connector services
 //!
 //! ## Package feature
 //!
@@ -17,6 +17,8 @@ mod error;
 mod resolve;
 mod service;
 pub mod ssl;
+#[cfg(unix)]
+mod uds;
 
 #[cfg(feature = "uri")]
 mod uri;
@@ -39,6 +41,9 @@ pub use self::error::ConnectError;
 pub use self::resolve::{Resolver, ResolverFactory};
 pub use self::service::{ConnectService, ConnectServiceFactory, TcpConnectService};
 
+#[cfg(unix)]
+pub use self::uds::{UnixConnector, UnixConnectorFactory};
+
 pub fn start_resolver(cfg: ResolverConfig, opts: ResolverOpts) -> AsyncResolver {
     let (resolver, bg) = AsyncResolver::new(cfg, opts);
     actix_rt::spawn(bg);
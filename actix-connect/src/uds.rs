@@ -0,0 +1,105 @@
+use std::marker::PhantomData;
+use std::path::Path;
+use std::task::{Context, Poll};
+
+use actix_rt::net::UnixStream;
+use actix_service::{Service, ServiceFactory};
+use futures::future::{ok, BoxFuture, FutureExt, Ready};
+
+use super::connect::Connection;
+use super::error::ConnectError;
+
+/// Unix domain socket connector service factory
+#[derive(Debug)]
+pub struct UnixConnectorFactory<T>(PhantomData<T>);
+
+impl<T> UnixConnectorFactory<T> {
+    pub fn new() -> Self {
+        UnixConnectorFactory(PhantomData)
+    }
+
+    /// Create unix domain socket connector service
+    pub fn service(&self) -> UnixConnector<T> {
+        UnixConnector::new()
+    }
+}
+
+impl<T> Default for UnixConnectorFactory<T> {
+    fn default() -> Self {
+        UnixConnectorFactory::new()
+    }
+}
+
+impl<T> Clone for UnixConnectorFactory<T> {
+    fn clone(&self) -> Self {
+        UnixConnectorFactory(PhantomData)
+    }
+}
+
+impl<T: AsRef<Path> + Send + 'static> ServiceFactory for UnixConnectorFactory<T> {
+    type Request = T;
+    type Response = Connection<T, UnixStream>;
+    type Error = ConnectError;
+    type Config = ();
+    type Service = UnixConnector<T>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ok(self.service())
+    }
+}
+
+/// Unix domain socket connector service
+///
+/// Request is a socket path. On linux path that starts with NUL byte,
+/// i.e. `"\0name"`, is a name in abstract namespace.
+#[derive(Debug)]
+pub struct UnixConnector<T>(PhantomData<T>);
+
+impl<T> UnixConnector<T> {
+    pub fn new() -> Self {
+        UnixConnector(PhantomData)
+    }
+}
+
+impl<T> Default for UnixConnector<T> {
+    fn default() -> Self {
+        UnixConnector::new()
+    }
+}
+
+impl<T> Clone for UnixConnector<T> {
+    fn clone(&self) -> Self {
+        UnixConnector(PhantomData)
+    }
+}
+
+impl<T: AsRef<Path> + Send + 'static> Service for UnixConnector<T> {
+    type Request = T;
+    type Response = Connection<T, UnixStream>;
+    type Error = ConnectError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: T) -> Self::Future {
+        let path = req.as_ref().to_path_buf();
+
+        async move {
+            match UnixStream::connect(&path).await {
+                Ok(io) => {
+                    trace!("Unix connector - successfully connected to {:?}", path);
+                    Ok(Connection::new(io, req))
+                }
+                Err(e) => {
+                    trace!("Unix connector - failed to connect to {:?}", path);
+                    Err(ConnectError::Io(e))
+                }
+            }
+        }
+        .boxed()
+    }
+}
@@ -135,3 +135,77 @@ async fn test_rustls_uri() {
     let con = conn.call(addr.into()).await.unwrap();
     assert_eq!(con.peer_addr().unwrap(), srv.addr());
 }
+
+#[cfg(target_os = "linux")]
+#[actix_rt::test]
+async fn test_fastopen() {
+    use actix_connect::TcpConnector;
+    use futures::StreamExt;
+
+    let srv = TestServer::with(|| {
+        fn_service(|io: TcpStream| {
+            async {
+                let mut framed = Framed::new(io, BytesCodec);
+                if let Some(msg) = framed.next().await {
+                    framed.send(msg?.freeze()).await?;
+                }
+                Ok::<_, io::Error>(())
+            }
+        })
+    });
+
+    // data of first write is sent in SYN
+    let mut conn = TcpConnector::new().fastopen(true);
+    for _ in 0..2 {
+        let con = conn.call(Connect::with("10", srv.addr())).await.unwrap();
+        let mut framed = Framed::new(con.into_parts().0, BytesCodec);
+        framed.send(Bytes::from_static(b"test")).await.unwrap();
+        let msg = framed.next().await.unwrap().unwrap();
+        assert_eq!(&msg[..], b"test");
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[actix_rt::test]
+async fn test_unix_abstract() {
+    use std::sync::mpsc;
+    use std::{thread, time};
+
+    use actix_connect::UnixConnector;
+    use futures::StreamExt;
+
+    let name = format!("\0actix-connect-{}", std::process::id());
+    let (tx, rx) = mpsc::channel();
+
+    let name2 = name.clone();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        actix_server::Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_uds("test", &name2, || {
+                fn_service(|io: actix_rt::net::UnixStream| {
+                    async {
+                        let mut framed = Framed::new(io, BytesCodec);
+                        framed.send(Bytes::from_static(b"test")).await?;
+                        Ok::<_, io::Error>(())
+                    }
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send(actix_rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = UnixConnector::new();
+    let con = conn.call(name).await.unwrap();
+    let mut framed = Framed::new(con.into_parts().0, BytesCodec);
+    let msg = framed.next().await.unwrap().unwrap();
+    assert_eq!(&msg[..], b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
@@ -207,6 +207,10 @@ where
                         return Poll::Pending;
                     }
                 }
+                // let service finish its work
+                if self.service.poll_shutdown(cx, true).is_pending() {
+                    return Poll::Pending;
+                }
                 Poll::Ready(Err(self.state.take_error()))
             }
             FramedState::FlushAndStop => {
@@ -233,10 +237,18 @@ where
                         Poll::Ready(_) => (),
                     }
                 };
+                if self.service.poll_shutdown(cx, false).is_pending() {
+                    return Poll::Pending;
+                }
                 Poll::Ready(Ok(()))
             }
-            FramedState::FramedError(_) => Poll::Ready(Err(self.state.take_framed_error())),
-            FramedState::Stopping => Poll::Ready(Ok(())),
+            FramedState::FramedError(_) => {
+                if self.service.poll_shutdown(cx, true).is_pending() {
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(self.state.take_framed_error()))
+            }
+            FramedState::Stopping => self.service.poll_shutdown(cx, false).map(Ok),
         }
     }
 }
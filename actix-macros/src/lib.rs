@@ -1,4 +1,4 @@
-//! Macros for use with Tokio
+//! Macros for use with Tokio and actix services
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
@@ -98,3 +98,150 @@ pub fn test(_: TokenStream, item: TokenStream) -> TokenStream {
 
     result.into()
 }
+
+/// Implements `actix_service::Service` for a type from its `async fn call`.
+///
+/// Attribute is placed on an impl block that defines
+/// `async fn call(&self, req: Req) -> Result<Res, Err>`. Response future is
+/// boxed, it owns a clone of the service, so type must implement `Clone`
+/// and must be `'static`. Generated service is always ready.
+///
+/// ## Usage
+///
+/// ```rust
+/// use actix_service::Service;
+///
+/// #[derive(Clone)]
+/// struct Greeter {
+///     greeting: &'static str,
+/// }
+///
+/// #[actix_macros::service]
+/// impl Greeter {
+///     async fn call(&self, name: String) -> Result<String, ()> {
+///         Ok(format!("{}, {}", self.greeting, name))
+///     }
+/// }
+///
+/// #[actix_rt::main]
+/// async fn main() {
+///     let mut srv = Greeter { greeting: "Hello" };
+///     assert_eq!(srv.call("world".to_string()).await.unwrap(), "Hello, world");
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn service(_: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input = syn::parse_macro_input!(item as syn::ItemImpl);
+
+    if let Some((_, path, _)) = &input.trait_ {
+        return syn::Error::new_spanned(path, "service attribute expects inherent impl block")
+            .to_compile_error()
+            .into();
+    }
+
+    let method = input.items.iter_mut().find_map(|item| match item {
+        syn::ImplItem::Method(method) if method.sig.ident == "call" => Some(method),
+        _ => None,
+    });
+    let method = match method {
+        Some(method) => method,
+        None => {
+            return syn::Error::new_spanned(&input.self_ty, "async fn call is not found")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let (req, res, err) = match service_types(&method.sig) {
+        Ok(types) => types,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let call = syn::Ident::new("__service_call", method.sig.ident.span());
+    method.sig.ident = call.clone();
+    method.attrs.push(syn::parse_quote!(#[doc(hidden)]));
+
+    let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+    let self_ty = &input.self_ty;
+
+    (quote! {
+        #input
+
+        impl #impl_generics actix_service::Service for #self_ty #where_clause {
+            type Request = #req;
+            type Response = #res;
+            type Error = #err;
+            type Future = ::std::pin::Pin<
+                Box<dyn ::std::future::Future<Output = Result<#res, #err>>>,
+            >;
+
+            fn poll_ready(
+                &mut self,
+                _: &mut ::std::task::Context<'_>,
+            ) -> ::std::task::Poll<Result<(), Self::Error>> {
+                ::std::task::Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, req: #req) -> Self::Future {
+                let srv = ::std::clone::Clone::clone(self);
+                Box::pin(async move { srv.#call(req).await })
+            }
+        }
+    })
+    .into()
+}
+
+/// Extract request, response and error types from `async fn call`
+/// signature.
+fn service_types(sig: &syn::Signature) -> syn::Result<(syn::Type, syn::Type, syn::Type)> {
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "only async fn is supported",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(syn::FnArg::Receiver(recv))
+            if recv.reference.is_some() && recv.mutability.is_none() => {}
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "call has to take `&self` receiver",
+            ))
+        }
+    }
+    let req = match (inputs.next(), inputs.next()) {
+        (Some(syn::FnArg::Typed(arg)), None) => (*arg.ty).clone(),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "call has to take exactly one request argument",
+            ))
+        }
+    };
+
+    let err = syn::Error::new_spanned(&sig.output, "call has to return `Result<Res, Err>`");
+    let ty = match &sig.output {
+        syn::ReturnType::Type(_, ty) => ty,
+        syn::ReturnType::Default => return Err(err),
+    };
+    let segment = match &**ty {
+        syn::Type::Path(path) => path.path.segments.last().ok_or_else(|| err.clone())?,
+        _ => return Err(err),
+    };
+    if segment.ident != "Result" {
+        return Err(err);
+    }
+    let mut args = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args.args.iter(),
+        _ => return Err(err),
+    };
+    match (args.next(), args.next(), args.next()) {
+        (Some(syn::GenericArgument::Type(res)), Some(syn::GenericArgument::Type(e)), None) => {
+            Ok((req, res.clone(), e.clone()))
+        }
+        _ => Err(err),
+    }
+}
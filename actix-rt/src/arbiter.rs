@@ -12,6 +12,8 @@ use futures::{future, Future, FutureExt, Stream};
 
 use crate::runtime::Runtime;
 use crate::system::System;
+use crate::task::{self, JoinHandle};
+use crate::RuntimeBuilder;
 
 use copyless::BoxHelper;
 
@@ -91,55 +93,13 @@ impl Arbiter {
     /// Spawn new thread and run event loop in spawned thread.
     /// Returns address of newly created arbiter.
     pub fn new() -> Arbiter {
-        let id = COUNT.fetch_add(1, Ordering::Relaxed);
-        let name = format!("actix-rt:worker:{}", id);
-        let sys = System::current();
-        let (arb_tx, arb_rx) = unbounded();
-        let arb_tx2 = arb_tx.clone();
-
-        let handle = thread::Builder::new()
-            .name(name.clone())
-            .spawn(move || {
-                let mut rt = Runtime::new().expect("Can not create Runtime");
-                let arb = Arbiter::with_sender(arb_tx);
-
-                let (stop, stop_rx) = channel();
-                RUNNING.with(|cell| cell.set(true));
-                STORAGE.with(|cell| cell.borrow_mut().clear());
-
-                System::set_current(sys);
-
-                // start arbiter controller
-                rt.spawn(ArbiterController {
-                    stop: Some(stop),
-                    rx: arb_rx,
-                });
-                ADDR.with(|cell| *cell.borrow_mut() = Some(arb.clone()));
-
-                // register arbiter
-                let _ = System::current()
-                    .sys()
-                    .unbounded_send(SystemCommand::RegisterArbiter(id, arb));
-
-                // run loop
-                let _ = match rt.block_on(stop_rx) {
-                    Ok(code) => code,
-                    Err(_) => 1,
-                };
-
-                // unregister arbiter
-                let _ = System::current()
-                    .sys()
-                    .unbounded_send(SystemCommand::UnregisterArbiter(id));
-            })
-            .unwrap_or_else(|err| {
-                panic!("Cannot spawn an arbiter's thread {:?}: {:?}", &name, err)
-            });
+        Arbiter::builder().start()
+    }
 
-        Arbiter {
-            sender: arb_tx2,
-            thread_handle: Some(handle),
-        }
+    /// Returns builder of arbiter, that allows to configure thread and
+    /// runtime of arbiter.
+    pub fn builder() -> ArbiterBuilder {
+        ArbiterBuilder::default()
     }
 
     pub(crate) fn run_system(rt: Option<&Runtime>) {
@@ -182,6 +142,15 @@ impl Arbiter {
         });
     }
 
+    /// Spawn a future on the current thread, returns handle for awaiting
+    /// output of the future or cancelling it.
+    pub fn spawn_handle<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+    {
+        task::spawn_handle(future)
+    }
+
     /// Executes a future on the current thread. This does not create a new Arbiter
     /// or Arbiter address, it is simply a helper for executing futures on the current
     /// thread.
@@ -296,6 +265,121 @@ impl Arbiter {
     }
 }
 
+/// Builder of arbiter thread.
+#[derive(Default)]
+pub struct ArbiterBuilder {
+    name: Option<String>,
+    stack_size: Option<usize>,
+    runtime: Option<Box<dyn FnOnce(&mut RuntimeBuilder) + Send>>,
+}
+
+impl fmt::Debug for ArbiterBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArbiterBuilder")
+            .field("name", &self.name)
+            .field("stack_size", &self.stack_size)
+            .finish()
+    }
+}
+
+impl ArbiterBuilder {
+    /// Set name of arbiter thread, by default `actix-rt:worker:{id}`.
+    pub fn name<T: Into<String>>(mut self, name: T) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set stack size of arbiter thread.
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Set function that configures tokio runtime of arbiter, i.e. number
+    /// of blocking threads.
+    ///
+    /// Runtime is configured with basic scheduler and io and time drivers
+    /// before function is called.
+    pub fn runtime<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut RuntimeBuilder) + Send + 'static,
+    {
+        self.runtime = Some(Box::new(f));
+        self
+    }
+
+    /// Spawn new thread and run event loop in spawned thread.
+    /// Returns address of newly created arbiter.
+    pub fn start(self) -> Arbiter {
+        let id = COUNT.fetch_add(1, Ordering::Relaxed);
+        let name = self
+            .name
+            .unwrap_or_else(|| format!("actix-rt:worker:{}", id));
+        let sys = System::current();
+        let (arb_tx, arb_rx) = unbounded();
+        let arb_tx2 = arb_tx.clone();
+
+        let mut builder = thread::Builder::new().name(name.clone());
+        if let Some(size) = self.stack_size {
+            builder = builder.stack_size(size);
+        }
+        let runtime = self.runtime;
+
+        let handle = builder
+            .spawn(move || {
+                // arbiter of multi-threaded system executes its futures on
+                // its own thread, io and timers are driven by system runtime
+                let mut rt = match (sys.runtime(), runtime) {
+                    (Some(handle), None) => Runtime::shared(handle.clone()),
+                    (_, runtime) => Runtime::with_config(|builder| {
+                        if let Some(f) = runtime {
+                            f(builder)
+                        }
+                    })
+                    .expect("Can not create Runtime"),
+                };
+                let arb = Arbiter::with_sender(arb_tx);
+
+                let (stop, stop_rx) = channel();
+                RUNNING.with(|cell| cell.set(true));
+                STORAGE.with(|cell| cell.borrow_mut().clear());
+
+                System::set_current(sys);
+
+                // start arbiter controller
+                rt.spawn(ArbiterController {
+                    stop: Some(stop),
+                    rx: arb_rx,
+                });
+                ADDR.with(|cell| *cell.borrow_mut() = Some(arb.clone()));
+
+                // register arbiter
+                let _ = System::current()
+                    .sys()
+                    .unbounded_send(SystemCommand::RegisterArbiter(id, arb));
+
+                // run loop
+                let _ = match rt.block_on(stop_rx) {
+                    Ok(code) => code,
+                    Err(_) => 1,
+                };
+
+                // unregister arbiter
+                let _ = System::current()
+                    .sys()
+                    .unbounded_send(SystemCommand::UnregisterArbiter(id));
+            })
+            .unwrap_or_else(|err| {
+                panic!("Cannot spawn an arbiter's thread {:?}: {:?}", &name, err)
+            });
+
+        Arbiter {
+            sender: arb_tx2,
+            thread_handle: Some(handle),
+        }
+    }
+}
+
 struct ArbiterController {
     stop: Option<Sender<i32>>,
     rx: UnboundedReceiver<ArbiterCommand>,
@@ -21,6 +21,10 @@ pub struct Builder {
 
     /// Whether the Arbiter will stop the whole System on uncaught panic. Defaults to false.
     stop_on_panic: bool,
+
+    /// Number of threads of multi-threaded runtime. Defaults to single threaded runtime.
+    #[cfg(feature = "multi-thread")]
+    threads: Option<usize>,
 }
 
 impl Builder {
@@ -28,6 +32,8 @@ impl Builder {
         Builder {
             name: Cow::Borrowed("actix"),
             stop_on_panic: false,
+            #[cfg(feature = "multi-thread")]
+            threads: None,
         }
     }
 
@@ -46,6 +52,44 @@ impl Builder {
         self
     }
 
+    /// Drive the System by multi-threaded tokio runtime with `threads`
+    /// worker threads.
+    ///
+    /// System and arbiters keep their own threads and `LocalSet`s, so
+    /// `actix_rt::spawn()` and `Arbiter::spawn()` work as usual, while io,
+    /// timers and `Send` futures spawned with `tokio::spawn()` are driven by
+    /// worker threads of the runtime. Such futures are moved between worker
+    /// threads by work stealing, `System::current()` is not available in
+    /// them. Arbiters with custom `runtime()` configuration create their own
+    /// runtime.
+    ///
+    /// Requires `multi-thread` feature.
+    ///
+    /// ```rust
+    /// use std::thread;
+    ///
+    /// use std::time::Duration;
+    ///
+    /// let mut sys = actix_rt::System::builder().multi_thread(2).build();
+    /// let worker = sys.block_on(async {
+    ///     tokio::spawn(async { thread::current().name().map(String::from) }).await
+    /// });
+    /// assert_ne!(worker.unwrap(), thread::current().name().map(String::from));
+    ///
+    /// // arbiters use timers and io of system runtime
+    /// let (tx, rx) = futures::channel::oneshot::channel();
+    /// actix_rt::Arbiter::new().send(Box::pin(async move {
+    ///     actix_rt::time::delay_for(Duration::from_millis(10)).await;
+    ///     let _ = tx.send(());
+    /// }));
+    /// assert!(sys.block_on(rx).is_ok());
+    /// ```
+    #[cfg(feature = "multi-thread")]
+    pub fn multi_thread(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
     /// Create new System.
     ///
     /// This method panics if it can not create tokio runtime
@@ -92,12 +136,29 @@ impl Builder {
         let (stop_tx, stop) = channel();
         let (sys_sender, sys_receiver) = unbounded();
 
-        let system = System::construct(sys_sender, Arbiter::new_system(), self.stop_on_panic);
+        #[cfg(not(feature = "multi-thread"))]
+        let mut rt = Runtime::new().unwrap();
+        #[cfg(feature = "multi-thread")]
+        let mut rt = match self.threads {
+            Some(threads) => Runtime::with_config(|builder| {
+                builder.threaded_scheduler().core_threads(threads);
+            })
+            .unwrap(),
+            None => Runtime::new().unwrap(),
+        };
+
+        #[allow(unused_mut)]
+        let mut system =
+            System::construct(sys_sender, Arbiter::new_system(), self.stop_on_panic);
+        #[cfg(feature = "multi-thread")]
+        {
+            if self.threads.is_some() {
+                system.set_runtime(rt.handle());
+            }
+        }
 
         // system arbiter
         let arb = SystemArbiter::new(stop_tx, sys_receiver);
-
-        let mut rt = Runtime::new().unwrap();
         rt.spawn(arb);
 
         // init system arbiter and run configuration method
@@ -9,11 +9,16 @@ mod arbiter;
 mod builder;
 mod runtime;
 mod system;
+mod task;
 
-pub use self::arbiter::Arbiter;
+pub use self::arbiter::{Arbiter, ArbiterBuilder};
 pub use self::builder::{Builder, SystemRunner};
 pub use self::runtime::Runtime;
 pub use self::system::System;
+pub use self::task::{JoinError, JoinHandle};
+
+/// Tokio runtime configuration, see `ArbiterBuilder::runtime()`
+pub use tokio::runtime::Builder as RuntimeBuilder;
 
 #[doc(hidden)]
 pub use actix_threadpool as blocking;
@@ -34,6 +39,34 @@ where
     Arbiter::spawn(f);
 }
 
+/// Spawns a future on the current arbiter, returns handle for awaiting
+/// output of the future or cancelling it.
+///
+/// ```rust
+/// actix_rt::System::new("example").block_on(async {
+///     let handle = actix_rt::spawn_handle(async { 1 + 1 });
+///     assert_eq!(handle.await, Ok(2));
+///
+///     let handle = actix_rt::spawn_handle(futures::future::pending::<()>());
+///     handle.abort();
+///     assert!(handle.await.is_err());
+/// });
+/// ```
+///
+/// # Panics
+///
+/// This function panics if actix system is not running.
+pub fn spawn_handle<F>(f: F) -> JoinHandle<F::Output>
+where
+    F: futures::Future + 'static,
+{
+    if !System::is_set() {
+        panic!("System is not running");
+    }
+
+    Arbiter::spawn_handle(f)
+}
+
 /// Asynchronous signal handling
 pub mod signal {
     #[cfg(unix)]
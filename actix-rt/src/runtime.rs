@@ -11,25 +11,56 @@ use tokio::{runtime, task::LocalSet};
 #[derive(Debug)]
 pub struct Runtime {
     local: LocalSet,
-    rt: runtime::Runtime,
+    rt: Inner,
+}
+
+#[derive(Debug)]
+enum Inner {
+    Owned(runtime::Runtime),
+    /// Multi-threaded runtime of system, shared by arbiters
+    Shared(runtime::Handle),
 }
 
 impl Runtime {
     #[allow(clippy::new_ret_no_self)]
     /// Returns a new runtime initialized with default configuration values.
     pub fn new() -> io::Result<Runtime> {
-        let rt = runtime::Builder::new()
-            .enable_io()
-            .enable_time()
-            .basic_scheduler()
-            .build()?;
+        Runtime::with_config(|_| ())
+    }
+
+    /// Returns a new runtime, default configuration could be changed by `f`.
+    pub(crate) fn with_config<F>(f: F) -> io::Result<Runtime>
+    where
+        F: FnOnce(&mut runtime::Builder),
+    {
+        let mut builder = runtime::Builder::new();
+        builder.enable_io().enable_time().basic_scheduler();
+        f(&mut builder);
 
         Ok(Runtime {
-            rt,
+            rt: Inner::Owned(builder.build()?),
             local: LocalSet::new(),
         })
     }
 
+    /// Returns a new runtime of arbiter thread, futures are executed by the
+    /// thread while io and timers are driven by shared runtime.
+    pub(crate) fn shared(handle: runtime::Handle) -> Runtime {
+        Runtime {
+            rt: Inner::Shared(handle),
+            local: LocalSet::new(),
+        }
+    }
+
+    /// Handle of underlying tokio runtime.
+    #[cfg(feature = "multi-thread")]
+    pub(crate) fn handle(&self) -> runtime::Handle {
+        match self.rt {
+            Inner::Owned(ref rt) => rt.handle().clone(),
+            Inner::Shared(ref handle) => handle.clone(),
+        }
+    }
+
     /// Spawn a future onto the single-threaded runtime.
     ///
     /// See [module level][mod] documentation for more details.
@@ -86,7 +117,9 @@ impl Runtime {
     where
         F: Future + 'static,
     {
-        let res = self.local.block_on(&mut self.rt, f);
-        res
+        match self.rt {
+            Inner::Owned(ref mut rt) => self.local.block_on(rt, f),
+            Inner::Shared(ref handle) => handle.block_on(self.local.run_until(f)),
+        }
     }
 }
@@ -4,6 +4,7 @@ use std::io;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use futures::channel::mpsc::UnboundedSender;
+use tokio::runtime::Handle;
 use tokio::task::LocalSet;
 
 use crate::arbiter::{Arbiter, SystemCommand};
@@ -18,6 +19,7 @@ pub struct System {
     sys: UnboundedSender<SystemCommand>,
     arbiter: Arbiter,
     stop_on_panic: bool,
+    runtime: Option<Handle>,
 }
 
 thread_local!(
@@ -35,6 +37,7 @@ impl System {
             sys,
             arbiter,
             stop_on_panic,
+            runtime: None,
             id: SYSTEM_COUNT.fetch_add(1, Ordering::SeqCst),
         };
         System::set_current(sys.clone());
@@ -71,6 +74,55 @@ impl System {
             .run_nonblocking()
     }
 
+    /// Run future with new system in context of already running tokio
+    /// runtime, i.e. inside of `#[tokio::main]`.
+    ///
+    /// System is created once the future is first polled, on the thread
+    /// that runs it. System, its arbiters and futures spawned with
+    /// `actix_rt::spawn()` live until the future completes, system is
+    /// stopped afterwards.
+    ///
+    /// ```rust
+    /// let mut rt = actix_rt::RuntimeBuilder::new()
+    ///     .basic_scheduler()
+    ///     .enable_all()
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let res = rt.block_on(actix_rt::System::attach_to_tokio("example", async {
+    ///     let (tx, rx) = futures::channel::oneshot::channel();
+    ///     let arbiter = actix_rt::Arbiter::new();
+    ///     arbiter.exec_fn(move || {
+    ///         let _ = tx.send(1);
+    ///     });
+    ///     let one = rx.await.unwrap();
+    ///     actix_rt::spawn_handle(async move { one + 1 }).await.unwrap()
+    /// }));
+    /// assert_eq!(res, 2);
+    /// ```
+    pub fn attach_to_tokio<T, F>(name: T, fut: F) -> impl Future<Output = F::Output>
+    where
+        T: Into<String>,
+        F: Future,
+    {
+        let name = name.into();
+
+        // system is current on the thread that runs the future
+        async move {
+            let local = LocalSet::new();
+            let system = Self::run_in_tokio(name, &local);
+            local
+                .run_until(async move {
+                    let system = tokio::task::spawn_local(system);
+                    let res = fut.await;
+                    System::current().stop();
+                    let _ = system.await;
+                    res
+                })
+                .await
+        }
+    }
+
     /// Get current running system.
     pub fn current() -> System {
         CURRENT.with(|cell| match *cell.borrow() {
@@ -122,6 +174,18 @@ impl System {
         &self.sys
     }
 
+    /// Share multi-threaded runtime of system with arbiters
+    #[cfg(feature = "multi-thread")]
+    pub(crate) fn set_runtime(&mut self, handle: Handle) {
+        self.runtime = Some(handle);
+        System::set_current(self.clone());
+    }
+
+    /// Multi-threaded runtime of system, if any
+    pub(crate) fn runtime(&self) -> Option<&Handle> {
+        self.runtime.as_ref()
+    }
+
     /// Return status of 'stop_on_panic' option which controls whether the System is stopped when an
     /// uncaught panic is thrown from a worker thread.
     pub fn stop_on_panic(&self) -> bool {
@@ -0,0 +1,64 @@
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::oneshot::{channel, Receiver};
+use futures::future::{AbortHandle, Abortable};
+use futures::Future;
+
+use crate::arbiter::Arbiter;
+
+/// Handle of a task spawned with `spawn_handle()`.
+///
+/// Awaiting the handle returns output of the task. Dropping the handle
+/// detaches the task, it keeps running in background.
+#[derive(Debug)]
+pub struct JoinHandle<T> {
+    rx: Receiver<T>,
+    abort: AbortHandle,
+}
+
+impl<T> JoinHandle<T> {
+    /// Cancel the task, it is dropped next time arbiter polls it.
+    ///
+    /// Awaiting the handle of aborted task returns `JoinError`, unless the
+    /// task is already completed.
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx).map_err(|_| JoinError(()))
+    }
+}
+
+/// Task did not complete, it is aborted or its arbiter is stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinError(());
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Task is cancelled")
+    }
+}
+
+impl Error for JoinError {}
+
+pub(crate) fn spawn_handle<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+{
+    let (tx, rx) = channel();
+    let (abort, reg) = AbortHandle::new_pair();
+    Arbiter::spawn(async move {
+        if let Ok(res) = Abortable::new(future, reg).await {
+            let _ = tx.send(res);
+        }
+    });
+    JoinHandle { rx, abort }
+}
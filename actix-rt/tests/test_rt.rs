@@ -0,0 +1,124 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use actix_rt::{spawn_handle, Arbiter, System};
+use futures::future::pending;
+
+#[test]
+fn test_spawn_handle() {
+    System::new("test").block_on(async {
+        assert_eq!(spawn_handle(async { 1 }).await, Ok(1));
+
+        // completed task is not affected by abort
+        let handle = spawn_handle(async { 2 });
+        actix_rt::time::delay_for(Duration::from_millis(10)).await;
+        handle.abort();
+        assert_eq!(handle.await, Ok(2));
+    });
+}
+
+#[test]
+fn test_spawn_handle_abort() {
+    System::new("test").block_on(async {
+        // task is dropped on abort
+        let state = Rc::new(());
+        let state2 = state.clone();
+        let handle = spawn_handle(async move {
+            let _state = state2;
+            pending::<()>().await
+        });
+        actix_rt::time::delay_for(Duration::from_millis(10)).await;
+        assert_eq!(Rc::strong_count(&state), 2);
+
+        handle.abort();
+        assert!(handle.await.is_err());
+        assert_eq!(Rc::strong_count(&state), 1);
+    });
+}
+
+#[test]
+fn test_spawn_handle_arbiter_stop() {
+    let mut sys = System::new("test");
+
+    // task of stopped arbiter does not complete
+    let arbiter = Arbiter::new();
+    let handle = sys.block_on(arbiter.exec(|| Arbiter::spawn_handle(pending::<()>())));
+    arbiter.stop();
+    assert!(sys.block_on(handle.unwrap()).is_err());
+}
+
+#[test]
+fn test_attach_to_tokio() {
+    let mut rt = actix_rt::RuntimeBuilder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    // system is created once future runs, not when it is created
+    let fut1 = System::attach_to_tokio("one", async { System::current().id() });
+    let fut2 = System::attach_to_tokio("two", async { System::current().id() });
+    assert!(std::panic::catch_unwind(System::current).is_err());
+
+    let id1 = rt.block_on(fut1);
+    let id2 = rt.block_on(fut2);
+    assert_ne!(id1, id2);
+
+    // spawned futures are dropped with the system
+    let state = Rc::new(());
+    let state2 = state.clone();
+    rt.block_on(System::attach_to_tokio("test", async move {
+        let arbiter = Arbiter::new();
+        let res = arbiter.exec(|| System::current().id()).await.unwrap();
+        assert_eq!(res, System::current().id());
+        actix_rt::spawn(async move {
+            let _state = state2;
+            pending::<()>().await
+        });
+    }));
+    assert_eq!(Rc::strong_count(&state), 1);
+}
+
+#[test]
+#[cfg(feature = "multi-thread")]
+fn test_multi_thread_arbiter() {
+    use std::thread;
+
+    let mut sys = System::builder().multi_thread(2).build();
+    let id = System::current().id();
+
+    // arbiter runs its futures on its own thread, timers and `Send`
+    // futures are driven by system runtime
+    let arbiter = Arbiter::new();
+    let (tx, rx) = futures::channel::oneshot::channel();
+    arbiter.send(Box::pin(async move {
+        actix_rt::time::delay_for(Duration::from_millis(10)).await;
+        let worker = tokio::spawn(async { thread::current().id() }).await;
+        let _ = tx.send((
+            thread::current().id(),
+            worker.unwrap(),
+            System::current().id(),
+        ));
+    }));
+    let (arb_thread, worker_thread, arb_id) = sys.block_on(rx).unwrap();
+    assert_ne!(arb_thread, thread::current().id());
+    assert_ne!(arb_thread, worker_thread);
+    assert_eq!(arb_id, id);
+
+    // arbiter with custom runtime does not use system runtime
+    let arbiter2 = Arbiter::builder()
+        .runtime(|builder| {
+            builder.basic_scheduler();
+        })
+        .start();
+    let (tx, rx) = futures::channel::oneshot::channel();
+    arbiter2.send(Box::pin(async move {
+        let worker = tokio::spawn(async { thread::current().id() }).await;
+        let _ = tx.send((thread::current().id(), worker.unwrap()));
+    }));
+    let (arb_thread, worker_thread) = sys.block_on(rx).unwrap();
+    assert_eq!(arb_thread, worker_thread);
+
+    arbiter.stop();
+    arbiter2.stop();
+}
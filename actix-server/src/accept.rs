@@ -1,101 +1,377 @@
 use std::sync::mpsc as sync_mpsc;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{io, thread};
 
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::io::AsRawFd;
+
 use actix_rt::time::{delay_until, Instant};
 use actix_rt::System;
-use log::{error, info};
+use futures::channel::oneshot;
+use futures::future::join_all;
+use futures::FutureExt;
+use log::{error, info, trace};
 use slab::Slab;
 
+use crate::affinity::Affinity;
+use crate::filter::IpFilter;
+use crate::metrics::AcceptMetrics;
+use crate::peer::{PeerGuard, PeerLimit};
+use crate::rate::{RateLimitPolicy, RateLimiter};
 use crate::server::Server;
-use crate::socket::{SocketAddr, SocketListener, StdListener};
+use crate::socket::{SocketAddr, SocketListener, StdListener, StdStream, TcpOptions};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::uring::{self, Ring};
 use crate::worker::{Conn, WorkerClient};
 use crate::Token;
 
 pub(crate) enum Command {
     Pause,
     Resume,
+    /// Pause accepting connections on listeners
+    PauseListeners(Vec<Token>),
+    /// Resume accepting connections on listeners
+    ResumeListeners(Vec<Token>),
+    /// Replace peer filter of listeners
+    SetIpFilter(Vec<Token>, Option<IpFilter>),
     Stop,
+    /// Stop accepting, listeners are passed to other process
+    #[cfg(unix)]
+    Handoff,
     Worker(WorkerClient),
+    /// Stop passing connections to the worker
+    RemoveWorker(usize, oneshot::Sender<()>),
+    /// Start accepting connections on new listeners
+    Add(Vec<(Token, StdListener, Option<TcpOptions>)>),
+    /// Stop accepting connections on listeners and close them
+    Remove(Vec<Token>),
+}
+
+/// Behavior of listener once all workers are at max number of connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackpressurePolicy {
+    /// Stop accepting connections until worker is available, pending
+    /// connections wait in listen backlog
+    #[default]
+    Suspend,
+    /// Keep accepting and immediately close connections
+    SheddingClose,
+    /// Keep accepting and queue connections in workers, connections that
+    /// wait in queue for longer than timeout are closed
+    QueueWithTimeout(Duration),
 }
 
 struct ServerSocketInfo {
     addr: SocketAddr,
     token: Token,
     sock: SocketListener,
+    opts: Option<TcpOptions>,
     timeout: Option<Instant>,
+    paused: bool,
+    rate: Option<RateLimiter>,
+    filter: Option<IpFilter>,
+    peers: Option<PeerLimit>,
+    policy: BackpressurePolicy,
+    /// User data of in-flight accept request
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pending: Option<u64>,
+    /// Accept request is resubmitted on completion
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    accepting: bool,
+}
+
+impl ServerSocketInfo {
+    /// Check peer address of accepted connection with filter
+    fn allowed(&self, peer: &SocketAddr) -> bool {
+        match (&self.filter, peer) {
+            (Some(filter), SocketAddr::Tcp(addr)) => {
+                if filter.is_allowed(addr.ip()) {
+                    true
+                } else {
+                    trace!(
+                        "Peer {} is not allowed on {}, closing connection",
+                        addr,
+                        self.addr
+                    );
+                    false
+                }
+            }
+            _ => true,
+        }
+    }
+
+    /// Count connection of peer, `Err` if peer has too many connections
+    fn peer(&mut self, peer: &SocketAddr) -> Result<Option<PeerGuard>, ()> {
+        match (&mut self.peers, peer) {
+            (Some(peers), SocketAddr::Tcp(addr)) => match peers.acquire(addr.ip()) {
+                Some(guard) => Ok(Some(guard)),
+                None => {
+                    trace!("Peer {} has too many connections, closing connection", addr);
+                    Err(())
+                }
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Delay of next accept, if accepting is delayed by rate limit
+    fn delayed(&mut self) -> Option<Duration> {
+        match self.rate {
+            Some(ref mut rate) if rate.policy == RateLimitPolicy::Delay => rate.wait(),
+            _ => None,
+        }
+    }
+
+    /// Check accept rate of accepted connection, `false` if connection
+    /// should be closed.
+    fn acquire(&mut self) -> bool {
+        match self.rate {
+            Some(ref mut rate) => {
+                if rate.acquire() || rate.policy == RateLimitPolicy::Delay {
+                    true
+                } else {
+                    trace!(
+                        "Accept rate of {} is exceeded, closing connection",
+                        self.addr
+                    );
+                    false
+                }
+            }
+            None => true,
+        }
+    }
 }
 
+/// Wakes accept threads once worker becomes available
 #[derive(Clone)]
-pub(crate) struct AcceptNotify(mio::SetReadiness);
+pub(crate) struct AcceptNotify(Vec<mio::SetReadiness>);
 
 impl AcceptNotify {
-    pub(crate) fn new(ready: mio::SetReadiness) -> Self {
+    pub(crate) fn new(ready: Vec<mio::SetReadiness>) -> Self {
         AcceptNotify(ready)
     }
 
     pub(crate) fn notify(&self) {
-        let _ = self.0.set_readiness(mio::Ready::readable());
+        for ready in &self.0 {
+            let _ = ready.set_readiness(mio::Ready::readable());
+        }
     }
 }
 
 impl Default for AcceptNotify {
     fn default() -> Self {
-        AcceptNotify::new(mio::Registration::new2().1)
+        AcceptNotify::new(vec![mio::Registration::new2().1])
     }
 }
 
-pub(crate) struct AcceptLoop {
+/// Channels of single accept thread
+struct AcceptHandle {
     cmd_reg: Option<mio::Registration>,
     cmd_ready: mio::SetReadiness,
     notify_reg: Option<mio::Registration>,
     notify_ready: mio::SetReadiness,
     tx: sync_mpsc::Sender<Command>,
     rx: Option<sync_mpsc::Receiver<Command>>,
-    srv: Option<Server>,
 }
 
-impl AcceptLoop {
-    pub fn new(srv: Server) -> AcceptLoop {
+impl AcceptHandle {
+    fn new() -> Self {
         let (tx, rx) = sync_mpsc::channel();
         let (cmd_reg, cmd_ready) = mio::Registration::new2();
         let (notify_reg, notify_ready) = mio::Registration::new2();
 
-        AcceptLoop {
+        AcceptHandle {
             tx,
             cmd_ready,
             cmd_reg: Some(cmd_reg),
             notify_ready,
             notify_reg: Some(notify_reg),
             rx: Some(rx),
-            srv: Some(srv),
         }
     }
 
-    pub fn send(&self, msg: Command) {
+    fn send(&self, msg: Command) {
         let _ = self.tx.send(msg);
         let _ = self.cmd_ready.set_readiness(mio::Ready::readable());
     }
+}
+
+/// Accept threads, each thread owns a subset of listeners
+///
+/// Pinned threads accept reuseport sockets of a single worker and pass
+/// connections to that worker only.
+pub(crate) struct AcceptLoop {
+    handles: Vec<AcceptHandle>,
+    pinned: Slab<AcceptHandle>,
+    srv: Server,
+    metrics: Arc<AcceptMetrics>,
+    next: usize,
+}
+
+impl AcceptLoop {
+    pub fn new(srv: Server, threads: usize) -> AcceptLoop {
+        AcceptLoop {
+            handles: (0..std::cmp::max(threads, 1))
+                .map(|_| AcceptHandle::new())
+                .collect(),
+            pinned: Slab::new(),
+            srv,
+            metrics: Arc::new(AcceptMetrics::default()),
+            next: 0,
+        }
+    }
+
+    pub(crate) fn metrics(&self) -> &AcceptMetrics {
+        &self.metrics
+    }
+
+    /// Send command to accept threads. New listeners are distributed
+    /// between threads and new workers are used by all of them except
+    /// pinned ones, other commands are sent to all threads.
+    pub fn send(&mut self, msg: Command) {
+        match msg {
+            // state is reported by metrics once `Server::pause()` resolves
+            Command::Pause => {
+                self.metrics.paused(true);
+                self.all().for_each(|h| h.send(Command::Pause));
+            }
+            Command::Resume => {
+                self.metrics.paused(false);
+                self.all().for_each(|h| h.send(Command::Resume));
+            }
+            Command::PauseListeners(tokens) => {
+                for h in self.all() {
+                    h.send(Command::PauseListeners(tokens.clone()));
+                }
+            }
+            Command::ResumeListeners(tokens) => {
+                for h in self.all() {
+                    h.send(Command::ResumeListeners(tokens.clone()));
+                }
+            }
+            Command::SetIpFilter(tokens, filter) => {
+                for h in self.all() {
+                    h.send(Command::SetIpFilter(tokens.clone(), filter.clone()));
+                }
+            }
+            Command::Stop => self.all().for_each(|h| h.send(Command::Stop)),
+            #[cfg(unix)]
+            Command::Handoff => self.all().for_each(|h| h.send(Command::Handoff)),
+            Command::Worker(worker) => {
+                for h in &self.handles {
+                    h.send(Command::Worker(worker.clone()));
+                }
+            }
+            Command::RemoveWorker(idx, tx) => {
+                let removed: Vec<_> = self
+                    .all()
+                    .map(|h| {
+                        let (tx, rx) = oneshot::channel();
+                        h.send(Command::RemoveWorker(idx, tx));
+                        rx
+                    })
+                    .collect();
+                actix_rt::spawn(join_all(removed).map(move |_| {
+                    let _ = tx.send(());
+                }));
+            }
+            Command::Add(socks) => {
+                let mut shards: Vec<Vec<_>> = self.handles.iter().map(|_| Vec::new()).collect();
+                for sock in socks {
+                    shards[self.next].push(sock);
+                    self.next = (self.next + 1) % self.handles.len();
+                }
+                for (h, socks) in self.handles.iter().zip(shards) {
+                    if !socks.is_empty() {
+                        h.send(Command::Add(socks));
+                    }
+                }
+            }
+            Command::Remove(tokens) => {
+                for h in self.all() {
+                    h.send(Command::Remove(tokens.clone()));
+                }
+            }
+        }
+    }
+
+    /// Send command to pinned accept thread
+    pub(crate) fn send_pinned(&self, slot: usize, msg: Command) {
+        if let Some(h) = self.pinned.get(slot) {
+            h.send(msg);
+        }
+    }
+
+    /// Add pinned accept thread, it is started with `start_pinned()`.
+    ///
+    /// Workers started afterwards wake the thread once they are available.
+    pub(crate) fn add_pinned(&mut self) -> usize {
+        self.pinned.insert(AcceptHandle::new())
+    }
+
+    /// Start pinned accept thread with sockets of the worker
+    pub(crate) fn start_pinned(
+        &mut self,
+        slot: usize,
+        socks: Vec<(Token, StdListener, Option<TcpOptions>)>,
+        worker: WorkerClient,
+        affinity: &Affinity,
+    ) {
+        let h = &mut self.pinned[slot];
+        Accept::start(
+            h.rx.take().expect("Can not re-use AcceptInfo"),
+            h.cmd_reg.take().expect("Can not re-use AcceptInfo"),
+            h.notify_reg.take().expect("Can not re-use AcceptInfo"),
+            socks,
+            self.srv.clone(),
+            vec![worker],
+            self.metrics.clone(),
+            affinity.accept(),
+        );
+    }
+
+    /// Stop pinned accept thread and close its sockets
+    pub(crate) fn remove_pinned(&mut self, slot: usize) {
+        if self.pinned.contains(slot) {
+            self.pinned.remove(slot).send(Command::Stop);
+        }
+    }
+
+    fn all(&self) -> impl Iterator<Item = &AcceptHandle> {
+        self.handles
+            .iter()
+            .chain(self.pinned.iter().map(|(_, h)| h))
+    }
 
     pub fn get_notify(&self) -> AcceptNotify {
-        AcceptNotify::new(self.notify_ready.clone())
+        AcceptNotify::new(self.all().map(|h| h.notify_ready.clone()).collect())
     }
 
     pub(crate) fn start(
         &mut self,
-        socks: Vec<(Token, StdListener)>,
+        socks: Vec<(Token, StdListener, Option<TcpOptions>)>,
         workers: Vec<WorkerClient>,
+        affinity: &Affinity,
     ) {
-        let srv = self.srv.take().expect("Can not re-use AcceptInfo");
+        let mut shards: Vec<Vec<_>> = self.handles.iter().map(|_| Vec::new()).collect();
+        for sock in socks {
+            shards[self.next].push(sock);
+            self.next = (self.next + 1) % self.handles.len();
+        }
 
-        Accept::start(
-            self.rx.take().expect("Can not re-use AcceptInfo"),
-            self.cmd_reg.take().expect("Can not re-use AcceptInfo"),
-            self.notify_reg.take().expect("Can not re-use AcceptInfo"),
-            socks,
-            srv,
-            workers,
-        );
+        for (h, socks) in self.handles.iter_mut().zip(shards) {
+            Accept::start(
+                h.rx.take().expect("Can not re-use AcceptInfo"),
+                h.cmd_reg.take().expect("Can not re-use AcceptInfo"),
+                h.notify_reg.take().expect("Can not re-use AcceptInfo"),
+                socks,
+                self.srv.clone(),
+                workers.clone(),
+                self.metrics.clone(),
+                affinity.accept(),
+            );
+        }
     }
 }
 
@@ -108,12 +384,19 @@ struct Accept {
     timer: (mio::Registration, mio::SetReadiness),
     next: usize,
     backpressure: bool,
+    metrics: Arc<AcceptMetrics>,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    ring: Option<Ring>,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    ring_id: u64,
 }
 
 const DELTA: usize = 100;
 const CMD: mio::Token = mio::Token(0);
 const TIMER: mio::Token = mio::Token(1);
 const NOTIFY: mio::Token = mio::Token(2);
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+const RING: mio::Token = mio::Token(3);
 
 /// This function defines errors that are per-connection. Which basically
 /// means that if we get this error from `accept()` system call it means
@@ -128,15 +411,38 @@ fn connection_error(e: &io::Error) -> bool {
         || e.kind() == io::ErrorKind::ConnectionReset
 }
 
+/// Core that received connection, read with `SO_INCOMING_CPU` option
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn incoming_cpu(stream: &std::net::TcpStream) -> io::Result<usize> {
+    let mut cpu: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_INCOMING_CPU,
+            &mut cpu as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(cpu as usize)
+    }
+}
+
 impl Accept {
     #![allow(clippy::too_many_arguments)]
     pub(crate) fn start(
         rx: sync_mpsc::Receiver<Command>,
         cmd_reg: mio::Registration,
         notify_reg: mio::Registration,
-        socks: Vec<(Token, StdListener)>,
+        socks: Vec<(Token, StdListener, Option<TcpOptions>)>,
         srv: Server,
         workers: Vec<WorkerClient>,
+        metrics: Arc<AcceptMetrics>,
+        affinity: Option<Box<dyn FnOnce() + Send>>,
     ) {
         let sys = System::current();
 
@@ -145,7 +451,10 @@ impl Accept {
             .name("actix-server accept loop".to_owned())
             .spawn(move || {
                 System::set_current(sys);
-                let mut accept = Accept::new(rx, socks, workers, srv);
+                if let Some(pin) = affinity {
+                    pin();
+                }
+                let mut accept = Accept::new(rx, socks, workers, srv, metrics);
 
                 // Start listening for incoming commands
                 if let Err(err) = accept.poll.register(
@@ -173,9 +482,10 @@ impl Accept {
 
     fn new(
         rx: sync_mpsc::Receiver<Command>,
-        socks: Vec<(Token, StdListener)>,
+        socks: Vec<(Token, StdListener, Option<TcpOptions>)>,
         workers: Vec<WorkerClient>,
         srv: Server,
+        metrics: Arc<AcceptMetrics>,
     ) -> Accept {
         // Create a poll instance
         let poll = match mio::Poll::new() {
@@ -183,33 +493,6 @@ impl Accept {
             Err(err) => panic!("Can not create mio::Poll: {}", err),
         };
 
-        // Start accept
-        let mut sockets = Slab::new();
-        for (hnd_token, lst) in socks.into_iter() {
-            let addr = lst.local_addr();
-
-            let server = lst.into_listener();
-            let entry = sockets.vacant_entry();
-            let token = entry.key();
-
-            // Start listening for incoming connections
-            if let Err(err) = poll.register(
-                &server,
-                mio::Token(token + DELTA),
-                mio::Ready::readable(),
-                mio::PollOpt::edge(),
-            ) {
-                panic!("Can not register io: {}", err);
-            }
-
-            entry.insert(ServerSocketInfo {
-                addr,
-                token: hnd_token,
-                sock: server,
-                timeout: None,
-            });
-        }
-
         // Timer
         let (tm, tmr) = mio::Registration::new2();
         if let Err(err) =
@@ -218,16 +501,191 @@ impl Accept {
             panic!("Can not register Registration: {}", err);
         }
 
-        Accept {
+        // io_uring, falls back to epoll if not supported by kernel
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        let ring = match Ring::new(256) {
+            Ok(ring) => {
+                let fd = ring.as_raw_fd();
+                match poll.register(
+                    &mio::unix::EventedFd(&fd),
+                    RING,
+                    mio::Ready::readable(),
+                    mio::PollOpt::edge(),
+                ) {
+                    Ok(_) => Some(ring),
+                    Err(err) => {
+                        error!("Can not register io_uring: {}", err);
+                        None
+                    }
+                }
+            }
+            Err(err) => {
+                info!("io_uring is not available, using epoll: {}", err);
+                None
+            }
+        };
+
+        let mut accept = Accept {
             poll,
             rx,
-            sockets,
+            sockets: Slab::new(),
             workers,
             srv,
             next: 0,
             timer: (tm, tmr),
             backpressure: false,
+            metrics,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            ring,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            ring_id: 0,
+        };
+
+        // Start accept
+        for (hnd_token, lst, opts) in socks.into_iter() {
+            if let Err(err) = accept.add_socket(hnd_token, lst, opts) {
+                panic!("Can not register io: {}", err);
+            }
         }
+        accept
+    }
+
+    fn add_socket(
+        &mut self,
+        hnd_token: Token,
+        lst: StdListener,
+        opts: Option<TcpOptions>,
+    ) -> io::Result<()> {
+        let addr = lst.local_addr();
+
+        let server = lst.into_listener();
+        let rate = opts.as_ref().and_then(|opts| opts.rate_limiter());
+        let filter = opts.as_ref().and_then(|opts| opts.ip_filter.clone());
+        let peers = opts.as_ref().and_then(|opts| opts.peer_limit());
+        let policy = opts
+            .as_ref()
+            .map(|opts| opts.backpressure)
+            .unwrap_or_default();
+        let token = self.sockets.insert(ServerSocketInfo {
+            addr,
+            token: hnd_token,
+            sock: server,
+            opts,
+            timeout: None,
+            paused: false,
+            rate,
+            filter,
+            peers,
+            policy,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            pending: None,
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            accepting: false,
+        });
+
+        // Start listening for incoming connections
+        if !self.suspended(token) {
+            if let Err(err) = self.register(token) {
+                self.sockets.remove(token);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn keys(&self) -> Vec<usize> {
+        self.sockets.iter().map(|(key, _)| key).collect()
+    }
+
+    fn keys_of(&self, tokens: &[Token]) -> Vec<usize> {
+        self.sockets
+            .iter()
+            .filter(|(_, info)| tokens.contains(&info.token))
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    fn pause(&mut self, keys: Vec<usize>) {
+        for key in keys {
+            self.sockets[key].paused = true;
+            if let Err(err) = self.deregister(key) {
+                error!("Can not deregister server socket {}", err);
+            } else {
+                info!("Paused accepting connections on {}", self.sockets[key].addr);
+            }
+        }
+    }
+
+    fn resume(&mut self, keys: Vec<usize>) {
+        for key in keys {
+            let info = &mut self.sockets[key];
+            info.paused = false;
+            // listener is registered once backpressure is off or after timeout
+            if info.timeout.is_some() || self.suspended(key) {
+                continue;
+            }
+            if let Err(err) = self.register(key) {
+                error!("Can not resume socket accept process: {}", err);
+            } else {
+                info!(
+                    "Accepting connections on {} has been resumed",
+                    self.sockets[key].addr
+                );
+            }
+        }
+    }
+
+    /// Listener does not accept connections because of backpressure
+    fn suspended(&self, key: usize) -> bool {
+        self.backpressure && self.sockets[key].policy == BackpressurePolicy::Suspend
+    }
+
+    /// Start accepting connections on listener
+    fn register(&mut self, key: usize) -> io::Result<()> {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        {
+            if let Some(ref mut ring) = self.ring {
+                let info = &mut self.sockets[key];
+                if info.pending.is_none() {
+                    self.ring_id += 1;
+                    ring.accept(info.sock.as_raw_fd(), self.ring_id)?;
+                    info.pending = Some(self.ring_id);
+                    ring.submit()?;
+                }
+                info.accepting = true;
+                return Ok(());
+            }
+        }
+        self.poll.register(
+            &self.sockets[key].sock,
+            mio::Token(key + DELTA),
+            mio::Ready::readable(),
+            mio::PollOpt::edge(),
+        )
+    }
+
+    /// Stop accepting connections on listener
+    fn deregister(&mut self, key: usize) -> io::Result<()> {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        {
+            if let Some(ref mut ring) = self.ring {
+                let info = &mut self.sockets[key];
+                info.accepting = false;
+                if let Some(id) = info.pending {
+                    ring.cancel(id)?;
+                    ring.submit()?;
+                }
+                return Ok(());
+            }
+        }
+        self.poll.deregister(&self.sockets[key].sock)
+    }
+
+    /// Stop accepting connections on listener before it gets closed and
+    /// remove its unix socket file. Paused listeners keep socket file.
+    fn close(&mut self, key: usize) {
+        let _ = self.deregister(key);
+        self.sockets[key].sock.remove_file();
     }
 
     fn poll(&mut self) {
@@ -249,6 +707,8 @@ impl Accept {
                     }
                     TIMER => self.process_timer(),
                     NOTIFY => self.backpressure(false),
+                    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+                    RING => self.process_ring(),
                     _ => {
                         let token = usize::from(token);
                         if token < DELTA {
@@ -263,22 +723,28 @@ impl Accept {
 
     fn process_timer(&mut self) {
         let now = Instant::now();
-        for (token, info) in self.sockets.iter_mut() {
-            if let Some(inst) = info.timeout.take() {
-                if now > inst {
-                    if let Err(err) = self.poll.register(
-                        &info.sock,
-                        mio::Token(token + DELTA),
-                        mio::Ready::readable(),
-                        mio::PollOpt::edge(),
-                    ) {
-                        error!("Can not register server socket {}", err);
+        let backpressure = self.backpressure;
+        let keys: Vec<usize> = self
+            .sockets
+            .iter_mut()
+            .filter_map(|(key, info)| match info.timeout {
+                Some(inst) if now > inst => {
+                    info.timeout = None;
+                    let suspended = backpressure && info.policy == BackpressurePolicy::Suspend;
+                    if info.paused || suspended {
+                        None
                     } else {
-                        info!("Resume accepting connections on {}", info.addr);
+                        Some(key)
                     }
-                } else {
-                    info.timeout = Some(inst);
                 }
+                _ => None,
+            })
+            .collect();
+        for key in keys {
+            if let Err(err) = self.register(key) {
+                error!("Can not register server socket {}", err);
+            } else {
+                info!("Resume accepting connections on {}", self.sockets[key].addr);
             }
         }
     }
@@ -287,48 +753,58 @@ impl Accept {
         loop {
             match self.rx.try_recv() {
                 Ok(cmd) => match cmd {
-                    Command::Pause => {
-                        for (_, info) in self.sockets.iter_mut() {
-                            if let Err(err) = self.poll.deregister(&info.sock) {
-                                error!("Can not deregister server socket {}", err);
-                            } else {
-                                info!("Paused accepting connections on {}", info.addr);
-                            }
-                        }
-                    }
-                    Command::Resume => {
-                        for (token, info) in self.sockets.iter() {
-                            if let Err(err) = self.poll.register(
-                                &info.sock,
-                                mio::Token(token + DELTA),
-                                mio::Ready::readable(),
-                                mio::PollOpt::edge(),
-                            ) {
-                                error!("Can not resume socket accept process: {}", err);
-                            } else {
-                                info!(
-                                    "Accepting connections on {} has been resumed",
-                                    info.addr
-                                );
-                            }
+                    Command::Pause => self.pause(self.keys()),
+                    Command::Resume => self.resume(self.keys()),
+                    Command::PauseListeners(tokens) => self.pause(self.keys_of(&tokens)),
+                    Command::ResumeListeners(tokens) => self.resume(self.keys_of(&tokens)),
+                    Command::SetIpFilter(tokens, filter) => {
+                        for key in self.keys_of(&tokens) {
+                            self.sockets[key].filter = filter.clone();
                         }
                     }
                     Command::Stop => {
-                        for (_, info) in self.sockets.iter() {
-                            let _ = self.poll.deregister(&info.sock);
+                        for key in self.keys() {
+                            self.close(key);
                         }
                         return false;
                     }
+                    // listeners are closed without socket file cleanup
+                    #[cfg(unix)]
+                    Command::Handoff => return false,
                     Command::Worker(worker) => {
                         self.backpressure(false);
                         self.workers.push(worker);
                     }
+                    Command::RemoveWorker(idx, tx) => {
+                        self.workers.retain(|worker| worker.idx != idx);
+                        if self.next >= self.workers.len() {
+                            self.next = 0;
+                        }
+                        let _ = tx.send(());
+                    }
+                    Command::Add(socks) => {
+                        for (token, lst, opts) in socks {
+                            let addr = lst.local_addr();
+                            if let Err(err) = self.add_socket(token, lst, opts) {
+                                error!("Can not register server socket {}", err);
+                            } else {
+                                info!("Accepting connections on {}", addr);
+                            }
+                        }
+                    }
+                    Command::Remove(tokens) => {
+                        for key in self.keys_of(&tokens) {
+                            self.close(key);
+                            let info = self.sockets.remove(key);
+                            info!("Stopped accepting connections on {}", info.addr);
+                        }
+                    }
                 },
                 Err(err) => match err {
                     sync_mpsc::TryRecvError::Empty => break,
                     sync_mpsc::TryRecvError::Disconnected => {
-                        for (_, info) in self.sockets.iter() {
-                            let _ = self.poll.deregister(&info.sock);
+                        for key in self.keys() {
+                            self.close(key);
                         }
                         return false;
                     }
@@ -342,35 +818,91 @@ impl Accept {
         if self.backpressure {
             if !on {
                 self.backpressure = false;
-                for (token, info) in self.sockets.iter() {
-                    if let Err(err) = self.poll.register(
-                        &info.sock,
-                        mio::Token(token + DELTA),
-                        mio::Ready::readable(),
-                        mio::PollOpt::edge(),
-                    ) {
+                for key in self.keys() {
+                    // listener is registered after timeout
+                    let info = &self.sockets[key];
+                    if info.paused
+                        || info.timeout.is_some()
+                        || info.policy != BackpressurePolicy::Suspend
+                    {
+                        continue;
+                    }
+                    if let Err(err) = self.register(key) {
                         error!("Can not resume socket accept process: {}", err);
                     } else {
-                        info!("Accepting connections on {} has been resumed", info.addr);
+                        info!(
+                            "Accepting connections on {} has been resumed",
+                            self.sockets[key].addr
+                        );
                     }
                 }
             }
         } else if on {
             self.backpressure = true;
-            for (_, info) in self.sockets.iter() {
-                let _ = self.poll.deregister(&info.sock);
+            for key in self.keys() {
+                if self.sockets[key].policy == BackpressurePolicy::Suspend {
+                    let _ = self.deregister(key);
+                }
+            }
+        }
+    }
+
+    /// Pass accepted connection of listener to worker
+    fn dispatch(&mut self, key: usize, msg: Conn) {
+        let info = &self.sockets[key];
+        let policy = info.policy;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let msg = {
+            let steer = info.opts.as_ref().map(|opts| opts.incoming_cpu);
+            if !self.backpressure && steer == Some(true) {
+                match self.steer(msg) {
+                    Ok(_) => return,
+                    Err(msg) => *msg,
+                }
+            } else {
+                msg
             }
+        };
+        self.accept_one(msg, policy);
+    }
+
+    /// Pass connection to available worker pinned to core that received it,
+    /// connection is returned if there is no such worker
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn steer(&mut self, msg: Conn) -> Result<(), Box<Conn>> {
+        let cpu = match msg.io {
+            StdStream::Tcp(ref stream) => match incoming_cpu(stream) {
+                Ok(cpu) => cpu,
+                Err(e) => {
+                    trace!("Can not get incoming cpu of connection: {}", e);
+                    return Err(Box::new(msg));
+                }
+            },
+            #[cfg(unix)]
+            StdStream::Uds(_) => return Err(Box::new(msg)),
+        };
+        match self
+            .workers
+            .iter()
+            .find(|worker| worker.cpu == Some(cpu) && worker.available())
+        {
+            Some(worker) => worker.send(msg),
+            None => Err(Box::new(msg)),
         }
     }
 
-    fn accept_one(&mut self, mut msg: Conn) {
+    fn accept_one(&mut self, mut msg: Conn, policy: BackpressurePolicy) {
         if self.backpressure {
+            if policy == BackpressurePolicy::SheddingClose {
+                trace!("No available workers, closing connection");
+                return;
+            }
             while !self.workers.is_empty() {
                 match self.workers[self.next].send(msg) {
                     Ok(_) => (),
                     Err(tmp) => {
                         self.srv.worker_faulted(self.workers[self.next].idx);
-                        msg = tmp;
+                        msg = *tmp;
                         self.workers.swap_remove(self.next);
                         if self.workers.is_empty() {
                             error!("No workers");
@@ -396,7 +928,7 @@ impl Accept {
                         }
                         Err(tmp) => {
                             self.srv.worker_faulted(self.workers[self.next].idx);
-                            msg = tmp;
+                            msg = *tmp;
                             self.workers.swap_remove(self.next);
                             if self.workers.is_empty() {
                                 error!("No workers");
@@ -413,44 +945,178 @@ impl Accept {
             }
             // enable backpressure
             self.backpressure(true);
-            self.accept_one(msg);
+            self.accept_one(msg, policy);
         }
     }
 
     fn accept(&mut self, token: usize) {
         loop {
-            let msg = if let Some(info) = self.sockets.get_mut(token) {
-                match info.sock.accept() {
-                    Ok(Some((io, addr))) => Conn {
-                        io,
-                        token: info.token,
-                        peer: Some(addr),
-                    },
-                    Ok(None) => return,
-                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return,
-                    Err(ref e) if connection_error(e) => continue,
-                    Err(e) => {
-                        error!("Error accepting connection: {}", e);
-                        if let Err(err) = self.poll.deregister(&info.sock) {
-                            error!("Can not deregister server socket {}", err);
-                        }
+            let info = match self.sockets.get_mut(token) {
+                Some(info) => info,
+                None => return,
+            };
+            if let Some(delay) = info.delayed() {
+                self.sleep(token, delay);
+                return;
+            }
+            let msg = match info.sock.accept() {
+                Ok(Some((io, addr))) => {
+                    let info = &mut self.sockets[token];
+                    if !info.allowed(&addr) {
+                        continue;
+                    }
+                    let guard = match info.peer(&addr) {
+                        Ok(guard) => guard,
+                        Err(_) => continue,
+                    };
+                    if !info.acquire() {
+                        continue;
+                    }
+                    self.conn(token, io, Some(addr), guard)
+                }
+                Ok(None) => return,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return,
+                Err(ref e) if connection_error(e) => {
+                    self.metrics.error();
+                    continue;
+                }
+                Err(e) => {
+                    self.metrics.error();
+                    error!("Error accepting connection: {}", e);
+                    self.accept_error(token);
+                    return;
+                }
+            };
 
-                        // sleep after error
-                        info.timeout = Some(Instant::now() + Duration::from_millis(500));
+            self.dispatch(token, msg);
+        }
+    }
+
+    /// Handle completed accept requests
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn process_ring(&mut self) {
+        while let Some((id, res)) = self.ring.as_mut().and_then(|ring| ring.completion()) {
+            if id == uring::CANCEL {
+                continue;
+            }
+            let key = match self
+                .sockets
+                .iter()
+                .find(|(_, info)| info.pending == Some(id))
+            {
+                Some((key, _)) => key,
+                None => {
+                    // listener is removed
+                    if res >= 0 {
+                        unsafe { libc::close(res) };
+                    }
+                    continue;
+                }
+            };
+            self.sockets[key].pending = None;
 
-                        let r = self.timer.1.clone();
-                        System::current().arbiter().send(Box::pin(async move {
-                            delay_until(Instant::now() + Duration::from_millis(510)).await;
-                            let _ = r.set_readiness(mio::Ready::readable());
-                        }));
-                        return;
+            let msg = if res >= 0 {
+                let (io, addr) = self.sockets[key].sock.accepted(res);
+                let info = &mut self.sockets[key];
+                let allowed = addr.as_ref().map(|addr| info.allowed(addr)).unwrap_or(true);
+                let guard = match addr {
+                    Some(ref addr) if allowed => info.peer(addr),
+                    _ => Ok(None),
+                };
+                match guard {
+                    Ok(guard) if allowed && info.acquire() => {
+                        Some(self.conn(key, io, addr, guard))
                     }
+                    _ => None,
                 }
             } else {
-                return;
+                let e = io::Error::from_raw_os_error(-res);
+                if res == -libc::ECANCELED
+                    || res == -libc::EAGAIN
+                    || e.kind() == io::ErrorKind::Interrupted
+                {
+                    None
+                } else if connection_error(&e) {
+                    self.metrics.error();
+                    None
+                } else {
+                    self.metrics.error();
+                    error!("Error accepting connection: {}", e);
+                    self.accept_error(key);
+                    continue;
+                }
             };
 
-            self.accept_one(msg);
+            if self.sockets[key].accepting {
+                if let Some(delay) = self.sockets[key].delayed() {
+                    self.sleep(key, delay);
+                } else if let Err(err) = self.register(key) {
+                    error!("Can not register server socket {}", err);
+                }
+            }
+            if let Some(msg) = msg {
+                self.dispatch(key, msg);
+            }
         }
     }
+
+    fn conn(
+        &self,
+        key: usize,
+        io: StdStream,
+        peer: Option<SocketAddr>,
+        guard: Option<PeerGuard>,
+    ) -> Conn {
+        let info = &self.sockets[key];
+        self.metrics.accepted();
+        if let (Some(opts), StdStream::Tcp(ref stream)) = (&info.opts, &io) {
+            if let Err(e) = opts.apply(stream) {
+                error!("Can not set socket options: {}", e);
+            }
+        }
+        Conn {
+            io,
+            token: info.token,
+            peer,
+            limits: info
+                .opts
+                .as_ref()
+                .map(|opts| opts.limits())
+                .unwrap_or_default(),
+            guard,
+            deadline: match info.policy {
+                BackpressurePolicy::QueueWithTimeout(timeout) => Some(Instant::now() + timeout),
+                _ => None,
+            },
+        }
+    }
+
+    /// Stop accepting connections on listener for a while after error
+    fn accept_error(&mut self, key: usize) {
+        if let Err(err) = self.deregister(key) {
+            error!("Can not deregister server socket {}", err);
+        }
+
+        // sleep after error
+        self.sockets[key].timeout = Some(Instant::now() + Duration::from_millis(500));
+        self.wake_timer(Duration::from_millis(510));
+    }
+
+    /// Stop accepting connections on listener until accept rate allows,
+    /// listener is removed from poll only and keeps pending connections
+    fn sleep(&mut self, key: usize, delay: Duration) {
+        if let Err(err) = self.deregister(key) {
+            error!("Can not deregister server socket {}", err);
+        }
+        self.sockets[key].timeout = Some(Instant::now() + delay);
+        self.wake_timer(delay + Duration::from_millis(1));
+    }
+
+    fn wake_timer(&self, delay: Duration) {
+        let r = self.timer.1.clone();
+        System::current().arbiter().send(Box::pin(async move {
+            delay_until(Instant::now() + delay).await;
+            let _ = r.set_readiness(mio::Ready::readable());
+        }));
+    }
 }
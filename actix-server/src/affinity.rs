@@ -0,0 +1,102 @@
+//! Thread CPU affinity.
+use std::io;
+use std::sync::Arc;
+
+use log::error;
+
+/// Pins current thread to the core
+pub(crate) type AffinityBackend = Arc<dyn Fn(usize) -> io::Result<()> + Send + Sync>;
+
+/// Server threads affinity settings
+#[derive(Clone)]
+pub(crate) struct Affinity {
+    pub(crate) workers: Vec<usize>,
+    pub(crate) accept: Option<usize>,
+    pub(crate) backend: AffinityBackend,
+}
+
+impl Default for Affinity {
+    fn default() -> Self {
+        Affinity {
+            workers: Vec::new(),
+            accept: None,
+            backend: Arc::new(set_affinity),
+        }
+    }
+}
+
+impl Affinity {
+    /// Pin function for worker thread with index `idx`
+    pub(crate) fn worker(&self, idx: usize) -> Option<Box<dyn FnOnce() + Send>> {
+        self.core(idx).map(|core| self.pin(core, "worker"))
+    }
+
+    /// Core of worker thread with index `idx`
+    pub(crate) fn core(&self, idx: usize) -> Option<usize> {
+        if self.workers.is_empty() {
+            None
+        } else {
+            Some(self.workers[idx % self.workers.len()])
+        }
+    }
+
+    /// Pin function for accept thread
+    pub(crate) fn accept(&self) -> Option<Box<dyn FnOnce() + Send>> {
+        self.accept.map(|core| self.pin(core, "accept"))
+    }
+
+    fn pin(&self, core: usize, thread: &'static str) -> Box<dyn FnOnce() + Send> {
+        let backend = self.backend.clone();
+        Box::new(move || {
+            if let Err(e) = (*backend)(core) {
+                error!("Can not pin {} thread to core {}: {}", thread, core, e);
+            }
+        })
+    }
+}
+
+/// Pin current thread to the core.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn set_affinity(core: usize) -> io::Result<()> {
+    use std::mem;
+
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Core index is out of range",
+        ));
+    }
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    unsafe { libc::CPU_SET(core, &mut set) };
+    if unsafe { libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Pin current thread to the core.
+#[cfg(windows)]
+pub(crate) fn set_affinity(core: usize) -> io::Result<()> {
+    use winapi::um::processthreadsapi::GetCurrentThread;
+    use winapi::um::winbase::SetThreadAffinityMask;
+
+    if core >= std::mem::size_of::<usize>() * 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Core index is out of range",
+        ));
+    }
+    if unsafe { SetThreadAffinityMask(GetCurrentThread(), 1 << core) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Pin current thread to the core.
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+pub(crate) fn set_affinity(_: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "Thread affinity is not supported on this platform",
+    ))
+}
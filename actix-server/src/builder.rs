@@ -1,29 +1,75 @@
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
 use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
-use std::{io, mem, net};
+use std::{fmt, io, mem, net};
 
-use actix_rt::net::TcpStream;
-use actix_rt::time::{delay_until, Instant};
-use actix_rt::{spawn, System};
+use actix_rt::net::{TcpStream, UdpSocket};
+use actix_rt::time::{delay_until, timeout, Instant};
+use actix_rt::{spawn, RuntimeBuilder, System};
+use actix_service::ServiceFactory as ActixServiceFactory;
 use futures::channel::mpsc::{unbounded, UnboundedReceiver};
 use futures::channel::oneshot;
-use futures::future::ready;
-use futures::stream::FuturesUnordered;
-use futures::{ready, Future, FutureExt, Stream, StreamExt};
+use futures::future::{join_all, LocalBoxFuture};
+use futures::{ready, Future, FutureExt, Stream};
 use log::{error, info};
 use net2::TcpBuilder;
 use num_cpus;
+use tokio_util::udp::UdpFramed;
 
 use crate::accept::{AcceptLoop, AcceptNotify, Command};
+use crate::affinity::Affinity;
 use crate::config::{ConfiguredService, ServiceConfig};
-use crate::server::{Server, ServerCommand};
+use crate::connection::{Connection, ConnectionNewService, Extensions, OnConnect};
+#[cfg(unix)]
+use crate::handoff;
+use crate::metrics::{HandshakeMetrics, ServerMetrics};
+#[cfg(windows)]
+use crate::named_pipe::{NamedPipeStream, PipeListener};
+use crate::proxy::{ProxyNewService, ProxyStream};
+use crate::restart::RestartPolicy;
+#[cfg(target_os = "linux")]
+use crate::sctp::{create_sctp_listener, SctpStream};
+use crate::server::{ListenerFactory, ReloadedServices, Server, ServerCommand, StopReason};
 use crate::service::{InternalServiceFactory, ServiceFactory, StreamNewService};
-use crate::signals::{Signal, Signals};
-use crate::socket::StdListener;
-use crate::worker::{self, Worker, WorkerAvailability, WorkerClient};
+use crate::signals::{SignalAction, SignalSet, Signals};
+#[cfg(unix)]
+use crate::socket::UdsOptions;
+use crate::socket::{StdListener, TcpOptions};
+#[cfg(unix)]
+use crate::systemd::{self, ActivatedSocket};
+use crate::tls::TlsNewService;
+use crate::udp::{DatagramNewService, IntoDatagram};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::uring_stream::UringStream;
+use crate::worker::{self, Worker, WorkerAvailability, WorkerClient, WorkerRuntime};
 use crate::Token;
 
+/// Time given to worker to report its metrics
+const METRICS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Hook executed on server stop with remaining shutdown time
+type OnStop = Box<dyn FnOnce(Duration) -> LocalBoxFuture<'static, ()> + Send>;
+
+/// Creates service factory of listener bound with `bind_async`
+type ConfigureListener =
+    Box<dyn FnOnce() -> LocalBoxFuture<'static, io::Result<ListenerFactory>> + Send>;
+
+#[cfg(unix)]
+/// Listener bound with `bind_reuseport()`, every worker accepts socket of
+/// its own
+struct ReuseportListener {
+    token: Token,
+    name: String,
+    addr: net::SocketAddr,
+    opts: Option<TcpOptions>,
+    /// Socket bound by `bind_reuseport()`, it is used by first worker
+    lst: Option<net::TcpListener>,
+}
+
 /// Server builder
 pub struct ServerBuilder {
     threads: usize,
@@ -32,13 +78,46 @@ pub struct ServerBuilder {
     workers: Vec<(usize, WorkerClient)>,
     services: Vec<Box<dyn InternalServiceFactory>>,
     sockets: Vec<(Token, String, StdListener)>,
+    datagrams: Vec<(String, net::SocketAddr)>,
+    configuring: Vec<(String, Vec<net::TcpListener>, ConfigureListener)>,
+    tcp_options: HashMap<Token, TcpOptions>,
+    names: Vec<(Token, String)>,
+    affinity: Affinity,
+    runtime: WorkerRuntime,
+    on_connect: Option<OnConnect>,
+    on_stop: Vec<OnStop>,
+    handshakes: Vec<(String, Arc<HandshakeMetrics>)>,
     accept: AcceptLoop,
     exit: bool,
     shutdown_timeout: Duration,
-    no_signals: bool,
+    signals: SignalSet,
     cmd: UnboundedReceiver<ServerCommand>,
     server: Server,
-    notify: Vec<oneshot::Sender<()>>,
+    notify: Vec<oneshot::Sender<io::Result<()>>>,
+    restart: RestartPolicy,
+    failures: usize,
+    failure: Option<String>,
+    heartbeat: Option<(Duration, Duration)>,
+    restart_hung: bool,
+    heartbeats: HashMap<usize, Instant>,
+    stopping: bool,
+    /// Listener is being added or reloaded, services of workers are not in sync
+    updating: bool,
+    /// Updates received while other update is in progress
+    deferred: VecDeque<ServerCommand>,
+    reason: Option<StopReason>,
+    stopped: bool,
+    wait_stopped: Vec<oneshot::Sender<StopReason>>,
+    #[cfg(unix)]
+    activated: Option<Vec<ActivatedSocket>>,
+    #[cfg(unix)]
+    listeners: Vec<(String, StdListener)>,
+    #[cfg(unix)]
+    reuseport: Vec<ReuseportListener>,
+    /// Pinned accept threads of workers
+    pinned: HashMap<usize, usize>,
+    /// Pinned accept threads of stopped workers
+    unpinned: Vec<usize>,
 }
 
 impl Default for ServerBuilder {
@@ -59,13 +138,42 @@ impl ServerBuilder {
             workers: Vec::new(),
             services: Vec::new(),
             sockets: Vec::new(),
-            accept: AcceptLoop::new(server.clone()),
+            datagrams: Vec::new(),
+            configuring: Vec::new(),
+            tcp_options: HashMap::new(),
+            names: Vec::new(),
+            affinity: Affinity::default(),
+            runtime: WorkerRuntime::default(),
+            on_connect: None,
+            on_stop: Vec::new(),
+            handshakes: Vec::new(),
+            accept: AcceptLoop::new(server.clone(), 1),
             backlog: 2048,
             exit: false,
             shutdown_timeout: Duration::from_secs(30),
-            no_signals: false,
+            signals: SignalSet::default(),
             cmd: rx,
             notify: Vec::new(),
+            restart: RestartPolicy::default(),
+            failures: 0,
+            failure: None,
+            heartbeat: None,
+            restart_hung: false,
+            heartbeats: HashMap::new(),
+            stopping: false,
+            updating: false,
+            deferred: VecDeque::new(),
+            reason: None,
+            stopped: false,
+            wait_stopped: Vec::new(),
+            #[cfg(unix)]
+            activated: None,
+            #[cfg(unix)]
+            listeners: Vec::new(),
+            #[cfg(unix)]
+            reuseport: Vec::new(),
+            pinned: HashMap::new(),
+            unpinned: Vec::new(),
             server,
         }
     }
@@ -105,6 +213,103 @@ impl ServerBuilder {
         self
     }
 
+    /// Set number of accept threads.
+    ///
+    /// Listeners are distributed between accept threads, so several
+    /// threads are useful only if server has several listeners, i.e. same
+    /// address is bound several times with `bind_reuseport()`.
+    ///
+    /// By default server uses one accept thread.
+    pub fn accept_threads(mut self, num: usize) -> Self {
+        self.accept = AcceptLoop::new(self.server.clone(), num);
+        self
+    }
+
+    /// Pin worker threads to CPU cores.
+    ///
+    /// Worker with index `i` is pinned to the core `cores[i % cores.len()]`.
+    pub fn worker_affinity(mut self, cores: Vec<usize>) -> Self {
+        self.affinity.workers = cores;
+        self
+    }
+
+    /// Pin accept thread to CPU core.
+    pub fn accept_affinity(mut self, core: usize) -> Self {
+        self.affinity.accept = Some(core);
+        self
+    }
+
+    /// Set function that pins current thread to CPU core.
+    ///
+    /// By default `sched_setaffinity` is used on linux and
+    /// `SetThreadAffinityMask` on windows, pinning is not supported on other
+    /// platforms. Errors are logged, thread keeps running unpinned.
+    pub fn affinity_backend<F>(mut self, f: F) -> Self
+    where
+        F: Fn(usize) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.affinity.backend = Arc::new(f);
+        self
+    }
+
+    /// Set name prefix of worker threads, threads are named `{prefix}:{idx}`.
+    pub fn worker_thread_name<T: Into<String>>(mut self, prefix: T) -> Self {
+        self.runtime.name = Some(prefix.into());
+        self
+    }
+
+    /// Set stack size of worker threads.
+    pub fn worker_stack_size(mut self, size: usize) -> Self {
+        self.runtime.stack_size = Some(size);
+        self
+    }
+
+    /// Set function that configures tokio runtime of every worker, i.e.
+    /// number of blocking threads with `RuntimeBuilder::max_threads()`.
+    ///
+    /// Runtime is configured with basic scheduler and io and time drivers
+    /// before function is called.
+    pub fn worker_runtime<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&mut RuntimeBuilder) + Send + Sync + 'static,
+    {
+        self.runtime.configure = Some(Arc::new(f));
+        self
+    }
+
+    /// Set hook that is called on worker for every connection accepted by
+    /// services added with `bind_connection()`.
+    ///
+    /// Hook receives accepted stream, i.e. `TcpStream` or `UnixStream`, and
+    /// could attach data to connection `Extensions`, that are passed to the
+    /// service with the stream in `Connection`. Unix socket connections
+    /// have `PeerCredentials` of peer process attached before the hook.
+    ///
+    /// This method should be called before `bind_connection()` method call.
+    pub fn on_connect<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&dyn std::any::Any, &mut Extensions) + Send + Sync + 'static,
+    {
+        self.on_connect = Some(Arc::new(f));
+        self
+    }
+
+    /// Add hook that is executed on server stop.
+    ///
+    /// Hooks are executed after accept loop is stopped and workers finished
+    /// graceful shutdown, but before server future resolves. Hook receives
+    /// remaining time of shutdown timeout, hooks that do not complete in
+    /// this time are dropped. Hooks run concurrently, only once.
+    pub fn on_stop<F, R>(mut self, f: F) -> Self
+    where
+        F: FnOnce(Duration) -> R + Send + 'static,
+        R: Future<Output = ()> + 'static,
+    {
+        self.on_stop
+            .push(Box::new(move |budget| f(budget).boxed_local()));
+        self
+    }
+
     /// Stop actix system.
     pub fn system_exit(mut self) -> Self {
         self.exit = true;
@@ -113,7 +318,41 @@ impl ServerBuilder {
 
     /// Disable signal handling
     pub fn disable_signals(mut self) -> Self {
-        self.no_signals = true;
+        self.signals = SignalSet::new();
+        self
+    }
+
+    /// Set process signals handled by server and their actions.
+    ///
+    /// By default server stops immediately on `SIGINT` and `SIGQUIT` and
+    /// gracefully on `SIGTERM`.
+    pub fn signals(mut self, signals: SignalSet) -> Self {
+        self.signals = signals;
+        self
+    }
+
+    /// Set policy for restarting workers that can not start their services.
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart = policy;
+        self
+    }
+
+    /// Enable supervision of workers with heartbeats.
+    ///
+    /// Workers send heartbeat every `interval`. Worker that does not send
+    /// heartbeat for `timeout` duration, i.e. its thread is blocked by
+    /// service, is considered hung. Connections are not passed to hung
+    /// worker anymore and it is stopped once it recovers.
+    pub fn worker_heartbeat(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.heartbeat = Some((interval, timeout));
+        self
+    }
+
+    /// Start new worker in place of hung one, disabled by default.
+    ///
+    /// Used only if supervision is enabled with `worker_heartbeat()`.
+    pub fn restart_hung_workers(mut self, restart: bool) -> Self {
+        self.restart_hung = restart;
         self
     }
 
@@ -178,26 +417,361 @@ impl ServerBuilder {
         Ok(self)
     }
 
+    /// Add new service to the server, options are applied to listener and
+    /// accepted connections.
+    pub fn bind_with<F, U, N>(
+        mut self,
+        name: N,
+        addr: U,
+        opts: TcpOptions,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr_with(addr, self.backlog, false, &opts)?;
+
+        for lst in sockets {
+            let token = self.token.next();
+            self.services.push(StreamNewService::create(
+                name.as_ref().to_string(),
+                token,
+                factory.clone(),
+                lst.local_addr()?,
+            ));
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+            self.tcp_options.insert(token, opts.clone());
+        }
+        Ok(self)
+    }
+
+    #[cfg(unix)]
+    /// Add new service to the server, listening sockets are bound with
+    /// `SO_REUSEPORT` option.
+    ///
+    /// Several server processes could bind the same address this way, kernel
+    /// balances incoming connections between their sockets.
+    ///
+    /// Every worker gets socket of its own, it is accepted by accept thread
+    /// of the worker and connections are passed to that worker only. Sockets
+    /// are bound in order of workers, program set with
+    /// `TcpOptions::reuseport_program()` selects worker by index of socket.
+    /// Socket of removed worker is closed and kernel moves last socket of
+    /// the group to its index.
+    pub fn bind_reuseport<F, U, N>(self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        self.add_reuseport(name.as_ref(), addr, None, factory)
+    }
+
+    #[cfg(unix)]
+    /// Add new service to the server, listening sockets are bound with
+    /// `SO_REUSEPORT` option and listener options.
+    ///
+    /// Program that selects socket of the group is attached if it is set
+    /// with `TcpOptions::reuseport_program()`.
+    pub fn bind_reuseport_with<F, U, N>(
+        self,
+        name: N,
+        addr: U,
+        opts: TcpOptions,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        self.add_reuseport(name.as_ref(), addr, Some(opts), factory)
+    }
+
+    #[cfg(unix)]
+    fn add_reuseport<F, U>(
+        mut self,
+        name: &str,
+        addr: U,
+        opts: Option<TcpOptions>,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+    {
+        let sockets = bind_addr_with(
+            addr,
+            self.backlog,
+            true,
+            opts.as_ref().unwrap_or(&TcpOptions::default()),
+        )?;
+
+        for lst in sockets {
+            let token = self.token.next();
+            let addr = lst.local_addr()?;
+            self.services.push(StreamNewService::create(
+                name.to_string(),
+                token,
+                factory.clone(),
+                addr,
+            ));
+            self.reuseport.push(ReuseportListener {
+                token,
+                name: name.to_string(),
+                addr,
+                opts: opts.clone(),
+                lst: Some(lst),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server, PROXY protocol header is read from
+    /// accepted connections.
+    ///
+    /// Service receives `ProxyStream` with client address passed by proxy.
+    /// Connections without valid header are closed.
+    pub fn bind_proxy<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<ProxyStream<TcpStream>>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.backlog)?;
+
+        for lst in sockets {
+            let token = self.token.next();
+            self.services.push(ProxyNewService::create(
+                name.as_ref().to_string(),
+                token,
+                factory.clone(),
+            ));
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server, TLS handshake is completed with
+    /// `acceptor` before stream is passed to the service.
+    ///
+    /// Acceptor is any service factory that turns tcp stream into TLS
+    /// stream, i.e. acceptors of `actix-tls` crate, which also limit number
+    /// of concurrent handshakes per worker. Connections with failed
+    /// handshake are closed, handshake errors are not passed to the service.
+    ///
+    /// Several virtual services could share listener with `SniServices`
+    /// passed as service factory, service is selected by SNI hostname.
+    pub fn bind_tls<A, F, U, N>(
+        mut self,
+        name: N,
+        addr: U,
+        acceptor: A,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        A: ActixServiceFactory<Config = (), Request = TcpStream> + Clone + Send + 'static,
+        A::Service: 'static,
+        A::Response: 'static,
+        A::Error: fmt::Debug,
+        F: ServiceFactory<A::Response>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.backlog)?;
+        let metrics = Arc::new(HandshakeMetrics::default());
+        self.handshakes
+            .push((name.as_ref().to_string(), metrics.clone()));
+
+        for lst in sockets {
+            let token = self.token.next();
+            self.services.push(TlsNewService::create(
+                name.as_ref().to_string(),
+                token,
+                acceptor.clone(),
+                factory.clone(),
+                metrics.clone(),
+            ));
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        }
+        Ok(self)
+    }
+
+    #[cfg(feature = "prometheus")]
+    /// Add listener that serves server metrics in Prometheus text format.
+    ///
+    /// Metrics are served on `GET /metrics` requests, listener is intended
+    /// for dedicated admin port.
+    pub fn bind_prometheus<U, N>(self, name: N, addr: U) -> io::Result<Self>
+    where
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let factory = crate::prometheus::factory(self.server.clone());
+        self.bind(name, addr, move || factory.clone())
+    }
+
+    /// Add new service to the server, service receives `Connection` with
+    /// data attached by `on_connect` hook.
+    pub fn bind_connection<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<Connection<TcpStream>>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.backlog)?;
+
+        for lst in sockets {
+            let token = self.token.next();
+            self.services
+                .push(ConnectionNewService::<_, TcpStream>::create(
+                    name.as_ref().to_string(),
+                    token,
+                    factory.clone(),
+                    self.on_connect.clone(),
+                ));
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server, service factory is created
+    /// asynchronously.
+    ///
+    /// Listener is bound immediately, but starts accepting connections once
+    /// future returned by `f` resolves and service is started on all
+    /// workers. Server stops with an error if future resolves with an error.
+    pub fn bind_async<F, Fut, S, U, N>(mut self, name: N, addr: U, f: F) -> io::Result<Self>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = io::Result<S>> + 'static,
+        S: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.backlog)?;
+        let name = name.as_ref().to_string();
+        let name2 = name.clone();
+        let configure = move || {
+            f().map(move |res| {
+                res.map(move |factory| {
+                    ListenerFactory(Box::new(move |token, addr| {
+                        StreamNewService::create(name2.clone(), token, factory.clone(), addr)
+                    }))
+                })
+            })
+            .boxed_local()
+        };
+        self.configuring.push((name, sockets, Box::new(configure)));
+        Ok(self)
+    }
+
+    /// Add new datagram service to the server.
+    ///
+    /// Every worker gets its own handle of bound socket, service is called
+    /// once per worker and should receive datagrams until it is done. Running
+    /// datagram services are not counted as connections, they are dropped
+    /// once worker stops. Use `shutdown_signal()` to finish on graceful
+    /// shutdown.
+    pub fn bind_udp<F, U, N>(self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<UdpSocket>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        self.bind_datagram(name, addr, factory, Arc::new(|sock| sock))
+    }
+
+    /// Add new datagram service to the server, service receives socket
+    /// framed with provided codec.
+    ///
+    /// See `ServerBuilder::bind_udp()` for details.
+    pub fn bind_udp_framed<F, U, N, C>(
+        self,
+        name: N,
+        addr: U,
+        codec: C,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<UdpFramed<C>>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+        C: Clone + Send + Sync + 'static,
+    {
+        let into = move |sock| UdpFramed::new(sock, codec.clone());
+        self.bind_datagram(name, addr, factory, Arc::new(into))
+    }
+
+    fn bind_datagram<F, U, N, Io>(
+        mut self,
+        name: N,
+        addr: U,
+        factory: F,
+        into: IntoDatagram<Io>,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<Io>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+        Io: 'static,
+    {
+        for sock in bind_udp_addr(addr)? {
+            let token = self.token.next();
+            self.datagrams
+                .push((name.as_ref().to_string(), sock.local_addr()?));
+            self.services.push(DatagramNewService::create(
+                name.as_ref().to_string(),
+                token,
+                factory.clone(),
+                sock,
+                into.clone(),
+            ));
+        }
+        Ok(self)
+    }
+
     #[cfg(all(unix))]
     /// Add new unix domain service to the server.
+    ///
+    /// On linux socket is bound in abstract namespace if path starts with
+    /// NUL byte, i.e. `"\0name"`.
     pub fn bind_uds<F, U, N>(self, name: N, addr: U, factory: F) -> io::Result<Self>
     where
         F: ServiceFactory<actix_rt::net::UnixStream>,
         N: AsRef<str>,
         U: AsRef<std::path::Path>,
     {
-        use std::os::unix::net::UnixListener;
-
         // The path must not exist when we try to bind.
         // Try to remove it to avoid bind error.
-        if let Err(e) = std::fs::remove_file(addr.as_ref()) {
-            // NotFound is expected and not an issue. Anything else is.
-            if e.kind() != std::io::ErrorKind::NotFound {
-                return Err(e);
-            }
-        }
+        self.bind_uds_with(name, addr, UdsOptions::default(), factory)
+    }
 
-        let lst = UnixListener::bind(addr)?;
+    #[cfg(unix)]
+    /// Add new unix domain service to the server, socket file is created
+    /// with provided options.
+    ///
+    /// Options control socket file permissions, ownership and removal of a
+    /// stale socket file before bind.
+    pub fn bind_uds_with<F, U, N>(
+        self,
+        name: N,
+        addr: U,
+        opts: UdsOptions,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<actix_rt::net::UnixStream>,
+        N: AsRef<str>,
+        U: AsRef<std::path::Path>,
+    {
+        let lst = opts.bind(addr.as_ref())?;
         self.listen_uds(name, lst, factory)
     }
 
@@ -228,18 +802,144 @@ impl ServerBuilder {
         Ok(self)
     }
 
-    /// Add new service to the server.
-    pub fn listen<F, N: AsRef<str>>(
+    #[cfg(unix)]
+    /// Add new unix domain service to the server, service receives
+    /// `Connection` with data attached by `on_connect` hook.
+    pub fn bind_uds_connection<F, U, N>(
         mut self,
         name: N,
-        lst: net::TcpListener,
+        addr: U,
         factory: F,
     ) -> io::Result<Self>
     where
-        F: ServiceFactory<TcpStream>,
+        F: ServiceFactory<Connection<actix_rt::net::UnixStream>>,
+        N: AsRef<str>,
+        U: AsRef<std::path::Path>,
     {
+        let lst = UdsOptions::default().bind(addr.as_ref())?;
         let token = self.token.next();
-        self.services.push(StreamNewService::create(
+        self.services.push(
+            ConnectionNewService::<_, actix_rt::net::UnixStream>::create(
+                name.as_ref().to_string(),
+                token,
+                factory,
+                self.on_connect.clone(),
+            ),
+        );
+        self.sockets
+            .push((token, name.as_ref().to_string(), StdListener::Uds(lst)));
+        Ok(self)
+    }
+
+    #[cfg(windows)]
+    /// Add new named pipe service to the server.
+    ///
+    /// Path has `\\.\pipe\name` form, bind fails if pipe with the path
+    /// exists. Accept thread keeps pipe instances waiting for clients and
+    /// connected pipes are passed to workers the same way as accepted
+    /// connections of other listeners.
+    pub fn bind_named_pipe<F, U, N>(mut self, name: N, path: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<NamedPipeStream>,
+        N: AsRef<str>,
+        U: AsRef<str>,
+    {
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        let lst = PipeListener::bind(path.as_ref())?;
+        let token = self.token.next();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        self.services.push(StreamNewService::create(
+            name.as_ref().to_string(),
+            token,
+            factory,
+            addr,
+        ));
+        self.sockets.push((
+            token,
+            name.as_ref().to_string(),
+            StdListener::NamedPipe(lst),
+        ));
+        Ok(self)
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Add new SCTP service to the server, listener is one-to-one style
+    /// socket and service receives association per connection.
+    pub fn bind_sctp<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<SctpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let mut err = None;
+        let mut sockets = Vec::new();
+        for addr in addr.to_socket_addrs()? {
+            match create_sctp_listener(addr, self.backlog) {
+                Ok(lst) => sockets.push(lst),
+                Err(e) => err = Some(e),
+            }
+        }
+        if sockets.is_empty() {
+            return Err(err.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::Other, "Can not bind to address.")
+            }));
+        }
+
+        for lst in sockets {
+            let token = self.token.next();
+            self.services.push(StreamNewService::create(
+                name.as_ref().to_string(),
+                token,
+                factory.clone(),
+                lst.local_addr()?,
+            ));
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        }
+        Ok(self)
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    /// Add new service to the server, reads and writes of accepted streams
+    /// are done with io_uring of worker.
+    ///
+    /// Requires linux 5.7+, connections are closed if io_uring is not
+    /// supported by kernel.
+    pub fn bind_uring<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<UringStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let sockets = bind_addr(addr, self.backlog)?;
+
+        for lst in sockets {
+            let token = self.token.next();
+            self.services.push(StreamNewService::create(
+                name.as_ref().to_string(),
+                token,
+                factory.clone(),
+                lst.local_addr()?,
+            ));
+            self.sockets
+                .push((token, name.as_ref().to_string(), StdListener::Tcp(lst)));
+        }
+        Ok(self)
+    }
+
+    /// Add new service to the server.
+    pub fn listen<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        lst: net::TcpListener,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        let token = self.token.next();
+        self.services.push(StreamNewService::create(
             name.as_ref().to_string(),
             token,
             factory,
@@ -250,6 +950,200 @@ impl ServerBuilder {
         Ok(self)
     }
 
+    /// Add new service to the server, options are applied to accepted
+    /// connections.
+    pub fn listen_with<F, N: AsRef<str>>(
+        mut self,
+        name: N,
+        lst: net::TcpListener,
+        opts: TcpOptions,
+        factory: F,
+    ) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+    {
+        #[cfg(unix)]
+        {
+            if let Some(backlog) = opts.backlog {
+                use std::os::unix::io::AsRawFd;
+
+                if unsafe { libc::listen(lst.as_raw_fd(), backlog) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        self = self.listen(name, lst, factory)?;
+        let token = self.sockets[self.sockets.len() - 1].0;
+        self.tcp_options.insert(token, opts);
+        Ok(self)
+    }
+
+    #[cfg(unix)]
+    /// Add new service to the server, listening sockets are passed by
+    /// systemd socket activation.
+    ///
+    /// Tcp sockets are selected by name, set with `FileDescriptorName=`
+    /// option of the socket unit, by default it is socket unit name.
+    /// Sockets received with `receive_handoff()` are selected the same way.
+    /// Returns `NotFound` error if no socket with such name is passed, or
+    /// sockets are passed without `LISTEN_PID` of this process.
+    pub fn listen_from_env<F, N>(mut self, name: N, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        N: AsRef<str>,
+    {
+        use std::os::unix::io::FromRawFd;
+
+        for fd in self.activated_sockets(name.as_ref(), false)? {
+            let lst = unsafe { net::TcpListener::from_raw_fd(fd) };
+            self = self.listen(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    #[cfg(unix)]
+    /// Add new unix domain service to the server, listening sockets are
+    /// passed by systemd socket activation.
+    ///
+    /// Sockets are selected by name the same way as in `listen_from_env()`.
+    pub fn listen_uds_from_env<F, N>(mut self, name: N, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<actix_rt::net::UnixStream>,
+        N: AsRef<str>,
+    {
+        use std::os::unix::io::FromRawFd;
+
+        for fd in self.activated_sockets(name.as_ref(), true)? {
+            let lst = unsafe { std::os::unix::net::UnixListener::from_raw_fd(fd) };
+            self = self.listen_uds(name.as_ref(), lst, factory.clone())?;
+        }
+        Ok(self)
+    }
+
+    #[cfg(unix)]
+    /// Add new service to the server, listening socket is passed by parent
+    /// process with `listenfd` convention, i.e. by `systemfd`.
+    ///
+    /// Socket is selected by index, first passed socket has index `0`.
+    /// Returns `NotFound` error if no socket with such index is passed.
+    pub fn listen_external<F, N>(mut self, name: N, idx: usize, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        N: AsRef<str>,
+    {
+        use std::os::unix::io::FromRawFd;
+
+        let fd = self.external_socket(Some(idx))?;
+        let lst = unsafe { net::TcpListener::from_raw_fd(fd) };
+        self.listen(name, lst, factory)
+    }
+
+    /// Add new service to the server, listening on first not yet used tcp
+    /// socket passed with `listenfd` convention, or on `addr` if there is no
+    /// such socket.
+    ///
+    /// Useful for auto-reload during development, i.e. with
+    /// `systemfd --no-pid -s http::8080 -- cargo watch -x run`. Socket is
+    /// kept open by `systemfd` between restarts, so connections are not
+    /// refused while server is rebuilt. Passed sockets are supported on
+    /// unix only.
+    pub fn bind_or_external<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        match self.external_listener()? {
+            Some(lst) => self.listen(name, lst, factory),
+            None => self.bind(name, addr, factory),
+        }
+    }
+
+    /// Take first tcp listener passed in environment.
+    fn external_listener(&mut self) -> io::Result<Option<net::TcpListener>> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::FromRawFd;
+
+            match self.external_socket(None) {
+                Ok(fd) => Ok(Some(unsafe { net::TcpListener::from_raw_fd(fd) })),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+        #[cfg(not(unix))]
+        Ok(None)
+    }
+
+    #[cfg(unix)]
+    /// Wait for listening sockets passed by old server process with
+    /// `Server::handoff()`.
+    ///
+    /// Blocks until sockets are received on unix socket at `path`. Received
+    /// sockets keep their names and are added to the server with
+    /// `listen_from_env()` and `listen_uds_from_env()`.
+    pub fn receive_handoff<P: AsRef<std::path::Path>>(mut self, path: P) -> io::Result<Self> {
+        let sockets = handoff::receive(path.as_ref())?;
+        if self.activated.is_none() {
+            self.activated = Some(systemd::listen_fds()?);
+        }
+        self.activated.as_mut().unwrap().extend(sockets);
+        Ok(self)
+    }
+
+    #[cfg(unix)]
+    /// Take activated sockets with provided name, each socket is taken once.
+    fn activated_sockets(
+        &mut self,
+        name: &str,
+        unix: bool,
+    ) -> io::Result<Vec<std::os::unix::io::RawFd>> {
+        if self.activated.is_none() {
+            self.activated = Some(systemd::listen_fds()?);
+        }
+        let activated = self.activated.as_mut().unwrap();
+
+        let (matched, rest): (Vec<_>, Vec<_>) = activated
+            .drain(..)
+            .partition(|sock| sock.verified && sock.name == name && sock.unix == unix);
+        *activated = rest;
+
+        if matched.is_empty() {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No activated socket with name {:?}", name),
+            ))
+        } else {
+            Ok(matched.into_iter().map(|sock| sock.fd).collect())
+        }
+    }
+
+    #[cfg(unix)]
+    /// Take tcp socket passed in environment with provided index, or first
+    /// one if index is not set.
+    fn external_socket(&mut self, idx: Option<usize>) -> io::Result<std::os::unix::io::RawFd> {
+        if self.activated.is_none() {
+            self.activated = Some(systemd::listen_fds()?);
+        }
+        let activated = self.activated.as_mut().unwrap();
+
+        let pos = activated.iter().position(|sock| match idx {
+            Some(idx) => sock.idx == Some(idx),
+            None => sock.idx.is_some() && !sock.unix,
+        });
+        match pos {
+            Some(pos) if activated[pos].unix => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Passed socket {} is not a tcp socket", activated[pos].fd),
+            )),
+            Some(pos) => Ok(activated.remove(pos).fd),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No passed socket with such index",
+            )),
+        }
+    }
+
     #[doc(hidden)]
     pub fn start(self) -> Server {
         self.run()
@@ -257,34 +1151,99 @@ impl ServerBuilder {
 
     /// Starts processing incoming connections and return server controller.
     pub fn run(mut self) -> Server {
-        if self.sockets.is_empty() {
+        if self.sockets.is_empty()
+            && self.datagrams.is_empty()
+            && self.configuring.is_empty()
+            && !self.has_reuseport()
+        {
             panic!("Server should have at least one bound socket");
         } else {
             info!("Starting {} workers", self.threads);
 
-            // start workers
+            #[cfg(unix)]
+            for item in &self.reuseport {
+                info!(
+                    "Starting \"{}\" service on {} in every worker",
+                    item.name, item.addr
+                );
+                self.names.push((item.token, item.name.clone()));
+                // keep listeners for handoff
+                if let Some(Ok(lst)) = item.lst.as_ref().map(|lst| lst.try_clone()) {
+                    self.listeners
+                        .push((item.name.clone(), StdListener::Tcp(lst)));
+                }
+            }
+
+            // start workers, pinned accept thread is created before its
+            // worker and started with its sockets once accept loop starts
             let mut workers = Vec::new();
+            let mut pinned = Vec::new();
             for idx in 0..self.threads {
+                let socks = self
+                    .reuseport_sockets()
+                    .map(|socks| (self.accept.add_pinned(), socks));
                 let worker = self.start_worker(idx, self.accept.get_notify());
+                if let Some((slot, socks)) = socks {
+                    self.pinned.insert(idx, slot);
+                    pinned.push((slot, socks, worker.clone()));
+                }
                 workers.push(worker.clone());
                 self.workers.push((idx, worker));
             }
 
+            for (name, addr) in &self.datagrams {
+                info!("Starting \"{}\" service on udp {}", name, addr);
+            }
+
             // start accept thread
             for sock in &self.sockets {
                 info!("Starting \"{}\" service on {}", sock.1, sock.2);
+                self.names.push((sock.0, sock.1.clone()));
+                // keep listeners for handoff
+                #[cfg(unix)]
+                match sock.2.try_clone() {
+                    Ok(lst) => self.listeners.push((sock.1.clone(), lst)),
+                    Err(e) => error!("Can not clone listener: {}", e),
+                }
             }
+            let sockets = mem::replace(&mut self.sockets, Vec::new());
+            let mut tcp_options = mem::replace(&mut self.tcp_options, HashMap::new());
             self.accept.start(
-                mem::replace(&mut self.sockets, Vec::new())
+                sockets
                     .into_iter()
-                    .map(|t| (t.0, t.2))
+                    .map(|t| (t.0, t.2, tcp_options.remove(&t.0)))
                     .collect(),
                 workers,
+                &self.affinity,
             );
+            for (slot, socks, worker) in pinned {
+                self.accept
+                    .start_pinned(slot, socks, worker, &self.affinity);
+            }
+
+            // create service factories of async listeners
+            for (name, sockets, configure) in mem::replace(&mut self.configuring, Vec::new()) {
+                let server = self.server.clone();
+                spawn(async move {
+                    let factory = configure().await;
+                    server.listener_configured(name, sockets, factory);
+                });
+            }
+
+            // check heartbeats of workers
+            if let Some((interval, _)) = self.heartbeat {
+                let server = self.server.clone();
+                spawn(async move {
+                    loop {
+                        delay_until(Instant::now() + interval).await;
+                        server.check_heartbeats();
+                    }
+                });
+            }
 
             // handle signals
-            if !self.no_signals {
-                Signals::start(self.server.clone()).unwrap();
+            if !self.signals.is_empty() {
+                Signals::start(self.server.clone(), self.signals.signals()).unwrap();
             }
 
             // start http server actor
@@ -299,10 +1258,144 @@ impl ServerBuilder {
         let services: Vec<Box<dyn InternalServiceFactory>> =
             self.services.iter().map(|v| v.clone_factory()).collect();
 
-        Worker::start(idx, services, avail, self.shutdown_timeout)
+        // thread is pinned before worker starts
+        let arbiter = self.runtime.arbiter(idx);
+        if let Some(pin) = self.affinity.worker(idx) {
+            arbiter.exec_fn(pin);
+        }
+
+        let mut worker = Worker::start(
+            idx,
+            self.server.clone(),
+            services,
+            avail,
+            self.shutdown_timeout,
+            self.heartbeat.map(|(interval, _)| interval),
+            arbiter,
+        );
+        worker.cpu = self.affinity.core(idx);
+        worker
+    }
+
+    /// Start new worker and pass it to accept threads
+    fn add_worker(&mut self) {
+        let idx = self.next_worker_idx();
+        let slot = match self.unpinned.pop() {
+            Some(slot) => Some((slot, None)),
+            None => self
+                .reuseport_sockets()
+                .map(|socks| (self.accept.add_pinned(), Some(socks))),
+        };
+        let worker = self.start_worker(idx, self.accept.get_notify());
+
+        // worker takes over pinned accept thread of stopped worker
+        if let Some((slot, socks)) = slot {
+            match socks {
+                Some(socks) => {
+                    self.accept
+                        .start_pinned(slot, socks, worker.clone(), &self.affinity)
+                }
+                None => self
+                    .accept
+                    .send_pinned(slot, Command::Worker(worker.clone())),
+            }
+            self.pinned.insert(idx, slot);
+        }
+        self.workers.push((idx, worker.clone()));
+        self.accept.send(Command::Worker(worker));
+    }
+
+    /// Release pinned accept thread of stopped worker, it is closed if
+    /// the worker is retired
+    fn unpin_worker(&mut self, idx: usize, retire: bool) {
+        if let Some(slot) = self.pinned.remove(&idx) {
+            if retire {
+                self.accept.remove_pinned(slot);
+            } else {
+                self.unpinned.push(slot);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn has_reuseport(&self) -> bool {
+        !self.reuseport.is_empty()
+    }
+
+    #[cfg(not(unix))]
+    fn has_reuseport(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    /// Sockets of reuseport listeners for new worker, socket bound by
+    /// `bind_reuseport()` is used first
+    fn reuseport_sockets(&mut self) -> Option<Vec<(Token, StdListener, Option<TcpOptions>)>> {
+        if self.reuseport.is_empty() {
+            return None;
+        }
+
+        let backlog = self.backlog;
+        let socks = self
+            .reuseport
+            .iter_mut()
+            .filter_map(|item| {
+                let lst = match item.lst.take() {
+                    Some(lst) => lst,
+                    None => match create_tcp_listener(
+                        item.addr,
+                        backlog,
+                        true,
+                        item.opts.as_ref().unwrap_or(&TcpOptions::default()),
+                    ) {
+                        Ok(lst) => lst,
+                        Err(e) => {
+                            error!(
+                                "Can not bind \"{}\" service to {}: {}",
+                                item.name, item.addr, e
+                            );
+                            return None;
+                        }
+                    },
+                };
+                Some((item.token, StdListener::Tcp(lst), item.opts.clone()))
+            })
+            .collect();
+        Some(socks)
+    }
+
+    #[cfg(not(unix))]
+    fn reuseport_sockets(&mut self) -> Option<Vec<(Token, StdListener, Option<TcpOptions>)>> {
+        None
+    }
+
+    fn next_worker_idx(&self) -> usize {
+        let mut new_idx = self.workers.len();
+        'found: loop {
+            for i in 0..self.workers.len() {
+                if self.workers[i].0 == new_idx {
+                    new_idx += 1;
+                    continue 'found;
+                }
+            }
+            break;
+        }
+        new_idx
     }
 
     fn handle_cmd(&mut self, item: ServerCommand) {
+        if self.updating {
+            match item {
+                ServerCommand::AddListener { .. }
+                | ServerCommand::ListenerConfigured { .. }
+                | ServerCommand::Reload { .. } => {
+                    self.deferred.push_back(item);
+                    return;
+                }
+                _ => (),
+            }
+        }
+
         match item {
             ServerCommand::Pause(tx) => {
                 self.accept.send(Command::Pause);
@@ -312,99 +1405,386 @@ impl ServerBuilder {
                 self.accept.send(Command::Resume);
                 let _ = tx.send(());
             }
+            ServerCommand::PauseListener { name, result } => match self.tokens(&name) {
+                Ok(tokens) => {
+                    info!("Pausing \"{}\" listeners", name);
+                    self.accept.send(Command::PauseListeners(tokens));
+                    let _ = result.send(Ok(()));
+                }
+                Err(e) => {
+                    let _ = result.send(Err(e));
+                }
+            },
+            ServerCommand::ResumeListener { name, result } => match self.tokens(&name) {
+                Ok(tokens) => {
+                    info!("Resuming \"{}\" listeners", name);
+                    self.accept.send(Command::ResumeListeners(tokens));
+                    let _ = result.send(Ok(()));
+                }
+                Err(e) => {
+                    let _ = result.send(Err(e));
+                }
+            },
+            ServerCommand::SetIpFilter {
+                name,
+                filter,
+                result,
+            } => match self.tokens(&name) {
+                Ok(tokens) => {
+                    info!("Updating ip filter of \"{}\" listeners", name);
+                    self.accept.send(Command::SetIpFilter(tokens, filter));
+                    let _ = result.send(Ok(()));
+                }
+                Err(e) => {
+                    let _ = result.send(Err(e));
+                }
+            },
             ServerCommand::Signal(sig) => {
                 // Signals support
-                // Handle `SIGINT`, `SIGTERM`, `SIGQUIT` signals and stop actix system
-                match sig {
-                    Signal::Int => {
-                        info!("SIGINT received, exiting");
-                        self.exit = true;
+                // Stop actix system or call custom handler, as configured
+                let graceful = match self.signals.get(sig) {
+                    Some(SignalAction::Graceful) => {
+                        info!("{} received, stopping", sig);
+                        true
+                    }
+                    Some(SignalAction::Immediate) => {
+                        info!("{} received, exiting", sig);
+                        false
+                    }
+                    Some(SignalAction::Custom(f)) => {
+                        info!("{} received", sig);
+                        f(&self.server);
+                        return;
+                    }
+                    Some(SignalAction::Ignore) | None => return,
+                };
+                self.exit = true;
+                if !self.stopping {
+                    self.reason = Some(StopReason::Signal(sig));
+                }
+                self.handle_cmd(ServerCommand::Stop {
+                    graceful,
+                    completion: None,
+                })
+            }
+            ServerCommand::Notify(tx) => {
+                if self.stopping {
+                    let _ = tx.send(stop_result(&self.failure));
+                } else {
+                    self.notify.push(tx);
+                }
+            }
+            ServerCommand::Stopped(tx) => {
+                if self.stopped {
+                    let _ = tx.send(self.reason.clone().unwrap_or(StopReason::Dropped));
+                } else {
+                    self.wait_stopped.push(tx);
+                }
+            }
+            ServerCommand::WorkersStopped => {
+                self.stopped = true;
+                let reason = self.reason.clone().unwrap_or(StopReason::Dropped);
+                for tx in self.wait_stopped.drain(..) {
+                    let _ = tx.send(reason.clone());
+                }
+            }
+            ServerCommand::Metrics(tx) => {
+                let metrics = self.accept.metrics();
+                let accepted = metrics.accepted.load(Ordering::Relaxed);
+                let accept_errors = metrics.errors.load(Ordering::Relaxed);
+                let paused = metrics.paused.load(Ordering::Relaxed);
+                let workers = self
+                    .workers
+                    .iter()
+                    .map(|(_, worker)| worker.metrics(METRICS_TIMEOUT));
+                let tls = self
+                    .handshakes
+                    .iter()
+                    .map(|(name, metrics)| metrics.snapshot(name))
+                    .collect();
+                spawn(join_all(workers).map(move |workers| {
+                    let _ = tx.send(ServerMetrics {
+                        workers,
+                        tls,
+                        accepted,
+                        accept_errors,
+                        paused,
+                    });
+                }));
+            }
+            ServerCommand::SetMaxConnections(num, tx) => {
+                info!("Changing max connections to {}", num);
+                // restarted and new workers use new limit too
+                worker::max_concurrent_connections(num);
+                for (_, worker) in &self.workers {
+                    worker.set_max_connections(num);
+                }
+                let _ = tx.send(());
+            }
+            ServerCommand::SetWorkers(num, tx) => {
+                let num = std::cmp::max(num, 1);
+                info!("Changing number of workers to {}", num);
+                self.threads = num;
+
+                while self.workers.len() < num {
+                    self.add_worker();
+                }
+
+                // retire surplus workers once accept loop stops using them
+                let mut stopped = Vec::new();
+                while self.workers.len() > num {
+                    let (idx, worker) = self.workers.pop().unwrap();
+                    self.unpin_worker(idx, true);
+                    let (removed, rx) = oneshot::channel();
+                    self.accept.send(Command::RemoveWorker(idx, removed));
+                    stopped.push(rx.then(move |_| worker.stop(true)));
+                }
+                spawn(join_all(stopped).map(move |_| {
+                    let _ = tx.send(());
+                }));
+            }
+            ServerCommand::AddListener {
+                name,
+                addrs,
+                factory,
+                result,
+            } => match bind_addr(&addrs[..], self.backlog) {
+                Ok(sockets) => self.add_listener(name, sockets, factory, result),
+                Err(e) => {
+                    let _ = result.send(Err(e));
+                }
+            },
+            ServerCommand::ListenerConfigured {
+                name,
+                sockets,
+                factory,
+            } => match factory {
+                Ok(factory) => {
+                    let (tx, rx) = oneshot::channel();
+                    self.add_listener(name.clone(), sockets, factory, tx);
+                    spawn(rx.map(move |res| {
+                        if let Ok(Err(e)) = res {
+                            error!("Can not start \"{}\" service: {}", name, e);
+                        }
+                    }));
+                }
+                Err(e) => {
+                    error!("Can not configure \"{}\" service: {}", name, e);
+                    if !self.stopping {
+                        self.failure =
+                            Some(format!("Can not configure {:?} service: {}", name, e));
                         self.handle_cmd(ServerCommand::Stop {
                             graceful: false,
                             completion: None,
-                        })
+                        });
+                    }
+                }
+            },
+            ServerCommand::ListenerStarted {
+                name,
+                sockets,
+                result,
+            } => {
+                for (token, lst) in &sockets {
+                    info!("Starting \"{}\" service on {}", name, lst);
+                    self.names.push((*token, name.clone()));
+                    #[cfg(unix)]
+                    match lst.try_clone() {
+                        Ok(lst) => self.listeners.push((name.clone(), lst)),
+                        Err(e) => error!("Can not clone listener: {}", e),
                     }
-                    Signal::Term => {
-                        info!("SIGTERM received, stopping");
-                        self.exit = true;
+                }
+                self.accept.send(Command::Add(
+                    sockets
+                        .into_iter()
+                        .map(|(token, lst)| (token, lst, None))
+                        .collect(),
+                ));
+                let _ = result.send(Ok(()));
+                self.update_done();
+            }
+            ServerCommand::ListenerFailed {
+                name,
+                factory,
+                token,
+                result,
+            } => {
+                // drop services started on other workers, later listeners
+                // reuse factory indexes and tokens
+                for idx in (factory..self.services.len()).rev() {
+                    for worker in &self.workers {
+                        worker.1.remove_service(idx);
+                    }
+                }
+                self.services.truncate(factory);
+                self.token = token;
+                let _ = result.send(Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Can not start {:?} service", name),
+                )));
+                self.update_done();
+            }
+            ServerCommand::Reload {
+                name,
+                factory,
+                result,
+            } => match self.tokens(&name) {
+                Ok(tokens) => {
+                    info!("Reloading \"{}\" service", name);
+                    self.reload(name, tokens, factory, result);
+                }
+                Err(e) => {
+                    let _ = result.send(Err(e));
+                }
+            },
+            ServerCommand::Reloaded { services, result } => {
+                // restarted and new workers use new factories
+                for (idx, srv) in services.0 {
+                    self.services[idx] = srv;
+                }
+                let _ = result.send(Ok(()));
+                self.update_done();
+            }
+            ServerCommand::ReloadFailed { reloaded, result } => {
+                // reload is all or nothing, workers that started new
+                // services go back to old ones
+                let mut restored = Vec::new();
+                for (id, idx) in reloaded {
+                    if let Some(worker) = self.workers.iter().find(|w| w.0 == id) {
+                        let srv = self.services[idx].clone_factory();
+                        restored.push(worker.1.replace_service(idx, srv));
+                    }
+                }
+                spawn(join_all(restored).map(|res| {
+                    if !res.into_iter().all(|res| res == Ok(true)) {
+                        error!("Can not restore services of failed reload");
+                    }
+                }));
+                let _ = result.send(Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "Can not start service",
+                )));
+                self.update_done();
+            }
+            ServerCommand::RemoveListener { name, result } => {
+                let tokens = match self.tokens(&name) {
+                    Ok(tokens) => tokens,
+                    Err(e) => {
+                        let _ = result.send(Err(e));
+                        return;
+                    }
+                };
+
+                info!("Removing \"{}\" listeners", name);
+                self.names.retain(|item| item.1 != name);
+                #[cfg(unix)]
+                {
+                    self.listeners.retain(|item| item.0 != name);
+                    self.reuseport.retain(|item| item.name != name);
+                }
+                // pinned accept threads are not needed without reuseport listeners
+                if !self.has_reuseport() {
+                    let mut slots: Vec<_> = self.pinned.drain().map(|(_, slot)| slot).collect();
+                    slots.append(&mut self.unpinned);
+                    for slot in slots {
+                        self.accept.remove_pinned(slot);
+                    }
+                }
+                self.accept.send(Command::Remove(tokens));
+                let _ = result.send(Ok(()));
+            }
+            #[cfg(unix)]
+            ServerCommand::Handoff { path, result } => {
+                use std::os::unix::io::AsRawFd;
+
+                let sockets: Vec<_> = self
+                    .listeners
+                    .iter()
+                    .map(|(name, lst)| (name.clone(), lst.as_raw_fd()))
+                    .collect();
+                match handoff::send(&path, &sockets) {
+                    Ok(()) => {
+                        info!("Listeners are passed to {:?}, stopping", path);
+                        self.accept.send(Command::Handoff);
+                        self.listeners.clear();
+                        let _ = result.send(Ok(()));
+                        if !self.stopping {
+                            self.reason = Some(StopReason::Handoff);
+                        }
                         self.handle_cmd(ServerCommand::Stop {
                             graceful: true,
                             completion: None,
-                        })
+                        });
                     }
-                    Signal::Quit => {
-                        info!("SIGQUIT received, exiting");
-                        self.exit = true;
-                        self.handle_cmd(ServerCommand::Stop {
-                            graceful: false,
-                            completion: None,
-                        })
+                    Err(e) => {
+                        error!("Can not pass listeners to {:?}: {}", path, e);
+                        let _ = result.send(Err(e));
                     }
-                    _ => (),
                 }
             }
-            ServerCommand::Notify(tx) => {
-                self.notify.push(tx);
-            }
             ServerCommand::Stop {
                 graceful,
                 completion,
             } => {
                 let exit = self.exit;
+                let failure = self.failure.clone();
+                if let Some(ref msg) = failure {
+                    self.reason = Some(StopReason::Failed(msg.clone()));
+                } else if !self.stopping {
+                    self.reason = Some(StopReason::Stop { graceful });
+                }
+                self.stopping = true;
+                let server = self.server.clone();
 
                 // stop accept thread
                 self.accept.send(Command::Stop);
+                #[cfg(unix)]
+                self.listeners.clear();
                 let notify = std::mem::replace(&mut self.notify, Vec::new());
+                let on_stop = std::mem::replace(&mut self.on_stop, Vec::new());
+                let deadline = Instant::now() + self.shutdown_timeout;
 
                 // stop workers
-                if !self.workers.is_empty() && graceful {
-                    spawn(
-                        self.workers
-                            .iter()
-                            .map(move |worker| worker.1.stop(graceful))
-                            .collect::<FuturesUnordered<_>>()
-                            .collect::<Vec<_>>()
-                            .then(move |_| {
-                                if let Some(tx) = completion {
-                                    let _ = tx.send(());
-                                }
-                                for tx in notify {
-                                    let _ = tx.send(());
-                                }
-                                if exit {
-                                    spawn(
-                                        async {
-                                            delay_until(
-                                                Instant::now() + Duration::from_millis(300),
-                                            )
-                                            .await;
-                                            System::current().stop();
-                                        }
-                                        .boxed(),
-                                    );
-                                }
-                                ready(())
-                            }),
-                    )
+                let workers: Vec<_> = if graceful {
+                    self.workers
+                        .iter()
+                        .map(move |worker| worker.1.stop(graceful))
+                        .collect()
                 } else {
-                    // we need to stop system if server was spawned
-                    if self.exit {
-                        spawn(
-                            delay_until(Instant::now() + Duration::from_millis(300)).then(
-                                |_| {
-                                    System::current().stop();
-                                    ready(())
-                                },
-                            ),
-                        );
+                    Vec::new()
+                };
+                spawn(async move {
+                    let closed: usize = join_all(workers)
+                        .await
+                        .into_iter()
+                        .map(|res| res.unwrap_or(0))
+                        .sum();
+                    if closed != 0 {
+                        info!("{} connections were closed forcibly", closed);
                     }
+
+                    if !on_stop.is_empty() {
+                        let budget = deadline.saturating_duration_since(Instant::now());
+                        let hooks = join_all(on_stop.into_iter().map(|f| f(budget)));
+                        if timeout(budget, hooks).await.is_err() {
+                            error!("Stop hooks did not complete in {:?}", budget);
+                        }
+                    }
+
                     if let Some(tx) = completion {
                         let _ = tx.send(());
                     }
                     for tx in notify {
-                        let _ = tx.send(());
+                        let _ = tx.send(stop_result(&failure));
                     }
-                }
+                    server.workers_stopped();
+
+                    // we need to stop system if server was spawned
+                    if exit {
+                        delay_until(Instant::now() + Duration::from_millis(300)).await;
+                        System::current().stop();
+                    }
+                });
             }
             ServerCommand::WorkerFaulted(idx) => {
                 let mut found = false;
@@ -418,25 +1798,232 @@ impl ServerBuilder {
 
                 if found {
                     error!("Worker has died {:?}, restarting", idx);
+                    self.unpin_worker(idx, false);
+                    self.add_worker();
+                }
+            }
+            ServerCommand::WorkerStarted(idx) => {
+                self.failures = 0;
+                if self.heartbeat.is_some() {
+                    self.heartbeats.insert(idx, Instant::now());
+                }
+            }
+            ServerCommand::WorkerHeartbeat(idx) => {
+                if let Some(beat) = self.heartbeats.get_mut(&idx) {
+                    *beat = Instant::now();
+                }
+            }
+            ServerCommand::CheckHeartbeats => {
+                let timeout = match self.heartbeat {
+                    Some((_, timeout)) if !self.stopping => timeout,
+                    _ => return,
+                };
+                let workers = &self.workers;
+                self.heartbeats
+                    .retain(|idx, _| workers.iter().any(|(i, _)| i == idx));
 
-                    let mut new_idx = self.workers.len();
-                    'found: loop {
-                        for i in 0..self.workers.len() {
-                            if self.workers[i].0 == new_idx {
-                                new_idx += 1;
-                                continue 'found;
-                            }
-                        }
-                        break;
+                let now = Instant::now();
+                let hung: Vec<usize> = self
+                    .heartbeats
+                    .iter()
+                    .filter(|(_, beat)| now.duration_since(**beat) > timeout)
+                    .map(|(idx, _)| *idx)
+                    .collect();
+                for idx in hung {
+                    self.heartbeats.remove(&idx);
+                    let pos = match self.workers.iter().position(|(i, _)| *i == idx) {
+                        Some(pos) => pos,
+                        None => continue,
+                    };
+                    let (_, worker) = self.workers.swap_remove(pos);
+                    self.unpin_worker(idx, false);
+                    let (tx, _) = oneshot::channel();
+                    self.accept.send(Command::RemoveWorker(idx, tx));
+                    // worker stops once it recovers
+                    drop(worker.stop(true));
+
+                    if self.restart_hung {
+                        error!("Worker {:?} is not responding, restarting", idx);
+                        self.add_worker();
+                    } else {
+                        error!("Worker {:?} is not responding, removing", idx);
+                    }
+                }
+            }
+            ServerCommand::WorkerFailed(idx) => {
+                match self.workers.iter().position(|(i, _)| *i == idx) {
+                    Some(pos) => {
+                        self.workers.swap_remove(pos);
                     }
+                    None => return,
+                }
+                self.unpin_worker(idx, false);
+                let (tx, _) = oneshot::channel();
+                self.accept.send(Command::RemoveWorker(idx, tx));
+                if self.stopping {
+                    return;
+                }
 
-                    let worker = self.start_worker(new_idx, self.accept.get_notify());
-                    self.workers.push((new_idx, worker.clone()));
-                    self.accept.send(Command::Worker(worker));
+                self.failures += 1;
+                match self.restart.delay(self.failures) {
+                    Some(delay) => {
+                        error!(
+                            "Worker {:?} can not start services, restarting in {:?}",
+                            idx, delay
+                        );
+                        let server = self.server.clone();
+                        spawn(async move {
+                            delay_until(Instant::now() + delay).await;
+                            server.restart_worker();
+                        });
+                    }
+                    None => {
+                        error!("Worker {:?} can not start services, stopping server", idx);
+                        self.failure = Some(format!(
+                            "Worker can not start services after {} attempts",
+                            self.failures
+                        ));
+                        self.handle_cmd(ServerCommand::Stop {
+                            graceful: false,
+                            completion: None,
+                        });
+                    }
+                }
+            }
+            ServerCommand::RestartWorker => {
+                if !self.stopping && self.workers.len() < self.threads {
+                    self.add_worker();
                 }
             }
         }
     }
+
+    /// Tokens of listeners with the name
+    fn tokens(&self, name: &str) -> io::Result<Vec<Token>> {
+        let tokens: Vec<Token> = self
+            .names
+            .iter()
+            .filter(|item| item.1 == name)
+            .map(|item| item.0)
+            .collect();
+        if tokens.is_empty() {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No listener with name {:?}", name),
+            ))
+        } else {
+            Ok(tokens)
+        }
+    }
+
+    /// Create services of listeners with new factory on all workers
+    fn reload(
+        &mut self,
+        name: String,
+        tokens: Vec<Token>,
+        factory: Box<dyn Any + Send>,
+        result: oneshot::Sender<io::Result<()>>,
+    ) {
+        let services: Vec<_> = self
+            .services
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, srv)| srv.reload(&tokens, &*factory).map(|srv| (idx, srv)))
+            .collect();
+        if services.len() != tokens.len() {
+            let _ = result.send(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Service of {:?} listener can not be reloaded", name),
+            )));
+            return;
+        }
+
+        // other updates wait until all workers are reloaded
+        self.updating = true;
+        let mut started = Vec::new();
+        let mut reloaded = Vec::new();
+        for (idx, srv) in &services {
+            for worker in &self.workers {
+                started.push(worker.1.replace_service(*idx, srv.clone_factory()));
+                reloaded.push((worker.0, *idx));
+            }
+        }
+
+        let server = self.server.clone();
+        spawn(join_all(started).map(move |res| {
+            if res.iter().all(|res| *res == Ok(true)) {
+                server.reloaded(ReloadedServices(services), result);
+            } else {
+                let reloaded = reloaded
+                    .into_iter()
+                    .zip(res)
+                    .filter(|(_, res)| *res == Ok(true))
+                    .map(|(item, _)| item)
+                    .collect();
+                server.reload_failed(reloaded, result);
+            }
+        }));
+    }
+
+    /// Start services of bound listener on all workers, listener starts
+    /// accepting connections once services are started.
+    fn add_listener(
+        &mut self,
+        name: String,
+        sockets: Vec<net::TcpListener>,
+        factory: ListenerFactory,
+        result: oneshot::Sender<io::Result<()>>,
+    ) {
+        let addrs: Vec<_> = match sockets.iter().map(|lst| lst.local_addr()).collect() {
+            Ok(addrs) => addrs,
+            Err(e) => {
+                let _ = result.send(Err(e));
+                return;
+            }
+        };
+
+        // other updates wait until services are started on all workers
+        self.updating = true;
+        let first = (self.services.len(), self.token);
+        let mut started = Vec::new();
+        let mut listeners = Vec::new();
+        for (lst, addr) in sockets.into_iter().zip(addrs) {
+            let token = self.token.next();
+            let srv = (factory.0)(token, addr);
+            for worker in &self.workers {
+                started.push(worker.1.add_service(srv.clone_factory()));
+            }
+            self.services.push(srv);
+            listeners.push((token, StdListener::Tcp(lst)));
+        }
+
+        let server = self.server.clone();
+        spawn(join_all(started).map(move |res| {
+            if res.into_iter().all(|res| res == Ok(true)) {
+                server.listener_started(name, listeners, result);
+            } else {
+                server.listener_failed(name, first.0, first.1, result);
+            }
+        }));
+    }
+
+    /// Listener update is finished, handle updates received meanwhile.
+    fn update_done(&mut self) {
+        self.updating = false;
+        while !self.updating {
+            match self.deferred.pop_front() {
+                Some(cmd) => self.handle_cmd(cmd),
+                None => break,
+            }
+        }
+    }
+}
+
+fn stop_result(failure: &Option<String>) -> io::Result<()> {
+    match failure {
+        Some(msg) => Err(io::Error::new(io::ErrorKind::Other, msg.clone())),
+        None => Ok(()),
+    }
 }
 
 impl Future for ServerBuilder {
@@ -457,12 +2044,21 @@ impl Future for ServerBuilder {
 pub(super) fn bind_addr<S: net::ToSocketAddrs>(
     addr: S,
     backlog: i32,
+) -> io::Result<Vec<net::TcpListener>> {
+    bind_addr_with(addr, backlog, false, &TcpOptions::default())
+}
+
+fn bind_addr_with<S: net::ToSocketAddrs>(
+    addr: S,
+    backlog: i32,
+    reuse_port: bool,
+    opts: &TcpOptions,
 ) -> io::Result<Vec<net::TcpListener>> {
     let mut err = None;
     let mut succ = false;
     let mut sockets = Vec::new();
     for addr in addr.to_socket_addrs()? {
-        match create_tcp_listener(addr, backlog) {
+        match create_tcp_listener(addr, backlog, reuse_port, opts) {
             Ok(lst) => {
                 succ = true;
                 sockets.push(lst);
@@ -485,12 +2081,63 @@ pub(super) fn bind_addr<S: net::ToSocketAddrs>(
     }
 }
 
-fn create_tcp_listener(addr: net::SocketAddr, backlog: i32) -> io::Result<net::TcpListener> {
+fn bind_udp_addr<S: net::ToSocketAddrs>(addr: S) -> io::Result<Vec<net::UdpSocket>> {
+    let mut err = None;
+    let mut sockets = Vec::new();
+    for addr in addr.to_socket_addrs()? {
+        match net::UdpSocket::bind(addr) {
+            Ok(sock) => sockets.push(sock),
+            Err(e) => err = Some(e),
+        }
+    }
+
+    if sockets.is_empty() {
+        if let Some(e) = err.take() {
+            Err(e)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Can not bind to address.",
+            ))
+        }
+    } else {
+        Ok(sockets)
+    }
+}
+
+fn create_tcp_listener(
+    addr: net::SocketAddr,
+    backlog: i32,
+    reuse_port: bool,
+    opts: &TcpOptions,
+) -> io::Result<net::TcpListener> {
     let builder = match addr {
         net::SocketAddr::V4(_) => TcpBuilder::new_v4()?,
-        net::SocketAddr::V6(_) => TcpBuilder::new_v6()?,
+        net::SocketAddr::V6(_) => {
+            let builder = TcpBuilder::new_v6()?;
+            if let Some(only_v6) = opts.only_v6 {
+                builder.only_v6(only_v6)?;
+            }
+            builder
+        }
     };
-    builder.reuse_address(true)?;
+    builder.reuse_address(opts.reuse_address.unwrap_or(true))?;
+    if reuse_port {
+        #[cfg(unix)]
+        {
+            use net2::unix::UnixTcpBuilderExt;
+            builder.reuse_port(true)?;
+        }
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    opts.apply_listener(&builder, addr.is_ipv6())?;
     builder.bind(addr)?;
-    Ok(builder.listen(backlog)?)
+    let lst = builder.listen(opts.backlog.unwrap_or(backlog))?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if let (true, Some(program)) = (reuse_port, &opts.reuseport_program) {
+            program.attach(&lst)?;
+        }
+    }
+    Ok(lst)
 }
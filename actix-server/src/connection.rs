@@ -0,0 +1,320 @@
+//! Connection state attached by `on_connect` hook.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_service::{Service, ServiceFactory as ActixServiceFactory};
+use futures::future::LocalBoxFuture;
+use futures::{FutureExt, TryFutureExt};
+
+use crate::service::{
+    BoxedServerService, InternalServiceFactory, ServiceFactory, StreamService,
+};
+use crate::socket::FromStream;
+use crate::Token;
+
+/// Hook that extracts connection data right after accept
+pub(crate) type OnConnect = Arc<dyn Fn(&dyn Any, &mut Extensions) + Send + Sync>;
+
+/// Type map of connection data
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Extensions {
+    /// Create empty `Extensions`.
+    pub fn new() -> Extensions {
+        Extensions {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Insert value, previous value of the same type is returned.
+    pub fn insert<T: 'static>(&mut self, val: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(val))
+            .and_then(|val| val.downcast().ok().map(|val| *val))
+    }
+
+    /// Check if value of the type is present.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Get reference to value of the type.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|val| val.downcast_ref())
+    }
+
+    /// Get mutable reference to value of the type.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|val| val.downcast_mut())
+    }
+
+    /// Remove value of the type.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|val| val.downcast().ok().map(|val| *val))
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+/// Credentials of peer process of unix domain socket connection.
+///
+/// Credentials are attached to `Extensions` of unix socket connections
+/// before `on_connect` hook is called.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pid: Option<i32>,
+    uid: u32,
+    gid: u32,
+}
+
+#[cfg(unix)]
+impl PeerCredentials {
+    /// Get credentials of peer of the stream.
+    pub fn of<T: std::os::unix::io::AsRawFd>(stream: &T) -> io::Result<Self> {
+        peer_credentials(stream.as_raw_fd())
+    }
+
+    /// Process id of peer, available on linux only.
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
+
+    /// Effective user id of peer.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Effective group id of peer.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_credentials(fd: std::os::unix::io::RawFd) -> io::Result<PeerCredentials> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    if unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    } != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(PeerCredentials {
+        pid: Some(cred.pid),
+        uid: cred.uid,
+        gid: cred.gid,
+    })
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn peer_credentials(fd: std::os::unix::io::RawFd) -> io::Result<PeerCredentials> {
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(PeerCredentials {
+        pid: None,
+        uid,
+        gid,
+    })
+}
+
+/// Accepted stream together with data attached by `on_connect` hook.
+///
+/// Services registered with `ServerBuilder::bind_connection()` receive this
+/// type instead of a bare stream.
+#[derive(Debug)]
+pub struct Connection<T> {
+    io: T,
+    ext: Extensions,
+}
+
+impl<T> Connection<T> {
+    /// Get reference to the stream.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Get mutable reference to the stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Get connection data.
+    pub fn extensions(&self) -> &Extensions {
+        &self.ext
+    }
+
+    /// Get mutable connection data.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.ext
+    }
+
+    /// Consume self, returns the stream and connection data.
+    pub fn into_parts(self) -> (T, Extensions) {
+        (self.io, self.ext)
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Connection<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Connection<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+/// Runs `on_connect` hook and passes `Connection` to inner service
+pub(crate) struct ConnectionService<S, Io> {
+    service: S,
+    on_connect: Option<OnConnect>,
+    _t: PhantomData<Io>,
+}
+
+impl<S, Io> Service for ConnectionService<S, Io>
+where
+    S: Service<Request = Connection<Io>>,
+    Io: 'static,
+{
+    type Request = Io;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: Io) -> Self::Future {
+        let mut ext = Extensions::new();
+        #[cfg(unix)]
+        {
+            let uds = (&io as &dyn Any).downcast_ref::<actix_rt::net::UnixStream>();
+            if let Some(Ok(cred)) = uds.map(PeerCredentials::of) {
+                ext.insert(cred);
+            }
+        }
+        if let Some(ref on_connect) = self.on_connect {
+            (*on_connect)(&io, &mut ext);
+        }
+        self.service.call(Connection { io, ext })
+    }
+}
+
+pub(crate) struct ConnectionNewService<F: ServiceFactory<Connection<Io>>, Io> {
+    name: String,
+    inner: F,
+    token: Token,
+    on_connect: Option<OnConnect>,
+    _t: PhantomData<Io>,
+}
+
+impl<F, Io> ConnectionNewService<F, Io>
+where
+    F: ServiceFactory<Connection<Io>>,
+    Io: FromStream + Send + 'static,
+{
+    pub(crate) fn create(
+        name: String,
+        token: Token,
+        inner: F,
+        on_connect: Option<OnConnect>,
+    ) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name,
+            token,
+            inner,
+            on_connect,
+            _t: PhantomData,
+        })
+    }
+}
+
+impl<F, Io> InternalServiceFactory for ConnectionNewService<F, Io>
+where
+    F: ServiceFactory<Connection<Io>>,
+    Io: FromStream + Send + 'static,
+{
+    fn name(&self, _: Token) -> &str {
+        &self.name
+    }
+
+    fn clone_factory(&self) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+            token: self.token,
+            on_connect: self.on_connect.clone(),
+            _t: PhantomData,
+        })
+    }
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+        let token = self.token;
+        let on_connect = self.on_connect.clone();
+        self.inner
+            .create()
+            .new_service(())
+            .map_err(|_| ())
+            .map_ok(move |service| {
+                let service: BoxedServerService =
+                    Box::new(StreamService::new(ConnectionService {
+                        service,
+                        on_connect,
+                        _t: PhantomData,
+                    }));
+                vec![(token, service)]
+            })
+            .boxed_local()
+    }
+}
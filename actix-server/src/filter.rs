@@ -0,0 +1,156 @@
+//! Peer address filtering of listeners.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::{fmt, io};
+
+/// Network in CIDR notation, i.e. `10.0.0.0/8` or `fd00::/8`
+///
+/// Address without prefix length matches single address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    /// Create network from address and prefix length.
+    pub fn new(addr: IpAddr, prefix: u8) -> io::Result<IpNet> {
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix > max {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid prefix length {} of {}", prefix, addr),
+            ))
+        } else {
+            Ok(IpNet { addr, prefix })
+        }
+    }
+
+    /// Check if network contains address.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, normalize(addr)) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = mask(self.prefix, 32) as u32;
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = mask(self.prefix, 128);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<IpNet> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid network {:?}", s),
+            )
+        };
+        let mut parts = s.splitn(2, '/');
+        let addr = parts
+            .next()
+            .and_then(|addr| addr.parse::<IpAddr>().ok())
+            .ok_or_else(invalid)?;
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        IpNet::new(addr, prefix)
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl From<Ipv6Addr> for IpNet {
+    fn from(addr: Ipv6Addr) -> IpNet {
+        IpNet {
+            addr: IpAddr::V6(addr),
+            prefix: 128,
+        }
+    }
+}
+
+impl From<Ipv4Addr> for IpNet {
+    fn from(addr: Ipv4Addr) -> IpNet {
+        IpNet {
+            addr: IpAddr::V4(addr),
+            prefix: 32,
+        }
+    }
+}
+
+/// Mask of `prefix` high bits of `bits` wide address
+fn mask(prefix: u8, bits: u32) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        (!0u128 << (128 - u32::from(prefix))) >> (128 - bits)
+    }
+}
+
+/// Ipv4 mapped to ipv6 is matched as ipv4 address
+fn normalize(addr: IpAddr) -> IpAddr {
+    if let IpAddr::V6(v6) = addr {
+        if let [0, 0, 0, 0, 0, 0xffff, hi, lo] = v6.segments() {
+            return IpAddr::V4(Ipv4Addr::new(
+                (hi >> 8) as u8,
+                hi as u8,
+                (lo >> 8) as u8,
+                lo as u8,
+            ));
+        }
+    }
+    addr
+}
+
+/// Allow and deny lists of listener peer addresses
+///
+/// Filter is evaluated by accept loop, so rejected connections are closed
+/// before they are passed to worker. Denied networks take precedence, if
+/// allow list is not empty only peers from allowed networks are accepted.
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+impl IpFilter {
+    /// Create filter that accepts all peers.
+    pub fn new() -> Self {
+        IpFilter::default()
+    }
+
+    /// Accept peers from network.
+    pub fn allow(mut self, net: IpNet) -> Self {
+        self.allow.push(net);
+        self
+    }
+
+    /// Reject peers from network.
+    pub fn deny(mut self, net: IpNet) -> Self {
+        self.deny.push(net);
+        self
+    }
+
+    /// Check if peer is accepted by filter.
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        if self.deny.iter().any(|net| net.contains(addr)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(addr))
+    }
+}
@@ -0,0 +1,210 @@
+//! Listener handoff between server processes.
+//!
+//! Old server process connects to unix socket of new process and passes
+//! listener file descriptors in a single message. Message payload starts
+//! with length of the rest of payload, followed by listener names, each
+//! prefixed with its length. Lengths are big endian `u32`. Descriptors are
+//! passed as `SCM_RIGHTS` ancillary data in the same order.
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::{mem, ptr};
+
+use crate::systemd::{self, ActivatedSocket};
+
+/// Max number of descriptors passed in one message
+const MAX_FDS: usize = 253;
+const MAX_PAYLOAD: usize = 64 * 1024;
+
+/// Pass listeners to the process listening on `path`.
+pub(crate) fn send(path: &Path, sockets: &[(String, RawFd)]) -> io::Result<()> {
+    if sockets.len() > MAX_FDS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Too many listeners to hand off",
+        ));
+    }
+    let mut payload = vec![0; 4];
+    for (name, _) in sockets {
+        payload.extend_from_slice(&(name.len() as u32).to_be_bytes());
+        payload.extend_from_slice(name.as_bytes());
+    }
+    if payload.len() > MAX_PAYLOAD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Listener names are too long to hand off",
+        ));
+    }
+    let len = (payload.len() - 4) as u32;
+    payload[..4].copy_from_slice(&len.to_be_bytes());
+
+    let mut stream = UnixStream::connect(path)?;
+    let fds: Vec<RawFd> = sockets.iter().map(|(_, fd)| *fd).collect();
+
+    let fds_len = (fds.len() * mem::size_of::<RawFd>()) as libc::c_uint;
+    let mut cmsg_buf = vec![0u64; cmsg_space(fds_len) / mem::size_of::<u64>() + 1];
+
+    let mut iov = libc::iovec {
+        iov_base: payload.as_mut_ptr() as *mut libc::c_void,
+        iov_len: payload.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_space(fds_len) as _;
+
+    let n = unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as _;
+        ptr::copy_nonoverlapping(
+            fds.as_ptr() as *const u8,
+            libc::CMSG_DATA(cmsg),
+            fds_len as usize,
+        );
+
+        libc::sendmsg(stream.as_raw_fd(), &msg, 0)
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // descriptors are sent with first part of payload
+    stream.write_all(&payload[n as usize..])
+}
+
+/// Wait for listeners passed by old server process.
+pub(crate) fn receive(path: &Path) -> io::Result<Vec<ActivatedSocket>> {
+    // stale socket file prevents bind
+    if let Err(e) = std::fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    let lst = UnixListener::bind(path)?;
+    let res = lst.accept();
+    let _ = std::fs::remove_file(path);
+    let (mut stream, _) = res?;
+
+    let mut payload = vec![0u8; MAX_PAYLOAD];
+    let fds_len = (MAX_FDS * mem::size_of::<RawFd>()) as libc::c_uint;
+    let mut cmsg_buf = vec![0u64; cmsg_space(fds_len) / mem::size_of::<u64>() + 1];
+
+    let mut iov = libc::iovec {
+        iov_base: payload.as_mut_ptr() as *mut libc::c_void,
+        iov_len: payload.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_space(fds_len) as _;
+
+    let n = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, 0) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut fds = Vec::new();
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg);
+                let len = (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+                for i in 0..len / mem::size_of::<RawFd>() {
+                    let mut fd: RawFd = 0;
+                    ptr::copy_nonoverlapping(
+                        data.add(i * mem::size_of::<RawFd>()),
+                        &mut fd as *mut RawFd as *mut u8,
+                        mem::size_of::<RawFd>(),
+                    );
+                    fds.push(fd);
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    let res = if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Handoff descriptors are truncated",
+        ))
+    } else {
+        read_names(&mut stream, &mut payload, n as usize).and_then(|names| {
+            if names.len() != fds.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid handoff message",
+                ));
+            }
+            let mut sockets = Vec::new();
+            for (name, fd) in names.into_iter().zip(fds.iter().copied()) {
+                if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                sockets.push(ActivatedSocket {
+                    name,
+                    fd,
+                    unix: systemd::is_unix_stream(fd)?,
+                    idx: None,
+                    verified: true,
+                });
+            }
+            Ok(sockets)
+        })
+    };
+    if res.is_err() {
+        for fd in fds {
+            unsafe { libc::close(fd) };
+        }
+    }
+    res
+}
+
+/// Read rest of payload and parse listener names, `len` bytes of payload
+/// are already received.
+fn read_names(
+    stream: &mut UnixStream,
+    buf: &mut [u8],
+    mut len: usize,
+) -> io::Result<Vec<String>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid handoff message");
+
+    while len < 4 {
+        match stream.read(&mut buf[len..4])? {
+            0 => return Err(invalid()),
+            n => len += n,
+        }
+    }
+    let total = 4 + u32::from_be_bytes(buf[..4].try_into().unwrap()) as usize;
+    if total > buf.len() || len > total {
+        return Err(invalid());
+    }
+    stream.read_exact(&mut buf[len..total])?;
+
+    let mut names = Vec::new();
+    let mut rest = &buf[4..total];
+    while !rest.is_empty() {
+        if rest.len() < 4 {
+            return Err(invalid());
+        }
+        let (size, tail) = rest.split_at(4);
+        let size = u32::from_be_bytes(size.try_into().unwrap()) as usize;
+        if tail.len() < size {
+            return Err(invalid());
+        }
+        let (name, tail) = tail.split_at(size);
+        names.push(String::from_utf8(name.to_vec()).map_err(|_| invalid())?);
+        rest = tail;
+    }
+    Ok(names)
+}
+
+fn cmsg_space(len: libc::c_uint) -> usize {
+    unsafe { libc::CMSG_SPACE(len) as usize }
+}
@@ -3,18 +3,76 @@
 #![allow(clippy::type_complexity)]
 
 mod accept;
+mod affinity;
 mod builder;
 mod config;
+mod connection;
+mod filter;
+#[cfg(unix)]
+mod handoff;
+mod lifetime;
+mod metrics;
+#[cfg(windows)]
+mod named_pipe;
+mod peer;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+mod proxy;
+mod rate;
+mod restart;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod reuseport;
+#[cfg(target_os = "linux")]
+mod sctp;
 mod server;
 mod service;
+mod shutdown;
 mod signals;
+mod sni;
 mod socket;
+#[cfg(unix)]
+mod systemd;
+mod tls;
+mod udp;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring_stream;
 mod worker;
 
+pub use self::accept::BackpressurePolicy;
 pub use self::builder::ServerBuilder;
 pub use self::config::{ServiceConfig, ServiceRuntime};
-pub use self::server::Server;
+pub use self::connection::{Connection, Extensions};
+pub use self::filter::{IpFilter, IpNet};
+pub use self::metrics::{ServerMetrics, TlsMetrics, WorkerMetrics};
+pub use self::proxy::{ProxyInfo, ProxyStream};
+pub use self::rate::RateLimitPolicy;
+pub use self::restart::RestartPolicy;
+pub use self::server::{Server, StopReason};
 pub use self::service::ServiceFactory;
+pub use self::shutdown::{shutdown_signal, ShutdownSignal};
+pub use self::signals::{Signal, SignalSet};
+pub use self::sni::SniServices;
+pub use self::socket::TcpOptions;
+pub use tokio_util::udp::UdpFramed;
+
+#[cfg(unix)]
+pub use self::connection::PeerCredentials;
+#[cfg(unix)]
+pub use self::socket::UdsOptions;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::reuseport::{BpfInstruction, ReuseportProgram};
+
+#[cfg(target_os = "linux")]
+pub use self::sctp::SctpStream;
+
+#[cfg(windows)]
+pub use self::named_pipe::NamedPipeStream;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use self::uring_stream::UringStream;
 
 #[doc(hidden)]
 pub use self::socket::FromStream;
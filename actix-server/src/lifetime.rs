@@ -0,0 +1,156 @@
+//! Idle timeout and max lifetime of accepted connections.
+use std::future::Future;
+use std::net::{Shutdown, TcpStream};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_rt::time::{delay_for, Delay, Instant};
+use futures::future::LocalBoxFuture;
+use log::trace;
+
+/// Time given to service to finish connection after read half of its
+/// stream is shut down.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connection limits of listener
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Limits {
+    pub(crate) idle: Option<Duration>,
+    pub(crate) lifetime: Option<Duration>,
+}
+
+impl Limits {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.idle.is_none() && self.lifetime.is_none()
+    }
+}
+
+/// Service future of connection that is closed once one of limits is
+/// exceeded.
+///
+/// Connection is idle while no data is sent or received on its socket.
+/// Once limit is exceeded read half of the socket is shut down, so that
+/// service reads end of stream and could finish connection. Service future
+/// is dropped if it is not finished in `CLOSE_TIMEOUT`.
+pub(crate) struct Limited {
+    fut: LocalBoxFuture<'static, ()>,
+    /// Handle of connection socket
+    sock: Option<TcpStream>,
+    idle: Option<(Duration, Delay)>,
+    lifetime: Option<Delay>,
+    closing: Option<Delay>,
+}
+
+impl Limited {
+    pub(crate) fn new(
+        fut: LocalBoxFuture<'static, ()>,
+        sock: Option<TcpStream>,
+        limits: Limits,
+    ) -> Self {
+        Limited {
+            fut,
+            sock,
+            idle: limits.idle.map(|dur| (dur, delay_for(dur))),
+            lifetime: limits.lifetime.map(delay_for),
+            closing: None,
+        }
+    }
+
+    /// Shut down read half of the socket and wait for service to finish.
+    fn close(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.sock {
+            Some(ref sock) if sock.shutdown(Shutdown::Read).is_ok() => {
+                let mut delay = delay_for(CLOSE_TIMEOUT);
+                let _ = Pin::new(&mut delay).poll(cx);
+                self.closing = Some(delay);
+                Poll::Pending
+            }
+            _ => Poll::Ready(()),
+        }
+    }
+}
+
+impl Future for Limited {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.fut.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(());
+        }
+
+        if let Some(ref mut delay) = self.closing {
+            if Pin::new(delay).poll(cx).is_ready() {
+                trace!("Connection is not closed by service, dropping");
+                return Poll::Ready(());
+            }
+            return Poll::Pending;
+        }
+
+        if let Some(ref mut delay) = self.lifetime {
+            if Pin::new(delay).poll(cx).is_ready() {
+                trace!("Connection lifetime is exceeded, closing");
+                return self.close(cx);
+            }
+        }
+
+        let this = &mut *self;
+        if let Some((dur, ref mut delay)) = this.idle {
+            let elapsed = Pin::new(&mut *delay).poll(cx).is_ready();
+            match this.sock {
+                // socket activity is known on linux only
+                Some(ref sock) if cfg!(target_os = "linux") => {
+                    if elapsed {
+                        match idle_time(sock) {
+                            Some(idle) if idle < dur => {
+                                // check again once socket may be idle
+                                delay.reset(Instant::now() + (dur - idle));
+                                let _ = Pin::new(delay).poll(cx);
+                            }
+                            _ => {
+                                trace!("Connection is idle for {:?}, closing", dur);
+                                return this.close(cx);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    if elapsed {
+                        trace!("Connection is idle for {:?}, closing", dur);
+                        return this.close(cx);
+                    }
+                    // service is woken up, so connection is active
+                    delay.reset(Instant::now() + dur);
+                    let _ = Pin::new(delay).poll(cx);
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// Time since last data is sent or received on the socket, `None` if it is
+/// not known.
+fn idle_time(_sock: &TcpStream) -> Option<Duration> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+        let res = unsafe {
+            libc::getsockopt(
+                _sock.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_INFO,
+                &mut info as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if res == 0 {
+            let ms = info.tcpi_last_data_recv.min(info.tcpi_last_data_sent);
+            return Some(Duration::from_millis(u64::from(ms)));
+        }
+    }
+    None
+}
@@ -0,0 +1,152 @@
+//! Server metrics.
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Accept thread counters
+#[derive(Default)]
+pub(crate) struct AcceptMetrics {
+    pub(crate) accepted: AtomicUsize,
+    pub(crate) errors: AtomicUsize,
+    pub(crate) paused: AtomicBool,
+}
+
+/// Handshake counters of TLS listener
+#[derive(Default)]
+pub(crate) struct HandshakeMetrics {
+    pub(crate) completed: AtomicUsize,
+    pub(crate) failed: AtomicUsize,
+}
+
+impl HandshakeMetrics {
+    pub(crate) fn completed(&self) {
+        self.completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, name: &str) -> TlsMetrics {
+        TlsMetrics {
+            name: name.to_string(),
+            completed: self.completed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl AcceptMetrics {
+    pub(crate) fn accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of server metrics
+///
+/// This is created by the `Server::metrics()` method.
+#[derive(Clone, Debug)]
+pub struct ServerMetrics {
+    pub(crate) workers: Vec<WorkerMetrics>,
+    pub(crate) tls: Vec<TlsMetrics>,
+    pub(crate) accepted: usize,
+    pub(crate) accept_errors: usize,
+    pub(crate) paused: bool,
+}
+
+impl ServerMetrics {
+    /// Metrics of running workers
+    pub fn workers(&self) -> &[WorkerMetrics] {
+        &self.workers
+    }
+
+    /// Handshake metrics of TLS listeners
+    pub fn tls(&self) -> &[TlsMetrics] {
+        &self.tls
+    }
+
+    /// Number of active connections on all workers
+    pub fn connections(&self) -> usize {
+        self.workers.iter().map(|w| w.connections).sum()
+    }
+
+    /// Total number of accepted connections
+    pub fn accepted(&self) -> usize {
+        self.accepted
+    }
+
+    /// Total number of failed accept calls
+    pub fn accept_errors(&self) -> usize {
+        self.accept_errors
+    }
+
+    /// Returns `true` if accepting connections is paused with
+    /// `Server::pause()`.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+/// Snapshot of worker metrics
+#[derive(Clone, Copy, Debug)]
+pub struct WorkerMetrics {
+    pub(crate) idx: usize,
+    pub(crate) connections: usize,
+    pub(crate) queued: usize,
+    pub(crate) available: bool,
+}
+
+impl WorkerMetrics {
+    /// Worker index
+    pub fn idx(&self) -> usize {
+        self.idx
+    }
+
+    /// Number of active connections
+    pub fn connections(&self) -> usize {
+        self.connections
+    }
+
+    /// Number of accepted connections waiting for worker to start processing
+    /// them
+    pub fn queued(&self) -> usize {
+        self.queued
+    }
+
+    /// Returns `true` if worker accepts new connections, i.e. it is not over
+    /// max connections limit.
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+}
+
+/// Snapshot of handshake metrics of TLS listener
+#[derive(Clone, Debug)]
+pub struct TlsMetrics {
+    pub(crate) name: String,
+    pub(crate) completed: usize,
+    pub(crate) failed: usize,
+}
+
+impl TlsMetrics {
+    /// Listener name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of completed handshakes
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Number of failed handshakes
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+}
@@ -0,0 +1,514 @@
+//! Windows named pipe listeners.
+//!
+//! Named pipes have no accept, every pipe instance is created and then
+//! connected to a single client. Listener keeps several instances waiting
+//! for clients and connected instance is passed to worker like accepted
+//! socket. Overlapped I/O of instances is completed by thread pool of the
+//! process, so pipe handle is not bound to completion port of accept thread
+//! and worker can use it, completion callbacks wake accept thread or task
+//! of the stream.
+use std::cell::{RefCell, UnsafeCell};
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+use std::{cmp, fmt, io, mem, ptr};
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use futures::ready;
+use log::{error, trace};
+use mio::{Evented, PollOpt, Ready};
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::minwindef::{BOOL, DWORD, ULONG};
+use winapi::shared::winerror::{
+    ERROR_BROKEN_PIPE, ERROR_IO_PENDING, ERROR_NO_DATA, ERROR_PIPE_CONNECTED,
+    ERROR_PIPE_NOT_CONNECTED, NO_ERROR,
+};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::fileapi::{ReadFile, WriteFile};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::ioapiset::CancelIoEx;
+use winapi::um::minwinbase::OVERLAPPED;
+use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+use winapi::um::threadpoolapiset::{
+    CancelThreadpoolIo, CloseThreadpoolIo, CreateThreadpoolIo, StartThreadpoolIo,
+    WaitForThreadpoolIoCallbacks,
+};
+use winapi::um::winbase::{
+    FILE_FLAG_FIRST_PIPE_INSTANCE, FILE_FLAG_OVERLAPPED, PIPE_ACCESS_DUPLEX,
+    PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES,
+    PIPE_WAIT,
+};
+use winapi::um::winnt::{HANDLE, PTP_CALLBACK_INSTANCE, PTP_IO, PVOID};
+
+use crate::socket::{FromStream, StdStream};
+
+/// Size of read buffer of stream and max size of single write
+const BUF_SIZE: usize = 16 * 1024;
+
+/// Number of pipe instances waiting for clients
+const LISTENING: usize = 4;
+
+/// Overlapped operation of pipe instance
+#[repr(C)]
+struct Op {
+    /// Passed to the system, completion callback casts it back to `Op`
+    overlapped: UnsafeCell<OVERLAPPED>,
+    state: Mutex<OpState>,
+}
+
+#[derive(Default)]
+struct OpState {
+    /// Result of completed operation
+    result: Option<io::Result<usize>>,
+    waker: Option<Waker>,
+    /// Readiness of listener, it is set once client connects
+    ready: Option<mio::SetReadiness>,
+}
+
+impl Op {
+    fn new() -> Box<Op> {
+        Box::new(Op {
+            overlapped: UnsafeCell::new(unsafe { mem::zeroed() }),
+            state: Mutex::new(OpState::default()),
+        })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, OpState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(err) => err.into_inner(),
+        }
+    }
+
+    /// Take result of operation, task is woken once it completes
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut state = self.state();
+        match state.result.take() {
+            Some(res) => Poll::Ready(res),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Thread pool callback of completed operation
+unsafe extern "system" fn complete(
+    _: PTP_CALLBACK_INSTANCE,
+    _: PVOID,
+    overlapped: PVOID,
+    result: ULONG,
+    bytes: ULONG_PTR,
+    _: PTP_IO,
+) {
+    let op = &*(overlapped as *const Op);
+    let mut state = op.state();
+    state.result = Some(if result == NO_ERROR {
+        Ok(bytes as usize)
+    } else {
+        Err(io::Error::from_raw_os_error(result as i32))
+    });
+    if let Some(ref ready) = state.ready {
+        let _ = ready.set_readiness(Ready::readable());
+    }
+    let waker = state.waker.take();
+    drop(state);
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Pipe instance with thread pool I/O object
+struct Pipe {
+    handle: HANDLE,
+    io: PTP_IO,
+    /// Connect and read operation
+    read: Box<Op>,
+    write: Box<Op>,
+    reading: bool,
+    writing: bool,
+    rbuf: Vec<u8>,
+    wbuf: Vec<u8>,
+}
+
+// handle and operations are used by owner only, callbacks of thread pool
+// access operation state under lock
+unsafe impl Send for Pipe {}
+
+impl Pipe {
+    fn create(name: &[u16], first: bool) -> io::Result<Pipe> {
+        let mut mode = PIPE_ACCESS_DUPLEX | FILE_FLAG_OVERLAPPED;
+        if first {
+            mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+        }
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUF_SIZE as DWORD,
+                BUF_SIZE as DWORD,
+                0,
+                ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let io = unsafe {
+            CreateThreadpoolIo(handle, Some(complete), ptr::null_mut(), ptr::null_mut())
+        };
+        if io.is_null() {
+            let err = io::Error::last_os_error();
+            unsafe { CloseHandle(handle) };
+            return Err(err);
+        }
+
+        Ok(Pipe {
+            handle,
+            io,
+            read: Op::new(),
+            write: Op::new(),
+            reading: false,
+            writing: false,
+            rbuf: vec![0; BUF_SIZE],
+            wbuf: Vec::with_capacity(BUF_SIZE),
+        })
+    }
+
+    /// Start overlapped operation, thread pool callback is called once it
+    /// completes unless it fails right away.
+    unsafe fn start<F>(io: PTP_IO, op: &Op, f: F) -> io::Result<()>
+    where
+        F: FnOnce(*mut OVERLAPPED) -> BOOL,
+    {
+        let overlapped = op.overlapped.get();
+        *overlapped = mem::zeroed();
+        StartThreadpoolIo(io);
+        if f(overlapped) == 0 {
+            let err = GetLastError();
+            if err != ERROR_IO_PENDING {
+                CancelThreadpoolIo(io);
+                return Err(io::Error::from_raw_os_error(err as i32));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for client, listener readiness is set once it connects
+    fn connect(&mut self, ready: &mio::SetReadiness) -> io::Result<()> {
+        self.read.state().ready = Some(ready.clone());
+
+        let handle = self.handle;
+        match unsafe { Pipe::start(self.io, &self.read, |ov| ConnectNamedPipe(handle, ov)) } {
+            Ok(_) => {
+                self.reading = true;
+                Ok(())
+            }
+            // client connected before instance started waiting
+            Err(ref e) if e.raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32) => {
+                self.read.state().result = Some(Ok(0));
+                let _ = ready.set_readiness(Ready::readable());
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Result of connect, `None` if client is not connected yet
+    fn connected(&mut self) -> Option<io::Result<()>> {
+        let res = {
+            let mut state = self.read.state();
+            let res = state.result.take()?;
+            state.ready = None;
+            res
+        };
+        self.reading = false;
+        Some(res.map(|_| ()))
+    }
+
+    fn start_read(&mut self) -> io::Result<()> {
+        let handle = self.handle;
+        let buf = self.rbuf.as_mut_ptr();
+        unsafe {
+            Pipe::start(self.io, &self.read, |ov| {
+                ReadFile(handle, buf as _, BUF_SIZE as DWORD, ptr::null_mut(), ov)
+            })?
+        };
+        self.reading = true;
+        Ok(())
+    }
+
+    fn start_write(&mut self, pos: usize) -> io::Result<()> {
+        let handle = self.handle;
+        let buf = &self.wbuf[pos..];
+        let (data, len) = (buf.as_ptr(), buf.len());
+        unsafe {
+            Pipe::start(self.io, &self.write, |ov| {
+                WriteFile(handle, data as _, len as DWORD, ptr::null_mut(), ov)
+            })?
+        };
+        self.writing = true;
+        Ok(())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe {
+            // operations and buffers are used until callbacks of cancelled
+            // operations are done
+            if self.reading || self.writing {
+                CancelIoEx(self.handle, ptr::null_mut());
+            }
+            WaitForThreadpoolIoCallbacks(self.io, 0);
+            CloseThreadpoolIo(self.io);
+            CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Client is gone, read returns end of stream
+fn is_closed(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(code) => {
+            code == ERROR_BROKEN_PIPE as i32
+                || code == ERROR_PIPE_NOT_CONNECTED as i32
+                || code == ERROR_NO_DATA as i32
+        }
+        None => false,
+    }
+}
+
+/// Listener of named pipe, instances waiting for clients are replaced with
+/// new ones once clients connect
+pub(crate) struct PipeListener {
+    name: String,
+    wide: Vec<u16>,
+    pipes: RefCell<Vec<Pipe>>,
+    registration: mio::Registration,
+    ready: mio::SetReadiness,
+}
+
+impl PipeListener {
+    /// Create instances of pipe, it fails if pipe with the name exists
+    pub(crate) fn bind(name: &str) -> io::Result<PipeListener> {
+        let wide: Vec<u16> = OsStr::new(name).encode_wide().chain(Some(0)).collect();
+        let (registration, ready) = mio::Registration::new2();
+
+        let mut pipes = Vec::with_capacity(LISTENING);
+        for idx in 0..LISTENING {
+            let mut pipe = Pipe::create(&wide, idx == 0)?;
+            pipe.connect(&ready)?;
+            pipes.push(pipe);
+        }
+
+        Ok(PipeListener {
+            name: name.to_string(),
+            wide,
+            pipes: RefCell::new(pipes),
+            registration,
+            ready,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn listen(&self) -> io::Result<Pipe> {
+        let mut pipe = Pipe::create(&self.wide, false)?;
+        pipe.connect(&self.ready)?;
+        Ok(pipe)
+    }
+
+    /// Take connected instance, `None` if no client is connected
+    pub(crate) fn accept(&self) -> io::Result<Option<NamedPipeStream>> {
+        let mut pipes = self.pipes.borrow_mut();
+
+        // readiness is cleared before instances are checked, client that
+        // connects meanwhile sets it again
+        let _ = self.ready.set_readiness(Ready::empty());
+
+        while pipes.len() < LISTENING {
+            match self.listen() {
+                Ok(pipe) => pipes.push(pipe),
+                Err(e) => {
+                    error!("Can not create instance of named pipe {}: {}", self.name, e);
+                    break;
+                }
+            }
+        }
+
+        let mut idx = 0;
+        while idx < pipes.len() {
+            let res = match pipes[idx].connected() {
+                Some(res) => res,
+                None => {
+                    idx += 1;
+                    continue;
+                }
+            };
+            let pipe = match self.listen() {
+                Ok(new) => mem::replace(&mut pipes[idx], new),
+                Err(e) => {
+                    error!("Can not create instance of named pipe {}: {}", self.name, e);
+                    pipes.swap_remove(idx)
+                }
+            };
+            match res {
+                Ok(_) => return Ok(Some(NamedPipeStream::new(pipe))),
+                Err(e) => trace!("Client of named pipe {} is gone: {}", self.name, e),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Evented for PipeListener {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.registration.register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.registration.reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        self.registration.deregister(poll)
+    }
+}
+
+/// Connected named pipe, stream of `bind_named_pipe()` listeners.
+///
+/// Writes are completed in background, error of write is returned by next
+/// write or flush. Pipe has no half close, shutdown flushes the stream and
+/// client gets end of stream once it is dropped.
+pub struct NamedPipeStream {
+    pipe: Pipe,
+    /// Unread part of read buffer
+    pos: usize,
+    end: usize,
+    /// Written part of write buffer
+    written: usize,
+}
+
+impl NamedPipeStream {
+    fn new(pipe: Pipe) -> Self {
+        NamedPipeStream {
+            pipe,
+            pos: 0,
+            end: 0,
+            written: 0,
+        }
+    }
+
+    /// Wait for write in flight
+    fn poll_written(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pipe.writing {
+            let res = ready!(self.pipe.write.poll(cx));
+            self.pipe.writing = false;
+            self.written += res?;
+            if self.written < self.pipe.wbuf.len() {
+                self.pipe.start_write(self.written)?;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl fmt::Debug for NamedPipeStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedPipeStream").finish()
+    }
+}
+
+impl FromStream for NamedPipeStream {
+    fn from_stdstream(sock: StdStream) -> io::Result<Self> {
+        match sock {
+            StdStream::NamedPipe(stream) => Ok(stream),
+            StdStream::Tcp(_) => panic!("Should not happen, bug in server impl"),
+        }
+    }
+}
+
+impl AsyncRead for NamedPipeStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if this.pos < this.end {
+                let n = cmp::min(buf.len(), this.end - this.pos);
+                buf[..n].copy_from_slice(&this.pipe.rbuf[this.pos..this.pos + n]);
+                this.pos += n;
+                return Poll::Ready(Ok(n));
+            }
+
+            if !this.pipe.reading {
+                match this.pipe.start_read() {
+                    Ok(_) => (),
+                    Err(ref e) if is_closed(e) => return Poll::Ready(Ok(0)),
+                    Err(e) => return Poll::Ready(Err(e)),
+                }
+            }
+            let res = ready!(this.pipe.read.poll(cx));
+            this.pipe.reading = false;
+            match res {
+                Ok(n) => {
+                    this.pos = 0;
+                    this.end = n;
+                }
+                Err(ref e) if is_closed(e) => return Poll::Ready(Ok(0)),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
+
+impl AsyncWrite for NamedPipeStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_written(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = cmp::min(buf.len(), BUF_SIZE);
+        this.pipe.wbuf.clear();
+        this.pipe.wbuf.extend_from_slice(&buf[..n]);
+        this.written = 0;
+        this.pipe.start_write(0)?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_written(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_written(cx)
+    }
+}
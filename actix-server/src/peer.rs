@@ -0,0 +1,84 @@
+//! Concurrent connections limit of peer addresses.
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+
+/// Number of recent offenders that are remembered by listener
+const OFFENDERS: usize = 128;
+
+type Peers = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+/// Concurrent connections of peers of listener.
+///
+/// Connections are counted by accept loop and released by workers once
+/// connection is closed.
+pub(crate) struct PeerLimit {
+    max: usize,
+    peers: Peers,
+    /// Peers that exceeded limit recently, most recent first
+    offenders: VecDeque<IpAddr>,
+}
+
+impl PeerLimit {
+    pub(crate) fn new(max: usize) -> Self {
+        PeerLimit {
+            max: std::cmp::max(max, 1),
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            offenders: VecDeque::with_capacity(OFFENDERS),
+        }
+    }
+
+    /// Count connection of peer, `None` if peer has too many connections.
+    pub(crate) fn acquire(&mut self, addr: IpAddr) -> Option<PeerGuard> {
+        {
+            let mut peers = self.peers.lock().unwrap();
+            let num = peers.entry(addr).or_insert(0);
+            if *num < self.max {
+                *num += 1;
+                return Some(PeerGuard {
+                    addr,
+                    peers: self.peers.clone(),
+                });
+            }
+        }
+        self.offended(addr);
+        None
+    }
+
+    /// Remember offender, warning is logged only for new offenders
+    fn offended(&mut self, addr: IpAddr) {
+        if let Some(pos) = self.offenders.iter().position(|ip| *ip == addr) {
+            self.offenders.remove(pos);
+        } else {
+            warn!(
+                "Peer {} exceeded limit of {} connections, closing connections",
+                addr, self.max
+            );
+            if self.offenders.len() == OFFENDERS {
+                self.offenders.pop_back();
+            }
+        }
+        self.offenders.push_front(addr);
+    }
+}
+
+/// Connection of peer, released on drop
+#[derive(Debug)]
+pub(crate) struct PeerGuard {
+    addr: IpAddr,
+    peers: Peers,
+}
+
+impl Drop for PeerGuard {
+    fn drop(&mut self) {
+        let mut peers = self.peers.lock().unwrap();
+        if let Some(num) = peers.get_mut(&self.addr) {
+            *num -= 1;
+            if *num == 0 {
+                peers.remove(&self.addr);
+            }
+        }
+    }
+}
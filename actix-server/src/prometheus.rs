@@ -0,0 +1,158 @@
+//! Server metrics in Prometheus text format.
+use std::fmt::Write;
+use std::pin::Pin;
+use std::time::Duration;
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_rt::net::TcpStream;
+use actix_rt::time::timeout;
+use actix_service::{fn_service, ServiceFactory as ActixServiceFactory};
+use futures::future::poll_fn;
+
+use crate::metrics::{ServerMetrics, TlsMetrics, WorkerMetrics};
+use crate::server::Server;
+
+/// Time given to client to send request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Max size of request head
+const MAX_REQUEST: usize = 8192;
+
+impl ServerMetrics {
+    /// Render metrics in Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, values: &[(String, usize)]| {
+            let _ = writeln!(out, "# HELP actix_server_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE actix_server_{} {}", name, kind);
+            for (labels, value) in values {
+                let _ = writeln!(out, "actix_server_{}{} {}", name, labels, value);
+            }
+        };
+        let workers = |value: fn(&WorkerMetrics) -> usize| {
+            self.workers
+                .iter()
+                .map(|w| (format!("{{worker=\"{}\"}}", w.idx), value(w)))
+                .collect::<Vec<_>>()
+        };
+        let listeners = |value: fn(&TlsMetrics) -> usize| {
+            self.tls
+                .iter()
+                .map(|t| (format!("{{listener=\"{}\"}}", escape(&t.name)), value(t)))
+                .collect::<Vec<_>>()
+        };
+
+        metric(
+            "accepted_connections_total",
+            "counter",
+            "Total number of accepted connections.",
+            &[(String::new(), self.accepted)],
+        );
+        metric(
+            "accept_errors_total",
+            "counter",
+            "Total number of failed accept calls.",
+            &[(String::new(), self.accept_errors)],
+        );
+        metric(
+            "accept_paused",
+            "gauge",
+            "Whether accepting connections is paused.",
+            &[(String::new(), self.paused as usize)],
+        );
+        metric(
+            "workers",
+            "gauge",
+            "Number of running workers.",
+            &[(String::new(), self.workers.len())],
+        );
+        metric(
+            "connections",
+            "gauge",
+            "Number of active connections of worker.",
+            &workers(|w| w.connections),
+        );
+        metric(
+            "queued_connections",
+            "gauge",
+            "Number of accepted connections waiting for worker.",
+            &workers(|w| w.queued),
+        );
+        metric(
+            "worker_available",
+            "gauge",
+            "Whether worker accepts new connections.",
+            &workers(|w| w.available as usize),
+        );
+        metric(
+            "tls_handshakes_total",
+            "counter",
+            "Total number of completed TLS handshakes.",
+            &listeners(|t| t.completed),
+        );
+        metric(
+            "tls_handshake_errors_total",
+            "counter",
+            "Total number of failed TLS handshakes.",
+            &listeners(|t| t.failed),
+        );
+        out
+    }
+}
+
+/// Escape label value
+fn escape(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Factory of service that responds to `GET /metrics` with metrics of
+/// the server.
+pub(crate) fn factory(
+    server: Server,
+) -> impl ActixServiceFactory<Config = (), Request = TcpStream> + Clone + Send {
+    fn_service(move |io: TcpStream| {
+        let server = server.clone();
+        async move {
+            let _ = timeout(REQUEST_TIMEOUT, respond(io, server)).await;
+            Ok::<_, ()>(())
+        }
+    })
+}
+
+async fn respond(mut io: TcpStream, server: Server) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buf.len() >= MAX_REQUEST {
+            return Ok(());
+        }
+        let mut chunk = [0; 1024];
+        let n = poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut chunk)).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let line = buf.split(|b| *b == b'\r').next().unwrap_or(&[]);
+    let mut parts = line.split(|b| *b == b' ');
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some(b"GET"), Some(b"/metrics")) => ("200 OK", server.metrics().await.to_prometheus()),
+        (Some(b"GET"), _) => ("404 Not Found", String::new()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    let res = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    let mut data = res.as_bytes();
+    while !data.is_empty() {
+        let n = poll_fn(|cx| Pin::new(&mut io).poll_write(cx, data)).await?;
+        data = &data[n..];
+    }
+    poll_fn(|cx| Pin::new(&mut io).poll_shutdown(cx)).await
+}
@@ -0,0 +1,378 @@
+//! PROXY protocol support.
+//!
+//! Load balancers that work on transport level pass real client address to
+//! the backend via PROXY protocol header, sent before any application data.
+//! Both text (v1) and binary (v2) versions of the header are supported.
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::{cmp, io, str};
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_rt::time::timeout;
+use actix_service::{Service, ServiceFactory as ActixServiceFactory};
+use futures::future::{poll_fn, LocalBoxFuture};
+use futures::{FutureExt, TryFutureExt};
+use log::trace;
+
+use crate::service::{
+    BoxedServerService, InternalServiceFactory, ServiceFactory, StreamService,
+};
+use crate::socket::FromStream;
+use crate::Token;
+
+/// Time given to peer to send PROXY protocol header
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Max length of v1 header, including `CRLF`
+const V1_MAX_LEN: usize = 107;
+const V1_SIGNATURE: &[u8] = b"PROXY ";
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Connection info passed in PROXY protocol header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProxyInfo {
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+}
+
+impl ProxyInfo {
+    /// Address of the client.
+    ///
+    /// Returns `None` if proxy did not pass addresses, i.e. for health
+    /// checks made by proxy itself.
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// Address client connected to.
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+}
+
+/// Stream with parsed PROXY protocol header.
+///
+/// Data read after the header is buffered and returned first, so service
+/// reads from the stream as usual.
+pub struct ProxyStream<T> {
+    io: T,
+    info: ProxyInfo,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<T> ProxyStream<T> {
+    /// Connection info passed by proxy.
+    pub fn info(&self) -> &ProxyInfo {
+        &self.info
+    }
+
+    /// Returns a shared reference to the underlying stream.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+}
+
+impl<T: AsyncRead + Unpin> ProxyStream<T> {
+    /// Read PROXY protocol header from the stream.
+    pub(crate) async fn accept(mut io: T) -> io::Result<Self> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 256];
+
+        loop {
+            let n = poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut chunk)).await?;
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Connection closed before PROXY protocol header",
+                ));
+            }
+            buf.extend_from_slice(&chunk[..n]);
+
+            if let Some((info, len)) = parse_header(&buf)? {
+                return Ok(ProxyStream {
+                    io,
+                    info,
+                    buf,
+                    pos: len,
+                });
+            }
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ProxyStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.pos < this.buf.len() {
+            let n = cmp::min(buf.len(), this.buf.len() - this.pos);
+            buf[..n].copy_from_slice(&this.buf[this.pos..this.pos + n]);
+            this.pos += n;
+            if this.pos == this.buf.len() {
+                this.buf = Vec::new();
+                this.pos = 0;
+            }
+            return Poll::Ready(Ok(n));
+        }
+        Pin::new(&mut this.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ProxyStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parse PROXY protocol header, returns `None` if more data is needed.
+fn parse_header(buf: &[u8]) -> io::Result<Option<(ProxyInfo, usize)>> {
+    let len = cmp::min(buf.len(), V2_SIGNATURE.len());
+    if buf[..len] == V2_SIGNATURE[..len] {
+        if len < V2_SIGNATURE.len() {
+            return Ok(None);
+        }
+        return parse_v2(buf);
+    }
+
+    let len = cmp::min(buf.len(), V1_SIGNATURE.len());
+    if buf[..len] == V1_SIGNATURE[..len] {
+        if len < V1_SIGNATURE.len() {
+            return Ok(None);
+        }
+        return parse_v1(buf);
+    }
+
+    Err(invalid("Invalid PROXY protocol signature"))
+}
+
+fn parse_v1(buf: &[u8]) -> io::Result<Option<(ProxyInfo, usize)>> {
+    let end = match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(end) if end + 2 <= V1_MAX_LEN => end,
+        Some(_) => return Err(invalid("PROXY protocol header is too long")),
+        None if buf.len() >= V1_MAX_LEN => {
+            return Err(invalid("PROXY protocol header is too long"))
+        }
+        None => return Ok(None),
+    };
+    let line =
+        str::from_utf8(&buf[..end]).map_err(|_| invalid("Invalid PROXY protocol header"))?;
+
+    let parts: Vec<&str> = line.split(' ').collect();
+    let info = match parts.get(1) {
+        Some(&"UNKNOWN") => ProxyInfo {
+            source: None,
+            destination: None,
+        },
+        Some(&"TCP4") | Some(&"TCP6") if parts.len() == 6 => {
+            let addr = |ip: &str, port: &str| -> io::Result<SocketAddr> {
+                let ip = ip
+                    .parse::<IpAddr>()
+                    .map_err(|_| invalid("Invalid address in PROXY protocol header"))?;
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| invalid("Invalid port in PROXY protocol header"))?;
+                Ok(SocketAddr::new(ip, port))
+            };
+            ProxyInfo {
+                source: Some(addr(parts[2], parts[4])?),
+                destination: Some(addr(parts[3], parts[5])?),
+            }
+        }
+        _ => return Err(invalid("Invalid PROXY protocol header")),
+    };
+    Ok(Some((info, end + 2)))
+}
+
+fn parse_v2(buf: &[u8]) -> io::Result<Option<(ProxyInfo, usize)>> {
+    if buf.len() < 16 {
+        return Ok(None);
+    }
+    let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < len {
+        return Ok(None);
+    }
+    if buf[12] >> 4 != 2 {
+        return Err(invalid("Unsupported PROXY protocol version"));
+    }
+
+    let addrs = &buf[16..len];
+    let info = match (buf[12] & 0x0f, buf[13] >> 4) {
+        // LOCAL command, connection is made by proxy itself
+        (0, _) => ProxyInfo {
+            source: None,
+            destination: None,
+        },
+        (1, 1) if addrs.len() >= 12 => {
+            let ip = |b: &[u8]| IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3]));
+            let port = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]);
+            ProxyInfo {
+                source: Some(SocketAddr::new(ip(&addrs[0..4]), port(&addrs[8..10]))),
+                destination: Some(SocketAddr::new(ip(&addrs[4..8]), port(&addrs[10..12]))),
+            }
+        }
+        (1, 2) if addrs.len() >= 36 => {
+            let ip = |b: &[u8]| {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(b);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            let port = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]);
+            ProxyInfo {
+                source: Some(SocketAddr::new(ip(&addrs[0..16]), port(&addrs[32..34]))),
+                destination: Some(SocketAddr::new(ip(&addrs[16..32]), port(&addrs[34..36]))),
+            }
+        }
+        // unix and unspecified families do not carry ip addresses
+        (1, 0) | (1, 3) => ProxyInfo {
+            source: None,
+            destination: None,
+        },
+        _ => return Err(invalid("Invalid PROXY protocol header")),
+    };
+    Ok(Some((info, len)))
+}
+
+/// Service that reads PROXY protocol header before passing stream to the
+/// inner service.
+pub(crate) struct ProxyService<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S> ProxyService<S> {
+    pub(crate) fn new(service: S) -> Self {
+        ProxyService {
+            service: Rc::new(RefCell::new(service)),
+        }
+    }
+}
+
+impl<S, T> Service for ProxyService<S>
+where
+    S: Service<Request = ProxyStream<T>> + 'static,
+    T: AsyncRead + Unpin + 'static,
+{
+    type Request = T;
+    type Response = ();
+    type Error = ();
+    type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx).map_err(|_| ())
+    }
+
+    fn call(&mut self, io: T) -> Self::Future {
+        let service = self.service.clone();
+
+        async move {
+            let stream = match timeout(HEADER_TIMEOUT, ProxyStream::accept(io)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(e)) => {
+                    trace!("Can not read PROXY protocol header: {}", e);
+                    return Err(());
+                }
+                Err(_) => {
+                    trace!("PROXY protocol header timeout");
+                    return Err(());
+                }
+            };
+
+            // stream could wait for header for a while
+            poll_fn(|cx| service.borrow_mut().poll_ready(cx))
+                .await
+                .map_err(|_| ())?;
+            let fut = service.borrow_mut().call(stream);
+            fut.await.map(|_| ()).map_err(|_| ())
+        }
+        .boxed_local()
+    }
+}
+
+pub(crate) struct ProxyNewService<F: ServiceFactory<ProxyStream<Io>>, Io> {
+    name: String,
+    inner: F,
+    token: Token,
+    _t: PhantomData<Io>,
+}
+
+impl<F, Io> ProxyNewService<F, Io>
+where
+    F: ServiceFactory<ProxyStream<Io>>,
+    Io: FromStream + Unpin + Send + 'static,
+{
+    pub(crate) fn create(
+        name: String,
+        token: Token,
+        inner: F,
+    ) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name,
+            token,
+            inner,
+            _t: PhantomData,
+        })
+    }
+}
+
+impl<F, Io> InternalServiceFactory for ProxyNewService<F, Io>
+where
+    F: ServiceFactory<ProxyStream<Io>>,
+    Io: FromStream + Unpin + Send + 'static,
+{
+    fn name(&self, _: Token) -> &str {
+        &self.name
+    }
+
+    fn clone_factory(&self) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+            token: self.token,
+            _t: PhantomData,
+        })
+    }
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+        let token = self.token;
+        self.inner
+            .create()
+            .new_service(())
+            .map_err(|_| ())
+            .map_ok(move |inner| {
+                let service: BoxedServerService =
+                    Box::new(StreamService::new(ProxyService::new(inner)));
+                vec![(token, service)]
+            })
+            .boxed_local()
+    }
+}
@@ -0,0 +1,64 @@
+//! Accept rate limiting of listeners.
+use std::time::{Duration, Instant};
+
+/// Behavior of listener once its accept rate is exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitPolicy {
+    /// Stop accepting until connection is allowed, pending connections wait
+    /// in listen backlog
+    #[default]
+    Delay,
+    /// Accept and immediately close connections
+    Close,
+}
+
+/// Token bucket of listener, refilled with `rate` tokens per second up to
+/// `burst` tokens.
+pub(crate) struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+    pub(crate) policy: RateLimitPolicy,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(rate: u32, burst: u32, policy: RateLimitPolicy) -> Self {
+        let burst = f64::from(std::cmp::max(burst, 1));
+        RateLimiter {
+            rate: f64::from(std::cmp::max(rate, 1)),
+            burst,
+            tokens: burst,
+            last: Instant::now(),
+            policy,
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last = now;
+    }
+
+    /// Take token for accepted connection, `false` if rate is exceeded.
+    pub(crate) fn acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Delay until next connection is allowed, `None` if it is allowed now.
+    pub(crate) fn wait(&mut self) -> Option<Duration> {
+        self.refill();
+        if self.tokens >= 1.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
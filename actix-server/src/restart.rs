@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+/// Worker restart policy
+///
+/// Policy is used when worker can not start its services. Worker is
+/// restarted after backoff delay, that doubles with every consecutive
+/// failure. Once number of consecutive failures exceeds max attempts, server
+/// stops and `Server` future resolves with an error.
+///
+/// By default workers are restarted without attempts limit, backoff delay
+/// starts at 100 milliseconds and is capped at 10 seconds.
+#[derive(Clone, Debug)]
+pub struct RestartPolicy {
+    max_attempts: Option<usize>,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_attempts: None,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Create default policy.
+    pub fn new() -> Self {
+        RestartPolicy::default()
+    }
+
+    /// Stop server after number of consecutive failed restarts.
+    pub fn max_attempts(mut self, num: usize) -> Self {
+        self.max_attempts = Some(num);
+        self
+    }
+
+    /// Set initial and maximum backoff delay.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Delay before restart after number of consecutive failures, `None` if
+    /// worker should not be restarted.
+    pub(crate) fn delay(&self, failures: usize) -> Option<Duration> {
+        if let Some(max) = self.max_attempts {
+            if failures > max {
+                return None;
+            }
+        }
+        let mut delay = self.backoff;
+        for _ in 1..failures {
+            if delay >= self.max_backoff {
+                break;
+            }
+            delay *= 2;
+        }
+        Some(std::cmp::min(delay, self.max_backoff))
+    }
+}
@@ -0,0 +1,113 @@
+//! Steering programs of `SO_REUSEPORT` groups.
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// Instruction of classic BPF program, see `struct sock_filter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BpfInstruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+impl BpfInstruction {
+    pub fn new(code: u16, jt: u8, jf: u8, k: u32) -> Self {
+        BpfInstruction { code, jt, jf, k }
+    }
+}
+
+/// Program that selects socket of `SO_REUSEPORT` group for incoming
+/// connection.
+///
+/// Program returns index of socket in group, sockets are indexed in order
+/// they are bound. Kernel falls back to default selection if index is out
+/// of range.
+///
+/// Listener of `ServerBuilder::bind_reuseport_with()` binds socket for
+/// every worker, so program with `workers` sockets selects worker.
+#[derive(Debug, Clone)]
+pub enum ReuseportProgram {
+    /// Classic BPF program, attached with `SO_ATTACH_REUSEPORT_CBPF`
+    Cbpf(Vec<BpfInstruction>),
+    /// File descriptor of loaded eBPF program of
+    /// `BPF_PROG_TYPE_SOCKET_FILTER` type, attached with
+    /// `SO_ATTACH_REUSEPORT_EBPF`
+    Ebpf(RawFd),
+}
+
+impl ReuseportProgram {
+    /// Select socket by flow hash of connection, so connections of the same
+    /// 4-tuple are passed to the same socket of group with `sockets` sockets.
+    pub fn hash(sockets: u32) -> Self {
+        ReuseportProgram::ancillary(libc::SKF_AD_RXHASH, sockets)
+    }
+
+    /// Select socket by CPU core that received connection.
+    pub fn cpu(sockets: u32) -> Self {
+        ReuseportProgram::ancillary(libc::SKF_AD_CPU, sockets)
+    }
+
+    /// `A = ancillary data; A %= sockets; return A`
+    fn ancillary(data: i32, sockets: u32) -> Self {
+        ReuseportProgram::Cbpf(vec![
+            BpfInstruction::new(
+                (libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as u16,
+                0,
+                0,
+                (libc::SKF_AD_OFF + data) as u32,
+            ),
+            BpfInstruction::new(
+                (libc::BPF_ALU | libc::BPF_MOD | libc::BPF_K) as u16,
+                0,
+                0,
+                std::cmp::max(sockets, 1),
+            ),
+            BpfInstruction::new((libc::BPF_RET | libc::BPF_A) as u16, 0, 0, 0),
+        ])
+    }
+
+    /// Attach program to group of bound socket
+    pub(crate) fn attach<T: AsRawFd>(&self, sock: &T) -> io::Result<()> {
+        let res = match self {
+            ReuseportProgram::Cbpf(ref program) => {
+                let mut filter: Vec<libc::sock_filter> = program
+                    .iter()
+                    .map(|ins| libc::sock_filter {
+                        code: ins.code,
+                        jt: ins.jt,
+                        jf: ins.jf,
+                        k: ins.k,
+                    })
+                    .collect();
+                let prog = libc::sock_fprog {
+                    len: filter.len() as libc::c_ushort,
+                    filter: filter.as_mut_ptr(),
+                };
+                unsafe {
+                    libc::setsockopt(
+                        sock.as_raw_fd(),
+                        libc::SOL_SOCKET,
+                        libc::SO_ATTACH_REUSEPORT_CBPF,
+                        &prog as *const _ as *const libc::c_void,
+                        std::mem::size_of::<libc::sock_fprog>() as libc::socklen_t,
+                    )
+                }
+            }
+            ReuseportProgram::Ebpf(fd) => unsafe {
+                libc::setsockopt(
+                    sock.as_raw_fd(),
+                    libc::SOL_SOCKET,
+                    libc::SO_ATTACH_REUSEPORT_EBPF,
+                    fd as *const RawFd as *const libc::c_void,
+                    std::mem::size_of::<RawFd>() as libc::socklen_t,
+                )
+            },
+        };
+        if res != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
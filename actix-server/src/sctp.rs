@@ -0,0 +1,164 @@
+//! SCTP one-to-one style listeners.
+//!
+//! One-to-one style SCTP socket is accepted like tcp socket, each accepted
+//! socket is an association with single peer. Messages of all SCTP streams
+//! of association are delivered as a byte stream.
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{io, mem};
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use actix_rt::net::TcpStream;
+
+use crate::socket::{FromStream, StdStream};
+
+/// Association of SCTP one-to-one style socket.
+#[derive(Debug)]
+pub struct SctpStream {
+    io: TcpStream,
+}
+
+impl SctpStream {
+    /// Local address of association.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.local_addr()
+    }
+
+    /// Primary address of peer of association.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.peer_addr()
+    }
+
+    /// Send messages without delay, disables Nagle-like algorithm of SCTP.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        setsockopt(&self.io, libc::IPPROTO_SCTP, SCTP_NODELAY, nodelay as _)
+    }
+}
+
+impl FromStream for SctpStream {
+    fn from_stdstream(sock: StdStream) -> io::Result<Self> {
+        match sock {
+            StdStream::Tcp(stream) => Ok(SctpStream {
+                io: TcpStream::from_std(stream)?,
+            }),
+            StdStream::Uds(_) => panic!("Should not happen, bug in server impl"),
+        }
+    }
+}
+
+impl AsyncRead for SctpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SctpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+/// `SCTP_NODELAY` of `linux/sctp.h`, not exported by libc
+const SCTP_NODELAY: libc::c_int = 3;
+
+/// Create one-to-one style SCTP listener.
+///
+/// Listener is accepted the same way as tcp listener, so it is kept as
+/// `TcpListener`. Tcp socket options must not be applied to it.
+pub(crate) fn create_sctp_listener(addr: SocketAddr, backlog: i32) -> io::Result<TcpListener> {
+    let domain = match addr {
+        SocketAddr::V4(_) => libc::AF_INET,
+        SocketAddr::V6(_) => libc::AF_INET6,
+    };
+    let fd = unsafe {
+        libc::socket(
+            domain,
+            libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+            libc::IPPROTO_SCTP,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // listener owns socket, it is closed on error
+    let lst = unsafe { TcpListener::from_raw_fd(fd) };
+    setsockopt(&lst, libc::SOL_SOCKET, libc::SO_REUSEADDR, 1)?;
+
+    let res = match addr {
+        SocketAddr::V4(ref addr) => {
+            let mut sa: libc::sockaddr_in = unsafe { mem::zeroed() };
+            sa.sin_family = libc::AF_INET as libc::sa_family_t;
+            sa.sin_port = addr.port().to_be();
+            sa.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+            unsafe {
+                libc::bind(
+                    fd,
+                    &sa as *const _ as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+                )
+            }
+        }
+        SocketAddr::V6(ref addr) => {
+            let mut sa: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+            sa.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sa.sin6_port = addr.port().to_be();
+            sa.sin6_addr.s6_addr = addr.ip().octets();
+            sa.sin6_flowinfo = addr.flowinfo();
+            sa.sin6_scope_id = addr.scope_id();
+            unsafe {
+                libc::bind(
+                    fd,
+                    &sa as *const _ as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_in6>() as libc::socklen_t,
+                )
+            }
+        }
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { libc::listen(fd, backlog) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(lst)
+}
+
+fn setsockopt<T: AsRawFd>(
+    sock: &T,
+    level: libc::c_int,
+    opt: libc::c_int,
+    val: libc::c_int,
+) -> io::Result<()> {
+    let res = unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            level,
+            opt,
+            &val as *const _ as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if res != 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
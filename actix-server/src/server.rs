@@ -1,20 +1,55 @@
+use std::any::Any;
 use std::future::Future;
-use std::io;
+use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::{fmt, io, net};
 
 use futures::channel::mpsc::UnboundedSender;
 use futures::channel::oneshot;
 use futures::FutureExt;
 
+use actix_rt::net::TcpStream;
+
 use crate::builder::ServerBuilder;
+use crate::filter::IpFilter;
+use crate::metrics::ServerMetrics;
+use crate::service::{InternalServiceFactory, ReloadFactory, ServiceFactory, StreamNewService};
 use crate::signals::Signal;
+use crate::socket::{FromStream, StdListener};
+use crate::Token;
 
 #[derive(Debug)]
 pub(crate) enum ServerCommand {
     WorkerFaulted(usize),
+    /// Worker started its services
+    WorkerStarted(usize),
+    /// Worker can not start its services
+    WorkerFailed(usize),
+    /// Start worker in place of failed one
+    RestartWorker,
+    /// Worker is alive
+    WorkerHeartbeat(usize),
+    /// Check heartbeats of workers
+    CheckHeartbeats,
     Pause(oneshot::Sender<()>),
     Resume(oneshot::Sender<()>),
+    /// Pause accepting connections on listeners with the name
+    PauseListener {
+        name: String,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Resume accepting connections on listeners with the name
+    ResumeListener {
+        name: String,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Replace peer filter of listeners with the name
+    SetIpFilter {
+        name: String,
+        filter: Option<IpFilter>,
+        result: oneshot::Sender<io::Result<()>>,
+    },
     Signal(Signal),
     /// Whether to try and shut down gracefully
     Stop {
@@ -22,13 +57,116 @@ pub(crate) enum ServerCommand {
         completion: Option<oneshot::Sender<()>>,
     },
     /// Notify of server stop
-    Notify(oneshot::Sender<()>),
+    Notify(oneshot::Sender<io::Result<()>>),
+    /// Notify once server is fully stopped
+    Stopped(oneshot::Sender<StopReason>),
+    /// Workers of stopping server are stopped
+    WorkersStopped,
+    /// Bind new listener
+    AddListener {
+        name: String,
+        addrs: Vec<net::SocketAddr>,
+        factory: ListenerFactory,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Service factory of listener bound with `bind_async` is created
+    ListenerConfigured {
+        name: String,
+        sockets: Vec<net::TcpListener>,
+        factory: io::Result<ListenerFactory>,
+    },
+    /// Services of new listener are started on all workers
+    ListenerStarted {
+        name: String,
+        sockets: Vec<(Token, StdListener)>,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Services of new listener can not be started on some workers,
+    /// factories starting from the index are removed
+    ListenerFailed {
+        name: String,
+        factory: usize,
+        token: Token,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Collect server metrics
+    Metrics(oneshot::Sender<ServerMetrics>),
+    /// Change max number of concurrent connections per worker
+    SetMaxConnections(usize, oneshot::Sender<()>),
+    /// Change number of workers
+    SetWorkers(usize, oneshot::Sender<()>),
+    /// Replace service factory of listeners with the name
+    Reload {
+        name: String,
+        factory: Box<dyn Any + Send>,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Services of reloaded listeners are started on workers
+    Reloaded {
+        services: ReloadedServices,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Services of reloaded listeners can not be started on some workers,
+    /// workers with the ids restore old services with the indexes
+    ReloadFailed {
+        reloaded: Vec<(usize, usize)>,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Close listeners with the name
+    RemoveListener {
+        name: String,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+    /// Pass listeners to other process
+    #[cfg(unix)]
+    Handoff {
+        path: std::path::PathBuf,
+        result: oneshot::Sender<io::Result<()>>,
+    },
+}
+
+/// Creates service factory for listener added to running server
+pub(crate) struct ListenerFactory(
+    pub(crate) Box<dyn Fn(Token, net::SocketAddr) -> Box<dyn InternalServiceFactory> + Send>,
+);
+
+impl fmt::Debug for ListenerFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ListenerFactory")
+    }
+}
+
+/// Service factories of reloaded listeners with their indexes
+pub(crate) struct ReloadedServices(pub(crate) Vec<(usize, Box<dyn InternalServiceFactory>)>);
+
+impl fmt::Debug for ReloadedServices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReloadedServices")
+    }
+}
+
+/// Reason of server stop
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// Server is stopped with `Server::stop()`
+    Stop {
+        /// Whether server is stopped gracefully
+        graceful: bool,
+    },
+    /// Server is stopped by process signal
+    Signal(Signal),
+    /// Listeners are passed to new server process
+    Handoff,
+    /// Server is stopped after failure, i.e. workers can not start services
+    Failed(String),
+    /// Server is dropped without being stopped, i.e. actix system is stopped
+    Dropped,
 }
 
 #[derive(Debug)]
 pub struct Server(
     UnboundedSender<ServerCommand>,
-    Option<oneshot::Receiver<()>>,
+    Option<oneshot::Receiver<io::Result<()>>>,
 );
 
 impl Server {
@@ -49,6 +187,91 @@ impl Server {
         let _ = self.0.unbounded_send(ServerCommand::WorkerFaulted(idx));
     }
 
+    pub(crate) fn worker_started(&self, idx: usize) {
+        let _ = self.0.unbounded_send(ServerCommand::WorkerStarted(idx));
+    }
+
+    pub(crate) fn worker_failed(&self, idx: usize) {
+        let _ = self.0.unbounded_send(ServerCommand::WorkerFailed(idx));
+    }
+
+    pub(crate) fn worker_heartbeat(&self, idx: usize) {
+        let _ = self.0.unbounded_send(ServerCommand::WorkerHeartbeat(idx));
+    }
+
+    pub(crate) fn check_heartbeats(&self) {
+        let _ = self.0.unbounded_send(ServerCommand::CheckHeartbeats);
+    }
+
+    pub(crate) fn restart_worker(&self) {
+        let _ = self.0.unbounded_send(ServerCommand::RestartWorker);
+    }
+
+    pub(crate) fn workers_stopped(&self) {
+        let _ = self.0.unbounded_send(ServerCommand::WorkersStopped);
+    }
+
+    pub(crate) fn listener_configured(
+        &self,
+        name: String,
+        sockets: Vec<net::TcpListener>,
+        factory: io::Result<ListenerFactory>,
+    ) {
+        let _ = self.0.unbounded_send(ServerCommand::ListenerConfigured {
+            name,
+            sockets,
+            factory,
+        });
+    }
+
+    pub(crate) fn listener_started(
+        &self,
+        name: String,
+        sockets: Vec<(Token, StdListener)>,
+        result: oneshot::Sender<io::Result<()>>,
+    ) {
+        let _ = self.0.unbounded_send(ServerCommand::ListenerStarted {
+            name,
+            sockets,
+            result,
+        });
+    }
+
+    pub(crate) fn listener_failed(
+        &self,
+        name: String,
+        factory: usize,
+        token: Token,
+        result: oneshot::Sender<io::Result<()>>,
+    ) {
+        let _ = self.0.unbounded_send(ServerCommand::ListenerFailed {
+            name,
+            factory,
+            token,
+            result,
+        });
+    }
+
+    pub(crate) fn reloaded(
+        &self,
+        services: ReloadedServices,
+        result: oneshot::Sender<io::Result<()>>,
+    ) {
+        let _ = self
+            .0
+            .unbounded_send(ServerCommand::Reloaded { services, result });
+    }
+
+    pub(crate) fn reload_failed(
+        &self,
+        reloaded: Vec<(usize, usize)>,
+        result: oneshot::Sender<io::Result<()>>,
+    ) {
+        let _ = self
+            .0
+            .unbounded_send(ServerCommand::ReloadFailed { reloaded, result });
+    }
+
     /// Pause accepting incoming connections
     ///
     /// If socket contains some pending connection, they might be dropped.
@@ -66,6 +289,53 @@ impl Server {
         rx.map(|_| ())
     }
 
+    /// Pause accepting incoming connections on listeners with provided name.
+    ///
+    /// Other listeners keep accepting connections.
+    pub fn pause_listener<N: AsRef<str>>(
+        &self,
+        name: N,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::PauseListener {
+            name: name.as_ref().to_string(),
+            result: tx,
+        });
+        rx.map(Self::result)
+    }
+
+    /// Resume accepting incoming connections on listeners with provided
+    /// name.
+    pub fn resume_listener<N: AsRef<str>>(
+        &self,
+        name: N,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::ResumeListener {
+            name: name.as_ref().to_string(),
+            result: tx,
+        });
+        rx.map(Self::result)
+    }
+
+    /// Replace peer filter of listeners with provided name, `None` removes
+    /// filter.
+    ///
+    /// Filter applies to connections accepted after update.
+    pub fn set_ip_filter<N: AsRef<str>>(
+        &self,
+        name: N,
+        filter: Option<IpFilter>,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::SetIpFilter {
+            name: name.as_ref().to_string(),
+            filter,
+            result: tx,
+        });
+        rx.map(Self::result)
+    }
+
     /// Stop incoming connection processing, stop all workers and exit.
     ///
     /// If server starts with `spawn()` method, then spawned thread get terminated.
@@ -77,6 +347,166 @@ impl Server {
         });
         rx.map(|_| ())
     }
+
+    /// Change number of workers of running server.
+    ///
+    /// New workers are started right away. Surplus workers stop getting new
+    /// connections and shut down gracefully, returned future resolves once
+    /// their connections are finished or shutdown timeout expires.
+    pub fn set_workers(&self, num: usize) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::SetWorkers(num, tx));
+        rx.map(|_| ())
+    }
+
+    /// Get snapshot of server metrics.
+    ///
+    /// Returned future resolves once all workers report their state. Worker
+    /// that does not answer in 500 milliseconds, for example because it is
+    /// blocked by service, is reported as unavailable without connections.
+    pub fn metrics(&self) -> impl Future<Output = ServerMetrics> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::Metrics(tx));
+        rx.map(|res| {
+            res.unwrap_or_else(|_| ServerMetrics {
+                workers: Vec::new(),
+                tls: Vec::new(),
+                accepted: 0,
+                accept_errors: 0,
+                paused: false,
+            })
+        })
+    }
+
+    /// Change the maximum per-worker number of concurrent connections of
+    /// running server.
+    ///
+    /// Workers stop accepting connections once new limit is reached,
+    /// connections above the limit are not closed.
+    pub fn set_max_connections(&self, num: usize) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self
+            .0
+            .unbounded_send(ServerCommand::SetMaxConnections(num, tx));
+        rx.map(|_| ())
+    }
+
+    /// Bind new listener and start accepting connections on running server.
+    ///
+    /// Service is started on all workers before the listener starts
+    /// accepting connections.
+    pub fn add_listener<F, U, N>(
+        &self,
+        name: N,
+        addr: U,
+        factory: F,
+    ) -> impl Future<Output = io::Result<()>>
+    where
+        F: ServiceFactory<TcpStream>,
+        U: net::ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        let (tx, rx) = oneshot::channel();
+        match addr.to_socket_addrs() {
+            Ok(addrs) => {
+                let name = name.as_ref().to_string();
+                let name2 = name.clone();
+                let factory = ListenerFactory(Box::new(move |token, addr| {
+                    StreamNewService::create(name2.clone(), token, factory.clone(), addr)
+                }));
+                let _ = self.0.unbounded_send(ServerCommand::AddListener {
+                    name,
+                    addrs: addrs.collect(),
+                    factory,
+                    result: tx,
+                });
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e));
+            }
+        }
+        rx.map(Self::result)
+    }
+
+    /// Stop accepting connections on listeners with provided name and close
+    /// them.
+    ///
+    /// Active connections are not affected.
+    pub fn remove_listener<N: AsRef<str>>(
+        &self,
+        name: N,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::RemoveListener {
+            name: name.as_ref().to_string(),
+            result: tx,
+        });
+        rx.map(Self::result)
+    }
+
+    /// Replace service factory of listeners with provided name.
+    ///
+    /// Workers create services with the new factory and pass new
+    /// connections to them, existing connections are finished by old
+    /// services. If service can not be created on any of workers, all
+    /// workers keep old service and error is returned.
+    ///
+    /// Only listeners of streams of type `Io`, bound with `bind()`,
+    /// `listen()` and similar methods, can be reloaded.
+    pub fn reload<F, Io, N>(&self, name: N, factory: F) -> impl Future<Output = io::Result<()>>
+    where
+        F: ServiceFactory<Io>,
+        Io: FromStream + Send + 'static,
+        N: AsRef<str>,
+    {
+        let (tx, rx) = oneshot::channel();
+        let factory = ReloadFactory::<Io>(
+            Box::new(move |name, token, addr| {
+                StreamNewService::create(name, token, factory.clone(), addr)
+            }),
+            PhantomData,
+        );
+        let _ = self.0.unbounded_send(ServerCommand::Reload {
+            name: name.as_ref().to_string(),
+            factory: Box::new(factory),
+            result: tx,
+        });
+        rx.map(Self::result)
+    }
+
+    /// Wait for server to stop.
+    ///
+    /// Future resolves with stop reason once server is stopped and its
+    /// workers are joined. Unlike `Server` future it does not resolve with
+    /// an error on failure, `StopReason::Failed` is returned instead.
+    pub fn stopped(&self) -> impl Future<Output = StopReason> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::Stopped(tx));
+        rx.map(|res| res.unwrap_or(StopReason::Dropped))
+    }
+
+    fn result(res: Result<io::Result<()>, oneshot::Canceled>) -> io::Result<()> {
+        res.unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "Server is stopped")))
+    }
+
+    #[cfg(unix)]
+    /// Pass listening sockets to the new server process and stop gracefully.
+    ///
+    /// New process waits for sockets on unix socket at `path`, see
+    /// `ServerBuilder::receive_handoff()`. Both processes share listening
+    /// sockets, so pending connections are accepted by the new process,
+    /// while the old one finishes active connections.
+    pub fn handoff<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> impl Future<Output = io::Result<()>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.0.unbounded_send(ServerCommand::Handoff {
+            path: path.as_ref().to_path_buf(),
+            result: tx,
+        });
+        rx.map(Self::result)
+    }
 }
 
 impl Clone for Server {
@@ -101,7 +531,7 @@ impl Future for Server {
 
         match Pin::new(this.1.as_mut().unwrap()).poll(cx) {
             Poll::Pending => Poll::Pending,
-            Poll::Ready(Ok(_)) => Poll::Ready(Ok(())),
+            Poll::Ready(Ok(res)) => Poll::Ready(res),
             Poll::Ready(Err(_)) => Poll::Ready(Ok(())),
         }
     }
@@ -1,3 +1,4 @@
+use std::any::Any;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
 use std::task::{Context, Poll};
@@ -11,19 +12,21 @@ use futures::{FutureExt, TryFutureExt};
 use log::error;
 
 use super::Token;
+use crate::lifetime::{Limited, Limits};
+use crate::peer::PeerGuard;
 use crate::socket::{FromStream, StdStream};
 
 /// Server message
 pub(crate) enum ServerMessage {
-    /// New stream
-    Connect(StdStream),
+    /// New stream, with its limits and peer connection guard
+    Connect(StdStream, Limits, Option<PeerGuard>),
     /// Gracefull shutdown
     Shutdown(Duration),
     /// Force shutdown
     ForceShutdown,
 }
 
-pub trait ServiceFactory<Stream: FromStream>: Send + Clone + 'static {
+pub trait ServiceFactory<Stream>: Send + Clone + 'static {
     type Factory: actix::ServiceFactory<Config = (), Request = Stream>;
 
     fn create(&self) -> Self::Factory;
@@ -35,8 +38,25 @@ pub(crate) trait InternalServiceFactory: Send {
     fn clone_factory(&self) -> Box<dyn InternalServiceFactory>;
 
     fn create(&self) -> LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>>;
+
+    /// Factory of listener with new service factory, `None` if factory does
+    /// not serve one of listeners or does not support reload.
+    fn reload(
+        &self,
+        _tokens: &[Token],
+        _factory: &(dyn Any + Send),
+    ) -> Option<Box<dyn InternalServiceFactory>> {
+        None
+    }
 }
 
+/// Service factory passed to `Server::reload()`, creates factory of
+/// listener with its name, token and address.
+pub(crate) struct ReloadFactory<Io>(
+    pub(crate) Box<dyn Fn(String, Token, SocketAddr) -> Box<dyn InternalServiceFactory> + Send>,
+    pub(crate) PhantomData<fn() -> Io>,
+);
+
 pub(crate) type BoxedServerService = Box<
     dyn Service<
         Request = (Option<CounterGuard>, ServerMessage),
@@ -74,17 +94,32 @@ where
 
     fn call(&mut self, (guard, req): (Option<CounterGuard>, ServerMessage)) -> Self::Future {
         match req {
-            ServerMessage::Connect(stream) => {
+            ServerMessage::Connect(stream, limits, peer) => {
+                // handle of socket to check activity and shut it down
+                let sock = match stream {
+                    StdStream::Tcp(ref stream) if !limits.is_empty() => stream.try_clone().ok(),
+                    _ => None,
+                };
                 let stream = FromStream::from_stdstream(stream).map_err(|e| {
                     error!("Can not convert to an async tcp stream: {}", e);
                 });
 
                 if let Ok(stream) = stream {
                     let f = self.service.call(stream);
-                    spawn(async move {
-                        let _ = f.await;
-                        drop(guard);
-                    });
+                    if limits.is_empty() {
+                        spawn(async move {
+                            let _ = f.await;
+                            drop(guard);
+                            drop(peer);
+                        });
+                    } else {
+                        let f = Limited::new(f.map(|_| ()).boxed_local(), sock, limits);
+                        spawn(async move {
+                            f.await;
+                            drop(guard);
+                            drop(peer);
+                        });
+                    }
                     ok(())
                 } else {
                     err(())
@@ -100,13 +135,13 @@ pub(crate) struct StreamNewService<F: ServiceFactory<Io>, Io: FromStream> {
     inner: F,
     token: Token,
     addr: SocketAddr,
-    _t: PhantomData<Io>,
+    _t: PhantomData<fn() -> Io>,
 }
 
 impl<F, Io> StreamNewService<F, Io>
 where
     F: ServiceFactory<Io>,
-    Io: FromStream + Send + 'static,
+    Io: FromStream + 'static,
 {
     pub(crate) fn create(
         name: String,
@@ -127,7 +162,7 @@ where
 impl<F, Io> InternalServiceFactory for StreamNewService<F, Io>
 where
     F: ServiceFactory<Io>,
-    Io: FromStream + Send + 'static,
+    Io: FromStream + 'static,
 {
     fn name(&self, _: Token) -> &str {
         &self.name
@@ -155,6 +190,19 @@ where
             })
             .boxed_local()
     }
+
+    fn reload(
+        &self,
+        tokens: &[Token],
+        factory: &(dyn Any + Send),
+    ) -> Option<Box<dyn InternalServiceFactory>> {
+        if !tokens.contains(&self.token) {
+            return None;
+        }
+        factory
+            .downcast_ref::<ReloadFactory<Io>>()
+            .map(|f| (f.0)(self.name.clone(), self.token, self.addr))
+    }
 }
 
 impl InternalServiceFactory for Box<dyn InternalServiceFactory> {
@@ -169,13 +217,20 @@ impl InternalServiceFactory for Box<dyn InternalServiceFactory> {
     fn create(&self) -> LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
         self.as_ref().create()
     }
+
+    fn reload(
+        &self,
+        tokens: &[Token],
+        factory: &(dyn Any + Send),
+    ) -> Option<Box<dyn InternalServiceFactory>> {
+        self.as_ref().reload(tokens, factory)
+    }
 }
 
 impl<F, T, I> ServiceFactory<I> for F
 where
     F: Fn() -> T + Send + Clone + 'static,
     T: actix::ServiceFactory<Config = (), Request = I>,
-    I: FromStream,
 {
     type Factory = T;
 
@@ -0,0 +1,76 @@
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use slab::Slab;
+
+struct State {
+    notified: Cell<bool>,
+    wakers: RefCell<Slab<Option<Waker>>>,
+}
+
+thread_local! {
+    static STATE: Rc<State> = Rc::new(State {
+        notified: Cell::new(false),
+        wakers: RefCell::new(Slab::new()),
+    });
+}
+
+/// Get graceful shutdown signal of current worker.
+///
+/// Signal resolves once worker starts graceful shutdown, so that services
+/// could finish active connections, i.e. close idle keep-alive connections.
+/// Should be called from within a service running on server worker.
+pub fn shutdown_signal() -> ShutdownSignal {
+    let state = STATE.with(|state| state.clone());
+    let token = state.wakers.borrow_mut().insert(None);
+    ShutdownSignal { state, token }
+}
+
+/// Notify services of current worker about graceful shutdown.
+pub(crate) fn notify() {
+    STATE.with(|state| {
+        state.notified.set(true);
+        for (_, waker) in state.wakers.borrow_mut().iter_mut() {
+            if let Some(waker) = waker.take() {
+                waker.wake();
+            }
+        }
+    })
+}
+
+/// Worker graceful shutdown signal
+///
+/// This is created by the `shutdown_signal` function.
+#[must_use = "ShutdownSignal do nothing unless polled"]
+pub struct ShutdownSignal {
+    state: Rc<State>,
+    token: usize,
+}
+
+impl ShutdownSignal {
+    /// Returns `true` if worker is shutting down.
+    pub fn is_shutdown(&self) -> bool {
+        self.state.notified.get()
+    }
+}
+
+impl Future for ShutdownSignal {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.state.notified.get() {
+            return Poll::Ready(());
+        }
+        self.state.wakers.borrow_mut()[self.token] = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for ShutdownSignal {
+    fn drop(&mut self) {
+        self.state.wakers.borrow_mut().remove(self.token);
+    }
+}
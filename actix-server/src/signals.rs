@@ -1,16 +1,18 @@
 use std::future::Future;
-use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::{fmt, io};
 
 use futures::future::lazy;
+use futures::FutureExt;
 
 use crate::server::Server;
+use crate::service::ServiceFactory;
+use crate::socket::FromStream;
 
 /// Different types of process signals
-#[allow(dead_code)]
-#[derive(PartialEq, Clone, Copy, Debug)]
-pub(crate) enum Signal {
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Signal {
     /// SIGHUP
     Hup,
     /// SIGINT
@@ -19,6 +21,164 @@ pub(crate) enum Signal {
     Term,
     /// SIGQUIT
     Quit,
+    /// SIGUSR1
+    Usr1,
+    /// SIGUSR2
+    Usr2,
+}
+
+impl Signal {
+    fn name(self) -> &'static str {
+        match self {
+            Signal::Hup => "SIGHUP",
+            Signal::Int => "SIGINT",
+            Signal::Term => "SIGTERM",
+            Signal::Quit => "SIGQUIT",
+            Signal::Usr1 => "SIGUSR1",
+            Signal::Usr2 => "SIGUSR2",
+        }
+    }
+
+    #[cfg(unix)]
+    fn kind(self) -> actix_rt::signal::unix::SignalKind {
+        use actix_rt::signal::unix::SignalKind;
+
+        match self {
+            Signal::Hup => SignalKind::hangup(),
+            Signal::Int => SignalKind::interrupt(),
+            Signal::Term => SignalKind::terminate(),
+            Signal::Quit => SignalKind::quit(),
+            Signal::Usr1 => SignalKind::user_defined1(),
+            Signal::Usr2 => SignalKind::user_defined2(),
+        }
+    }
+}
+
+pub(crate) enum SignalAction {
+    /// Stop server gracefully
+    Graceful,
+    /// Stop server immediately
+    Immediate,
+    /// Call custom handler
+    Custom(Box<dyn Fn(&Server) + Send>),
+    /// Signal is handled, nothing is done
+    Ignore,
+}
+
+/// Set of process signals handled by server
+///
+/// Default set stops server immediately on `SIGINT` and `SIGQUIT`,
+/// gracefully on `SIGTERM` and ignores `SIGHUP`. On non-unix platforms only
+/// `SIGINT` (ctrl-c) is supported.
+pub struct SignalSet {
+    actions: Vec<(Signal, SignalAction)>,
+}
+
+impl Default for SignalSet {
+    fn default() -> Self {
+        SignalSet::new()
+            .immediate(Signal::Int)
+            .ignore(Signal::Hup)
+            .graceful(Signal::Term)
+            .immediate(Signal::Quit)
+    }
+}
+
+impl SignalSet {
+    /// Create empty set, no signals are handled.
+    pub fn new() -> Self {
+        SignalSet {
+            actions: Vec::new(),
+        }
+    }
+
+    /// Stop server gracefully on signal.
+    pub fn graceful(self, sig: Signal) -> Self {
+        self.action(sig, SignalAction::Graceful)
+    }
+
+    /// Stop server immediately on signal.
+    pub fn immediate(self, sig: Signal) -> Self {
+        self.action(sig, SignalAction::Immediate)
+    }
+
+    /// Call function on signal.
+    ///
+    /// Function is called on the thread that starts server, with server
+    /// handle as an argument.
+    pub fn on<F>(self, sig: Signal, f: F) -> Self
+    where
+        F: Fn(&Server) + Send + 'static,
+    {
+        self.action(sig, SignalAction::Custom(Box::new(f)))
+    }
+
+    /// Reload service of listeners with provided name on signal, i.e. on
+    /// `SIGHUP`. See `Server::reload()`.
+    pub fn reload<F, Io, N>(self, sig: Signal, name: N, factory: F) -> Self
+    where
+        F: ServiceFactory<Io>,
+        Io: FromStream + Send + 'static,
+        N: AsRef<str>,
+    {
+        let name = name.as_ref().to_string();
+        self.on(sig, move |srv| {
+            let name = name.clone();
+            let fut = srv.reload(name.clone(), factory.clone());
+            actix_rt::spawn(fut.map(move |res| {
+                if let Err(e) = res {
+                    log::error!("Can not reload {:?} service: {}", name, e);
+                }
+            }));
+        })
+    }
+
+    /// Handle signal without doing anything, i.e. `SIGHUP` does not terminate
+    /// process.
+    pub fn ignore(self, sig: Signal) -> Self {
+        self.action(sig, SignalAction::Ignore)
+    }
+
+    /// Stop handling signal, default signal action is restored.
+    pub fn remove(mut self, sig: Signal) -> Self {
+        self.actions.retain(|(s, _)| *s != sig);
+        self
+    }
+
+    fn action(mut self, sig: Signal, action: SignalAction) -> Self {
+        self = self.remove(sig);
+        self.actions.push((sig, action));
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub(crate) fn signals(&self) -> Vec<Signal> {
+        self.actions.iter().map(|(sig, _)| *sig).collect()
+    }
+
+    pub(crate) fn get(&self, sig: Signal) -> Option<&SignalAction> {
+        self.actions
+            .iter()
+            .find(|(s, _)| *s == sig)
+            .map(|(_, action)| action)
+    }
+}
+
+impl fmt::Debug for SignalSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.actions.iter().map(|(sig, _)| sig.name()))
+            .finish()
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 pub(crate) struct Signals {
@@ -30,14 +190,16 @@ pub(crate) struct Signals {
 }
 
 impl Signals {
-    pub(crate) fn start(srv: Server) -> io::Result<()> {
-        actix_rt::spawn(lazy(|_| {
+    pub(crate) fn start(srv: Server, signals: Vec<Signal>) -> io::Result<()> {
+        actix_rt::spawn(lazy(move |_| {
             #[cfg(not(unix))]
             {
-                actix_rt::spawn(Signals {
-                    srv,
-                    stream: Box::pin(actix_rt::signal::ctrl_c()),
-                });
+                if signals.contains(&Signal::Int) {
+                    actix_rt::spawn(Signals {
+                        srv,
+                        stream: Box::pin(actix_rt::signal::ctrl_c()),
+                    });
+                }
             }
             #[cfg(unix)]
             {
@@ -45,16 +207,9 @@ impl Signals {
 
                 let mut streams = Vec::new();
 
-                let sig_map = [
-                    (unix::SignalKind::interrupt(), Signal::Int),
-                    (unix::SignalKind::hangup(), Signal::Hup),
-                    (unix::SignalKind::terminate(), Signal::Term),
-                    (unix::SignalKind::quit(), Signal::Quit),
-                ];
-
-                for (kind, sig) in sig_map.iter() {
-                    match unix::signal(*kind) {
-                        Ok(stream) => streams.push((*sig, stream)),
+                for sig in signals {
+                    match unix::signal(sig.kind()) {
+                        Ok(stream) => streams.push((sig, stream)),
                         Err(e) => log::error!(
                             "Can not initialize stream handler for {:?} err: {}",
                             sig,
@@ -0,0 +1,255 @@
+//! Services of TLS listener selected by SNI hostname.
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, ServiceFactory as ActixServiceFactory};
+use futures::future::{join_all, poll_fn, LocalBoxFuture};
+use futures::FutureExt;
+use log::trace;
+
+use crate::service::ServiceFactory;
+
+type BoxedHostService<Io> = Box<
+    dyn Service<
+        Request = Io,
+        Response = (),
+        Error = (),
+        Future = LocalBoxFuture<'static, Result<(), ()>>,
+    >,
+>;
+
+type BoxedSniService<Io> = Rc<RefCell<BoxedHostService<Io>>>;
+
+/// Virtual services of TLS listener, service is selected by server name
+/// that client sent in SNI extension during handshake.
+///
+/// Services are passed to `ServerBuilder::bind_tls()` in place of service
+/// factory, `server_name` extracts server name from TLS stream.
+///
+/// ```rust,ignore
+/// let services = SniServices::new(|io: &SslStream<TcpStream>| {
+///     io.ssl().servername(NameType::HOST_NAME)
+/// })
+/// .host("api.example.com", api_factory)
+/// .host("*.example.com", www_factory);
+///
+/// Server::build().bind_tls("https", "0.0.0.0:443", acceptor, services)?;
+/// ```
+pub struct SniServices<Io> {
+    server_name: Arc<dyn Fn(&Io) -> Option<&str> + Send + Sync>,
+    hosts: Vec<(String, Box<dyn SniFactory<Io>>)>,
+    default: Option<Box<dyn SniFactory<Io>>>,
+}
+
+impl<Io: 'static> SniServices<Io> {
+    /// Create services with function that extracts server name from stream.
+    pub fn new<F>(server_name: F) -> Self
+    where
+        F: Fn(&Io) -> Option<&str> + Send + Sync + 'static,
+    {
+        SniServices {
+            server_name: Arc::new(server_name),
+            hosts: Vec::new(),
+            default: None,
+        }
+    }
+
+    /// Add service of host.
+    ///
+    /// Host names are case insensitive, wildcard `*.example.com` matches
+    /// single label subdomains of `example.com`. Exact names take precedence
+    /// over wildcards.
+    pub fn host<F, H>(mut self, host: H, factory: F) -> Self
+    where
+        F: ServiceFactory<Io>,
+        H: AsRef<str>,
+    {
+        self.hosts
+            .push((host.as_ref().to_ascii_lowercase(), Box::new(factory)));
+        self
+    }
+
+    /// Service of connections without server name or with unknown name.
+    ///
+    /// By default such connections are closed.
+    pub fn default_service<F>(mut self, factory: F) -> Self
+    where
+        F: ServiceFactory<Io>,
+    {
+        self.default = Some(Box::new(factory));
+        self
+    }
+}
+
+impl<Io> Clone for SniServices<Io> {
+    fn clone(&self) -> Self {
+        SniServices {
+            server_name: self.server_name.clone(),
+            hosts: self
+                .hosts
+                .iter()
+                .map(|(host, factory)| (host.clone(), factory.clone_factory()))
+                .collect(),
+            default: self.default.as_ref().map(|factory| factory.clone_factory()),
+        }
+    }
+}
+
+impl<Io: 'static> ServiceFactory<Io> for SniServices<Io> {
+    type Factory = SniServicesFactory<Io>;
+
+    fn create(&self) -> SniServicesFactory<Io> {
+        SniServicesFactory(self.clone())
+    }
+}
+
+#[doc(hidden)]
+pub struct SniServicesFactory<Io>(SniServices<Io>);
+
+impl<Io: 'static> ActixServiceFactory for SniServicesFactory<Io> {
+    type Request = Io;
+    type Response = ();
+    type Error = ();
+    type Config = ();
+    type Service = SniService<Io>;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<SniService<Io>, ()>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let server_name = self.0.server_name.clone();
+        let names: Vec<_> = self.0.hosts.iter().map(|(host, _)| host.clone()).collect();
+        let hosts = join_all(self.0.hosts.iter().map(|(_, factory)| factory.create()));
+        let default = self.0.default.as_ref().map(|factory| factory.create());
+
+        async move {
+            let mut services = Vec::new();
+            for (name, srv) in names.into_iter().zip(hosts.await) {
+                services.push((name, srv?));
+            }
+            let default = match default {
+                Some(fut) => Some(fut.await?),
+                None => None,
+            };
+            Ok(SniService {
+                server_name,
+                hosts: services,
+                default,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+#[doc(hidden)]
+pub struct SniService<Io> {
+    server_name: Arc<dyn Fn(&Io) -> Option<&str> + Send + Sync>,
+    hosts: Vec<(String, BoxedSniService<Io>)>,
+    default: Option<BoxedSniService<Io>>,
+}
+
+impl<Io> SniService<Io> {
+    fn lookup(&self, name: &str) -> Option<&BoxedSniService<Io>> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        self.hosts
+            .iter()
+            .find(|(host, _)| *host == name)
+            .or_else(|| {
+                let (_, parent) = name.split_at(name.find('.')?);
+                self.hosts
+                    .iter()
+                    .find(|(host, _)| host.starts_with('*') && host[1..] == *parent)
+            })
+            .map(|(_, srv)| srv)
+    }
+}
+
+impl<Io: 'static> Service for SniService<Io> {
+    type Request = Io;
+    type Response = ();
+    type Error = ();
+    type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+        // service is not known until stream is passed
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: Io) -> Self::Future {
+        let srv = match (self.server_name)(&io) {
+            Some(name) => match self.lookup(name) {
+                Some(srv) => Some(srv),
+                None => {
+                    trace!("No service for SNI hostname {:?}", name);
+                    self.default.as_ref()
+                }
+            },
+            None => self.default.as_ref(),
+        };
+        let srv = match srv {
+            Some(srv) => srv.clone(),
+            None => return futures::future::ok(()).boxed_local(),
+        };
+
+        async move {
+            poll_fn(|cx| srv.borrow_mut().poll_ready(cx)).await?;
+            let fut = srv.borrow_mut().call(io);
+            fut.await
+        }
+        .boxed_local()
+    }
+}
+
+/// Type erased service factory of host
+trait SniFactory<Io>: Send {
+    fn clone_factory(&self) -> Box<dyn SniFactory<Io>>;
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<BoxedSniService<Io>, ()>>;
+}
+
+impl<F, Io> SniFactory<Io> for F
+where
+    F: ServiceFactory<Io>,
+    Io: 'static,
+{
+    fn clone_factory(&self) -> Box<dyn SniFactory<Io>> {
+        Box::new(self.clone())
+    }
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<BoxedSniService<Io>, ()>> {
+        let fut = ServiceFactory::create(self).new_service(());
+
+        async move {
+            let srv = fut.await.map_err(|_| ())?;
+            let srv: BoxedHostService<Io> = Box::new(HostService(srv));
+            Ok(Rc::new(RefCell::new(srv)))
+        }
+        .boxed_local()
+    }
+}
+
+/// Service of host with response and error discarded
+struct HostService<S>(S);
+
+impl<S> Service for HostService<S>
+where
+    S: Service,
+    S::Future: 'static,
+{
+    type Request = S::Request;
+    type Response = ();
+    type Error = ();
+    type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+        self.0.poll_ready(cx).map_err(|_| ())
+    }
+
+    fn call(&mut self, io: S::Request) -> Self::Future {
+        self.0
+            .call(io)
+            .map(|res| res.map(|_| ()).map_err(|_| ()))
+            .boxed_local()
+    }
+}
@@ -1,18 +1,502 @@
+use std::time::Duration;
 use std::{fmt, io, net};
 
 use actix_codec::{AsyncRead, AsyncWrite};
 use actix_rt::net::TcpStream;
 
+use crate::accept::BackpressurePolicy;
+use crate::filter::IpFilter;
+use crate::lifetime::Limits;
+#[cfg(windows)]
+use crate::named_pipe::{NamedPipeStream, PipeListener};
+use crate::peer::PeerLimit;
+use crate::rate::{RateLimitPolicy, RateLimiter};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::reuseport::ReuseportProgram;
+
+/// Unix domain socket listener options.
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UdsOptions {
+    mode: Option<u32>,
+    owner: Option<u32>,
+    group: Option<u32>,
+    unlink: bool,
+}
+
+#[cfg(unix)]
+impl Default for UdsOptions {
+    fn default() -> Self {
+        UdsOptions {
+            mode: None,
+            owner: None,
+            group: None,
+            unlink: true,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl UdsOptions {
+    /// Create default options.
+    ///
+    /// By default socket file permissions and ownership are not changed and
+    /// existing file at the socket path is removed before bind.
+    ///
+    /// On linux path that starts with NUL byte, i.e. `"\0name"`, is bound in
+    /// abstract namespace. Such socket has no file, so options are not
+    /// applied to it.
+    pub fn new() -> Self {
+        UdsOptions::default()
+    }
+
+    /// Set socket file permissions, i.e. `0o660`.
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Set socket file owner user id.
+    pub fn owner(mut self, uid: u32) -> Self {
+        self.owner = Some(uid);
+        self
+    }
+
+    /// Set socket file owner group id.
+    pub fn group(mut self, gid: u32) -> Self {
+        self.group = Some(gid);
+        self
+    }
+
+    /// Remove existing file at the socket path before bind.
+    ///
+    /// Stale socket file left by previous server process prevents bind.
+    /// Enabled by default.
+    pub fn unlink(mut self, unlink: bool) -> Self {
+        self.unlink = unlink;
+        self
+    }
+
+    pub(crate) fn bind(
+        &self,
+        path: &std::path::Path,
+    ) -> io::Result<std::os::unix::net::UnixListener> {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        // abstract namespace, there is no socket file
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if path.as_os_str().as_bytes().first() == Some(&0) {
+                use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+                let lst = mio_uds::UnixListener::bind(path)?;
+                return Ok(unsafe {
+                    std::os::unix::net::UnixListener::from_raw_fd(lst.into_raw_fd())
+                });
+            }
+        }
+
+        if self.unlink {
+            // NotFound is expected and not an issue. Anything else is.
+            if let Err(e) = std::fs::remove_file(path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+        }
+
+        let lst = match self.mode {
+            Some(mode) => {
+                // socket file must not be accessible with broader permissions
+                // until mode is set. umask is process wide, so it is only
+                // made stricter while socket is bound.
+                let mask = !mode as libc::mode_t & 0o777;
+                let prev = unsafe { libc::umask(0o777) };
+                unsafe { libc::umask(prev | mask) };
+                let res = std::os::unix::net::UnixListener::bind(path);
+                unsafe { libc::umask(prev) };
+                res?
+            }
+            None => std::os::unix::net::UnixListener::bind(path)?,
+        };
+
+        if let Some(mode) = self.mode {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+        if self.owner.is_some() || self.group.is_some() {
+            let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            // `-1` keeps current value
+            let uid = self.owner.unwrap_or(u32::MAX) as libc::uid_t;
+            let gid = self.group.unwrap_or(u32::MAX) as libc::gid_t;
+            if unsafe { libc::chown(c_path.as_ptr(), uid, gid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(lst)
+    }
+}
+
+/// Options of tcp listener and accepted connections.
+#[derive(Debug, Clone, Default)]
+pub struct TcpOptions {
+    nodelay: Option<bool>,
+    keepalive: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    keepalive_retries: Option<u32>,
+    linger: Option<Option<Duration>>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+    idle_timeout: Option<Duration>,
+    max_lifetime: Option<Duration>,
+    accept_rate: Option<(u32, u32)>,
+    rate_limit_policy: RateLimitPolicy,
+    pub(crate) ip_filter: Option<IpFilter>,
+    max_per_ip: Option<usize>,
+    pub(crate) backpressure: BackpressurePolicy,
+    pub(crate) backlog: Option<i32>,
+    pub(crate) reuse_address: Option<bool>,
+    pub(crate) only_v6: Option<bool>,
+    freebind: Option<bool>,
+    transparent: Option<bool>,
+    fastopen: Option<u32>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) reuseport_program: Option<ReuseportProgram>,
+    pub(crate) incoming_cpu: bool,
+}
+
+impl TcpOptions {
+    /// Create default options, system defaults are not changed.
+    pub fn new() -> Self {
+        TcpOptions::default()
+    }
+
+    /// Set `TCP_NODELAY` option.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Enable `SO_KEEPALIVE`, first probe is sent after connection is idle
+    /// for `idle` duration.
+    ///
+    /// Duration is rounded up to whole seconds, min value is one second.
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(ceil_secs(idle, 1));
+        self
+    }
+
+    /// Set interval between keepalive probes.
+    ///
+    /// Duration is rounded up to whole seconds, min value is one second.
+    /// Supported on linux only, ignored on other platforms.
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = Some(ceil_secs(interval, 1));
+        self
+    }
+
+    /// Set number of unacknowledged keepalive probes before connection is
+    /// dropped.
+    ///
+    /// Supported on linux only, ignored on other platforms.
+    pub fn keepalive_retries(mut self, retries: u32) -> Self {
+        self.keepalive_retries = Some(retries);
+        self
+    }
+
+    /// Set `SO_LINGER` option, `None` disables lingering.
+    ///
+    /// Duration is rounded up to whole seconds, zero duration resets
+    /// connection on close.
+    pub fn linger(mut self, linger: Option<Duration>) -> Self {
+        self.linger = Some(linger.map(|linger| ceil_secs(linger, 0)));
+        self
+    }
+
+    /// Set `SO_SNDBUF` option.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set `SO_RCVBUF` option.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set listen backlog of listener, overrides `ServerBuilder::backlog()`.
+    ///
+    /// Backlog of listener passed to `ServerBuilder::listen_with()` is
+    /// updated on unix only.
+    pub fn backlog(mut self, num: i32) -> Self {
+        self.backlog = Some(num);
+        self
+    }
+
+    /// Set `SO_REUSEADDR` option of listener, enabled by default.
+    ///
+    /// Used only for listeners bound by server.
+    pub fn reuse_address(mut self, reuse: bool) -> Self {
+        self.reuse_address = Some(reuse);
+        self
+    }
+
+    /// Set `IPV6_V6ONLY` option of ipv6 listener, system default is used
+    /// otherwise.
+    ///
+    /// Used only for listeners bound by server.
+    pub fn only_v6(mut self, only_v6: bool) -> Self {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Set `IP_FREEBIND` option of listener, allows to bind to address that
+    /// is not yet configured on the host.
+    ///
+    /// Used only for listeners bound by server, supported on linux only.
+    pub fn freebind(mut self, freebind: bool) -> Self {
+        self.freebind = Some(freebind);
+        self
+    }
+
+    /// Set `IP_TRANSPARENT` (`IPV6_TRANSPARENT` for ipv6) option of listener,
+    /// allows to accept connections to non-local addresses redirected with
+    /// tproxy. Requires `CAP_NET_ADMIN` capability.
+    ///
+    /// Used only for listeners bound by server, supported on linux only.
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = Some(transparent);
+        self
+    }
+
+    /// Set `TCP_FASTOPEN` option of listener, enables TCP Fast Open with
+    /// queue of `qlen` pending fast open requests. Data sent in SYN by client
+    /// is available to service before handshake is completed.
+    ///
+    /// Used only for listeners bound by server, supported on linux only.
+    pub fn fastopen(mut self, qlen: u32) -> Self {
+        self.fastopen = Some(qlen);
+        self
+    }
+
+    /// Attach program that selects socket of `SO_REUSEPORT` group for
+    /// incoming connections, i.e. `ReuseportProgram::hash()`.
+    ///
+    /// Program is attached once listener is bound with
+    /// `ServerBuilder::bind_reuseport_with()` and applies to all sockets of
+    /// the group.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn reuseport_program(mut self, program: ReuseportProgram) -> Self {
+        self.reuseport_program = Some(program);
+        self
+    }
+
+    /// Pass accepted connections to worker pinned to CPU core that received
+    /// them, core is read with `SO_INCOMING_CPU` option.
+    ///
+    /// Workers are pinned with `ServerBuilder::worker_affinity()`, connection
+    /// is passed to next available worker if there is no available worker
+    /// on the core. Supported on linux only.
+    pub fn incoming_cpu(mut self, steer: bool) -> Self {
+        self.incoming_cpu = steer;
+        self
+    }
+
+    /// Close connection once it is idle for `timeout` duration.
+    ///
+    /// Connection is idle while no data is sent or received on its socket.
+    /// On platforms other than linux socket activity is not known, and
+    /// connection is idle while its service is not woken up.
+    ///
+    /// On timeout read half of the socket is shut down, so that service
+    /// reads end of stream and could finish connection. Service future is
+    /// dropped if it is not finished within 1 second.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Close connection once it is alive for `lifetime` duration, regardless
+    /// of activity.
+    ///
+    /// Connection is closed the same way as for `TcpOptions::idle_timeout()`.
+    pub fn max_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Limit rate of accepted connections to `rate` connections per second,
+    /// with bursts of up to `burst` connections.
+    ///
+    /// Unlike `ServerBuilder::maxconn()` limit is not related to number of
+    /// in-flight connections.
+    pub fn accept_rate(mut self, rate: u32, burst: u32) -> Self {
+        self.accept_rate = Some((rate, burst));
+        self
+    }
+
+    /// Set behavior once accept rate is exceeded, by default accepting is
+    /// delayed.
+    pub fn rate_limit_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = policy;
+        self
+    }
+
+    /// Accept connections only from peers allowed by filter.
+    ///
+    /// Filter can be replaced at runtime with `Server::set_ip_filter()`.
+    pub fn ip_filter(mut self, filter: IpFilter) -> Self {
+        self.ip_filter = Some(filter);
+        self
+    }
+
+    /// Set max number of concurrent connections of single peer address.
+    ///
+    /// Connections are counted by accept loop, excess connections are
+    /// closed immediately. Warning is logged once for recent offenders.
+    pub fn max_connections_per_ip(mut self, max: usize) -> Self {
+        self.max_per_ip = Some(max);
+        self
+    }
+
+    /// Set behavior of listener once all workers are at max number of
+    /// connections, by default listener stops accepting connections.
+    pub fn backpressure_policy(mut self, policy: BackpressurePolicy) -> Self {
+        self.backpressure = policy;
+        self
+    }
+
+    pub(crate) fn peer_limit(&self) -> Option<PeerLimit> {
+        self.max_per_ip.map(PeerLimit::new)
+    }
+
+    pub(crate) fn rate_limiter(&self) -> Option<RateLimiter> {
+        self.accept_rate
+            .map(|(rate, burst)| RateLimiter::new(rate, burst, self.rate_limit_policy))
+    }
+
+    pub(crate) fn limits(&self) -> Limits {
+        Limits {
+            idle: self.idle_timeout,
+            lifetime: self.max_lifetime,
+        }
+    }
+
+    /// Apply options of listener before it is bound
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn apply_listener<T>(&self, sock: &T, v6: bool) -> io::Result<()>
+    where
+        T: std::os::unix::io::AsRawFd,
+    {
+        let setsockopt = |level: libc::c_int, opt: libc::c_int, val: libc::c_int| {
+            let res = unsafe {
+                libc::setsockopt(
+                    sock.as_raw_fd(),
+                    level,
+                    opt,
+                    &val as *const _ as *const libc::c_void,
+                    std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                )
+            };
+            if res != 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        };
+        if let Some(freebind) = self.freebind {
+            setsockopt(libc::SOL_IP, libc::IP_FREEBIND, freebind as libc::c_int)?;
+        }
+        if let Some(transparent) = self.transparent {
+            let transparent = transparent as libc::c_int;
+            if v6 {
+                setsockopt(libc::SOL_IPV6, libc::IPV6_TRANSPARENT, transparent)?;
+            } else {
+                setsockopt(libc::SOL_IP, libc::IP_TRANSPARENT, transparent)?;
+            }
+        }
+        if let Some(qlen) = self.fastopen {
+            setsockopt(libc::IPPROTO_TCP, libc::TCP_FASTOPEN, qlen as libc::c_int)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn apply(&self, stream: &net::TcpStream) -> io::Result<()> {
+        use net2::TcpStreamExt;
+
+        if let Some(nodelay) = self.nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+        if let Some(idle) = self.keepalive {
+            TcpStreamExt::set_keepalive(stream, Some(idle))?;
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let setsockopt = |opt: libc::c_int, val: libc::c_int| {
+                let res = unsafe {
+                    libc::setsockopt(
+                        stream.as_raw_fd(),
+                        libc::IPPROTO_TCP,
+                        opt,
+                        &val as *const _ as *const libc::c_void,
+                        std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                    )
+                };
+                if res != 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(())
+                }
+            };
+            if let Some(interval) = self.keepalive_interval {
+                let secs = std::cmp::min(interval.as_secs(), libc::c_int::MAX as u64);
+                setsockopt(libc::TCP_KEEPINTVL, secs as libc::c_int)?;
+            }
+            if let Some(retries) = self.keepalive_retries {
+                setsockopt(libc::TCP_KEEPCNT, retries as libc::c_int)?;
+            }
+        }
+        if let Some(linger) = self.linger {
+            TcpStreamExt::set_linger(stream, linger)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            stream.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            stream.set_recv_buffer_size(size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Round duration up to whole seconds, options are set in seconds
+fn ceil_secs(dur: Duration, min: u64) -> Duration {
+    let secs = if dur.subsec_nanos() > 0 {
+        dur.as_secs().saturating_add(1)
+    } else {
+        dur.as_secs()
+    };
+    Duration::from_secs(std::cmp::max(secs, min))
+}
+
 pub(crate) enum StdListener {
     Tcp(net::TcpListener),
     #[cfg(all(unix))]
     Uds(std::os::unix::net::UnixListener),
+    #[cfg(windows)]
+    NamedPipe(PipeListener),
 }
 
 pub(crate) enum SocketAddr {
     Tcp(net::SocketAddr),
     #[cfg(all(unix))]
     Uds(std::os::unix::net::SocketAddr),
+    #[cfg(windows)]
+    NamedPipe(String),
 }
 
 impl fmt::Display for SocketAddr {
@@ -21,6 +505,8 @@ impl fmt::Display for SocketAddr {
             SocketAddr::Tcp(ref addr) => write!(f, "{}", addr),
             #[cfg(all(unix))]
             SocketAddr::Uds(ref addr) => write!(f, "{:?}", addr),
+            #[cfg(windows)]
+            SocketAddr::NamedPipe(ref name) => write!(f, "{}", name),
         }
     }
 }
@@ -31,6 +517,8 @@ impl fmt::Debug for SocketAddr {
             SocketAddr::Tcp(ref addr) => write!(f, "{:?}", addr),
             #[cfg(all(unix))]
             SocketAddr::Uds(ref addr) => write!(f, "{:?}", addr),
+            #[cfg(windows)]
+            SocketAddr::NamedPipe(ref name) => write!(f, "{}", name),
         }
     }
 }
@@ -41,16 +529,39 @@ impl fmt::Display for StdListener {
             StdListener::Tcp(ref lst) => write!(f, "{}", lst.local_addr().ok().unwrap()),
             #[cfg(all(unix))]
             StdListener::Uds(ref lst) => write!(f, "{:?}", lst.local_addr().ok().unwrap()),
+            #[cfg(windows)]
+            StdListener::NamedPipe(ref lst) => write!(f, "{}", lst.name()),
         }
     }
 }
 
+impl fmt::Debug for StdListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StdListener({})", self)
+    }
+}
+
 impl StdListener {
     pub(crate) fn local_addr(&self) -> SocketAddr {
         match self {
             StdListener::Tcp(lst) => SocketAddr::Tcp(lst.local_addr().unwrap()),
             #[cfg(all(unix))]
             StdListener::Uds(lst) => SocketAddr::Uds(lst.local_addr().unwrap()),
+            #[cfg(windows)]
+            StdListener::NamedPipe(lst) => SocketAddr::NamedPipe(lst.name().to_string()),
+        }
+    }
+
+    pub(crate) fn try_clone(&self) -> io::Result<StdListener> {
+        match self {
+            StdListener::Tcp(lst) => lst.try_clone().map(StdListener::Tcp),
+            #[cfg(all(unix))]
+            StdListener::Uds(lst) => lst.try_clone().map(StdListener::Uds),
+            #[cfg(windows)]
+            StdListener::NamedPipe(_) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Named pipe listener can not be cloned",
+            )),
         }
     }
 
@@ -65,6 +576,18 @@ impl StdListener {
                 mio_uds::UnixListener::from_listener(lst)
                     .expect("Can not create mio_uds::UnixListener"),
             ),
+            #[cfg(windows)]
+            StdListener::NamedPipe(lst) => SocketListener::NamedPipe(lst),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::io::AsRawFd for StdListener {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        match self {
+            StdListener::Tcp(lst) => lst.as_raw_fd(),
+            StdListener::Uds(lst) => lst.as_raw_fd(),
         }
     }
 }
@@ -74,12 +597,16 @@ pub enum StdStream {
     Tcp(std::net::TcpStream),
     #[cfg(all(unix))]
     Uds(std::os::unix::net::UnixStream),
+    #[cfg(windows)]
+    NamedPipe(NamedPipeStream),
 }
 
 pub(crate) enum SocketListener {
     Tcp(mio::net::TcpListener),
     #[cfg(all(unix))]
     Uds(mio_uds::UnixListener),
+    #[cfg(windows)]
+    NamedPipe(PipeListener),
 }
 
 impl SocketListener {
@@ -92,6 +619,60 @@ impl SocketListener {
             SocketListener::Uds(ref lst) => lst.accept_std().map(|res| {
                 res.map(|(stream, addr)| (StdStream::Uds(stream), SocketAddr::Uds(addr)))
             }),
+            #[cfg(windows)]
+            SocketListener::NamedPipe(ref lst) => lst.accept().map(|res| {
+                res.map(|stream| {
+                    let addr = SocketAddr::NamedPipe(lst.name().to_string());
+                    (StdStream::NamedPipe(stream), addr)
+                })
+            }),
+        }
+    }
+
+    /// Remove socket file of unix listener that is closed.
+    pub(crate) fn remove_file(&self) {
+        #[cfg(all(unix))]
+        {
+            if let SocketListener::Uds(ref lst) = *self {
+                if let Ok(addr) = lst.local_addr() {
+                    if let Some(path) = addr.as_pathname() {
+                        let _ = std::fs::remove_file(path);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wrap socket accepted by io_uring, peer address is `None` if connection
+    /// is already closed.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) fn accepted(
+        &self,
+        fd: std::os::unix::io::RawFd,
+    ) -> (StdStream, Option<SocketAddr>) {
+        use std::os::unix::io::FromRawFd;
+
+        match *self {
+            SocketListener::Tcp(_) => {
+                let stream = unsafe { net::TcpStream::from_raw_fd(fd) };
+                let addr = stream.peer_addr().ok().map(SocketAddr::Tcp);
+                (StdStream::Tcp(stream), addr)
+            }
+            SocketListener::Uds(_) => {
+                let stream = unsafe { std::os::unix::net::UnixStream::from_raw_fd(fd) };
+                let addr = stream.peer_addr().ok().map(SocketAddr::Uds);
+                (StdStream::Uds(stream), addr)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::io::AsRawFd for SocketListener {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        match *self {
+            SocketListener::Tcp(ref lst) => lst.as_raw_fd(),
+            SocketListener::Uds(ref lst) => lst.as_raw_fd(),
         }
     }
 }
@@ -108,6 +689,8 @@ impl mio::Evented for SocketListener {
             SocketListener::Tcp(ref lst) => lst.register(poll, token, interest, opts),
             #[cfg(all(unix))]
             SocketListener::Uds(ref lst) => lst.register(poll, token, interest, opts),
+            #[cfg(windows)]
+            SocketListener::NamedPipe(ref lst) => lst.register(poll, token, interest, opts),
         }
     }
 
@@ -122,23 +705,17 @@ impl mio::Evented for SocketListener {
             SocketListener::Tcp(ref lst) => lst.reregister(poll, token, interest, opts),
             #[cfg(all(unix))]
             SocketListener::Uds(ref lst) => lst.reregister(poll, token, interest, opts),
+            #[cfg(windows)]
+            SocketListener::NamedPipe(ref lst) => lst.reregister(poll, token, interest, opts),
         }
     }
     fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
         match *self {
             SocketListener::Tcp(ref lst) => lst.deregister(poll),
             #[cfg(all(unix))]
-            SocketListener::Uds(ref lst) => {
-                let res = lst.deregister(poll);
-
-                // cleanup file path
-                if let Ok(addr) = lst.local_addr() {
-                    if let Some(path) = addr.as_pathname() {
-                        let _ = std::fs::remove_file(path);
-                    }
-                }
-                res
-            }
+            SocketListener::Uds(ref lst) => lst.deregister(poll),
+            #[cfg(windows)]
+            SocketListener::NamedPipe(ref lst) => lst.deregister(poll),
         }
     }
 }
@@ -155,6 +732,10 @@ impl FromStream for TcpStream {
             StdStream::Uds(_) => {
                 panic!("Should not happen, bug in server impl");
             }
+            #[cfg(windows)]
+            StdStream::NamedPipe(_) => {
+                panic!("Should not happen, bug in server impl");
+            }
         }
     }
 }
@@ -0,0 +1,106 @@
+//! Systemd socket activation.
+//!
+//! Systemd passes listening sockets to activated service as file
+//! descriptors starting at `3`. Number of passed sockets is set in
+//! `LISTEN_FDS` environment variable, socket names are set in
+//! `LISTEN_FDNAMES` as colon separated list.
+//!
+//! Same convention is used by `systemfd` and `listenfd` crates, sockets
+//! passed by them have no names and are selected by index. Such sockets
+//! might be passed without `LISTEN_PID`, i.e. by `systemfd --no-pid`,
+//! systemd always sets it.
+use std::os::unix::io::RawFd;
+use std::{env, io, mem};
+
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Socket passed by service manager
+pub(crate) struct ActivatedSocket {
+    pub(crate) name: String,
+    pub(crate) fd: RawFd,
+    pub(crate) unix: bool,
+    /// Index of socket passed in environment
+    pub(crate) idx: Option<usize>,
+    /// Socket is passed to this process, `LISTEN_PID` matches process id
+    /// or socket is received with handoff
+    pub(crate) verified: bool,
+}
+
+/// Read sockets passed by service manager.
+///
+/// Environment variables are removed, so sockets are not inherited by child
+/// processes.
+pub(crate) fn listen_fds() -> io::Result<Vec<ActivatedSocket>> {
+    let pid = env::var("LISTEN_PID").ok();
+    let fds = env::var("LISTEN_FDS").ok();
+    let names = env::var("LISTEN_FDNAMES").ok();
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    // sockets are passed to other process
+    let verified = match pid {
+        Some(pid) if pid.parse::<u32>().ok() == Some(std::process::id()) => true,
+        Some(_) => return Ok(Vec::new()),
+        None => false,
+    };
+    let fds = match fds {
+        Some(fds) => fds.parse::<RawFd>().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Invalid LISTEN_FDS value")
+        })?,
+        None => return Ok(Vec::new()),
+    };
+    let names: Vec<&str> = names
+        .as_ref()
+        .map(|names| names.split(':').collect())
+        .unwrap_or_default();
+    let mut names = names.into_iter();
+
+    let mut sockets = Vec::new();
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + fds {
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        sockets.push(ActivatedSocket {
+            name: names.next().unwrap_or("unknown").to_string(),
+            fd,
+            unix: is_unix_stream(fd)?,
+            idx: Some((fd - LISTEN_FDS_START) as usize),
+            verified,
+        });
+    }
+    Ok(sockets)
+}
+
+/// Check socket type, returns `true` for unix domain socket.
+pub(crate) fn is_unix_stream(fd: RawFd) -> io::Result<bool> {
+    let mut ty: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TYPE,
+            &mut ty as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if ty != libc::SOCK_STREAM {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Socket {} is not a stream socket", fd),
+        ));
+    }
+
+    let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let res =
+        unsafe { libc::getsockname(fd, &mut addr as *mut _ as *mut libc::sockaddr, &mut len) };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(libc::c_int::from(addr.ss_family) == libc::AF_UNIX)
+}
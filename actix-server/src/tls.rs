@@ -0,0 +1,158 @@
+//! TLS termination on listener.
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, ServiceFactory as ActixServiceFactory};
+use futures::future::{poll_fn, LocalBoxFuture};
+use futures::{FutureExt, TryFutureExt};
+use log::trace;
+
+use crate::metrics::HandshakeMetrics;
+use crate::service::{
+    BoxedServerService, InternalServiceFactory, ServiceFactory, StreamService,
+};
+use crate::socket::FromStream;
+use crate::Token;
+
+/// Service that completes TLS handshake with acceptor before passing stream
+/// to the inner service. Handshake errors close connection.
+pub(crate) struct TlsService<A, S> {
+    acceptor: A,
+    service: Rc<RefCell<S>>,
+    metrics: Arc<HandshakeMetrics>,
+}
+
+impl<A, S> Service for TlsService<A, S>
+where
+    A: Service,
+    A::Future: 'static,
+    A::Error: fmt::Debug,
+    S: Service<Request = A::Response> + 'static,
+{
+    type Request = A::Request;
+    type Response = ();
+    type Error = ();
+    type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // acceptor limits number of concurrent handshakes
+        if self.acceptor.poll_ready(cx).map_err(|_| ())?.is_pending() {
+            return Poll::Pending;
+        }
+        self.service.borrow_mut().poll_ready(cx).map_err(|_| ())
+    }
+
+    fn call(&mut self, io: A::Request) -> Self::Future {
+        let handshake = self.acceptor.call(io);
+        let service = self.service.clone();
+        let metrics = self.metrics.clone();
+
+        async move {
+            let stream = match handshake.await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    trace!("TLS handshake error: {:?}", e);
+                    metrics.failed();
+                    return Ok(());
+                }
+            };
+            metrics.completed();
+
+            // handshake could take a while
+            poll_fn(|cx| service.borrow_mut().poll_ready(cx))
+                .await
+                .map_err(|_| ())?;
+            let fut = service.borrow_mut().call(stream);
+            fut.await.map(|_| ()).map_err(|_| ())
+        }
+        .boxed_local()
+    }
+}
+
+pub(crate) struct TlsNewService<A, F, Io>
+where
+    A: ActixServiceFactory,
+    F: ServiceFactory<A::Response>,
+{
+    name: String,
+    acceptor: A,
+    inner: F,
+    token: Token,
+    metrics: Arc<HandshakeMetrics>,
+    _t: PhantomData<Io>,
+}
+
+impl<A, F, Io> TlsNewService<A, F, Io>
+where
+    A: ActixServiceFactory<Config = (), Request = Io> + Clone + Send + 'static,
+    A::Service: 'static,
+    A::Response: 'static,
+    A::Error: fmt::Debug,
+    F: ServiceFactory<A::Response>,
+    Io: FromStream + Send + 'static,
+{
+    pub(crate) fn create(
+        name: String,
+        token: Token,
+        acceptor: A,
+        inner: F,
+        metrics: Arc<HandshakeMetrics>,
+    ) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name,
+            token,
+            acceptor,
+            inner,
+            metrics,
+            _t: PhantomData,
+        })
+    }
+}
+
+impl<A, F, Io> InternalServiceFactory for TlsNewService<A, F, Io>
+where
+    A: ActixServiceFactory<Config = (), Request = Io> + Clone + Send + 'static,
+    A::Service: 'static,
+    A::Response: 'static,
+    A::Error: fmt::Debug,
+    F: ServiceFactory<A::Response>,
+    Io: FromStream + Send + 'static,
+{
+    fn name(&self, _: Token) -> &str {
+        &self.name
+    }
+
+    fn clone_factory(&self) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name: self.name.clone(),
+            acceptor: self.acceptor.clone(),
+            inner: self.inner.clone(),
+            token: self.token,
+            metrics: self.metrics.clone(),
+            _t: PhantomData,
+        })
+    }
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+        let token = self.token;
+        let acceptor = self.acceptor.new_service(()).map_err(|_| ());
+        let inner = self.inner.create().new_service(()).map_err(|_| ());
+        let metrics = self.metrics.clone();
+
+        async move {
+            let acceptor = acceptor.await?;
+            let inner = inner.await?;
+            let service: BoxedServerService = Box::new(StreamService::new(TlsService {
+                acceptor,
+                service: Rc::new(RefCell::new(inner)),
+                metrics,
+            }));
+            Ok(vec![(token, service)])
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,116 @@
+//! Datagram services.
+use std::net;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use actix_rt::net::UdpSocket;
+use actix_rt::spawn;
+use actix_service::{Service, ServiceFactory as ActixServiceFactory};
+use actix_utils::counter::CounterGuard;
+use futures::future::{err, ok, poll_fn, LocalBoxFuture, Ready};
+use futures::{FutureExt, TryFutureExt};
+use log::error;
+
+use crate::service::{
+    BoxedServerService, InternalServiceFactory, ServerMessage, ServiceFactory,
+};
+use crate::Token;
+
+/// Converts socket of worker to service request
+pub(crate) type IntoDatagram<Io> = Arc<dyn Fn(UdpSocket) -> Io + Send + Sync>;
+
+/// Worker service of datagram socket. Datagrams are not dispatched by accept
+/// loop, so it only keeps token slot of the service.
+struct DatagramService;
+
+impl Service for DatagramService {
+    type Request = (Option<CounterGuard>, ServerMessage);
+    type Response = ();
+    type Error = ();
+    type Future = Ready<Result<(), ()>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _: (Option<CounterGuard>, ServerMessage)) -> Self::Future {
+        ok(())
+    }
+}
+
+pub(crate) struct DatagramNewService<F: ServiceFactory<Io>, Io> {
+    name: String,
+    inner: F,
+    token: Token,
+    sock: Arc<net::UdpSocket>,
+    into: IntoDatagram<Io>,
+}
+
+impl<F, Io> DatagramNewService<F, Io>
+where
+    F: ServiceFactory<Io>,
+    Io: 'static,
+{
+    pub(crate) fn create(
+        name: String,
+        token: Token,
+        inner: F,
+        sock: net::UdpSocket,
+        into: IntoDatagram<Io>,
+    ) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name,
+            token,
+            inner,
+            sock: Arc::new(sock),
+            into,
+        })
+    }
+}
+
+impl<F, Io> InternalServiceFactory for DatagramNewService<F, Io>
+where
+    F: ServiceFactory<Io>,
+    Io: 'static,
+{
+    fn name(&self, _: Token) -> &str {
+        &self.name
+    }
+
+    fn clone_factory(&self) -> Box<dyn InternalServiceFactory> {
+        Box::new(Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+            token: self.token,
+            sock: self.sock.clone(),
+            into: self.into.clone(),
+        })
+    }
+
+    fn create(&self) -> LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>> {
+        let token = self.token;
+        let io = match self.sock.try_clone().and_then(UdpSocket::from_std) {
+            Ok(sock) => (*self.into)(sock),
+            Err(e) => {
+                error!("Can not clone udp socket of {:?}: {}", self.name, e);
+                return err(()).boxed_local();
+            }
+        };
+
+        self.inner
+            .create()
+            .new_service(())
+            .map_err(|_| ())
+            .map_ok(move |mut service| {
+                // service runs until it completes or worker is stopped
+                spawn(async move {
+                    if poll_fn(|cx| service.poll_ready(cx)).await.is_ok() {
+                        let _ = service.call(io).await;
+                    }
+                });
+                let service: BoxedServerService = Box::new(DatagramService);
+                vec![(token, service)]
+            })
+            .boxed_local()
+    }
+}
@@ -0,0 +1,397 @@
+//! Minimal io_uring instance for accept thread and worker streams.
+//!
+//! Accept thread submits `IORING_OP_ACCEPT` request for every listener and
+//! waits for completions, ring descriptor is registered with mio poll, so
+//! commands and notifications are handled as before.
+//!
+//! Workers submit `IORING_OP_RECV`/`IORING_OP_SEND` requests of
+//! `UringStream`s to ring of their own, see `uring_stream` module.
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::{io, mem, ptr};
+
+const IORING_OFF_SQ_RING: libc::off_t = 0;
+const IORING_OFF_CQ_RING: libc::off_t = 0x0800_0000;
+const IORING_OFF_SQES: libc::off_t = 0x1000_0000;
+
+/// Non-blocking accept and efficient polling are available since 5.7
+const IORING_FEAT_FAST_POLL: u32 = 1 << 5;
+
+const IORING_OP_ACCEPT: u8 = 13;
+const IORING_OP_ASYNC_CANCEL: u8 = 14;
+const IORING_OP_SEND: u8 = 26;
+const IORING_OP_RECV: u8 = 27;
+
+const IORING_ENTER_GETEVENTS: u32 = 1;
+
+/// User data of cancel requests, their completions are ignored
+pub(crate) const CANCEL: u64 = u64::MAX;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    resv2: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    resv2: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqringOffsets,
+    cq_off: CqringOffsets,
+}
+
+#[repr(C)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    pad: [u64; 2],
+}
+
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+// sizes of `linux/io_uring.h` structures, fields are naturally aligned, so
+// there is no padding and offsets follow field order
+const _: [(); 40] = [(); mem::size_of::<SqringOffsets>()];
+const _: [(); 40] = [(); mem::size_of::<CqringOffsets>()];
+const _: [(); 120] = [(); mem::size_of::<Params>()];
+const _: [(); 64] = [(); mem::size_of::<Sqe>()];
+const _: [(); 16] = [(); mem::size_of::<Cqe>()];
+
+/// Memory mapped region of the ring
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mmap {
+    fn new(fd: RawFd, offset: libc::off_t, len: usize) -> io::Result<Mmap> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Mmap { ptr, len })
+        }
+    }
+
+    unsafe fn at<T>(&self, offset: u32) -> *mut T {
+        (self.ptr as *mut u8).add(offset as usize) as *mut T
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+pub(crate) struct Ring {
+    sq_head: *const AtomicU32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_entries: u32,
+    sq_array: *mut u32,
+    sqes: *mut Sqe,
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const Cqe,
+    /// Pushed, but not yet submitted entries
+    queued: u32,
+    _sq: Mmap,
+    _cq: Mmap,
+    _sqes: Mmap,
+    cq_entries: u32,
+    fd: RawFd,
+}
+
+impl Ring {
+    pub(crate) fn new(entries: u32) -> io::Result<Ring> {
+        let mut params = Params::default();
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries as libc::c_long,
+                &mut params as *mut Params,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = fd as RawFd;
+
+        match Ring::map(fd, &params) {
+            Ok(ring) => Ok(ring),
+            Err(e) => {
+                unsafe { libc::close(fd) };
+                Err(e)
+            }
+        }
+    }
+
+    fn map(fd: RawFd, p: &Params) -> io::Result<Ring> {
+        if p.features & IORING_FEAT_FAST_POLL == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "io_uring fast poll is not supported",
+            ));
+        }
+
+        let sq_len = p.sq_off.array as usize + p.sq_entries as usize * mem::size_of::<u32>();
+        let cq_len = p.cq_off.cqes as usize + p.cq_entries as usize * mem::size_of::<Cqe>();
+        let sqes_len = p.sq_entries as usize * mem::size_of::<Sqe>();
+        let sq = Mmap::new(fd, IORING_OFF_SQ_RING, sq_len)?;
+        let cq = Mmap::new(fd, IORING_OFF_CQ_RING, cq_len)?;
+        let sqes = Mmap::new(fd, IORING_OFF_SQES, sqes_len)?;
+
+        unsafe {
+            Ok(Ring {
+                sq_head: sq.at(p.sq_off.head),
+                sq_tail: sq.at(p.sq_off.tail),
+                sq_mask: *sq.at::<u32>(p.sq_off.ring_mask),
+                sq_entries: *sq.at::<u32>(p.sq_off.ring_entries),
+                sq_array: sq.at(p.sq_off.array),
+                sqes: sqes.at(0),
+                cq_head: cq.at(p.cq_off.head),
+                cq_tail: cq.at(p.cq_off.tail),
+                cq_mask: *cq.at::<u32>(p.cq_off.ring_mask),
+                cqes: cq.at(p.cq_off.cqes),
+                cq_entries: p.cq_entries,
+                queued: 0,
+                _sq: sq,
+                _cq: cq,
+                _sqes: sqes,
+                fd,
+            })
+        }
+    }
+
+    /// Queue accept request, accepted socket is non-blocking and close on
+    /// exec.
+    pub(crate) fn accept(&mut self, fd: RawFd, user_data: u64) -> io::Result<()> {
+        self.push(
+            IORING_OP_ACCEPT,
+            fd,
+            0,
+            0,
+            (libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC) as u32,
+            user_data,
+        )
+    }
+
+    /// Queue receive request.
+    ///
+    /// # Safety
+    ///
+    /// Buffer must stay valid until request completes.
+    pub(crate) unsafe fn recv(
+        &mut self,
+        fd: RawFd,
+        buf: *mut u8,
+        len: usize,
+        user_data: u64,
+    ) -> io::Result<()> {
+        self.push(IORING_OP_RECV, fd, buf as u64, len as u32, 0, user_data)
+    }
+
+    /// Queue send request, `SIGPIPE` is not raised for closed connection.
+    ///
+    /// # Safety
+    ///
+    /// Buffer must stay valid until request completes.
+    pub(crate) unsafe fn send(
+        &mut self,
+        fd: RawFd,
+        buf: *const u8,
+        len: usize,
+        user_data: u64,
+    ) -> io::Result<()> {
+        self.push(
+            IORING_OP_SEND,
+            fd,
+            buf as u64,
+            len as u32,
+            libc::MSG_NOSIGNAL as u32,
+            user_data,
+        )
+    }
+
+    /// Queue cancel of request with provided user data.
+    pub(crate) fn cancel(&mut self, user_data: u64) -> io::Result<()> {
+        self.push(IORING_OP_ASYNC_CANCEL, -1, user_data, 0, 0, CANCEL)
+    }
+
+    fn push(
+        &mut self,
+        opcode: u8,
+        fd: RawFd,
+        addr: u64,
+        len: u32,
+        op_flags: u32,
+        user_data: u64,
+    ) -> io::Result<()> {
+        let head = unsafe { (*self.sq_head).load(Ordering::Acquire) };
+        let tail = unsafe { (*self.sq_tail).load(Ordering::Relaxed) };
+        if tail.wrapping_sub(head) == self.sq_entries {
+            self.submit()?;
+            let head = unsafe { (*self.sq_head).load(Ordering::Acquire) };
+            if tail.wrapping_sub(head) == self.sq_entries {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "io_uring submission queue is full",
+                ));
+            }
+        }
+
+        let idx = tail & self.sq_mask;
+        unsafe {
+            ptr::write(
+                self.sqes.add(idx as usize),
+                Sqe {
+                    opcode,
+                    flags: 0,
+                    ioprio: 0,
+                    fd,
+                    off: 0,
+                    addr,
+                    len,
+                    op_flags,
+                    user_data,
+                    buf_index: 0,
+                    personality: 0,
+                    splice_fd_in: 0,
+                    pad: [0; 2],
+                },
+            );
+            *self.sq_array.add(idx as usize) = idx;
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+        self.queued += 1;
+        Ok(())
+    }
+
+    /// Submit queued requests.
+    pub(crate) fn submit(&mut self) -> io::Result<()> {
+        while self.queued > 0 {
+            let res = self.enter(self.queued, 0)?;
+            if res == 0 {
+                break;
+            }
+            self.queued -= res;
+        }
+        Ok(())
+    }
+
+    /// Move completions that did not fit into completion queue back to the
+    /// queue, they are kept by kernel once queue is full.
+    pub(crate) fn flush_overflow(&mut self) -> io::Result<()> {
+        self.enter(0, IORING_ENTER_GETEVENTS).map(|_| ())
+    }
+
+    /// Number of entries of completion queue
+    pub(crate) fn cq_entries(&self) -> u32 {
+        self.cq_entries
+    }
+
+    fn enter(&self, to_submit: u32, flags: u32) -> io::Result<u32> {
+        loop {
+            let res = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd as libc::c_long,
+                    to_submit as libc::c_long,
+                    0 as libc::c_long,
+                    flags as libc::c_long,
+                    ptr::null::<libc::sigset_t>(),
+                    0 as libc::c_long,
+                )
+            };
+            if res >= 0 {
+                return Ok(res as u32);
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    /// Get next completion, returns user data and result.
+    pub(crate) fn completion(&mut self) -> Option<(u64, i32)> {
+        let head = unsafe { (*self.cq_head).load(Ordering::Relaxed) };
+        let tail = unsafe { (*self.cq_tail).load(Ordering::Acquire) };
+        if head == tail {
+            return None;
+        }
+        let cqe = unsafe { &*self.cqes.add((head & self.cq_mask) as usize) };
+        let res = (cqe.user_data, cqe.res);
+        unsafe { (*self.cq_head).store(head.wrapping_add(1), Ordering::Release) };
+        Some(res)
+    }
+}
+
+impl AsRawFd for Ring {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // pending requests are cancelled once ring is closed
+        unsafe { libc::close(self.fd) };
+    }
+}
@@ -0,0 +1,479 @@
+//! Streams of accepted connections driven by io_uring.
+//!
+//! Every worker thread has ring of its own, it is created once first stream
+//! is passed to service. Reads and writes of streams are submitted to the
+//! ring, its descriptor is registered with tokio reactor of worker and task
+//! of worker processes completions once it is readable.
+use std::cell::RefCell;
+use std::future::Future;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use std::{fmt, io, mem, net};
+
+use actix_codec::{AsyncRead, AsyncWrite};
+use futures::future::poll_fn;
+use log::error;
+use mio::unix::EventedFd;
+use mio::{Evented, PollOpt, Ready};
+use slab::Slab;
+use tokio::io::PollEvented;
+
+use crate::socket::{FromStream, StdStream};
+use crate::uring::{self, Ring};
+
+/// Size of read buffer of stream and max size of single write
+const BUF_SIZE: usize = 16 * 1024;
+
+thread_local!(static DRIVER: RefCell<Option<Rc<RefCell<Driver>>>> = const { RefCell::new(None) });
+
+/// State of submitted request
+enum Op {
+    /// Request is in flight, task is woken once it completes
+    Pending(Option<Waker>),
+    /// Request is completed with result
+    Complete(i32),
+    /// Stream of request is dropped, buffer is kept until request completes
+    Orphan(Vec<u8>),
+}
+
+/// Ring descriptor registered with tokio reactor
+struct RingFd(RawFd);
+
+impl Evented for RingFd {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: mio::Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        EventedFd(&self.0).deregister(poll)
+    }
+}
+
+/// Ring of worker thread with its requests
+struct Driver {
+    // deregistered before ring is closed
+    evented: PollEvented<RingFd>,
+    ring: Ring,
+    ops: Slab<Op>,
+}
+
+impl Driver {
+    /// Ring of current worker, it is started on first use.
+    fn current() -> io::Result<Rc<RefCell<Driver>>> {
+        if let Some(driver) = DRIVER.with(|d| d.borrow().clone()) {
+            return Ok(driver);
+        }
+        let ring = Ring::new(256)?;
+        let evented = PollEvented::new(RingFd(ring.as_raw_fd()))?;
+        let driver = Rc::new(RefCell::new(Driver {
+            evented,
+            ring,
+            ops: Slab::new(),
+        }));
+        DRIVER.with(|d| *d.borrow_mut() = Some(driver.clone()));
+        actix_rt::spawn(Driver::run(driver.clone()));
+        Ok(driver)
+    }
+
+    /// Process completions once ring descriptor is readable
+    fn run(driver: Rc<RefCell<Driver>>) -> impl Future<Output = ()> {
+        poll_fn(move |cx| loop {
+            let ready = driver
+                .borrow()
+                .evented
+                .poll_read_ready(cx, Ready::readable());
+            match ready {
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(e)) => {
+                    error!("Can not poll io_uring: {}", e);
+                    return Poll::Ready(());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            Driver::complete(&driver);
+            let res = driver
+                .borrow()
+                .evented
+                .clear_read_ready(cx, Ready::readable());
+            if let Err(e) = res {
+                error!("Can not poll io_uring: {}", e);
+                return Poll::Ready(());
+            }
+            // completions that arrived before readiness is cleared
+            Driver::complete(&driver);
+        })
+    }
+
+    /// Wake up tasks of completed requests.
+    fn complete(driver: &RefCell<Driver>) {
+        let mut wakers = Vec::new();
+        {
+            let mut this = driver.borrow_mut();
+            let this = &mut *this;
+            let mut completed = 0;
+            loop {
+                while let Some((id, res)) = this.ring.completion() {
+                    completed += 1;
+                    if id == uring::CANCEL {
+                        continue;
+                    }
+                    let key = id as usize;
+                    match this.ops.get_mut(key) {
+                        Some(Op::Pending(waker)) => {
+                            wakers.extend(waker.take());
+                            this.ops[key] = Op::Complete(res);
+                        }
+                        Some(Op::Orphan(_)) => {
+                            this.ops.remove(key);
+                        }
+                        _ => (),
+                    }
+                }
+                // completion queue was full, kernel could keep more of them
+                if completed < this.ring.cq_entries() {
+                    break;
+                }
+                completed = 0;
+                if let Err(e) = this.ring.flush_overflow() {
+                    error!("Can not get io_uring completions: {}", e);
+                    break;
+                }
+            }
+            // requests that could not be submitted before
+            if let Err(e) = this.ring.submit() {
+                error!("Can not submit io_uring requests: {}", e);
+            }
+        }
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    /// Submit request pushed by `f`, returns key of request.
+    fn submit<F>(&mut self, f: F) -> io::Result<usize>
+    where
+        F: FnOnce(&mut Ring, u64) -> io::Result<()>,
+    {
+        let entry = self.ops.vacant_entry();
+        f(&mut self.ring, entry.key() as u64)?;
+        let key = entry.key();
+        entry.insert(Op::Pending(None));
+        // request is queued, so it is submitted later if ring is busy
+        if let Err(e) = self.ring.submit() {
+            error!("Can not submit io_uring requests: {}", e);
+        }
+        Ok(key)
+    }
+
+    /// Result of request, task is woken once request completes.
+    fn poll_op(&mut self, key: usize, cx: &mut Context<'_>) -> Poll<i32> {
+        match self.ops[key] {
+            Op::Complete(res) => {
+                self.ops.remove(key);
+                Poll::Ready(res)
+            }
+            Op::Pending(ref mut waker) => {
+                match waker {
+                    Some(ref w) if w.will_wake(cx.waker()) => (),
+                    _ => *waker = Some(cx.waker().clone()),
+                }
+                Poll::Pending
+            }
+            Op::Orphan(_) => unreachable!(),
+        }
+    }
+
+    /// Cancel request of dropped stream, buffer is released once request
+    /// completes.
+    fn release(&mut self, key: usize, buf: Vec<u8>) {
+        if let Op::Complete(_) = self.ops[key] {
+            self.ops.remove(key);
+            return;
+        }
+        self.ops[key] = Op::Orphan(buf);
+        if let Err(e) = self
+            .ring
+            .cancel(key as u64)
+            .and_then(|_| self.ring.submit())
+        {
+            error!("Can not cancel io_uring request: {}", e);
+        }
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        // kernel could still use buffers of requests in flight after ring
+        // is closed, so they are leaked
+        for (_, op) in self.ops.iter_mut() {
+            if let Op::Orphan(buf) = op {
+                mem::forget(mem::take(buf));
+            }
+        }
+    }
+}
+
+enum ReadState {
+    /// Data of buffer from position to end is not returned yet
+    Idle(Vec<u8>, usize, usize),
+    /// Request reads into buffer
+    Reading(usize, Vec<u8>),
+}
+
+/// Stream of accepted connection, its reads and writes are done with
+/// io_uring of worker.
+///
+/// Written data is copied to buffer of stream and sent in background, error
+/// of send is returned by next write, flush or shutdown.
+pub struct UringStream {
+    io: StdStream,
+    driver: Rc<RefCell<Driver>>,
+    read: Option<ReadState>,
+    /// Buffer of write request and number of sent bytes
+    write: Vec<u8>,
+    written: usize,
+    writing: Option<usize>,
+    error: Option<io::Error>,
+}
+
+impl UringStream {
+    /// Local address of connection.
+    pub fn local_addr(&self) -> io::Result<net::SocketAddr> {
+        match self.io {
+            StdStream::Tcp(ref stream) => stream.local_addr(),
+            StdStream::Uds(_) => Err(not_tcp()),
+        }
+    }
+
+    /// Address of peer of connection.
+    pub fn peer_addr(&self) -> io::Result<net::SocketAddr> {
+        match self.io {
+            StdStream::Tcp(ref stream) => stream.peer_addr(),
+            StdStream::Uds(_) => Err(not_tcp()),
+        }
+    }
+
+    /// Set `TCP_NODELAY` option of connection.
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        match self.io {
+            StdStream::Tcp(ref stream) => stream.set_nodelay(nodelay),
+            StdStream::Uds(_) => Err(not_tcp()),
+        }
+    }
+
+    /// Complete write request, rest of buffer is sent by new request.
+    fn poll_send(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(e) = self.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        loop {
+            if let Some(key) = self.writing {
+                let res = match self.driver.borrow_mut().poll_op(key, cx) {
+                    Poll::Ready(res) => res,
+                    Poll::Pending => return Poll::Pending,
+                };
+                self.writing = None;
+                if res < 0 {
+                    self.write.clear();
+                    self.written = 0;
+                    return Poll::Ready(Err(io::Error::from_raw_os_error(-res)));
+                }
+                self.written += res as usize;
+            }
+            if self.written >= self.write.len() {
+                self.write.clear();
+                self.written = 0;
+                return Poll::Ready(Ok(()));
+            }
+
+            let fd = self.as_raw_fd();
+            let rest = &self.write[self.written..];
+            let key = self
+                .driver
+                .borrow_mut()
+                .submit(|ring, id| unsafe { ring.send(fd, rest.as_ptr(), rest.len(), id) })?;
+            self.writing = Some(key);
+        }
+    }
+}
+
+impl FromStream for UringStream {
+    fn from_stdstream(sock: StdStream) -> io::Result<Self> {
+        // io_uring returns `EAGAIN` instead of waiting for non-blocking socket
+        match sock {
+            StdStream::Tcp(ref stream) => stream.set_nonblocking(false)?,
+            StdStream::Uds(ref stream) => stream.set_nonblocking(false)?,
+        }
+        Ok(UringStream {
+            io: sock,
+            driver: Driver::current()?,
+            read: Some(ReadState::Idle(vec![0; BUF_SIZE], 0, 0)),
+            write: Vec::new(),
+            written: 0,
+            writing: None,
+            error: None,
+        })
+    }
+}
+
+impl AsRawFd for UringStream {
+    fn as_raw_fd(&self) -> RawFd {
+        match self.io {
+            StdStream::Tcp(ref stream) => stream.as_raw_fd(),
+            StdStream::Uds(ref stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+impl fmt::Debug for UringStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UringStream").field("io", &self.io).finish()
+    }
+}
+
+impl AsyncRead for UringStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            match this.read.take().unwrap() {
+                ReadState::Idle(data, pos, end) if pos < end => {
+                    let n = std::cmp::min(buf.len(), end - pos);
+                    buf[..n].copy_from_slice(&data[pos..pos + n]);
+                    this.read = Some(ReadState::Idle(data, pos + n, end));
+                    return Poll::Ready(Ok(n));
+                }
+                ReadState::Idle(mut data, _, _) => {
+                    if buf.is_empty() {
+                        this.read = Some(ReadState::Idle(data, 0, 0));
+                        return Poll::Ready(Ok(0));
+                    }
+                    let fd = this.as_raw_fd();
+                    let len = std::cmp::min(buf.len(), data.len());
+                    let ptr = data.as_mut_ptr();
+                    let res = this
+                        .driver
+                        .borrow_mut()
+                        .submit(|ring, id| unsafe { ring.recv(fd, ptr, len, id) });
+                    match res {
+                        Ok(key) => this.read = Some(ReadState::Reading(key, data)),
+                        Err(e) => {
+                            this.read = Some(ReadState::Idle(data, 0, 0));
+                            return Poll::Ready(Err(e));
+                        }
+                    }
+                }
+                ReadState::Reading(key, data) => {
+                    let res = this.driver.borrow_mut().poll_op(key, cx);
+                    match res {
+                        Poll::Pending => {
+                            this.read = Some(ReadState::Reading(key, data));
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(res) if res < 0 => {
+                            this.read = Some(ReadState::Idle(data, 0, 0));
+                            return Poll::Ready(Err(io::Error::from_raw_os_error(-res)));
+                        }
+                        // end of stream
+                        Poll::Ready(0) => {
+                            this.read = Some(ReadState::Idle(data, 0, 0));
+                            return Poll::Ready(Ok(0));
+                        }
+                        Poll::Ready(res) => {
+                            this.read = Some(ReadState::Idle(data, 0, res as usize));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for UringStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match this.poll_send(cx) {
+            Poll::Ready(Ok(_)) => (),
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let n = std::cmp::min(buf.len(), BUF_SIZE);
+        this.write.extend_from_slice(&buf[..n]);
+        let fd = this.as_raw_fd();
+        let (ptr, len) = (this.write.as_ptr(), this.write.len());
+        let key = this
+            .driver
+            .borrow_mut()
+            .submit(|ring, id| unsafe { ring.send(fd, ptr, len, id) });
+        match key {
+            Ok(key) => {
+                this.writing = Some(key);
+                Poll::Ready(Ok(n))
+            }
+            Err(e) => {
+                this.write.clear();
+                Poll::Ready(Err(e))
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_send(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_send(cx) {
+            Poll::Ready(Ok(_)) => (),
+            res => return res,
+        }
+        let res = match this.io {
+            StdStream::Tcp(ref stream) => stream.shutdown(net::Shutdown::Write),
+            StdStream::Uds(ref stream) => stream.shutdown(net::Shutdown::Write),
+        };
+        Poll::Ready(res)
+    }
+}
+
+impl Drop for UringStream {
+    fn drop(&mut self) {
+        let mut driver = self.driver.borrow_mut();
+        if let Some(ReadState::Reading(key, data)) = self.read.take() {
+            driver.release(key, data);
+        }
+        if let Some(key) = self.writing.take() {
+            driver.release(key, mem::take(&mut self.write));
+        }
+    }
+}
+
+fn not_tcp() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "Not a tcp stream")
+}
@@ -1,11 +1,13 @@
+use std::collections::VecDeque;
+use std::mem;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time;
 
-use actix_rt::time::{delay_until, Delay, Instant};
-use actix_rt::{spawn, Arbiter};
+use actix_rt::time::{delay_until, timeout, Delay, Instant};
+use actix_rt::{spawn, Arbiter, RuntimeBuilder};
 use actix_utils::counter::Counter;
 use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
 use futures::channel::oneshot;
@@ -14,24 +16,67 @@ use futures::{Future, FutureExt, Stream, TryFutureExt};
 use log::{error, info, trace};
 
 use crate::accept::AcceptNotify;
+use crate::lifetime::Limits;
+use crate::metrics::WorkerMetrics;
+use crate::peer::PeerGuard;
+use crate::server::Server;
 use crate::service::{BoxedServerService, InternalServiceFactory, ServerMessage};
+use crate::shutdown;
 use crate::socket::{SocketAddr, StdStream};
 use crate::Token;
 
+/// Max number of connections moved from worker channel to wait queue of
+/// unavailable worker, rest of connections wait in the channel.
+const MAX_QUEUED: usize = 1024;
+
 pub(crate) struct WorkerCommand(Conn);
 
-/// Stop worker message. Returns `true` on successful shutdown
-/// and `false` if some connections still alive.
+/// Stop worker message. Returns number of connections that were still
+/// alive and got closed forcibly.
 pub(crate) struct StopCommand {
     graceful: bool,
-    result: oneshot::Sender<bool>,
+    result: oneshot::Sender<usize>,
+}
+
+/// Service management message.
+pub(crate) enum ServiceCommand {
+    /// Add service, or replace service of factory with the index.
+    /// Returns `true` once service is created.
+    Add {
+        factory: Box<dyn InternalServiceFactory>,
+        replace: Option<usize>,
+        result: oneshot::Sender<bool>,
+    },
+    /// Remove services of last added factory with the index, so that
+    /// tokens stay in sync after factory failed on other worker.
+    Remove(usize),
 }
 
+/// Change max number of concurrent connections message.
+pub(crate) struct MaxConnsCommand(usize);
+
+/// Metrics message. Returns number of active connections.
+pub(crate) struct MetricsCommand(oneshot::Sender<usize>);
+
 #[derive(Debug)]
 pub(crate) struct Conn {
     pub io: StdStream,
     pub token: Token,
     pub peer: Option<SocketAddr>,
+    pub limits: Limits,
+    /// Connection of peer counted by peer limit of listener
+    pub guard: Option<PeerGuard>,
+    /// Connection is closed if it is not processed before deadline
+    pub deadline: Option<Instant>,
+}
+
+impl Conn {
+    fn expired(&self) -> bool {
+        match self.deadline {
+            Some(deadline) => deadline <= Instant::now(),
+            None => false,
+        }
+    }
 }
 
 static MAX_CONNS: AtomicUsize = AtomicUsize::new(25600);
@@ -55,44 +100,151 @@ thread_local! {
         Counter::new(MAX_CONNS.load(Ordering::Relaxed));
 }
 
+/// Thread and runtime configuration of workers
+#[derive(Clone, Default)]
+pub(crate) struct WorkerRuntime {
+    pub(crate) name: Option<String>,
+    pub(crate) stack_size: Option<usize>,
+    pub(crate) configure: Option<Arc<dyn Fn(&mut RuntimeBuilder) + Send + Sync>>,
+}
+
+impl WorkerRuntime {
+    /// Start arbiter of worker
+    pub(crate) fn arbiter(&self, idx: usize) -> Arbiter {
+        let mut builder = Arbiter::builder();
+        if let Some(ref prefix) = self.name {
+            builder = builder.name(format!("{}:{}", prefix, idx));
+        }
+        if let Some(size) = self.stack_size {
+            builder = builder.stack_size(size);
+        }
+        if let Some(ref f) = self.configure {
+            let f = f.clone();
+            builder = builder.runtime(move |rt| (*f)(rt));
+        }
+        builder.start()
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct WorkerClient {
     pub idx: usize,
+    /// Core that worker thread is pinned to
+    pub cpu: Option<usize>,
     tx1: UnboundedSender<WorkerCommand>,
     tx2: UnboundedSender<StopCommand>,
+    tx3: UnboundedSender<ServiceCommand>,
+    tx4: UnboundedSender<MaxConnsCommand>,
+    tx5: UnboundedSender<MetricsCommand>,
     avail: WorkerAvailability,
+    queued: Arc<AtomicUsize>,
 }
 
 impl WorkerClient {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         idx: usize,
         tx1: UnboundedSender<WorkerCommand>,
         tx2: UnboundedSender<StopCommand>,
+        tx3: UnboundedSender<ServiceCommand>,
+        tx4: UnboundedSender<MaxConnsCommand>,
+        tx5: UnboundedSender<MetricsCommand>,
         avail: WorkerAvailability,
+        queued: Arc<AtomicUsize>,
     ) -> Self {
         WorkerClient {
             idx,
+            cpu: None,
             tx1,
             tx2,
+            tx3,
+            tx4,
+            tx5,
             avail,
+            queued,
         }
     }
 
-    pub fn send(&self, msg: Conn) -> Result<(), Conn> {
-        self.tx1
-            .unbounded_send(WorkerCommand(msg))
-            .map_err(|msg| msg.into_inner().0)
+    /// Send connection to worker, connection is returned if worker is gone.
+    pub fn send(&self, msg: Conn) -> Result<(), Box<Conn>> {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        self.tx1.unbounded_send(WorkerCommand(msg)).map_err(|msg| {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            Box::new(msg.into_inner().0)
+        })
     }
 
     pub fn available(&self) -> bool {
         self.avail.available()
     }
 
-    pub fn stop(&self, graceful: bool) -> oneshot::Receiver<bool> {
+    pub fn stop(&self, graceful: bool) -> oneshot::Receiver<usize> {
         let (result, rx) = oneshot::channel();
         let _ = self.tx2.unbounded_send(StopCommand { graceful, result });
         rx
     }
+
+    pub fn add_service(
+        &self,
+        factory: Box<dyn InternalServiceFactory>,
+    ) -> oneshot::Receiver<bool> {
+        let (result, rx) = oneshot::channel();
+        let _ = self.tx3.unbounded_send(ServiceCommand::Add {
+            factory,
+            replace: None,
+            result,
+        });
+        rx
+    }
+
+    /// Replace services of factory with the index. Existing connections
+    /// are not affected.
+    pub fn replace_service(
+        &self,
+        idx: usize,
+        factory: Box<dyn InternalServiceFactory>,
+    ) -> oneshot::Receiver<bool> {
+        let (result, rx) = oneshot::channel();
+        let _ = self.tx3.unbounded_send(ServiceCommand::Add {
+            factory,
+            replace: Some(idx),
+            result,
+        });
+        rx
+    }
+
+    /// Remove services of last added factory with the index.
+    pub fn remove_service(&self, idx: usize) {
+        let _ = self.tx3.unbounded_send(ServiceCommand::Remove(idx));
+    }
+
+    pub fn set_max_connections(&self, num: usize) {
+        let _ = self.tx4.unbounded_send(MaxConnsCommand(num));
+    }
+
+    /// Metrics of worker, worker that does not answer in `dur` is reported
+    /// as unavailable.
+    pub fn metrics(&self, dur: time::Duration) -> impl Future<Output = WorkerMetrics> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.tx5.unbounded_send(MetricsCommand(tx));
+        let idx = self.idx;
+        let queued = self.queued.clone();
+        let avail = self.avail.clone();
+        timeout(dur, rx).map(move |res| match res {
+            Ok(Ok(connections)) => WorkerMetrics {
+                idx,
+                connections,
+                queued: queued.load(Ordering::Relaxed),
+                available: avail.available(),
+            },
+            _ => WorkerMetrics {
+                idx,
+                connections: 0,
+                queued: queued.load(Ordering::Relaxed),
+                available: false,
+            },
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -126,14 +278,34 @@ impl WorkerAvailability {
 /// Worker accepts Socket objects via unbounded channel and starts stream
 /// processing.
 pub(crate) struct Worker {
+    idx: usize,
+    srv: Server,
     rx: UnboundedReceiver<WorkerCommand>,
     rx2: UnboundedReceiver<StopCommand>,
+    rx3: UnboundedReceiver<ServiceCommand>,
+    rx4: UnboundedReceiver<MaxConnsCommand>,
+    rx5: UnboundedReceiver<MetricsCommand>,
+    queued: Arc<AtomicUsize>,
     services: Vec<WorkerService>,
     availability: WorkerAvailability,
     conns: Counter,
     factories: Vec<Box<dyn InternalServiceFactory>>,
     state: WorkerState,
     shutdown_timeout: time::Duration,
+    adding: Option<AddingService>,
+    heartbeat: Option<(time::Duration, Pin<Box<Delay>>)>,
+    /// Wakes worker up once first queued connection expires
+    deadline: Option<Pin<Box<Delay>>>,
+}
+
+type ServiceFuture = LocalBoxFuture<'static, Result<Vec<(Token, BoxedServerService)>, ()>>;
+
+struct AddingService {
+    factory: usize,
+    fut: ServiceFuture,
+    result: oneshot::Sender<bool>,
+    /// Replaced factory, restored if new service can not be created
+    replaced: Option<Box<dyn InternalServiceFactory>>,
 }
 
 struct WorkerService {
@@ -162,26 +334,45 @@ enum WorkerServiceStatus {
 impl Worker {
     pub(crate) fn start(
         idx: usize,
+        srv: Server,
         factories: Vec<Box<dyn InternalServiceFactory>>,
         availability: WorkerAvailability,
         shutdown_timeout: time::Duration,
+        heartbeat: Option<time::Duration>,
+        arbiter: Arbiter,
     ) -> WorkerClient {
         let (tx1, rx) = unbounded();
         let (tx2, rx2) = unbounded();
+        let (tx3, rx3) = unbounded();
+        let (tx4, rx4) = unbounded();
+        let (tx5, rx5) = unbounded();
         let avail = availability.clone();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let queued2 = queued.clone();
 
-        Arbiter::new().send(
+        arbiter.send(
             async move {
                 availability.set(false);
                 let mut wrk = MAX_CONNS_COUNTER.with(move |conns| Worker {
+                    idx,
+                    srv,
                     rx,
                     rx2,
+                    rx3,
+                    rx4,
+                    rx5,
+                    queued: queued2,
                     availability,
                     factories,
                     shutdown_timeout,
                     services: Vec::new(),
                     conns: conns.clone(),
-                    state: WorkerState::Unavailable(Vec::new()),
+                    state: WorkerState::Unavailable(VecDeque::new()),
+                    adding: None,
+                    heartbeat: heartbeat.map(|interval| {
+                        (interval, Box::pin(delay_until(Instant::now() + interval)))
+                    }),
+                    deadline: None,
                 });
 
                 let mut fut: Vec<MapOk<LocalBoxFuture<'static, _>, _>> = Vec::new();
@@ -200,7 +391,12 @@ impl Worker {
                         Ok(services) => {
                             for item in services {
                                 for (factory, token, service) in item {
-                                    assert_eq!(token.0, wrk.services.len());
+                                    if token.0 != wrk.services.len() {
+                                        error!("Can not start worker: tokens do not match");
+                                        wrk.srv.worker_failed(idx);
+                                        Arbiter::current().stop();
+                                        return;
+                                    }
                                     wrk.services.push(WorkerService {
                                         factory,
                                         service,
@@ -208,9 +404,11 @@ impl Worker {
                                     });
                                 }
                             }
+                            wrk.srv.worker_started(idx);
                         }
                         Err(e) => {
                             error!("Can not start worker: {:?}", e);
+                            wrk.srv.worker_failed(idx);
                             Arbiter::current().stop();
                         }
                     }
@@ -220,7 +418,7 @@ impl Worker {
             .boxed(),
         );
 
-        WorkerClient::new(idx, tx1, tx2, avail)
+        WorkerClient::new(idx, tx1, tx2, tx3, tx4, tx5, avail, queued)
     }
 
     fn shutdown(&mut self, force: bool) {
@@ -250,6 +448,180 @@ impl Worker {
         }
     }
 
+    /// Create services added to running server, one at a time so that
+    /// tokens match service indexes.
+    fn add_services(&mut self, cx: &mut Context<'_>) {
+        loop {
+            let res = match self.adding {
+                Some(ref mut adding) => match adding.fut.as_mut().poll(cx) {
+                    Poll::Ready(res) => res,
+                    Poll::Pending => return,
+                },
+                None => match Pin::new(&mut self.rx3).poll_next(cx) {
+                    Poll::Ready(Some(ServiceCommand::Add {
+                        factory,
+                        replace,
+                        result,
+                    })) => {
+                        let fut = factory.create();
+                        let (idx, replaced) = match replace {
+                            Some(idx) => (
+                                idx,
+                                Some(std::mem::replace(&mut self.factories[idx], factory)),
+                            ),
+                            None => {
+                                self.factories.push(factory);
+                                (self.factories.len() - 1, None)
+                            }
+                        };
+                        self.adding = Some(AddingService {
+                            factory: idx,
+                            fut,
+                            result,
+                            replaced,
+                        });
+                        continue;
+                    }
+                    Poll::Ready(Some(ServiceCommand::Remove(idx))) => {
+                        // only last factory can be removed without
+                        // shifting tokens of other services
+                        if idx + 1 == self.factories.len() {
+                            self.factories.pop();
+                            self.services.retain(|srv| srv.factory != idx);
+                        }
+                        continue;
+                    }
+                    _ => return,
+                },
+            };
+
+            let AddingService {
+                factory,
+                result,
+                replaced,
+                ..
+            } = self.adding.take().unwrap();
+            let res = match res {
+                Ok(services) if !self.valid_tokens(factory, &services, replaced.is_some()) => {
+                    error!("Tokens of created services do not match worker services");
+                    Err(())
+                }
+                res => res,
+            };
+            match res {
+                Ok(services) => {
+                    for (token, service) in services {
+                        if replaced.is_some() {
+                            // connections of old service are not affected
+                            trace!(
+                                "Service {:?} has been reloaded",
+                                self.factories[factory].name(token)
+                            );
+                            self.services[token.0].created(service);
+                        } else {
+                            self.services.push(WorkerService {
+                                factory,
+                                service,
+                                status: WorkerServiceStatus::Unavailable,
+                            });
+                        }
+                    }
+                    let _ = result.send(true);
+                }
+                Err(_) if replaced.is_some() => {
+                    error!("Can not reload service, old service is kept");
+                    self.factories[factory] = replaced.unwrap();
+                    let _ = result.send(false);
+                }
+                Err(_) => {
+                    error!(
+                        "Can not start {:?} service",
+                        self.factories[factory].name(Token(self.services.len()))
+                    );
+                    // services of other listeners keep running
+                    self.factories.pop();
+                    let _ = result.send(false);
+                }
+            }
+        }
+    }
+
+    /// Check that created services take next tokens, or tokens
+    /// of the replaced factory.
+    fn valid_tokens(
+        &self,
+        factory: usize,
+        services: &[(Token, BoxedServerService)],
+        replace: bool,
+    ) -> bool {
+        services.iter().enumerate().all(|(idx, (token, _))| {
+            if replace {
+                self.services
+                    .get(token.0)
+                    .is_some_and(|srv| srv.factory == factory)
+            } else {
+                token.0 == self.services.len() + idx
+            }
+        })
+    }
+
+    /// Move connections of unavailable worker to wait queue and close
+    /// connections that wait for too long.
+    fn expire_queued(&mut self, cx: &mut Context<'_>) {
+        let conns = match self.state {
+            WorkerState::Unavailable(ref mut conns)
+            | WorkerState::Restarting(_, _, _, ref mut conns) => conns,
+            _ => return,
+        };
+        let mut changed = false;
+        while conns.len() < MAX_QUEUED {
+            match Pin::new(&mut self.rx).poll_next(cx) {
+                Poll::Ready(Some(WorkerCommand(msg))) => {
+                    conns.push_back(msg);
+                    changed = true;
+                }
+                _ => break,
+            }
+        }
+        if let Some(ref mut delay) = self.deadline {
+            changed |= delay.as_mut().poll(cx).is_ready();
+        }
+        if !changed {
+            return;
+        }
+
+        let len = conns.len();
+        conns.retain(|conn| !conn.expired());
+        let expired = len - conns.len();
+        if expired != 0 {
+            trace!("Closing {} queued connections", expired);
+            self.queued.fetch_sub(expired, Ordering::Relaxed);
+        }
+
+        // wake up once first connection expires
+        match conns.iter().filter_map(|conn| conn.deadline).min() {
+            Some(deadline) => {
+                match self.deadline {
+                    Some(ref mut delay) => delay.as_mut().reset(deadline),
+                    None => self.deadline = Some(Box::pin(delay_until(deadline))),
+                }
+                if let Some(ref mut delay) = self.deadline {
+                    let _ = delay.as_mut().poll(cx);
+                }
+            }
+            None => self.deadline = None,
+        }
+    }
+
+    /// Take connections of wait queue, so that they are passed to next state.
+    fn take_queued(&mut self) -> VecDeque<Conn> {
+        match self.state {
+            WorkerState::Unavailable(ref mut conns)
+            | WorkerState::Restarting(_, _, _, ref mut conns) => mem::take(conns),
+            _ => VecDeque::new(),
+        }
+    }
+
     fn check_readiness(&mut self, cx: &mut Context<'_>) -> Result<bool, (Token, usize)> {
         let mut ready = self.conns.available(cx);
         let mut failed = None;
@@ -299,16 +671,17 @@ impl Worker {
 
 enum WorkerState {
     Available,
-    Unavailable(Vec<Conn>),
+    Unavailable(VecDeque<Conn>),
     Restarting(
         usize,
         Token,
         Pin<Box<dyn Future<Output = Result<Vec<(Token, BoxedServerService)>, ()>>>>,
+        VecDeque<Conn>,
     ),
     Shutdown(
         Pin<Box<Delay>>,
         Pin<Box<Delay>>,
-        Option<oneshot::Sender<bool>>,
+        Option<oneshot::Sender<usize>>,
     ),
 }
 
@@ -321,13 +694,20 @@ impl Future for Worker {
             Pin::new(&mut self.rx2).poll_next(cx)
         {
             self.availability.set(false);
+            let queued = self.take_queued();
+            if !queued.is_empty() {
+                trace!("Closing {} queued connections", queued.len());
+                self.queued.fetch_sub(queued.len(), Ordering::Relaxed);
+            }
             let num = num_connections();
             if num == 0 {
                 info!("Shutting down worker, 0 connections");
-                let _ = result.send(true);
+                let _ = result.send(0);
+                Arbiter::current().stop();
                 return Poll::Ready(());
             } else if graceful {
                 self.shutdown(false);
+                shutdown::notify();
                 let num = num_connections();
                 if num != 0 {
                     info!("Graceful worker shutdown, {} connections", num);
@@ -337,59 +717,92 @@ impl Future for Worker {
                         Some(result),
                     );
                 } else {
-                    let _ = result.send(true);
+                    let _ = result.send(0);
+                    Arbiter::current().stop();
                     return Poll::Ready(());
                 }
             } else {
                 info!("Force shutdown worker, {} connections", num);
                 self.shutdown(true);
-                let _ = result.send(false);
+                let _ = result.send(num);
                 return Poll::Ready(());
             }
         }
 
+        // heartbeat is sent only while worker is polled
+        let mut beat = false;
+        if let Some((interval, ref mut delay)) = self.heartbeat {
+            if delay.as_mut().poll(cx).is_ready() {
+                *delay = Box::pin(delay_until(Instant::now() + interval));
+                let _ = delay.as_mut().poll(cx);
+                beat = true;
+            }
+        }
+        if beat {
+            self.srv.worker_heartbeat(self.idx);
+        }
+
+        // `MaxConns` message handler
+        while let Poll::Ready(Some(MaxConnsCommand(num))) =
+            Pin::new(&mut self.rx4).poll_next(cx)
+        {
+            trace!("Setting max connections to {}", num);
+            self.conns.set_capacity(num);
+        }
+
+        // `Metrics` message handler
+        while let Poll::Ready(Some(MetricsCommand(tx))) = Pin::new(&mut self.rx5).poll_next(cx)
+        {
+            let _ = tx.send(num_connections());
+        }
+
+        // `AddService` message handler
+        self.add_services(cx);
+
+        self.expire_queued(cx);
+
         match self.state {
-            WorkerState::Unavailable(ref mut conns) => {
-                let conn = conns.pop();
+            WorkerState::Unavailable(_) => loop {
                 match self.check_readiness(cx) {
                     Ok(true) => {
                         // process requests from wait queue
+                        let conn = match self.state {
+                            WorkerState::Unavailable(ref mut conns) => conns.pop_front(),
+                            _ => None,
+                        };
                         if let Some(conn) = conn {
+                            self.queued.fetch_sub(1, Ordering::Relaxed);
                             let guard = self.conns.get();
-                            let _ = self.services[conn.token.0]
-                                .service
-                                .call((Some(guard), ServerMessage::Connect(conn.io)));
+                            let _ = self.services[conn.token.0].service.call((
+                                Some(guard),
+                                ServerMessage::Connect(conn.io, conn.limits, conn.guard),
+                            ));
                         } else {
                             self.state = WorkerState::Available;
                             self.availability.set(true);
+                            self.deadline = None;
+                            return self.poll(cx);
                         }
-                        self.poll(cx)
-                    }
-                    Ok(false) => {
-                        // push connection back to queue
-                        if let Some(conn) = conn {
-                            match self.state {
-                                WorkerState::Unavailable(ref mut conns) => {
-                                    conns.push(conn);
-                                }
-                                _ => (),
-                            }
-                        }
-                        Poll::Pending
                     }
+                    Ok(false) => return Poll::Pending,
                     Err((token, idx)) => {
                         trace!(
                             "Service {:?} failed, restarting",
                             self.factories[idx].name(token)
                         );
                         self.services[token.0].status = WorkerServiceStatus::Restarting;
-                        self.state =
-                            WorkerState::Restarting(idx, token, self.factories[idx].create());
-                        self.poll(cx)
+                        let conns = self.take_queued();
+                        self.state = WorkerState::Restarting(
+                            idx,
+                            token,
+                            self.factories[idx].create(),
+                            conns,
+                        );
+                        return self.poll(cx);
                     }
                 }
-            }
-            WorkerState::Restarting(idx, token, ref mut fut) => {
+            },
+            WorkerState::Restarting(idx, token, ref mut fut, _) => {
                 match Pin::new(fut).poll(cx) {
                     Poll::Ready(Ok(item)) => {
                         for (token, service) in item {
@@ -398,15 +811,22 @@ impl Future for Worker {
                                 self.factories[idx].name(token)
                             );
                             self.services[token.0].created(service);
-                            self.state = WorkerState::Unavailable(Vec::new());
+                            // queued connections are processed by restarted service
+                            let conns = self.take_queued();
+                            self.state = WorkerState::Unavailable(conns);
                             return self.poll(cx);
                         }
                     }
                     Poll::Ready(Err(_)) => {
-                        panic!(
+                        error!(
                             "Can not restart {:?} service",
                             self.factories[idx].name(token)
                         );
+                        let conns = self.take_queued();
+                        self.queued.fetch_sub(conns.len(), Ordering::Relaxed);
+                        self.srv.worker_failed(self.idx);
+                        Arbiter::current().stop();
+                        return Poll::Ready(());
                     }
                     Poll::Pending => {
                         return Poll::Pending;
@@ -417,7 +837,7 @@ impl Future for Worker {
             WorkerState::Shutdown(ref mut t1, ref mut t2, ref mut tx) => {
                 let num = num_connections();
                 if num == 0 {
-                    let _ = tx.take().unwrap().send(true);
+                    let _ = tx.take().unwrap().send(0);
                     Arbiter::current().stop();
                     return Poll::Ready(());
                 }
@@ -426,7 +846,8 @@ impl Future for Worker {
                 match t2.as_mut().poll(cx) {
                     Poll::Pending => (),
                     Poll::Ready(_) => {
-                        let _ = tx.take().unwrap().send(false);
+                        info!("Graceful shutdown timeout, {} connections", num);
+                        let _ = tx.take().unwrap().send(num);
                         self.shutdown(true);
                         Arbiter::current().stop();
                         return Poll::Ready(());
@@ -450,18 +871,30 @@ impl Future for Worker {
                     match Pin::new(&mut self.rx).poll_next(cx) {
                         // handle incoming io stream
                         Poll::Ready(Some(WorkerCommand(msg))) => {
+                            if msg.expired() {
+                                trace!("Closing queued connection");
+                                self.queued.fetch_sub(1, Ordering::Relaxed);
+                                continue;
+                            }
                             match self.check_readiness(cx) {
                                 Ok(true) => {
+                                    self.queued.fetch_sub(1, Ordering::Relaxed);
                                     let guard = self.conns.get();
-                                    let _ = self.services[msg.token.0]
-                                        .service
-                                        .call((Some(guard), ServerMessage::Connect(msg.io)));
+                                    let _ = self.services[msg.token.0].service.call((
+                                        Some(guard),
+                                        ServerMessage::Connect(msg.io, msg.limits, msg.guard),
+                                    ));
                                     continue;
                                 }
                                 Ok(false) => {
                                     trace!("Worker is unavailable");
                                     self.availability.set(false);
-                                    self.state = WorkerState::Unavailable(vec![msg]);
+                                    // wake up once connection expires
+                                    self.deadline =
+                                        msg.deadline.map(|d| Box::pin(delay_until(d)));
+                                    let mut conns = VecDeque::new();
+                                    conns.push_back(msg);
+                                    self.state = WorkerState::Unavailable(conns);
                                 }
                                 Err((token, idx)) => {
                                     trace!(
@@ -471,10 +904,16 @@ impl Future for Worker {
                                     self.availability.set(false);
                                     self.services[token.0].status =
                                         WorkerServiceStatus::Restarting;
+                                    // connection waits for restarted service
+                                    self.deadline =
+                                        msg.deadline.map(|d| Box::pin(delay_until(d)));
+                                    let mut conns = VecDeque::new();
+                                    conns.push_back(msg);
                                     self.state = WorkerState::Restarting(
                                         idx,
                                         token,
                                         self.factories[idx].create(),
+                                        conns,
                                     );
                                 }
                             }
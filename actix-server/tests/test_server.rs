@@ -70,6 +70,225 @@ fn test_listen() {
     let _ = h.join();
 }
 
+#[test]
+#[cfg(unix)]
+fn test_bind_reuseport() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_reuseport("test1", addr, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .bind_reuseport("test2", addr, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = tx.send(actix_rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+
+    thread::sleep(time::Duration::from_millis(500));
+    assert!(net::TcpStream::connect(addr).is_ok());
+    assert!(net::TcpStream::connect(addr).is_ok());
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_bind_uds_with() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("actix-server-{}.sock", std::process::id()));
+    let _ = std::fs::File::create(&path);
+    let (tx, rx) = mpsc::channel();
+
+    let path2 = path.clone();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_uds_with(
+                "test",
+                &path2,
+                actix_server::UdsOptions::new().mode(0o600),
+                move || fn_service(|_| ok::<_, ()>(())),
+            )
+            .unwrap()
+            .start();
+        let _ = tx.send(actix_rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+
+    thread::sleep(time::Duration::from_millis(500));
+    let meta = std::fs::metadata(&path).unwrap();
+    assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+    assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+    sys.stop();
+    let _ = h.join();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+#[cfg(windows)]
+fn test_bind_named_pipe() {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use actix_server::NamedPipeStream;
+    use futures::StreamExt;
+
+    let path = format!(r"\\.\pipe\actix-server-test-{}", std::process::id());
+    let path2 = path.clone();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_named_pipe("test", &path2, move || {
+                fn_service(|io: NamedPipeStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    if let Some(Ok(item)) = f.next().await {
+                        f.send(item.freeze()).await.unwrap();
+                    }
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send(actix_rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // pipe is bound already
+    assert!(Server::build()
+        .bind_named_pipe("test", &path, || fn_service(|_| ok::<_, ()>(())))
+        .is_err());
+
+    // more clients than instances waiting for clients
+    for _ in 0..8 {
+        let mut pipe = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        pipe.write_all(b"test").unwrap();
+        let mut buf = [0; 4];
+        pipe.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"test");
+
+        // pipe is closed once service is done
+        assert_eq!(pipe.read(&mut buf).unwrap_or(0), 0);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_listen_from_env_not_activated() {
+    let res = Server::build().listen_from_env("test", move || fn_service(|_| ok::<_, ()>(())));
+    match res {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        Ok(_) => panic!("socket is not activated"),
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn test_listen_from_env() {
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    // activated socket is passed at fd 3, that is only safe in new process
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+    let fd = lst.as_raw_fd();
+    let mut cmd = std::process::Command::new(std::env::current_exe().unwrap());
+    cmd.arg("--exact")
+        .arg("test_listen_from_env_activated")
+        .arg("--ignored")
+        .env("ACTIX_TEST_ACTIVATED_ADDR", addr.to_string());
+    unsafe {
+        cmd.pre_exec(move || {
+            // duplicate without close-on-exec flag
+            let tmp = libc::dup(fd);
+            if tmp < 0 || libc::dup2(tmp, 3) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            libc::close(tmp);
+            Ok(())
+        });
+    }
+    let out = cmd.output().unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "{}", stdout);
+    assert!(stdout.contains("1 passed"));
+}
+
+#[test]
+#[ignore]
+#[cfg(unix)]
+/// Started by `test_listen_from_env` with listening socket at fd 3.
+fn test_listen_from_env_activated() {
+    let addr: net::SocketAddr = match std::env::var("ACTIX_TEST_ACTIVATED_ADDR") {
+        Ok(addr) => addr.parse().unwrap(),
+        Err(_) => return,
+    };
+
+    // sockets without LISTEN_PID are not taken
+    std::env::set_var("LISTEN_FDS", "1");
+    std::env::set_var("LISTEN_FDNAMES", "test");
+    let res = Server::build().listen_from_env("test", move || fn_service(|_| ok::<_, ()>(())));
+    match res {
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::NotFound),
+        Ok(_) => panic!("LISTEN_PID is not set"),
+    }
+
+    std::env::set_var("LISTEN_PID", std::process::id().to_string());
+    std::env::set_var("LISTEN_FDS", "1");
+    std::env::set_var("LISTEN_FDNAMES", "test");
+    let (tx, rx) = mpsc::channel();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .listen_from_env("test", move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"activated")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut res = String::new();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "activated");
+
+    sys.stop();
+    let _ = h.join();
+}
+
 #[test]
 #[cfg(unix)]
 fn test_start() {
@@ -181,3 +400,2884 @@ fn test_configure() {
     let _ = sys.stop();
     let _ = h.join();
 }
+
+#[test]
+fn test_bind_proxy() {
+    use std::io::Write;
+
+    use actix_server::ProxyStream;
+    use futures::StreamExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_proxy("test", addr, move || {
+                fn_service(|io: ProxyStream<TcpStream>| async move {
+                    let source = io.info().source();
+                    let mut f = Framed::new(io, BytesCodec);
+                    let data = f.next().await.unwrap().unwrap();
+                    let msg = format!("{:?} {}", source, String::from_utf8_lossy(&data));
+                    f.send(Bytes::from(msg)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nv1")
+        .unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "Some(192.168.0.1:56324) v1");
+
+    let mut header = b"\r\n\r\n\0\r\nQUIT\n\x21\x21\x00\x24".to_vec();
+    header.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    header.extend_from_slice(&[0; 16]);
+    header.extend_from_slice(&[0x1f, 0x90, 0x01, 0xbb]);
+    header.extend_from_slice(b"v2");
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(&header).unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "Some([::1]:8080) v2");
+
+    // connection without header is closed
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut res = String::new();
+    let _ = conn.read_to_string(&mut res);
+    assert!(res.is_empty());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_with() {
+    use actix_server::TcpOptions;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let opts = TcpOptions::new()
+            .nodelay(true)
+            .keepalive(time::Duration::from_secs(60));
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("test", addr, opts, move || {
+                fn_service(|io: TcpStream| async move {
+                    let res =
+                        format!("{} {:?}", io.nodelay().unwrap(), io.keepalive().unwrap());
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from(res)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "true Some(60s)");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_bind_with_subsec_keepalive() {
+    use actix_server::TcpOptions;
+    use std::os::unix::io::AsRawFd;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let opts = TcpOptions::new()
+            .keepalive(time::Duration::from_millis(1500))
+            .keepalive_interval(time::Duration::from_millis(200));
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("test", addr, opts, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut interval: libc::c_int = 0;
+                    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+                    unsafe {
+                        libc::getsockopt(
+                            io.as_raw_fd(),
+                            libc::IPPROTO_TCP,
+                            libc::TCP_KEEPINTVL,
+                            &mut interval as *mut _ as *mut libc::c_void,
+                            &mut len,
+                        )
+                    };
+                    let res = format!("{:?} {}", io.keepalive().unwrap(), interval);
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from(res)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+
+    // durations are rounded up to whole seconds
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "Some(2s) 1");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_shutdown_signal() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .shutdown_timeout(10)
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let signal = actix_server::shutdown_signal();
+                    assert!(!signal.is_shutdown());
+                    signal.await;
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"bye")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(500));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(5)))
+        .unwrap();
+    thread::sleep(time::Duration::from_millis(200));
+
+    // active connection is finished before shutdown timeout
+    let _ = srv.stop(true);
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "bye");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_handoff() {
+    fn start(
+        tx: mpsc::Sender<(Server, actix_rt::System)>,
+        builder: actix_server::ServerBuilder,
+        msg: &'static [u8],
+    ) {
+        let sys = actix_rt::System::new("test");
+        let srv = builder
+            .workers(1)
+            .disable_signals()
+            .listen_from_env("test", move || {
+                fn_service(move |io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(msg)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    }
+
+    let addr = unused_addr();
+    let path = std::env::temp_dir().join(format!("actix-handoff-{}.sock", std::process::id()));
+    let (tx, rx) = mpsc::channel();
+
+    let h1 = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"old")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv1, sys1) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut res = String::new();
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "old");
+
+    // new server waits for listeners
+    let (tx, rx) = mpsc::channel();
+    let path2 = path.clone();
+    let h2 = thread::spawn(move || {
+        let builder = Server::build().receive_handoff(&path2).unwrap();
+        start(tx, builder, b"new");
+    });
+    thread::sleep(time::Duration::from_millis(300));
+
+    let _ = srv1.handoff(&path);
+    let (_, sys2) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for _ in 0..3 {
+        let mut res = String::new();
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.read_to_string(&mut res).unwrap();
+        assert_eq!(res, "new");
+    }
+
+    let _ = sys1.stop();
+    let _ = sys2.stop();
+    let _ = h1.join();
+    let _ = h2.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_handoff_names() {
+    fn reply(msg: &'static [u8]) -> impl actix_server::ServiceFactory<TcpStream> {
+        move || {
+            fn_service(move |io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(msg)).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        }
+    }
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let path =
+        std::env::temp_dir().join(format!("actix-handoff-names-{}.sock", std::process::id()));
+    let (tx, rx) = mpsc::channel();
+
+    let h1 = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind("multi\nline", addr1, reply(b"old"))
+            .unwrap()
+            .bind("test", addr2, reply(b"old"))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv1, sys1) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // names with newlines keep mapping of descriptors
+    let (tx, rx) = mpsc::channel();
+    let path2 = path.clone();
+    let h2 = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .receive_handoff(&path2)
+            .unwrap()
+            .workers(1)
+            .disable_signals()
+            .listen_from_env("multi\nline", reply(b"multi"))
+            .unwrap()
+            .listen_from_env("test", reply(b"test"))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    thread::sleep(time::Duration::from_millis(300));
+
+    let _ = srv1.handoff(&path);
+    let (_, sys2) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for (addr, msg) in &[(addr1, "multi"), (addr2, "test")] {
+        let mut res = String::new();
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.read_to_string(&mut res).unwrap();
+        assert_eq!(res, *msg);
+    }
+
+    let _ = sys1.stop();
+    let _ = sys2.stop();
+    let _ = h1.join();
+    let _ = h2.join();
+}
+
+#[test]
+fn test_add_remove_listener() {
+    use futures::executor::block_on;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test1", addr1, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(net::TcpStream::connect(addr2).is_err());
+
+    block_on(srv.add_listener("test2", addr2, move || {
+        fn_service(|io: TcpStream| async move {
+            let mut f = Framed::new(io, BytesCodec);
+            f.send(Bytes::from_static(b"test2")).await.unwrap();
+            Ok::<_, ()>(())
+        })
+    }))
+    .unwrap();
+    for _ in 0..4 {
+        let mut res = String::new();
+        let mut conn = net::TcpStream::connect(addr2).unwrap();
+        conn.read_to_string(&mut res).unwrap();
+        assert_eq!(res, "test2");
+    }
+
+    block_on(srv.remove_listener("test1")).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    assert!(net::TcpStream::connect(addr1).is_err());
+    assert!(net::TcpStream::connect(addr2).is_ok());
+
+    let err = block_on(srv.remove_listener("test1")).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_add_listener_failure() {
+    use actix_service::boxed::{self, BoxService};
+    use actix_service::fn_factory;
+    use futures::executor::block_on;
+
+    fn serve(name: &'static [u8]) -> BoxService<TcpStream, (), ()> {
+        boxed::service(fn_service(move |io: TcpStream| async move {
+            let mut f = Framed::new(io, BytesCodec);
+            f.send(Bytes::from_static(name)).await.unwrap();
+            Ok::<_, ()>(())
+        }))
+    }
+
+    fn check(addr: net::SocketAddr, name: &str) {
+        for _ in 0..4 {
+            let mut res = String::new();
+            let mut conn = net::TcpStream::connect(addr).unwrap();
+            conn.read_to_string(&mut res).unwrap();
+            assert_eq!(res, name);
+        }
+    }
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let addr3 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test1", addr1, move || {
+                fn_factory(|| async { Ok::<_, ()>(serve(b"test1")) })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // service fails on one of workers only
+    let calls = Arc::new(AtomicUsize::new(0));
+    let res = block_on(srv.add_listener("test2", addr2, move || {
+        let fail = calls.fetch_add(1, Relaxed) == 0;
+        fn_factory(move || async move {
+            if fail {
+                Err(())
+            } else {
+                Ok(serve(b"test2"))
+            }
+        })
+    }));
+    assert!(res.is_err());
+    assert!(net::TcpStream::connect(addr2).is_err());
+
+    // both workers keep serving, tokens of later listeners match services
+    check(addr1, "test1");
+    block_on(srv.add_listener("test3", addr3, move || {
+        fn_factory(|| async { Ok::<_, ()>(serve(b"test3")) })
+    }))
+    .unwrap();
+    check(addr3, "test3");
+    check(addr1, "test1");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_set_workers() {
+    use std::collections::HashSet;
+
+    use futures::executor::block_on;
+
+    fn threads(addr: net::SocketAddr) -> usize {
+        let mut threads = HashSet::new();
+        for _ in 0..6 {
+            let mut res = String::new();
+            let mut conn = net::TcpStream::connect(addr).unwrap();
+            conn.read_to_string(&mut res).unwrap();
+            threads.insert(res);
+        }
+        threads.len()
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let id = format!("{:?}", thread::current().id());
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from(id)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(threads(addr), 1);
+
+    block_on(srv.set_workers(3));
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(threads(addr), 3);
+
+    block_on(srv.set_workers(1));
+    assert_eq!(threads(addr), 1);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_affinity() {
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+
+    let addr = unused_addr();
+    let cores = Arc::new(Mutex::new(HashSet::new()));
+    let cores2 = cores.clone();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .worker_affinity(vec![3, 5])
+            .accept_affinity(1)
+            .affinity_backend(move |core| {
+                cores2.lock().unwrap().insert(core);
+                Ok(())
+            })
+            .bind("test", addr, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let expected: HashSet<usize> = vec![1, 3, 5].into_iter().collect();
+    assert_eq!(*cores.lock().unwrap(), expected);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_set_max_connections() {
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    fn connect(addr: net::SocketAddr) -> net::TcpStream {
+        let conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_millis(300)))
+            .unwrap();
+        conn
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"1")).await.unwrap();
+                    while let Some(Ok(_)) = f.next().await {}
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    block_on(srv.set_max_connections(1));
+    let mut buf = [0; 1];
+    let mut conn1 = connect(addr);
+    assert!(conn1.read_exact(&mut buf).is_ok());
+    let mut conn2 = connect(addr);
+    assert!(conn2.read_exact(&mut buf).is_err());
+
+    block_on(srv.set_max_connections(2));
+    assert!(conn2.read_exact(&mut buf).is_ok());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_metrics() {
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"1")).await.unwrap();
+                    while let Some(Ok(_)) = f.next().await {}
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let metrics = block_on(srv.metrics());
+    assert_eq!(metrics.workers().len(), 2);
+    assert_eq!(metrics.connections(), 0);
+    assert_eq!(metrics.accepted(), 0);
+
+    let mut buf = [0; 1];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    let metrics = block_on(srv.metrics());
+    assert_eq!(metrics.connections(), 1);
+    assert_eq!(metrics.accepted(), 1);
+    assert_eq!(metrics.accept_errors(), 0);
+    assert!(metrics.workers().iter().all(|w| w.queued() == 0));
+    assert!(!metrics.is_paused());
+
+    block_on(srv.pause());
+    assert!(block_on(srv.metrics()).is_paused());
+
+    drop(conn);
+    thread::sleep(time::Duration::from_millis(100));
+    assert_eq!(block_on(srv.metrics()).connections(), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_metrics_blocked_worker() {
+    use futures::executor::block_on;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"1")).await.unwrap();
+                    // block worker thread
+                    thread::sleep(time::Duration::from_secs(3));
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 1];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    // blocked worker does not delay metrics of other workers
+    let start = time::Instant::now();
+    let metrics = block_on(srv.metrics());
+    assert!(start.elapsed() < time::Duration::from_secs(2));
+    assert_eq!(metrics.workers().len(), 2);
+    assert_eq!(
+        metrics
+            .workers()
+            .iter()
+            .filter(|w| w.is_available())
+            .count(),
+        1
+    );
+    assert_eq!(metrics.connections(), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_on_connect() {
+    use actix_server::Connection;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .on_connect(|io, ext| {
+                if let Some(io) = io.downcast_ref::<TcpStream>() {
+                    ext.insert(io.peer_addr().unwrap());
+                }
+            })
+            .bind_connection("test", addr, move || {
+                fn_service(|conn: Connection<TcpStream>| async move {
+                    let peer = *conn.extensions().get::<net::SocketAddr>().unwrap();
+                    let mut f = Framed::new(conn, BytesCodec);
+                    f.send(Bytes::from(peer.to_string())).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut res = String::new();
+    conn.read_to_string(&mut res).unwrap();
+    assert_eq!(res, conn.local_addr().unwrap().to_string());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_signals() {
+    use actix_server::{Signal, SignalSet};
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let signals = SignalSet::new()
+            .on(Signal::Usr1, move |_| {
+                let _ = tx.send(());
+            })
+            .graceful(Signal::Usr2);
+        let _ = Server::build()
+            .workers(1)
+            .signals(signals)
+            .bind("test", addr, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = sys.run();
+    });
+    thread::sleep(time::Duration::from_millis(300));
+
+    unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) };
+    assert!(rx.recv_timeout(time::Duration::from_secs(1)).is_ok());
+
+    // server stops actix system
+    unsafe { libc::kill(libc::getpid(), libc::SIGUSR2) };
+    let _ = h.join();
+}
+
+#[test]
+fn test_restart_policy() {
+    use actix_server::RestartPolicy;
+    use actix_service::boxed::BoxService;
+    use actix_service::fn_factory;
+    use futures::executor::block_on;
+
+    let addr = unused_addr();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let attempts2 = attempts.clone();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .restart_policy(RestartPolicy::new().max_attempts(2).backoff(
+                time::Duration::from_millis(10),
+                time::Duration::from_secs(1),
+            ))
+            .bind("test", addr, move || {
+                attempts2.fetch_add(1, Relaxed);
+                fn_factory(|| async { Err::<BoxService<TcpStream, (), ()>, _>(()) })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    assert!(block_on(srv).is_err());
+    assert_eq!(attempts.load(Relaxed), 3);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_threads() {
+    use futures::executor::block_on;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .accept_threads(2)
+            .disable_signals()
+            .bind("test1", addr1, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .bind("test2", addr2, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for addr in &[addr1, addr2] {
+        let mut buf = Vec::new();
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.read_to_end(&mut buf).unwrap();
+    }
+    assert_eq!(block_on(srv.metrics()).accepted(), 2);
+
+    block_on(srv.set_workers(1));
+    for addr in &[addr1, addr2] {
+        let mut buf = Vec::new();
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.read_to_end(&mut buf).unwrap();
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_udp() {
+    use actix_rt::net::UdpSocket;
+    use actix_server::UdpFramed;
+    use futures::StreamExt;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind_udp("udp", addr1, || {
+                fn_service(|mut sock: UdpSocket| async move {
+                    let mut buf = [0; 64];
+                    while let Ok((n, peer)) = sock.recv_from(&mut buf).await {
+                        let _ = sock.send_to(&buf[..n], &peer).await;
+                    }
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .bind_udp_framed("framed", addr2, BytesCodec, || {
+                fn_service(|mut framed: UdpFramed<BytesCodec>| async move {
+                    while let Some(Ok((data, peer))) = framed.next().await {
+                        let _ = framed.send((data.freeze(), peer)).await;
+                    }
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let client = net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    client
+        .set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    let mut buf = [0; 64];
+    for addr in &[addr1, addr2] {
+        client.send_to(b"test", addr).unwrap();
+        let (n, peer) = client.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"test");
+        assert_eq!(peer, *addr);
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_connection_limits() {
+    use actix_server::TcpOptions;
+    use futures::StreamExt;
+    use std::io::Write;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let factory = || {
+            fn_service(|io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                while let Some(Ok(_)) = f.next().await {}
+                Ok::<_, ()>(())
+            })
+        };
+        let idle = TcpOptions::new().idle_timeout(time::Duration::from_millis(300));
+        let lifetime = TcpOptions::new().max_lifetime(time::Duration::from_millis(500));
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("idle", addr1, idle, factory)
+            .unwrap()
+            .bind_with("lifetime", addr2, lifetime, factory)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 1];
+
+    // active connection is not closed
+    let mut conn = net::TcpStream::connect(addr1).unwrap();
+    let start = time::Instant::now();
+    for _ in 0..4 {
+        thread::sleep(time::Duration::from_millis(150));
+        conn.write_all(b"1").unwrap();
+    }
+    conn.set_read_timeout(Some(time::Duration::from_millis(100)))
+        .unwrap();
+    assert!(conn.read(&mut buf).is_err());
+
+    // idle connection is closed
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+    assert!(start.elapsed() >= time::Duration::from_millis(900));
+
+    // connection is closed after max lifetime
+    let mut conn = net::TcpStream::connect(addr2).unwrap();
+    let start = time::Instant::now();
+    conn.set_read_timeout(Some(time::Duration::from_millis(100)))
+        .unwrap();
+    while conn.write_all(b"1").is_ok() {
+        match conn.read(&mut buf) {
+            Ok(0) => break,
+            Ok(_) => panic!(),
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                assert!(start.elapsed() < time::Duration::from_secs(2))
+            }
+            Err(_) => break,
+        }
+    }
+    assert!(start.elapsed() >= time::Duration::from_millis(500));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_connection_limits_shutdown() {
+    use actix_server::TcpOptions;
+    use futures::future::{select, Either};
+    use futures::StreamExt;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        // service is woken up by timer without any io
+        let factory = || {
+            fn_service(|io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                let mut tick = actix_rt::time::interval(time::Duration::from_millis(50));
+                while let Either::Left((Some(Ok(_)), _)) | Either::Right(_) =
+                    select(f.next(), Box::pin(tick.tick())).await
+                {}
+                // end of stream is read once limit is exceeded
+                f.send(Bytes::from_static(b"bye")).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        };
+        let idle = TcpOptions::new().idle_timeout(time::Duration::from_millis(300));
+        let lifetime = TcpOptions::new().max_lifetime(time::Duration::from_millis(300));
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("idle", addr1, idle, factory)
+            .unwrap()
+            .bind_with("lifetime", addr2, lifetime, factory)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut addrs = vec![addr2];
+    // idle connection is detected by socket activity on linux only
+    if cfg!(target_os = "linux") {
+        addrs.push(addr1);
+    }
+    for addr in addrs {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+            .unwrap();
+        let start = time::Instant::now();
+        let mut res = String::new();
+        conn.read_to_string(&mut res).unwrap();
+        assert_eq!(res, "bye");
+        assert!(start.elapsed() >= time::Duration::from_millis(300));
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_async() {
+    use actix_service::boxed::BoxServiceFactory;
+    use futures::executor::block_on;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_async("test", addr, || async {
+                actix_rt::time::delay_for(time::Duration::from_millis(300)).await;
+                Ok(|| {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+
+    // listener is bound before service is configured
+    let start = time::Instant::now();
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+    assert!(start.elapsed() >= time::Duration::from_millis(250));
+
+    sys.stop();
+    let _ = h.join();
+
+    // server stops if service can not be configured
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_async("test", addr, || async {
+                Err::<fn() -> BoxServiceFactory<(), TcpStream, (), (), ()>, _>(
+                    std::io::Error::new(std::io::ErrorKind::Other, "no config"),
+                )
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    assert!(block_on(srv).is_err());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_listener_options() {
+    use actix_server::TcpOptions;
+
+    let port = unused_addr().port();
+    let addr = net::SocketAddr::from((net::Ipv6Addr::UNSPECIFIED, port));
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let opts = TcpOptions::new().backlog(16).only_v6(true);
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("test", addr, opts, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // ipv4 address with the same port is not taken by listener
+    assert!(net::TcpListener::bind(("127.0.0.1", port)).is_ok());
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect((net::Ipv6Addr::LOCALHOST, port)).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_stopped() {
+    use actix_server::StopReason;
+    use futures::executor::block_on;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test", addr, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let srv2 = srv.clone();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || tx.send(block_on(srv2.stopped())));
+    thread::sleep(time::Duration::from_millis(300));
+    assert!(rx.try_recv().is_err());
+
+    block_on(srv.stop(true));
+    assert_eq!(rx.recv().unwrap(), StopReason::Stop { graceful: true });
+    // already stopped server resolves immediately
+    assert_eq!(block_on(srv.stopped()), StopReason::Stop { graceful: true });
+
+    sys.stop();
+    let _ = h.join();
+    assert_eq!(block_on(srv.stopped()), StopReason::Dropped);
+}
+
+#[test]
+fn test_pause_listener() {
+    use futures::executor::block_on;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let factory = || {
+            fn_service(|io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(b"1")).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        };
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind("public", addr1, factory)
+            .unwrap()
+            .bind("admin", addr2, factory)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    assert!(block_on(srv.pause_listener("unknown")).is_err());
+    block_on(srv.pause_listener("public")).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+
+    let mut buf = [0; 1];
+    let mut conn = net::TcpStream::connect(addr1).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_millis(300)))
+        .unwrap();
+    assert!(conn.read_exact(&mut buf).is_err());
+
+    // other listeners are not paused
+    let mut conn2 = net::TcpStream::connect(addr2).unwrap();
+    conn2.read_exact(&mut buf).unwrap();
+
+    // pending connection is accepted after resume
+    block_on(srv.resume_listener("public")).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    sys.stop();
+    let _ = h.join();
+}
+#[test]
+#[cfg(unix)]
+fn test_pause_uds_listener() {
+    use futures::executor::block_on;
+    use std::os::unix::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("actix-pause-{}.sock", std::process::id()));
+    let (tx, rx) = mpsc::channel();
+
+    let path2 = path.clone();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_uds("test", &path2, || {
+                fn_service(|io: actix_rt::net::UnixStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"1")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // socket file is kept while listener is paused
+    block_on(srv.pause_listener("test")).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    assert!(path.exists());
+
+    let mut buf = [0; 1];
+    let mut conn = UnixStream::connect(&path).unwrap();
+    block_on(srv.resume_listener("test")).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    // socket file is removed once listener is closed
+    block_on(srv.remove_listener("test")).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    assert!(!path.exists());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_tls() {
+    use actix_codec::AsyncRead;
+    use futures::future::poll_fn;
+    use std::io::Write;
+    use std::pin::Pin;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        // fake handshake, client has to send "1"
+        let acceptor = fn_service(|mut io: TcpStream| async move {
+            let mut buf = [0; 1];
+            poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut buf)).await?;
+            if buf[0] == b'1' {
+                Ok(io)
+            } else {
+                Err(std::io::Error::new(std::io::ErrorKind::Other, "handshake"))
+            }
+        });
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_tls("test", addr, acceptor, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"1").unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    // connection is closed on handshake error
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"0").unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_rate() {
+    use actix_server::{RateLimitPolicy, TcpOptions};
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let factory = || {
+            fn_service(|io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(b"test")).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        };
+        let delay = TcpOptions::new().accept_rate(2, 1);
+        let close = TcpOptions::new()
+            .accept_rate(2, 1)
+            .rate_limit_policy(RateLimitPolicy::Close);
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("delay", addr1, delay, factory)
+            .unwrap()
+            .bind_with("close", addr2, close, factory)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 4];
+
+    // accepting is delayed once rate is exceeded
+    let start = time::Instant::now();
+    for _ in 0..3 {
+        let mut conn = net::TcpStream::connect(addr1).unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"test");
+    }
+    assert!(start.elapsed() >= time::Duration::from_millis(900));
+
+    // connections over the rate are closed
+    let mut conn = net::TcpStream::connect(addr2).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+    let mut conn = net::TcpStream::connect(addr2).unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_accept_rate_pause() {
+    use actix_server::TcpOptions;
+    use futures::executor::block_on;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("test", addr, TcpOptions::new().accept_rate(1, 1), || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    // listener paused while it sleeps is not resumed by rate limit
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    block_on(srv.pause_listener("test")).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_millis(1500)))
+        .unwrap();
+    assert!(conn.read_exact(&mut buf).is_err());
+
+    // listener keeps pending connections
+    block_on(srv.resume_listener("test")).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_ip_filter() {
+    use actix_server::{IpFilter, IpNet, TcpOptions};
+
+    let net: IpNet = "10.0.0.0/8".parse().unwrap();
+    assert!(net.contains("10.1.2.3".parse().unwrap()));
+    assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+    assert!(!net.contains("11.0.0.1".parse().unwrap()));
+    assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+    let net: IpNet = "fd00::/8".parse().unwrap();
+    assert!(net.contains("fd12::1".parse().unwrap()));
+    assert!(!net.contains("fe80::1".parse().unwrap()));
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let filter = IpFilter::new().deny("127.0.0.0/8".parse().unwrap());
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with(
+                "test",
+                addr,
+                TcpOptions::new().ip_filter(filter),
+                move || {
+                    fn_service(|io: TcpStream| async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"test")).await.unwrap();
+                        Ok::<_, ()>(())
+                    })
+                },
+            )
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 4];
+
+    // denied peer
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    let filter = IpFilter::new().allow(std::net::Ipv4Addr::LOCALHOST.into());
+    futures::executor::block_on(srv.set_ip_filter("test", Some(filter))).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    assert!(futures::executor::block_on(srv.set_ip_filter("unknown", None)).is_err());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_freebind() {
+    use actix_server::TcpOptions;
+
+    // address is not configured on the host
+    let addr = net::SocketAddr::new("192.0.2.1".parse().unwrap(), unused_addr().port());
+    let factory = || fn_service(|_: TcpStream| ok::<_, ()>(()));
+
+    assert!(Server::build()
+        .bind_with("test", addr, TcpOptions::new(), factory)
+        .is_err());
+    assert!(Server::build()
+        .bind_with("test", addr, TcpOptions::new().freebind(true), factory)
+        .is_ok());
+}
+
+#[test]
+fn test_worker_heartbeat() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static BLOCKED: AtomicBool = AtomicBool::new(false);
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .worker_heartbeat(
+                time::Duration::from_millis(50),
+                time::Duration::from_millis(300),
+            )
+            .restart_hung_workers(true)
+            .bind("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    // first connection blocks worker thread
+                    if !BLOCKED.swap(true, Ordering::SeqCst) {
+                        thread::sleep(time::Duration::from_secs(3));
+                    }
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let _conn = net::TcpStream::connect(addr).unwrap();
+    thread::sleep(time::Duration::from_millis(800));
+
+    // connections are not passed to hung worker
+    let mut buf = [0; 4];
+    for _ in 0..4 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"test");
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_reload() {
+    use futures::StreamExt;
+    use std::io::Write;
+
+    fn factory(tag: &'static [u8]) -> impl actix_server::ServiceFactory<TcpStream> {
+        move || {
+            fn_service(move |io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                while let Some(Ok(_)) = f.next().await {
+                    f.send(Bytes::from_static(tag)).await.unwrap();
+                }
+                Ok::<_, ()>(())
+            })
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test", addr, factory(b"old"))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 3];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.write_all(b"1").unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"old");
+
+    futures::executor::block_on(srv.reload("test", factory(b"new"))).unwrap();
+
+    // existing connection is served by old service
+    conn.write_all(b"1").unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"old");
+
+    for _ in 0..2 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.write_all(b"1").unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"new");
+    }
+
+    assert!(futures::executor::block_on(srv.reload("unknown", factory(b"new"))).is_err());
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_reload_failure() {
+    use actix_service::boxed::{self, BoxService};
+    use actix_service::fn_factory;
+    use futures::executor::block_on;
+
+    fn serve(tag: &'static [u8]) -> BoxService<TcpStream, (), ()> {
+        boxed::service(fn_service(move |io: TcpStream| async move {
+            let mut f = Framed::new(io, BytesCodec);
+            f.send(Bytes::from_static(tag)).await.unwrap();
+            Ok::<_, ()>(())
+        }))
+    }
+
+    fn check(addr: net::SocketAddr, tag: &str) {
+        for _ in 0..6 {
+            let mut res = String::new();
+            let mut conn = net::TcpStream::connect(addr).unwrap();
+            conn.read_to_string(&mut res).unwrap();
+            assert_eq!(res, tag);
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind("test", addr, move || {
+                fn_factory(|| async { Ok::<_, ()>(serve(b"old")) })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // new service fails on one of workers only, other worker goes back
+    // to old service
+    let calls = Arc::new(AtomicUsize::new(0));
+    let res = block_on(srv.reload("test", move || {
+        let fail = calls.fetch_add(1, Relaxed) == 0;
+        fn_factory(move || async move {
+            if fail {
+                Err(())
+            } else {
+                Ok(serve(b"new"))
+            }
+        })
+    }));
+    assert!(res.is_err());
+    thread::sleep(time::Duration::from_millis(100));
+    check(addr, "old");
+
+    block_on(srv.reload("test", move || {
+        fn_factory(|| async { Ok::<_, ()>(serve(b"new")) })
+    }))
+    .unwrap();
+    check(addr, "new");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_bind_or_external() {
+    #[cfg(unix)]
+    {
+        let res = Server::build().listen_external("test", 0, move || {
+            fn_service(|_: TcpStream| ok::<_, ()>(()))
+        });
+        assert_eq!(res.err().unwrap().kind(), std::io::ErrorKind::NotFound);
+    }
+
+    // address is bound if no socket is passed
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_or_external("test", addr, move || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_backpressure_policy() {
+    use actix_server::{BackpressurePolicy, TcpOptions};
+    use futures::StreamExt;
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let factory = || {
+            fn_service(|io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(b"test")).await.unwrap();
+                while let Some(Ok(_)) = f.next().await {}
+                Ok::<_, ()>(())
+            })
+        };
+        let shed = TcpOptions::new().backpressure_policy(BackpressurePolicy::SheddingClose);
+        let queue = TcpOptions::new().backpressure_policy(
+            BackpressurePolicy::QueueWithTimeout(time::Duration::from_millis(200)),
+        );
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("shed", addr1, shed, factory)
+            .unwrap()
+            .bind_with("queue", addr2, queue, factory)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    futures::executor::block_on(srv.set_max_connections(1));
+
+    let connect = |addr| {
+        let conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+            .unwrap();
+        thread::sleep(time::Duration::from_millis(100));
+        conn
+    };
+    let mut buf = [0; 4];
+
+    let mut conn1 = connect(addr1);
+    conn1.read_exact(&mut buf).unwrap();
+    // worker becomes unavailable
+    let mut conn2 = connect(addr1);
+
+    // connection is closed
+    let mut conn = connect(addr1);
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    // connection is queued and closed after timeout
+    let mut conn = connect(addr2);
+    thread::sleep(time::Duration::from_millis(300));
+    drop(conn1);
+    conn2.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    // queued connection is processed
+    let mut conn = connect(addr2);
+    drop(conn2);
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    // limit is shared by servers of process
+    futures::executor::block_on(srv.set_max_connections(25600));
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_backpressure_queue() {
+    use actix_server::{BackpressurePolicy, TcpOptions};
+    use futures::StreamExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let queue = TcpOptions::new().backpressure_policy(
+            BackpressurePolicy::QueueWithTimeout(time::Duration::from_secs(10)),
+        );
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("queue", addr, queue, || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    while let Some(Ok(_)) = f.next().await {}
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    futures::executor::block_on(srv.set_max_connections(1));
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    // queued connections are processed at once when worker is available
+    let mut conns = Vec::new();
+    for _ in 0..256 {
+        let conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(5)))
+            .unwrap();
+        conns.push(conn);
+    }
+    thread::sleep(time::Duration::from_millis(300));
+    futures::executor::block_on(srv.set_max_connections(25600));
+    drop(conn);
+    for mut conn in conns {
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"test");
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_backpressure_queue_restart() {
+    use std::sync::atomic::AtomicBool;
+    use std::task::{Context, Poll};
+
+    use actix_server::{BackpressurePolicy, TcpOptions};
+    use actix_service::{fn_factory, Service};
+    use futures::future::LocalBoxFuture;
+    use futures::{FutureExt, StreamExt};
+
+    // service that fails readiness check once `fail` is set
+    struct Failing(Arc<AtomicBool>);
+
+    impl Service for Failing {
+        type Request = TcpStream;
+        type Response = ();
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            if self.0.swap(false, Relaxed) {
+                Poll::Ready(Err(()))
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        fn call(&mut self, io: TcpStream) -> Self::Future {
+            async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(b"test")).await.unwrap();
+                while let Some(Ok(_)) = f.next().await {}
+                Ok(())
+            }
+            .boxed_local()
+        }
+    }
+
+    let addr = unused_addr();
+    let fail = Arc::new(AtomicBool::new(false));
+    let fail2 = fail.clone();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let queue = TcpOptions::new().backpressure_policy(
+            BackpressurePolicy::QueueWithTimeout(time::Duration::from_secs(10)),
+        );
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("queue", addr, queue, move || {
+                let fail = fail2.clone();
+                fn_factory(move || ok::<_, ()>(Failing(fail.clone())))
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    futures::executor::block_on(srv.set_max_connections(1));
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+
+    let mut conns = Vec::new();
+    for _ in 0..16 {
+        let conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(5)))
+            .unwrap();
+        conns.push(conn);
+    }
+    thread::sleep(time::Duration::from_millis(300));
+    let metrics = futures::executor::block_on(srv.metrics());
+    assert_eq!(metrics.workers()[0].queued(), 16);
+
+    // queued connections are processed by restarted service
+    fail.store(true, Relaxed);
+    futures::executor::block_on(srv.set_max_connections(25600));
+    for conn in &mut conns {
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"test");
+    }
+    assert!(!fail.load(Relaxed));
+    let metrics = futures::executor::block_on(srv.metrics());
+    assert_eq!(metrics.workers()[0].queued(), 0);
+    assert_eq!(metrics.connections(), 17);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_worker_runtime() {
+    let addr = unused_addr();
+    let configured = Arc::new(AtomicUsize::new(0));
+    let configured2 = configured.clone();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .worker_thread_name("test-worker")
+            .worker_stack_size(4 * 1024 * 1024)
+            .worker_runtime(move |rt| {
+                configured2.fetch_add(1, Relaxed);
+                rt.max_threads(4);
+            })
+            .disable_signals()
+            .bind("test", addr, || {
+                fn_service(|io: TcpStream| async move {
+                    let name = thread::current().name().unwrap().to_owned();
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from(name)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    assert_eq!(configured.load(Relaxed), 2);
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut name = String::new();
+    conn.read_to_string(&mut name).unwrap();
+    assert!(name.starts_with("test-worker:"));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_incoming_cpu() {
+    use actix_server::TcpOptions;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .worker_thread_name("worker")
+            .worker_affinity(vec![1, 0])
+            .affinity_backend(|_| Ok(()))
+            .disable_signals()
+            .bind_with("test", addr, TcpOptions::new().incoming_cpu(true), || {
+                fn_service(|io: TcpStream| async move {
+                    let name = thread::current().name().unwrap().to_owned();
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from(name)).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // connections are received on core of client thread
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::CPU_SET(0, &mut set) };
+    let res =
+        unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) };
+    assert_eq!(res, 0);
+
+    for _ in 0..4 {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        let mut name = String::new();
+        conn.read_to_string(&mut name).unwrap();
+        assert_eq!(name, "worker:1");
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_fastopen() {
+    use actix_server::TcpOptions;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("test", addr, TcpOptions::new().fastopen(16), || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_reuseport_program() {
+    use actix_server::{BpfInstruction, ReuseportProgram, TcpOptions};
+
+    fn factory(name: &'static [u8]) -> impl actix_server::ServiceFactory<TcpStream> {
+        move || {
+            fn_service(move |io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(name)).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        }
+    }
+
+    // kernel accepts hash program
+    let opts = TcpOptions::new().reuseport_program(ReuseportProgram::hash(2));
+    assert!(Server::build()
+        .bind_reuseport_with("test", unused_addr(), opts, factory(b"a"))
+        .is_ok());
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        // second socket of group is always selected
+        let program = ReuseportProgram::Cbpf(vec![BpfInstruction::new(
+            (libc::BPF_RET | libc::BPF_K) as u16,
+            0,
+            0,
+            1,
+        )]);
+        let opts = TcpOptions::new().reuseport_program(program);
+
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_reuseport_with("a", addr, opts.clone(), factory(b"a"))
+            .unwrap()
+            .bind_reuseport_with("b", addr, opts, factory(b"b"))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for _ in 0..8 {
+        let mut buf = [0; 1];
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"b");
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_reuseport_workers() {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    use actix_server::{BpfInstruction, ReuseportProgram, TcpOptions};
+    use futures::executor::block_on;
+
+    let addr = unused_addr();
+    let threads = Arc::new(Mutex::new(HashSet::new()));
+    let threads2 = threads.clone();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        // third socket of group is selected, kernel hashes connections
+        // between sockets while there is no such socket
+        let program = ReuseportProgram::Cbpf(vec![BpfInstruction::new(
+            (libc::BPF_RET | libc::BPF_K) as u16,
+            0,
+            0,
+            2,
+        )]);
+        let opts = TcpOptions::new().reuseport_program(program);
+
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(2)
+            .disable_signals()
+            .bind_reuseport_with("test", addr, opts, move || {
+                let threads = threads2.clone();
+                fn_service(move |io: TcpStream| {
+                    threads.lock().unwrap().insert(thread::current().id());
+                    async move {
+                        let mut f = Framed::new(io, BytesCodec);
+                        f.send(Bytes::from_static(b"1")).await.unwrap();
+                        Ok::<_, ()>(())
+                    }
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let connect = || {
+        for _ in 0..32 {
+            let mut buf = [0; 1];
+            let mut conn = net::TcpStream::connect(addr).unwrap();
+            conn.read_exact(&mut buf).unwrap();
+        }
+        threads.lock().unwrap().drain().collect::<Vec<_>>()
+    };
+
+    // every worker accepts socket of its own
+    let seen = connect();
+    assert_eq!(seen.len(), 2);
+
+    // new worker binds third socket and gets all connections
+    block_on(srv.set_workers(3));
+    thread::sleep(time::Duration::from_millis(100));
+    let added = connect();
+    assert_eq!(added.len(), 1);
+    assert!(!seen.contains(&added[0]));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_max_connections_per_ip() {
+    use actix_server::TcpOptions;
+    use futures::StreamExt;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let opts = TcpOptions::new().max_connections_per_ip(2);
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_with("test", addr, opts, || {
+                fn_service(|io: TcpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    while let Some(Ok(_)) = f.next().await {}
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let connect = || {
+        let conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+            .unwrap();
+        conn
+    };
+    let mut buf = [0; 4];
+
+    let mut conn1 = connect();
+    conn1.read_exact(&mut buf).unwrap();
+    let mut conn2 = connect();
+    conn2.read_exact(&mut buf).unwrap();
+
+    // excess connection is closed
+    let mut conn = connect();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    // closed connection is released
+    drop(conn1);
+    thread::sleep(time::Duration::from_millis(100));
+    let mut conn = connect();
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_sctp() {
+    use std::os::unix::io::FromRawFd;
+
+    use actix_server::SctpStream;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_sctp("test", addr, || {
+                fn_service(|io: SctpStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            });
+        match srv {
+            Ok(srv) => {
+                let _ = tx.send(Some((srv.start(), actix_rt::System::current())));
+                let _ = sys.run();
+            }
+            // kernel is built without sctp
+            Err(ref e) if e.raw_os_error() == Some(libc::EPROTONOSUPPORT) => {
+                let _ = tx.send(None);
+            }
+            Err(e) => panic!("{:?}", e),
+        }
+    });
+    let sys = match rx.recv().unwrap() {
+        Some((_, sys)) => sys,
+        None => {
+            let _ = h.join();
+            return;
+        }
+    };
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_STREAM, libc::IPPROTO_SCTP);
+        assert!(fd >= 0);
+        let mut sa: libc::sockaddr_in = std::mem::zeroed();
+        sa.sin_family = libc::AF_INET as libc::sa_family_t;
+        sa.sin_port = addr.port().to_be();
+        sa.sin_addr.s_addr = u32::from_ne_bytes([127, 0, 0, 1]);
+        let res = libc::connect(
+            fd,
+            &sa as *const _ as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        );
+        assert_eq!(res, 0);
+        net::TcpStream::from_raw_fd(fd)
+    };
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    let mut buf = [0; 4];
+    conn.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"test");
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_bind_uds_abstract() {
+    let name = format!("\0actix-server-{}", std::process::id());
+    let (tx, rx) = mpsc::channel();
+
+    let name2 = name.clone();
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_uds("test", &name2, || {
+                fn_service(|io: actix_rt::net::UnixStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    f.send(Bytes::from_static(b"test")).await.unwrap();
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send(actix_rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    actix_rt::System::new("client").block_on(async move {
+        use futures::StreamExt;
+
+        let io = actix_rt::net::UnixStream::connect(&name).await.unwrap();
+        let mut f = Framed::new(io, BytesCodec);
+        assert_eq!(
+            f.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"test")
+        );
+    });
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_sni_services() {
+    use std::io::Write;
+    use std::pin::Pin;
+
+    use actix_codec::AsyncRead;
+    use actix_server::SniServices;
+    use futures::future::poll_fn;
+
+    fn host(name: &'static str) -> impl actix_server::ServiceFactory<(String, TcpStream)> {
+        move || {
+            fn_service(move |(_, io): (String, TcpStream)| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(name.as_bytes())).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        // "handshake" reads server name line
+        let acceptor = fn_service(|mut io: TcpStream| async move {
+            let mut name = Vec::new();
+            loop {
+                let mut buf = [0; 1];
+                let n = poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut buf)).await?;
+                if n == 0 || buf[0] == b'\n' {
+                    break;
+                }
+                name.push(buf[0]);
+            }
+            Ok::<_, std::io::Error>((String::from_utf8(name).unwrap(), io))
+        });
+        let services = SniServices::new(|io: &(String, TcpStream)| Some(io.0.as_str()))
+            .host("api.example.com", host("api"))
+            .host("*.example.com", host("www"))
+            .default_service(host("default"));
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_tls("test", addr, acceptor, services)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for (name, service) in &[
+        ("api.example.com", "api"),
+        ("Foo.Example.com", "www"),
+        ("a.b.example.com", "default"),
+        ("example.org", "default"),
+    ] {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+            .unwrap();
+        conn.write_all(format!("{}\n", name).as_bytes()).unwrap();
+        let mut buf = Vec::new();
+        conn.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, service.as_bytes());
+    }
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+fn test_on_stop() {
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+    let flushed = Arc::new(AtomicUsize::new(0));
+    let budget = Arc::new(AtomicUsize::new(0));
+
+    let (flushed2, budget2) = (flushed.clone(), budget.clone());
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .shutdown_timeout(5)
+            .on_stop(move |remaining| async move {
+                budget2.store(remaining.as_millis() as usize, Relaxed);
+                actix_rt::time::delay_for(time::Duration::from_millis(200)).await;
+                flushed2.fetch_add(1, Relaxed);
+            })
+            .bind("test", addr, move || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // server future resolves once hooks are completed
+    futures::executor::block_on(srv.stop(true));
+    assert_eq!(flushed.load(Relaxed), 1);
+    let budget = budget.load(Relaxed);
+    assert!(budget > 0 && budget <= 5000);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_uds_peer_credentials() {
+    use actix_server::{Connection, PeerCredentials};
+
+    let path =
+        std::env::temp_dir().join(format!("actix-server-cred-{}.sock", std::process::id()));
+    let cred = Arc::new(std::sync::Mutex::new(None));
+    let (tx, rx) = mpsc::channel();
+
+    let (path2, cred2) = (path.clone(), cred.clone());
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_uds_connection("test", &path2, move || {
+                let cred = cred2.clone();
+                fn_service(move |io: Connection<actix_rt::net::UnixStream>| {
+                    *cred.lock().unwrap() = io.extensions().get::<PeerCredentials>().cloned();
+                    ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send(actix_rt::System::current());
+        let _ = sys.run();
+    });
+    let sys = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    let mut conn = std::os::unix::net::UnixStream::connect(&path).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    let _ = conn.read(&mut [0; 1]);
+
+    let cred = cred.lock().unwrap().unwrap();
+    assert_eq!(cred.uid(), unsafe { libc::getuid() });
+    assert_eq!(cred.gid(), unsafe { libc::getgid() });
+    #[cfg(target_os = "linux")]
+    assert_eq!(cred.pid(), Some(std::process::id() as i32));
+
+    sys.stop();
+    let _ = h.join();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+#[cfg(feature = "prometheus")]
+fn test_prometheus() {
+    use std::io::Write;
+    use std::pin::Pin;
+
+    use actix_codec::AsyncRead;
+    use futures::future::poll_fn;
+
+    let addr = unused_addr();
+    let admin = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        // "handshake" fails unless client sends `1`
+        let acceptor = fn_service(|mut io: TcpStream| async move {
+            let mut buf = [0; 1];
+            poll_fn(|cx| Pin::new(&mut io).poll_read(cx, &mut buf)).await?;
+            if buf[0] == b'1' {
+                Ok(io)
+            } else {
+                Err(std::io::Error::new(std::io::ErrorKind::Other, "handshake"))
+            }
+        });
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_tls("tls", addr, acceptor, || fn_service(|_| ok::<_, ()>(())))
+            .unwrap()
+            .bind_prometheus("metrics", admin)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    for data in &[b"1", b"1", b"0"] {
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+            .unwrap();
+        conn.write_all(*data).unwrap();
+        let _ = conn.read(&mut [0; 1]);
+    }
+    thread::sleep(time::Duration::from_millis(100));
+
+    let metrics = futures::executor::block_on(srv.metrics());
+    assert_eq!(metrics.tls()[0].name(), "tls");
+    assert_eq!(metrics.tls()[0].completed(), 2);
+    assert_eq!(metrics.tls()[0].failed(), 1);
+
+    let get = |path: &str| {
+        let mut conn = net::TcpStream::connect(admin).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+            .unwrap();
+        write!(conn, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut res = String::new();
+        conn.read_to_string(&mut res).unwrap();
+        res
+    };
+    let res = get("/metrics");
+    assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(res.contains("\nactix_server_tls_handshakes_total{listener=\"tls\"} 2\n"));
+    assert!(res.contains("\nactix_server_tls_handshake_errors_total{listener=\"tls\"} 1\n"));
+    assert!(res.contains("\nactix_server_workers 1\n"));
+    assert!(res.contains("\nactix_server_worker_available{worker=\"0\"} 1\n"));
+    assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    sys.stop();
+    let _ = h.join();
+}
+
+/// Fast poll is available since 5.7, io_uring may be disabled by sysctl
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn io_uring_supported() -> bool {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap();
+    let mut ver = release
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|v| v.parse::<u32>().unwrap_or(0));
+    let ver = (ver.next().unwrap_or(0), ver.next().unwrap_or(0));
+    let disabled = std::fs::read_to_string("/proc/sys/kernel/io_uring_disabled")
+        .map(|val| val.trim() != "0")
+        .unwrap_or(false);
+    ver >= (5, 7) && !disabled
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn test_io_uring() {
+    // ring descriptors of accept threads are counted in new process
+    let out = std::process::Command::new(std::env::current_exe().unwrap())
+        .arg("--exact")
+        .arg("test_io_uring_accept")
+        .arg("--ignored")
+        .env("ACTIX_TEST_IO_URING", "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(out.status.success(), "{}", stdout);
+    assert!(stdout.contains("1 passed"));
+}
+
+#[test]
+#[ignore]
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+/// Started by `test_io_uring` in new process.
+fn test_io_uring_accept() {
+    use futures::executor::block_on;
+
+    fn rings() -> usize {
+        std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .filter_map(|entry| std::fs::read_link(entry.ok()?.path()).ok())
+            .filter(|path| path.to_string_lossy() == "anon_inode:[io_uring]")
+            .count()
+    }
+
+    fn check(addr: net::SocketAddr) {
+        let mut res = String::new();
+        let mut conn = net::TcpStream::connect(addr).unwrap();
+        conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+            .unwrap();
+        conn.read_to_string(&mut res).unwrap();
+        assert_eq!(res, "test");
+    }
+
+    if std::env::var("ACTIX_TEST_IO_URING").is_err() {
+        return;
+    }
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let factory = || {
+            fn_service(|io: TcpStream| async move {
+                let mut f = Framed::new(io, BytesCodec);
+                f.send(Bytes::from_static(b"test")).await.unwrap();
+                Ok::<_, ()>(())
+            })
+        };
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind("test1", addr1, factory)
+            .unwrap()
+            .bind("test2", addr2, factory)
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+    assert_eq!(rings() > 0, io_uring_supported());
+
+    // accept requests of listeners are completed over and over
+    for _ in 0..4 {
+        check(addr1);
+        check(addr2);
+    }
+
+    // accept request is cancelled on pause and submitted again on resume
+    block_on(srv.pause_listener("test1")).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    let mut buf = [0; 4];
+    let mut conn = net::TcpStream::connect(addr1).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_millis(300)))
+        .unwrap();
+    assert!(conn.read_exact(&mut buf).is_err());
+    check(addr2);
+    block_on(srv.resume_listener("test1")).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(1)))
+        .unwrap();
+    conn.read_exact(&mut buf).unwrap();
+    check(addr1);
+
+    // listener is closed once its request is cancelled
+    block_on(srv.remove_listener("test2")).unwrap();
+    thread::sleep(time::Duration::from_millis(100));
+    assert!(net::TcpStream::connect(addr2).is_err());
+    check(addr1);
+
+    sys.stop();
+    let _ = h.join();
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn test_bind_uring() {
+    use std::io::Write;
+
+    use actix_server::UringStream;
+    use futures::StreamExt;
+
+    if !io_uring_supported() {
+        return;
+    }
+
+    let addr1 = unused_addr();
+    let addr2 = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        let sys = actix_rt::System::new("test");
+        let srv = Server::build()
+            .workers(1)
+            .disable_signals()
+            .bind_uring("echo", addr1, || {
+                fn_service(|io: UringStream| async move {
+                    assert!(io.peer_addr().is_ok());
+                    let mut f = Framed::new(io, BytesCodec);
+                    while let Some(Ok(buf)) = f.next().await {
+                        f.send(buf.freeze()).await?;
+                    }
+                    Ok::<_, std::io::Error>(())
+                })
+            })
+            .unwrap()
+            // stream is dropped while it reads
+            .bind_uring("drop", addr2, || {
+                fn_service(|io: UringStream| async move {
+                    let mut f = Framed::new(io, BytesCodec);
+                    let next =
+                        actix_rt::time::timeout(time::Duration::from_millis(100), f.next());
+                    assert!(next.await.is_err());
+                    Ok::<_, ()>(())
+                })
+            })
+            .unwrap()
+            .start();
+        let _ = tx.send((srv, actix_rt::System::current()));
+        let _ = sys.run();
+    });
+    let (_, sys) = rx.recv().unwrap();
+    thread::sleep(time::Duration::from_millis(300));
+
+    // data larger than buffers of stream is echoed by concurrent connections
+    let clients: Vec<_> = (0..4u8)
+        .map(|n| {
+            thread::spawn(move || {
+                let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8 ^ n).collect();
+                let mut conn = net::TcpStream::connect(addr1).unwrap();
+                conn.set_read_timeout(Some(time::Duration::from_secs(5)))
+                    .unwrap();
+                let mut conn2 = conn.try_clone().unwrap();
+                let data2 = data.clone();
+                let writer = thread::spawn(move || conn2.write_all(&data2).unwrap());
+                let mut res = vec![0; data.len()];
+                conn.read_exact(&mut res).unwrap();
+                writer.join().unwrap();
+                assert!(res == data);
+            })
+        })
+        .collect();
+    for client in clients {
+        client.join().unwrap();
+    }
+
+    // connection is closed once its pending read is cancelled
+    let mut buf = [0; 1];
+    let mut conn = net::TcpStream::connect(addr2).unwrap();
+    conn.set_read_timeout(Some(time::Duration::from_secs(2)))
+        .unwrap();
+    assert_eq!(conn.read(&mut buf).unwrap(), 0);
+
+    sys.stop();
+    let _ = h.join();
+}
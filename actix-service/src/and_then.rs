@@ -48,6 +48,16 @@ where
         }
     }
 
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let a = self.0.borrow_mut().poll_shutdown(cx, is_error).is_ready();
+        let b = self.1.borrow_mut().poll_shutdown(cx, is_error).is_ready();
+        if a && b {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
     fn call(&mut self, req: A::Request) -> Self::Future {
         AndThenServiceResponse {
             state: State::A(self.0.borrow_mut().call(req), Some(self.1.clone())),
@@ -188,6 +198,63 @@ where
     }
 }
 
+/// `.and_then_split()` service factory combinator
+///
+/// Same as `AndThenServiceFactory`, but factories could have different
+/// config types, config argument is a `(A::Config, B::Config)` tuple.
+pub struct AndThenSplitServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<Request = A::Response, Error = A::Error, InitError = A::InitError>,
+{
+    a: A,
+    b: B,
+}
+
+impl<A, B> AndThenSplitServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<Request = A::Response, Error = A::Error, InitError = A::InitError>,
+{
+    /// Create new `AndThenSplitServiceFactory` combinator
+    pub(crate) fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<A, B> ServiceFactory for AndThenSplitServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<Request = A::Response, Error = A::Error, InitError = A::InitError>,
+{
+    type Request = A::Request;
+    type Response = B::Response;
+    type Error = A::Error;
+
+    type Config = (A::Config, B::Config);
+    type Service = AndThenService<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = AndThenServiceFactoryResponse<A, B>;
+
+    fn new_service(&self, cfg: (A::Config, B::Config)) -> Self::Future {
+        AndThenServiceFactoryResponse::new(self.a.new_service(cfg.0), self.b.new_service(cfg.1))
+    }
+}
+
+impl<A, B> Clone for AndThenSplitServiceFactory<A, B>
+where
+    A: ServiceFactory + Clone,
+    B: ServiceFactory<Request = A::Response, Error = A::Error, InitError = A::InitError>
+        + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
+
 #[pin_project::pin_project]
 pub struct AndThenServiceFactoryResponse<A, B>
 where
@@ -257,7 +324,10 @@ mod tests {
 
     use futures_util::future::{lazy, ok, ready, Ready};
 
-    use crate::{fn_factory, pipeline, pipeline_factory, Service, ServiceFactory};
+    use crate::{
+        fn_factory, fn_factory_with_config, into_service, pipeline, pipeline_factory, Service,
+        ServiceFactory, ServiceFactoryExt,
+    };
 
     struct Srv1(Rc<Cell<usize>>);
 
@@ -305,6 +375,44 @@ mod tests {
         assert_eq!(cnt.get(), 2);
     }
 
+    struct ShutdownSrv(Rc<Cell<usize>>);
+
+    impl Service for ShutdownSrv {
+        type Request = &'static str;
+        type Response = &'static str;
+        type Error = ();
+        type Future = Ready<Result<Self::Response, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(&mut self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+            self.0.set(self.0.get() + 1);
+            if self.0.get() > 1 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            ok(req)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_poll_shutdown() {
+        let cnt = Rc::new(Cell::new(0));
+        let mut srv = pipeline(Srv1(Rc::new(Cell::new(0)))).and_then(ShutdownSrv(cnt.clone()));
+        assert_eq!(lazy(|cx| srv.poll_shutdown(cx, false)).await, Poll::Pending);
+        assert_eq!(
+            lazy(|cx| srv.poll_shutdown(cx, false)).await,
+            Poll::Ready(())
+        );
+        assert_eq!(cnt.get(), 2);
+    }
+
     #[actix_rt::test]
     async fn test_call() {
         let cnt = Rc::new(Cell::new(0));
@@ -327,4 +435,17 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), ("srv1", "srv2"));
     }
+
+    #[actix_rt::test]
+    async fn test_split_config() {
+        let factory = fn_factory_with_config(|cfg: usize| {
+            ok::<_, ()>(into_service(move |req: usize| ok::<_, ()>(req + cfg)))
+        })
+        .and_then_split(fn_factory_with_config(|cfg: &'static str| {
+            ok::<_, ()>(into_service(move |req: usize| ok::<_, ()>((req, cfg))))
+        }));
+
+        let mut srv = factory.new_service((1, "srv2")).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok((2, "srv2")));
+    }
 }
@@ -78,6 +78,16 @@ where
         }
     }
 
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let a = self.a.poll_shutdown(cx, is_error).is_ready();
+        let b = self.b.borrow_mut().0.poll_shutdown(cx, is_error).is_ready();
+        if a && b {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
     fn call(&mut self, req: A::Request) -> Self::Future {
         AndThenApplyFnFuture {
             state: State::A(self.a.call(req), Some(self.b.clone())),
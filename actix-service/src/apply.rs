@@ -17,6 +17,9 @@ where
 }
 
 /// Service factory that prodices `apply_fn` service.
+///
+/// Function is cloned for each service created by the factory, state that
+/// has to be shared between services should be kept behind `Rc`.
 pub fn apply_fn_factory<T, F, R, In, Out, Err, U>(
     service: U,
     f: F,
@@ -86,6 +89,10 @@ where
         Poll::Ready(futures_util::ready!(self.service.poll_ready(cx)))
     }
 
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
     fn call(&mut self, req: In) -> Self::Future {
         (self.f)(req, &mut self.service)
     }
@@ -266,4 +273,24 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (("srv", ())));
     }
+
+    #[actix_rt::test]
+    async fn test_shared_state() {
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let calls2 = calls.clone();
+        let new_srv = apply_fn_factory(
+            || ok::<_, ()>(Srv),
+            move |req: usize, srv: &mut Srv| {
+                calls2.set(calls2.get() + 1);
+                let fut = srv.call(());
+                async move { fut.await.map(|_| req * 2) }
+            },
+        );
+
+        let mut srv1 = new_srv.new_service(()).await.unwrap();
+        let mut srv2 = new_srv.new_service(()).await.unwrap();
+        assert_eq!(srv1.call(1).await, Ok(2));
+        assert_eq!(srv2.call(2).await, Ok(4));
+        assert_eq!(calls.get(), 2);
+    }
 }
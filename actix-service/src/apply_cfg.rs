@@ -41,6 +41,48 @@ where
     }
 }
 
+/// Convert `FnOnce(Config, Service1) -> Future<Service2>` fn to a service factory
+///
+/// Unlike `apply_cfg`, closure takes ownership of the service and may move
+/// captured resources into returned future. Resulting factory (and all of its
+/// clones) can create service only once, subsequent `new_service` calls panic.
+pub fn apply_cfg_once<F, C, T, R, S, E>(
+    srv: T,
+    f: F,
+) -> ApplyConfigOnceService<F, C, T, R, S, E>
+where
+    F: FnOnce(C, T) -> R,
+    T: Service,
+    R: Future<Output = Result<S, E>>,
+    S: Service,
+{
+    ApplyConfigOnceService {
+        srv: Rc::new(RefCell::new(Some((srv, f)))),
+        _t: PhantomData,
+    }
+}
+
+/// Convert `FnOnce(Config, Service1) -> Future<Service2>` fn to a service factory
+///
+/// Service1 get constructed from `T` factory. Resulting factory can create
+/// service only once, subsequent `new_service` calls panic.
+pub fn apply_cfg_factory_once<F, C, T, R, S>(
+    factory: T,
+    f: F,
+) -> ApplyConfigOnceServiceFactory<F, C, T, R, S>
+where
+    F: FnOnce(C, T::Service) -> R,
+    T: ServiceFactory<Config = ()>,
+    T::InitError: From<T::Error>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    ApplyConfigOnceServiceFactory {
+        srv: Rc::new(RefCell::new(Some((factory, f)))),
+        _t: PhantomData,
+    }
+}
+
 /// Convert `Fn(Config, &mut Server) -> Future<Service>` fn to NewService\
 pub struct ApplyConfigService<F, C, T, R, S, E>
 where
@@ -209,3 +251,226 @@ where
         }
     }
 }
+
+/// `apply_cfg_once()` service factory
+pub struct ApplyConfigOnceService<F, C, T, R, S, E>
+where
+    F: FnOnce(C, T) -> R,
+    T: Service,
+    R: Future<Output = Result<S, E>>,
+    S: Service,
+{
+    srv: Rc<RefCell<Option<(T, F)>>>,
+    _t: PhantomData<(C, R, S)>,
+}
+
+impl<F, C, T, R, S, E> Clone for ApplyConfigOnceService<F, C, T, R, S, E>
+where
+    F: FnOnce(C, T) -> R,
+    T: Service,
+    R: Future<Output = Result<S, E>>,
+    S: Service,
+{
+    fn clone(&self) -> Self {
+        ApplyConfigOnceService {
+            srv: self.srv.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, C, T, R, S, E> ServiceFactory for ApplyConfigOnceService<F, C, T, R, S, E>
+where
+    F: FnOnce(C, T) -> R,
+    T: Service,
+    R: Future<Output = Result<S, E>>,
+    S: Service,
+{
+    type Config = C;
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Service = S;
+
+    type InitError = E;
+    type Future = R;
+
+    fn new_service(&self, cfg: C) -> Self::Future {
+        let (t, f) = self
+            .srv
+            .borrow_mut()
+            .take()
+            .expect("apply_cfg_once factory can create service only once");
+        f(cfg, t)
+    }
+}
+
+/// `apply_cfg_factory_once()` service factory
+pub struct ApplyConfigOnceServiceFactory<F, C, T, R, S>
+where
+    F: FnOnce(C, T::Service) -> R,
+    T: ServiceFactory<Config = ()>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    srv: Rc<RefCell<Option<(T, F)>>>,
+    _t: PhantomData<(C, R, S)>,
+}
+
+impl<F, C, T, R, S> Clone for ApplyConfigOnceServiceFactory<F, C, T, R, S>
+where
+    F: FnOnce(C, T::Service) -> R,
+    T: ServiceFactory<Config = ()>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    fn clone(&self) -> Self {
+        Self {
+            srv: self.srv.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, C, T, R, S> ServiceFactory for ApplyConfigOnceServiceFactory<F, C, T, R, S>
+where
+    F: FnOnce(C, T::Service) -> R,
+    T: ServiceFactory<Config = ()>,
+    T::InitError: From<T::Error>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    type Config = C;
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Service = S;
+
+    type InitError = T::InitError;
+    type Future = ApplyConfigOnceServiceFactoryResponse<F, C, T, R, S>;
+
+    fn new_service(&self, cfg: C) -> Self::Future {
+        let (factory, f) = self
+            .srv
+            .borrow_mut()
+            .take()
+            .expect("apply_cfg_factory_once factory can create service only once");
+
+        ApplyConfigOnceServiceFactoryResponse {
+            cfg: Some(cfg),
+            f: Some(f),
+            state: OnceState::A(factory.new_service(())),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct ApplyConfigOnceServiceFactoryResponse<F, C, T, R, S>
+where
+    F: FnOnce(C, T::Service) -> R,
+    T: ServiceFactory<Config = ()>,
+    T::InitError: From<T::Error>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    cfg: Option<C>,
+    f: Option<F>,
+    #[pin]
+    state: OnceState<T, R, S>,
+}
+
+#[pin_project::pin_project(project = OnceStateProj)]
+enum OnceState<T, R, S>
+where
+    T: ServiceFactory<Config = ()>,
+    T::InitError: From<T::Error>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    A(#[pin] T::Future),
+    B(Option<T::Service>),
+    C(#[pin] R),
+}
+
+impl<F, C, T, R, S> Future for ApplyConfigOnceServiceFactoryResponse<F, C, T, R, S>
+where
+    F: FnOnce(C, T::Service) -> R,
+    T: ServiceFactory<Config = ()>,
+    T::InitError: From<T::Error>,
+    R: Future<Output = Result<S, T::InitError>>,
+    S: Service,
+{
+    type Output = Result<S, T::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.state.as_mut().project() {
+                OnceStateProj::A(fut) => {
+                    let srv = futures_util::ready!(fut.poll(cx))?;
+                    this.state.set(OnceState::B(Some(srv)));
+                }
+                OnceStateProj::B(srv) => {
+                    futures_util::ready!(srv.as_mut().unwrap().poll_ready(cx))?;
+                    let f = this.f.take().unwrap();
+                    let fut = f(this.cfg.take().unwrap(), srv.take().unwrap());
+                    this.state.set(OnceState::C(fut));
+                }
+                OnceStateProj::C(fut) => return fut.poll(cx),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{ok, Ready};
+
+    use super::*;
+    use crate::{fn_factory, into_service};
+
+    /// Owned resource that is not `Clone`
+    struct Listener(usize);
+
+    #[actix_rt::test]
+    async fn test_apply_cfg_once() {
+        let listener = Listener(10);
+        let factory = apply_cfg_once(
+            into_service(|req: usize| ok::<_, ()>(req)),
+            move |cfg: usize, srv| {
+                let Listener(n) = listener;
+                ok::<_, ()>(srv.map(move |res| res + n + cfg))
+            },
+        );
+
+        let mut srv = factory.new_service(1).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(12));
+    }
+
+    #[actix_rt::test]
+    #[should_panic]
+    async fn test_apply_cfg_once_twice() {
+        let factory =
+            apply_cfg_once(into_service(|req: usize| ok::<_, ()>(req)), |_: (), srv| {
+                ok::<_, ()>(srv)
+            });
+        drop(factory.new_service(()));
+        drop(factory.clone().new_service(()));
+    }
+
+    #[actix_rt::test]
+    async fn test_apply_cfg_factory_once() {
+        let listener = Listener(10);
+        let factory = apply_cfg_factory_once(
+            fn_factory(|| ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req)))),
+            move |cfg: usize, srv| -> Ready<Result<_, ()>> {
+                let Listener(n) = listener;
+                ok(srv.map(move |res| res * n + cfg))
+            },
+        );
+
+        let mut srv = factory.new_service(1).await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(21));
+    }
+}
@@ -0,0 +1,455 @@
+//! Service that distributes calls across multiple inner services.
+//!
+//! Balancer uses "power of two choices" strategy: it picks two random inner
+//! services and uses the one with fewer in-flight calls. If none of picked
+//! services is ready, any other ready service is used.
+//!
+//! `WeightedBalance` uses smooth weighted round-robin strategy, weights of
+//! inner services could be changed at runtime with `WeightsHandle`.
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::Service;
+
+/// Distributes calls across inner services based on number of in-flight
+/// calls.
+pub struct Balance<S> {
+    endpoints: Vec<Endpoint<S>>,
+    ready: Option<usize>,
+}
+
+struct Endpoint<S> {
+    service: S,
+    pending: Rc<Cell<usize>>,
+}
+
+impl<S> Balance<S>
+where
+    S: Service,
+{
+    /// Create balancer over provided services.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `services` is empty.
+    pub fn new(services: Vec<S>) -> Self {
+        assert!(!services.is_empty(), "At least one service is required");
+        Balance {
+            endpoints: services
+                .into_iter()
+                .map(|service| Endpoint {
+                    service,
+                    pending: Rc::new(Cell::new(0)),
+                })
+                .collect(),
+            ready: None,
+        }
+    }
+
+    /// Number of inner services.
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Returns `true` if balancer has no inner services.
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Number of in-flight calls for each inner service.
+    pub fn pending(&self) -> Vec<usize> {
+        self.endpoints.iter().map(|e| e.pending.get()).collect()
+    }
+
+    /// Pick two random services, less loaded one goes first.
+    fn pick(&self) -> (usize, usize) {
+        let len = self.endpoints.len();
+        if len == 1 {
+            return (0, 0);
+        }
+
+        let rnd = RandomState::new().build_hasher().finish();
+        let a = (rnd % len as u64) as usize;
+        let b = (a + 1 + ((rnd >> 32) % (len as u64 - 1)) as usize) % len;
+        if self.endpoints[b].pending.get() < self.endpoints[a].pending.get() {
+            (b, a)
+        } else {
+            (a, b)
+        }
+    }
+}
+
+impl<S> Service for Balance<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BalanceResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(idx) = self.ready {
+            if self.endpoints[idx].service.poll_ready(cx)?.is_ready() {
+                return Poll::Ready(Ok(()));
+            }
+            self.ready = None;
+        }
+
+        let (a, b) = self.pick();
+        for idx in [a, b].iter().copied().chain(0..self.endpoints.len()) {
+            if self.endpoints[idx].service.poll_ready(cx)?.is_ready() {
+                self.ready = Some(idx);
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Poll::Pending
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let mut ready = true;
+        for srv in self.endpoints.iter_mut().map(|e| &mut e.service) {
+            ready &= srv.poll_shutdown(cx, is_error).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let idx = match self.ready.take() {
+            Some(idx) => idx,
+            None => self.pick().0,
+        };
+        let endpoint = &mut self.endpoints[idx];
+        endpoint.pending.set(endpoint.pending.get() + 1);
+
+        BalanceResponse {
+            fut: endpoint.service.call(req),
+            _guard: PendingGuard(endpoint.pending.clone()),
+        }
+    }
+}
+
+/// Decrements number of in-flight calls on drop.
+struct PendingGuard(Rc<Cell<usize>>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+/// `Balance` response future
+#[pin_project::pin_project]
+pub struct BalanceResponse<S: Service> {
+    #[pin]
+    fut: S::Future,
+    _guard: PendingGuard,
+}
+
+impl<S: Service> Future for BalanceResponse<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().fut.poll(cx)
+    }
+}
+
+struct Weights {
+    weights: Vec<Cell<usize>>,
+    waker: RefCell<Option<Waker>>,
+}
+
+/// Handle for changing weights of `WeightedBalance` inner services.
+#[derive(Clone)]
+pub struct WeightsHandle(Rc<Weights>);
+
+impl WeightsHandle {
+    /// Set weight of inner service with index `idx`.
+    ///
+    /// Service with zero weight does not receive new calls.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn set_weight(&self, idx: usize, weight: usize) {
+        self.0.weights[idx].set(weight);
+        if let Some(waker) = self.0.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    /// Weight of inner service with index `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of range.
+    pub fn weight(&self, idx: usize) -> usize {
+        self.0.weights[idx].get()
+    }
+
+    /// Number of inner services.
+    pub fn len(&self) -> usize {
+        self.0.weights.len()
+    }
+
+    /// Returns `true` if balancer has no inner services.
+    pub fn is_empty(&self) -> bool {
+        self.0.weights.is_empty()
+    }
+}
+
+/// Distributes calls across inner services proportionally to their weights.
+///
+/// If selected service is not ready, next ready service with non-zero weight
+/// is used. Balancer is not ready while all weights are zero.
+pub struct WeightedBalance<S> {
+    services: Vec<S>,
+    current: Vec<i64>,
+    weights: Rc<Weights>,
+    ready: Option<usize>,
+}
+
+impl<S> WeightedBalance<S>
+where
+    S: Service,
+{
+    /// Create balancer over provided services and their initial weights.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `services` is empty.
+    pub fn new(services: Vec<(S, usize)>) -> Self {
+        assert!(!services.is_empty(), "At least one service is required");
+        let (services, weights): (Vec<_>, Vec<_>) = services
+            .into_iter()
+            .map(|(service, weight)| (service, Cell::new(weight)))
+            .unzip();
+
+        WeightedBalance {
+            current: vec![0; services.len()],
+            services,
+            weights: Rc::new(Weights {
+                weights,
+                waker: RefCell::new(None),
+            }),
+            ready: None,
+        }
+    }
+
+    /// Get handle for changing weights at runtime.
+    pub fn handle(&self) -> WeightsHandle {
+        WeightsHandle(self.weights.clone())
+    }
+
+    /// Current weights of inner services.
+    pub fn weights(&self) -> Vec<usize> {
+        self.weights.weights.iter().map(|w| w.get()).collect()
+    }
+
+    fn weight(&self, idx: usize) -> usize {
+        self.weights.weights[idx].get()
+    }
+
+    /// Smooth weighted round-robin selection.
+    fn pick(&mut self) -> Option<usize> {
+        let mut total = 0;
+        let mut best: Option<usize> = None;
+        for idx in 0..self.services.len() {
+            let weight = self.weight(idx) as i64;
+            if weight == 0 {
+                continue;
+            }
+            self.current[idx] += weight;
+            total += weight;
+            if best
+                .map(|b| self.current[idx] > self.current[b])
+                .unwrap_or(true)
+            {
+                best = Some(idx);
+            }
+        }
+        if let Some(idx) = best {
+            self.current[idx] -= total;
+        }
+        best
+    }
+}
+
+impl<S> Service for WeightedBalance<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(idx) = self.ready {
+            if self.weight(idx) > 0 && self.services[idx].poll_ready(cx)?.is_ready() {
+                return Poll::Ready(Ok(()));
+            }
+            self.ready = None;
+        }
+
+        // weights could be changed by the handle
+        *self.weights.waker.borrow_mut() = Some(cx.waker().clone());
+
+        let first = match self.pick() {
+            Some(idx) => idx,
+            None => return Poll::Pending,
+        };
+        let len = self.services.len();
+        for idx in (first..len).chain(0..first) {
+            if self.weight(idx) > 0 && self.services[idx].poll_ready(cx)?.is_ready() {
+                self.ready = Some(idx);
+                return Poll::Ready(Ok(()));
+            }
+        }
+        Poll::Pending
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let mut ready = true;
+        for srv in self.services.iter_mut() {
+            ready &= srv.poll_shutdown(cx, is_error).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let idx = match self.ready.take() {
+            Some(idx) => idx,
+            None => self.pick().unwrap_or(0),
+        };
+        self.services[idx].call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, FutureExt, LocalBoxFuture};
+
+    use super::*;
+
+    struct Srv(usize, Rc<Cell<bool>>);
+
+    impl Service for Srv {
+        type Request = u64;
+        type Response = usize;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.1.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, req: u64) -> Self::Future {
+            let id = self.0;
+            actix_rt::time::delay_for(Duration::from_millis(req))
+                .then(move |_| ok(id))
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_least_pending() {
+        let ready = Rc::new(Cell::new(true));
+        let mut srv = Balance::new(vec![Srv(0, ready.clone()), Srv(1, ready)]);
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        let fut1 = srv.call(50);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        let fut2 = srv.call(0);
+        assert_eq!(srv.pending(), vec![1, 1]);
+
+        let id2 = fut2.await.unwrap();
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(0).await, Ok(id2));
+
+        assert_ne!(fut1.await.unwrap(), id2);
+        assert_eq!(srv.pending(), vec![0, 0]);
+    }
+
+    #[actix_rt::test]
+    async fn test_not_ready() {
+        let ready = Rc::new(Cell::new(true));
+        let not_ready = Rc::new(Cell::new(false));
+        let mut srv = Balance::new(vec![
+            Srv(0, not_ready.clone()),
+            Srv(1, ready),
+            Srv(2, not_ready.clone()),
+        ]);
+
+        for _ in 0..4 {
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+            assert_eq!(srv.call(0).await, Ok(1));
+        }
+
+        let mut srv = Balance::new(vec![Srv(0, not_ready.clone()), Srv(1, not_ready)]);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+    }
+
+    #[actix_rt::test]
+    async fn test_weighted() {
+        let ready = Rc::new(Cell::new(true));
+        let mut srv =
+            WeightedBalance::new(vec![(Srv(0, ready.clone()), 3), (Srv(1, ready.clone()), 1)]);
+        let handle = srv.handle();
+
+        let mut counts = [0; 2];
+        for _ in 0..8 {
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+            counts[srv.call(0).await.unwrap()] += 1;
+        }
+        assert_eq!(counts, [6, 2]);
+
+        // drain first service
+        handle.set_weight(0, 0);
+        assert_eq!(srv.weights(), vec![0, 1]);
+        for _ in 0..4 {
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+            assert_eq!(srv.call(0).await, Ok(1));
+        }
+
+        handle.set_weight(1, 0);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+        handle.set_weight(0, 1);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(0).await, Ok(0));
+    }
+
+    #[actix_rt::test]
+    async fn test_weighted_not_ready() {
+        let ready = Rc::new(Cell::new(true));
+        let not_ready = Rc::new(Cell::new(false));
+        let mut srv = WeightedBalance::new(vec![(Srv(0, not_ready), 5), (Srv(1, ready), 1)]);
+
+        for _ in 0..4 {
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+            assert_eq!(srv.call(0).await, Ok(1));
+        }
+    }
+}
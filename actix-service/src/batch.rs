@@ -0,0 +1,336 @@
+//! Service that groups requests into batches.
+//!
+//! Incoming requests are accumulated until batch reaches max size or max
+//! delay passes since the first request of the batch. Inner service is then
+//! called once with all requests of the batch and responses are handed back
+//! to original callers in order.
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_rt::time::delay_for;
+use futures_channel::oneshot;
+use futures_util::future::{ok, poll_fn, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Batch service error
+#[derive(Debug, PartialEq)]
+pub enum BatchError<E> {
+    /// Service error, it is reported to all requests of the batch
+    Service(E),
+    /// Inner service did not return response for the request
+    NoResponse,
+}
+
+impl<E> From<E> for BatchError<E> {
+    fn from(err: E) -> Self {
+        BatchError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BatchError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Service(e) => e.fmt(f),
+            BatchError::NoResponse => write!(f, "No response for batched request"),
+        }
+    }
+}
+
+/// Groups requests into batches.
+pub struct Batch<E = ()> {
+    max_size: usize,
+    max_delay: Duration,
+    _t: PhantomData<E>,
+}
+
+impl<E> Batch<E> {
+    /// Call inner service once `max_size` requests are accumulated, or
+    /// `max_delay` after first request of the batch.
+    pub fn new(max_size: usize, max_delay: Duration) -> Self {
+        Batch {
+            max_size,
+            max_delay,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for Batch<E> {
+    fn clone(&self) -> Self {
+        Batch::new(self.max_size, self.max_delay)
+    }
+}
+
+impl<S, R, Res, E> Transform<S> for Batch<E>
+where
+    S: Service<Request = Vec<R>, Response = Vec<Res>> + 'static,
+    S::Error: Clone,
+    R: 'static,
+    Res: 'static,
+{
+    type Request = R;
+    type Response = Res;
+    type Error = BatchError<S::Error>;
+    type InitError = E;
+    type Transform = BatchService<S, R, Res>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(BatchService::new(self.max_size, self.max_delay, service))
+    }
+}
+
+type Pending<R, Res, E> = Vec<(R, oneshot::Sender<Result<Res, BatchError<E>>>)>;
+
+struct Inner<S: Service, R, Res> {
+    service: RefCell<S>,
+    max_size: usize,
+    max_delay: Duration,
+    pending: RefCell<Pending<R, Res, S::Error>>,
+    batch_id: Cell<usize>,
+}
+
+impl<S, R, Res> Inner<S, R, Res>
+where
+    S: Service<Request = Vec<R>, Response = Vec<Res>> + 'static,
+    S::Error: Clone,
+    R: 'static,
+    Res: 'static,
+{
+    fn flush(self: &Rc<Self>) {
+        let batch = std::mem::take(&mut *self.pending.borrow_mut());
+        if batch.is_empty() {
+            return;
+        }
+        self.batch_id.set(self.batch_id.get().wrapping_add(1));
+
+        let inner = self.clone();
+        actix_rt::spawn(async move {
+            let (reqs, txs): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+
+            let res = match poll_fn(|cx| inner.service.borrow_mut().poll_ready(cx)).await {
+                Ok(_) => {
+                    let fut = inner.service.borrow_mut().call(reqs);
+                    fut.await
+                }
+                Err(e) => Err(e),
+            };
+
+            match res {
+                Ok(responses) => {
+                    let mut responses = responses.into_iter();
+                    for tx in txs {
+                        let _ = tx.send(responses.next().ok_or(BatchError::NoResponse));
+                    }
+                }
+                Err(e) => {
+                    for tx in txs {
+                        let _ = tx.send(Err(BatchError::Service(e.clone())));
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Groups requests into batches.
+///
+/// Inner service is called with a `Vec` of requests and must respond with a
+/// `Vec` of responses in the same order. Inner service error is reported to
+/// every request of the batch. Service must be used within an arbiter.
+pub struct BatchService<S: Service, R, Res> {
+    inner: Rc<Inner<S, R, Res>>,
+}
+
+impl<S, R, Res> BatchService<S, R, Res>
+where
+    S: Service<Request = Vec<R>, Response = Vec<Res>> + 'static,
+    S::Error: Clone,
+    R: 'static,
+    Res: 'static,
+{
+    /// Call inner service once `max_size` requests are accumulated, or
+    /// `max_delay` after first request of the batch.
+    pub fn new<U>(max_size: usize, max_delay: Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        BatchService {
+            inner: Rc::new(Inner {
+                max_size: std::cmp::max(max_size, 1),
+                max_delay,
+                service: RefCell::new(service.into_service()),
+                pending: RefCell::new(Vec::new()),
+                batch_id: Cell::new(0),
+            }),
+        }
+    }
+
+    /// Number of requests waiting for the current batch.
+    pub fn pending(&self) -> usize {
+        self.inner.pending.borrow().len()
+    }
+}
+
+impl<S: Service, R, Res> Clone for BatchService<S, R, Res> {
+    fn clone(&self) -> Self {
+        BatchService {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, R, Res> Service for BatchService<S, R, Res>
+where
+    S: Service<Request = Vec<R>, Response = Vec<Res>> + 'static,
+    S::Error: Clone,
+    R: 'static,
+    Res: 'static,
+{
+    type Request = R;
+    type Response = Res;
+    type Error = BatchError<S::Error>;
+    type Future = BatchResponse<Res, S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner
+            .service
+            .borrow_mut()
+            .poll_ready(cx)
+            .map_err(BatchError::Service)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.flush();
+        self.inner.service.borrow_mut().poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let (tx, rx) = oneshot::channel();
+
+        let len = {
+            let mut pending = self.inner.pending.borrow_mut();
+            pending.push((req, tx));
+            pending.len()
+        };
+
+        if len >= self.inner.max_size {
+            self.inner.flush();
+        } else if len == 1 {
+            // first request of the batch, schedule flush
+            let inner = self.inner.clone();
+            let batch_id = inner.batch_id.get();
+            actix_rt::spawn(async move {
+                delay_for(inner.max_delay).await;
+                if inner.batch_id.get() == batch_id {
+                    inner.flush();
+                }
+            });
+        }
+
+        BatchResponse { rx }
+    }
+}
+
+/// `BatchService` response future
+pub struct BatchResponse<Res, E> {
+    rx: oneshot::Receiver<Result<Res, BatchError<E>>>,
+}
+
+impl<Res, E> Future for BatchResponse<Res, E> {
+    type Output = Result<Res, BatchError<E>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match futures_util::ready!(Pin::new(&mut self.rx).poll(cx)) {
+            Ok(res) => Poll::Ready(res),
+            Err(_) => Poll::Ready(Err(BatchError::NoResponse)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{join_all, ready, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, ServiceFactory};
+
+    /// Records batch sizes, fails batches that contain zero
+    #[derive(Clone)]
+    struct Srv(Rc<RefCell<Vec<usize>>>);
+
+    impl Service for Srv {
+        type Request = Vec<usize>;
+        type Response = Vec<usize>;
+        type Error = &'static str;
+        type Future = Ready<Result<Vec<usize>, &'static str>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Vec<usize>) -> Self::Future {
+            self.0.borrow_mut().push(req.len());
+            if req.contains(&0) {
+                ready(Err("zero"))
+            } else {
+                ready(Ok(req.into_iter().map(|i| i * 10).collect()))
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_max_size() {
+        let batches = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = BatchService::new(3, Duration::from_secs(10), Srv(batches.clone()));
+
+        let res = join_all((1..=6).map(|i| srv.call(i))).await;
+        assert_eq!(res, (1..=6).map(|i| Ok(i * 10)).collect::<Vec<_>>());
+        assert_eq!(*batches.borrow(), vec![3, 3]);
+    }
+
+    #[actix_rt::test]
+    async fn test_max_delay() {
+        let batches = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = BatchService::new(10, Duration::from_millis(10), Srv(batches.clone()));
+
+        let fut1 = srv.call(1);
+        let fut2 = srv.call(2);
+        assert_eq!(srv.pending(), 2);
+        assert_eq!(fut1.await, Ok(10));
+        assert_eq!(fut2.await, Ok(20));
+        assert_eq!(*batches.borrow(), vec![2]);
+        assert_eq!(srv.pending(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_error() {
+        let batches = Rc::new(RefCell::new(Vec::new()));
+        let factory = apply(
+            Batch::new(2, Duration::from_millis(10)),
+            fn_factory(move || ok::<_, ()>(Srv(batches.clone()))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+
+        let res = join_all(vec![srv.call(1), srv.call(0)]).await;
+        assert_eq!(
+            res,
+            vec![
+                Err(BatchError::Service("zero")),
+                Err(BatchError::Service("zero"))
+            ]
+        );
+    }
+}
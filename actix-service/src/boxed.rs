@@ -4,13 +4,88 @@ use std::task::{Context, Poll};
 
 use futures_util::future::FutureExt;
 
-use crate::{Service, ServiceFactory};
+use crate::{Service, ServiceFactory, Transform};
 
 pub type BoxFuture<I, E> = Pin<Box<dyn Future<Output = Result<I, E>>>>;
 
 pub type BoxService<Req, Res, Err> =
     Box<dyn Service<Request = Req, Response = Res, Error = Err, Future = BoxFuture<Res, Err>>>;
 
+pub type SendBoxFuture<I, E> = Pin<Box<dyn Future<Output = Result<I, E>> + Send>>;
+
+pub type SendBoxService<Req, Res, Err> = Box<
+    dyn Service<Request = Req, Response = Res, Error = Err, Future = SendBoxFuture<Res, Err>>
+        + Send,
+>;
+
+/// Object-safe version of `Service` trait with boxed response future.
+///
+/// It is implemented for every service with `'static` future, and
+/// `dyn ServiceObj` implements `Service`, so `Box<dyn ServiceObj<..>>` or
+/// `&mut dyn ServiceObj<..>` could be used with combinators directly.
+pub trait ServiceObj {
+    /// Requests handled by the service.
+    type Request;
+
+    /// Responses given by the service.
+    type Response;
+
+    /// Errors produced by the service.
+    type Error;
+
+    /// Object-safe version of `Service::poll_ready()`
+    fn poll_ready_obj(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
+
+    /// Object-safe version of `Service::poll_shutdown()`
+    fn poll_shutdown_obj(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()>;
+
+    /// Object-safe version of `Service::call()`
+    fn call_obj(&mut self, req: Self::Request) -> BoxFuture<Self::Response, Self::Error>;
+}
+
+impl<S> ServiceObj for S
+where
+    S: Service,
+    S::Future: 'static,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+
+    fn poll_ready_obj(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_ready(ctx)
+    }
+
+    fn poll_shutdown_obj(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.poll_shutdown(ctx, is_error)
+    }
+
+    fn call_obj(&mut self, req: Self::Request) -> BoxFuture<Self::Response, Self::Error> {
+        Box::pin(self.call(req))
+    }
+}
+
+impl<'a, Req, Res, Err> Service
+    for dyn ServiceObj<Request = Req, Response = Res, Error = Err> + 'a
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = BoxFuture<Res, Err>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Err>> {
+        self.poll_ready_obj(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.poll_shutdown_obj(ctx, is_error)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.call_obj(req)
+    }
+}
+
 pub struct BoxServiceFactory<C, Req, Res, Err, InitErr>(Inner<C, Req, Res, Err, InitErr>);
 
 /// Create boxed service factory
@@ -41,6 +116,60 @@ where
     Box::new(ServiceWrapper(service))
 }
 
+/// Create boxed transform
+///
+/// Boxed transforms produce boxed services, so transforms with different
+/// types could be stored in one collection and applied at runtime.
+pub fn transform<T, S>(
+    transform: T,
+) -> BoxTransform<S, T::Request, T::Response, T::Error, T::InitError>
+where
+    T: Transform<S> + 'static,
+    T::Request: 'static,
+    T::Response: 'static,
+    T::Transform: 'static,
+    T::Future: 'static,
+    T::Error: 'static,
+    T::InitError: 'static,
+    S: 'static,
+{
+    BoxTransform(Box::new(TransformWrapper {
+        transform,
+        _t: std::marker::PhantomData,
+    }))
+}
+
+/// Create boxed service factory that can be sent to other threads
+///
+/// Factory, services it produces and their futures must be `Send`.
+pub fn send_factory<T>(
+    factory: T,
+) -> SendBoxServiceFactory<T::Config, T::Request, T::Response, T::Error, T::InitError>
+where
+    T: ServiceFactory + Send + 'static,
+    T::Request: 'static,
+    T::Response: 'static,
+    T::Service: Send + 'static,
+    T::Future: Send + 'static,
+    T::Error: 'static,
+    T::InitError: 'static,
+    <T::Service as Service>::Future: Send,
+{
+    SendBoxServiceFactory(Box::new(SendFactoryWrapper {
+        factory,
+        _t: std::marker::PhantomData,
+    }))
+}
+
+/// Create boxed service that can be sent to other threads
+pub fn send_service<T>(service: T) -> SendBoxService<T::Request, T::Response, T::Error>
+where
+    T: Service + Send + 'static,
+    T::Future: Send + 'static,
+{
+    Box::new(SendServiceWrapper(service))
+}
+
 type Inner<C, Req, Res, Err, InitErr> = Box<
     dyn ServiceFactory<
         Config = C,
@@ -139,7 +268,321 @@ where
         self.0.poll_ready(ctx)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.0.poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, req: Self::Request) -> Self::Future {
         Box::pin(self.0.call(req))
     }
 }
+
+pub struct BoxTransform<S, Req, Res, Err, InitErr>(TransformInner<S, Req, Res, Err, InitErr>);
+
+type TransformInner<S, Req, Res, Err, InitErr> = Box<
+    dyn Transform<
+        S,
+        Request = Req,
+        Response = Res,
+        Error = Err,
+        InitError = InitErr,
+        Transform = BoxService<Req, Res, Err>,
+        Future = BoxFuture<BoxService<Req, Res, Err>, InitErr>,
+    >,
+>;
+
+impl<S, Req, Res, Err, InitErr> Transform<S> for BoxTransform<S, Req, Res, Err, InitErr>
+where
+    Req: 'static,
+    Res: 'static,
+    Err: 'static,
+    InitErr: 'static,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type InitError = InitErr;
+    type Transform = BoxService<Req, Res, Err>;
+    type Future = BoxFuture<Self::Transform, InitErr>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        self.0.new_transform(service)
+    }
+}
+
+struct TransformWrapper<S, T: Transform<S>> {
+    transform: T,
+    _t: std::marker::PhantomData<S>,
+}
+
+impl<S, T, Req, Res, Err, InitErr> Transform<S> for TransformWrapper<S, T>
+where
+    Req: 'static,
+    Res: 'static,
+    Err: 'static,
+    InitErr: 'static,
+    T: Transform<S, Request = Req, Response = Res, Error = Err, InitError = InitErr>,
+    T::Future: 'static,
+    T::Transform: 'static,
+    <T::Transform as Service>::Future: 'static,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type InitError = InitErr;
+    type Transform = BoxService<Req, Res, Err>;
+    type Future = BoxFuture<Self::Transform, Self::InitError>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        Box::pin(
+            self.transform
+                .new_transform(service)
+                .map(|res| res.map(ServiceWrapper::boxed)),
+        )
+    }
+}
+
+pub struct SendBoxServiceFactory<C, Req, Res, Err, InitErr>(
+    SendInner<C, Req, Res, Err, InitErr>,
+);
+
+type SendInner<C, Req, Res, Err, InitErr> = Box<
+    dyn ServiceFactory<
+            Config = C,
+            Request = Req,
+            Response = Res,
+            Error = Err,
+            InitError = InitErr,
+            Service = SendBoxService<Req, Res, Err>,
+            Future = SendBoxFuture<SendBoxService<Req, Res, Err>, InitErr>,
+        > + Send,
+>;
+
+impl<C, Req, Res, Err, InitErr> ServiceFactory
+    for SendBoxServiceFactory<C, Req, Res, Err, InitErr>
+where
+    Req: 'static,
+    Res: 'static,
+    Err: 'static,
+    InitErr: 'static,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type InitError = InitErr;
+    type Config = C;
+    type Service = SendBoxService<Req, Res, Err>;
+
+    type Future = SendBoxFuture<Self::Service, InitErr>;
+
+    fn new_service(&self, cfg: C) -> Self::Future {
+        self.0.new_service(cfg)
+    }
+}
+
+struct SendFactoryWrapper<C, T: ServiceFactory> {
+    factory: T,
+    _t: std::marker::PhantomData<fn(C)>,
+}
+
+impl<C, T, Req, Res, Err, InitErr> ServiceFactory for SendFactoryWrapper<C, T>
+where
+    Req: 'static,
+    Res: 'static,
+    Err: 'static,
+    InitErr: 'static,
+    T: ServiceFactory<
+        Config = C,
+        Request = Req,
+        Response = Res,
+        Error = Err,
+        InitError = InitErr,
+    >,
+    T::Future: Send + 'static,
+    T::Service: Send + 'static,
+    <T::Service as Service>::Future: Send + 'static,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type InitError = InitErr;
+    type Config = C;
+    type Service = SendBoxService<Req, Res, Err>;
+    type Future = SendBoxFuture<Self::Service, Self::InitError>;
+
+    fn new_service(&self, cfg: C) -> Self::Future {
+        Box::pin(
+            self.factory
+                .new_service(cfg)
+                .map(|res| res.map(SendServiceWrapper::boxed)),
+        )
+    }
+}
+
+struct SendServiceWrapper<T: Service>(T);
+
+impl<T> SendServiceWrapper<T>
+where
+    T: Service + Send + 'static,
+    T::Future: Send + 'static,
+{
+    fn boxed(service: T) -> SendBoxService<T::Request, T::Response, T::Error> {
+        Box::new(SendServiceWrapper(service))
+    }
+}
+
+impl<T, Req, Res, Err> Service for SendServiceWrapper<T>
+where
+    T: Service<Request = Req, Response = Res, Error = Err>,
+    T::Future: Send + 'static,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = SendBoxFuture<Res, Err>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.0.poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        Box::pin(self.0.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::ok;
+
+    use super::*;
+    #[cfg(feature = "rt")]
+    use crate::timeout::{Timeout, TimeoutError};
+    use crate::{fn_factory, fn_service, into_service, pipeline, pipeline_factory, Pipeline};
+
+    struct Add(usize);
+
+    impl<S: Service<Request = usize>> Transform<S> for Add {
+        type Request = usize;
+        type Response = S::Response;
+        type Error = S::Error;
+        type InitError = ();
+        type Transform = AddService<S>;
+        type Future = futures_util::future::Ready<Result<Self::Transform, ()>>;
+
+        fn new_transform(&self, service: S) -> Self::Future {
+            ok(AddService(self.0, service))
+        }
+    }
+
+    struct AddService<S>(usize, S);
+
+    impl<S: Service<Request = usize>> Service for AddService<S> {
+        type Request = usize;
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.1.poll_ready(cx)
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            self.1.call(req + self.0)
+        }
+    }
+
+    fn is_send<T: Send>(t: T) -> T {
+        t
+    }
+
+    #[actix_rt::test]
+    async fn test_service_obj() {
+        let mut services: Vec<
+            Box<dyn ServiceObj<Request = usize, Response = usize, Error = ()>>,
+        > = vec![
+            Box::new(into_service(|req: usize| ok::<_, ()>(req + 1))),
+            Box::new(AddService(10, into_service(|req: usize| ok::<_, ()>(req)))),
+        ];
+        assert_eq!(services[0].call(1).await, Ok(2));
+
+        // trait objects work with combinators
+        let mut srv = crate::ServiceExt::and_then(
+            &mut services[1],
+            crate::fn_service(|res: usize| ok(res * 2)),
+        );
+        assert_eq!(srv.call(1).await, Ok(22));
+    }
+
+    #[actix_rt::test]
+    async fn test_send_service() {
+        let mut srv = is_send(send_service(fn_service(|req: usize| ok::<_, ()>(req * 2))));
+        assert_eq!(srv.call(2).await, Ok(4));
+    }
+
+    #[actix_rt::test]
+    async fn test_send_factory() {
+        let factory = is_send(send_factory(fn_factory(|| {
+            ok::<_, ()>(fn_service(|req: usize| ok::<_, ()>(req + 1)))
+        })));
+
+        let handle = std::thread::spawn(move || {
+            actix_rt::System::new("test").block_on(async move {
+                let mut srv = factory.new_service(()).await.unwrap();
+                srv.call(1).await
+            })
+        });
+        assert_eq!(handle.join().unwrap(), Ok(2));
+    }
+
+    #[cfg(feature = "rt")]
+    #[actix_rt::test]
+    async fn test_transform() {
+        let timeout = transform(Timeout::<()>::new(std::time::Duration::from_millis(20)));
+        let mut srv = timeout
+            .new_transform(into_service(|req: usize| {
+                actix_rt::time::delay_for(std::time::Duration::from_millis(req as u64))
+                    .map(move |_| Ok::<_, ()>(req))
+            }))
+            .await
+            .unwrap();
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(50).await, Err(TimeoutError::Timeout));
+    }
+
+    #[actix_rt::test]
+    async fn test_transform_collection() {
+        // middleware stack decided at runtime
+        let mut stack = Vec::new();
+        for n in &[1, 10, 100] {
+            stack.push(transform(Add(*n)));
+        }
+
+        let mut srv = service(into_service(|req: usize| ok::<_, ()>(req)));
+        for t in &stack {
+            srv = t.new_transform(srv).await.unwrap();
+        }
+        assert_eq!(srv.call(0).await, Ok(111));
+    }
+
+    #[actix_rt::test]
+    async fn test_boxed_pipeline() {
+        let srv: Pipeline<BoxService<usize, usize, ()>> =
+            pipeline(into_service(|req: usize| ok::<_, ()>(req + 1)))
+                .map(|res| res * 2)
+                .boxed();
+        let mut srv = srv.and_then(into_service(|req: usize| ok(req + 1))).boxed();
+        assert_eq!(srv.call(1).await, Ok(5));
+
+        let factory = pipeline_factory(fn_factory(|| {
+            ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req + 1)))
+        }))
+        .boxed()
+        .map(|res| res * 2);
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(4));
+    }
+}
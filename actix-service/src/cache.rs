@@ -0,0 +1,342 @@
+//! Service that memoizes responses of inner service.
+//!
+//! Responses are cached by a key extracted from the request. Cached entries
+//! expire after ttl. Once cache holds max number of entries, expired entries
+//! are removed, and if cache is still full, the oldest entry is evicted.
+//! Only successful responses are cached.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{self, Instant};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+/// Caches responses of inner service.
+pub struct Cache<F, E = ()> {
+    key: F,
+    ttl: time::Duration,
+    max_entries: usize,
+    _t: PhantomData<E>,
+}
+
+impl<F, E> Cache<F, E> {
+    /// Cache responses by key returned by `key` function for `ttl` duration.
+    pub fn new(key: F, ttl: time::Duration) -> Self {
+        Cache {
+            key,
+            ttl,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set max number of cached responses.
+    ///
+    /// By default max number of entries is set to 1024.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+}
+
+impl<F: Clone, E> Clone for Cache<F, E> {
+    fn clone(&self) -> Self {
+        Cache {
+            key: self.key.clone(),
+            ttl: self.ttl,
+            max_entries: self.max_entries,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, F, K, E> Transform<S> for Cache<F, E>
+where
+    S: Service,
+    S::Response: Clone,
+    F: Fn(&S::Request) -> K + Clone,
+    K: Hash + Eq + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = CacheService<S, F, K>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(
+            CacheService::new(self.key.clone(), self.ttl, service)
+                .max_entries(self.max_entries),
+        )
+    }
+}
+
+struct Store<K, R> {
+    ttl: time::Duration,
+    max_entries: usize,
+    entries: HashMap<K, (Instant, R)>,
+}
+
+impl<K: Hash + Eq + Clone, R: Clone> Store<K, R> {
+    fn get(&mut self, key: &K) -> Option<R> {
+        let expired = match self.entries.get(key) {
+            Some((created, res)) if created.elapsed() < self.ttl => return Some(res.clone()),
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            self.entries.remove(key);
+        }
+        None
+    }
+
+    fn insert(&mut self, key: K, res: R) {
+        if self.max_entries == 0 {
+            return;
+        }
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            let ttl = self.ttl;
+            self.entries
+                .retain(|_, (created, _)| created.elapsed() < ttl);
+        }
+        if self.entries.len() >= self.max_entries && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (created, _))| *created)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key, (Instant::now(), res));
+    }
+}
+
+/// Caches responses of inner service.
+pub struct CacheService<S: Service, F, K> {
+    service: S,
+    key: F,
+    store: Rc<RefCell<Store<K, S::Response>>>,
+}
+
+impl<S, F, K> CacheService<S, F, K>
+where
+    S: Service,
+    S::Response: Clone,
+    F: Fn(&S::Request) -> K,
+    K: Hash + Eq + Clone,
+{
+    /// Cache responses by key returned by `key` function for `ttl` duration.
+    pub fn new<U>(key: F, ttl: time::Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        CacheService {
+            key,
+            service: service.into_service(),
+            store: Rc::new(RefCell::new(Store {
+                ttl,
+                max_entries: DEFAULT_MAX_ENTRIES,
+                entries: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Set max number of cached responses.
+    pub fn max_entries(self, max: usize) -> Self {
+        self.store.borrow_mut().max_entries = max;
+        self
+    }
+
+    /// Number of cached responses, including expired ones.
+    pub fn len(&self) -> usize {
+        self.store.borrow().entries.len()
+    }
+
+    /// Returns `true` if cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.store.borrow().entries.is_empty()
+    }
+
+    /// Remove all cached responses.
+    pub fn clear(&self) {
+        self.store.borrow_mut().entries.clear();
+    }
+}
+
+impl<S, F, K> Service for CacheService<S, F, K>
+where
+    S: Service,
+    S::Response: Clone,
+    F: Fn(&S::Request) -> K,
+    K: Hash + Eq + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CacheResponse<S, K>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let key = (self.key)(&req);
+        if let Some(res) = self.store.borrow_mut().get(&key) {
+            return CacheResponse {
+                fut: None,
+                cached: Some(res),
+                key: None,
+                store: self.store.clone(),
+            };
+        }
+
+        CacheResponse {
+            fut: Some(self.service.call(req)),
+            cached: None,
+            key: Some(key),
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// `CacheService` response future
+#[pin_project::pin_project]
+pub struct CacheResponse<S: Service, K> {
+    #[pin]
+    fut: Option<S::Future>,
+    cached: Option<S::Response>,
+    key: Option<K>,
+    store: Rc<RefCell<Store<K, S::Response>>>,
+}
+
+impl<S, K> Future for CacheResponse<S, K>
+where
+    S: Service,
+    S::Response: Clone,
+    K: Hash + Eq + Clone,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.as_pin_mut() {
+            Some(fut) => {
+                let res = futures_util::ready!(fut.poll(cx))?;
+                if let Some(key) = this.key.take() {
+                    this.store.borrow_mut().insert(key, res.clone());
+                }
+                Poll::Ready(Ok(res))
+            }
+            None => Poll::Ready(Ok(this.cached.take().expect("Polled after completion"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{err, ok, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = (u32, &'static str);
+        type Response = String;
+        type Error = ();
+        type Future = Ready<Result<String, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: (u32, &'static str)) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            if req.0 == 0 {
+                err(())
+            } else {
+                ok(format!("{}-{}", req.0, self.0.get()))
+            }
+        }
+    }
+
+    fn key(req: &(u32, &'static str)) -> u32 {
+        req.0
+    }
+
+    #[actix_rt::test]
+    async fn test_cache() {
+        let calls = Rc::new(Cell::new(0));
+        let mut srv = CacheService::new(key, Duration::from_millis(50), Srv(calls.clone()));
+
+        assert_eq!(srv.call((1, "a")).await, Ok("1-1".to_string()));
+        assert_eq!(srv.call((1, "b")).await, Ok("1-1".to_string()));
+        assert_eq!(srv.call((2, "a")).await, Ok("2-2".to_string()));
+        assert_eq!(calls.get(), 2);
+
+        // errors are not cached
+        assert_eq!(srv.call((0, "a")).await, Err(()));
+        assert_eq!(srv.call((0, "a")).await, Err(()));
+        assert_eq!(calls.get(), 4);
+        assert_eq!(srv.len(), 2);
+
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        assert_eq!(srv.call((1, "a")).await, Ok("1-5".to_string()));
+    }
+
+    #[actix_rt::test]
+    async fn test_max_entries() {
+        let calls = Rc::new(Cell::new(0));
+        let mut srv =
+            CacheService::new(key, Duration::from_secs(10), Srv(calls.clone())).max_entries(2);
+
+        srv.call((1, "")).await.unwrap();
+        srv.call((2, "")).await.unwrap();
+        srv.call((3, "")).await.unwrap();
+        assert_eq!(srv.len(), 2);
+
+        // oldest entry has been evicted
+        assert_eq!(srv.call((1, "")).await, Ok("1-4".to_string()));
+        assert_eq!(srv.call((3, "")).await, Ok("3-3".to_string()));
+
+        srv.clear();
+        assert!(srv.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let factory = apply(
+            Cache::new(key, Duration::from_secs(10)),
+            fn_factory(move || ok::<_, ()>(Srv(calls2.clone()))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call((1, "")).await, Ok("1-1".to_string()));
+        assert_eq!(srv.call((1, "")).await, Ok("1-1".to_string()));
+        assert_eq!(calls.get(), 1);
+    }
+}
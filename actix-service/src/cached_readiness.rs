@@ -0,0 +1,108 @@
+use std::task::{Context, Poll};
+
+use super::Service;
+
+/// Service that remembers successful readiness check of inner service until
+/// the next call.
+///
+/// Useful when readiness check of inner service is expensive, i.e. it
+/// acquires lock or polls a channel, and dispatcher checks readiness many
+/// times per wakeup. Note that cached readiness is not re-validated, so inner
+/// service must stay ready until it is called.
+#[derive(Clone, Debug)]
+pub struct CachedReadiness<S> {
+    service: S,
+    ready: bool,
+}
+
+impl<S: Service> CachedReadiness<S> {
+    /// Create new `CachedReadiness` combinator
+    pub fn new(service: S) -> Self {
+        CachedReadiness {
+            service,
+            ready: false,
+        }
+    }
+
+    /// Returns `true` if successful readiness check is cached.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+}
+
+impl<S: Service> Service for CachedReadiness<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.ready {
+            futures_util::ready!(self.service.poll_ready(cx))?;
+            self.ready = true;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.ready = false;
+        self.service.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{lazy, ok, Ready};
+
+    use super::*;
+
+    /// Counts readiness checks
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.0.set(self.0.get() + 1);
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_cached_readiness() {
+        let cnt = Rc::new(Cell::new(0));
+        let mut srv = CachedReadiness::new(Srv(cnt.clone()));
+        assert!(!srv.is_ready());
+
+        for _ in 0..3 {
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        }
+        assert!(srv.is_ready());
+        assert_eq!(cnt.get(), 1);
+
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert!(!srv.is_ready());
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(cnt.get(), 2);
+    }
+}
@@ -0,0 +1,226 @@
+//! Drive a service over a stream of requests.
+//!
+//! `CallAll` pulls next request from the stream only when the service is
+//! ready, so service readiness propagates backpressure to the stream.
+//! Responses are yielded in request order, `unordered()` yields them as soon
+//! as they complete.
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::stream::{FuturesUnordered, Stream};
+
+use crate::{IntoService, Service};
+
+/// Call service with every request from the stream, returns stream of
+/// responses.
+pub fn call_all<T, S, St>(service: T, stream: St) -> CallAll<S, St>
+where
+    T: IntoService<S>,
+    S: Service,
+    St: Stream<Item = S::Request>,
+{
+    CallAll {
+        service: service.into_service(),
+        stream,
+        eof: false,
+        ordered: true,
+        queue: FuturesUnordered::new(),
+        next_in: 0,
+        next_out: 0,
+        done: BTreeMap::new(),
+    }
+}
+
+/// Stream of responses for the `call_all` helper.
+///
+/// If service's `poll_ready` fails, the error is yielded and no more requests
+/// are taken from the stream, stream ends once in-flight calls complete.
+#[pin_project::pin_project]
+pub struct CallAll<S: Service, St> {
+    service: S,
+    #[pin]
+    stream: St,
+    eof: bool,
+    ordered: bool,
+    queue: FuturesUnordered<Indexed<S::Future>>,
+    next_in: usize,
+    next_out: usize,
+    done: BTreeMap<usize, Result<S::Response, S::Error>>,
+}
+
+impl<S: Service, St> CallAll<S, St> {
+    /// Yield responses in completion order instead of request order.
+    pub fn unordered(mut self) -> Self {
+        self.ordered = false;
+        self
+    }
+
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// Consume stream and return inner service
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<S, St> Stream for CallAll<S, St>
+where
+    S: Service,
+    St: Stream<Item = S::Request>,
+{
+    type Item = Result<S::Response, S::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            // completed responses
+            if let Some(res) = this.done.remove(&*this.next_out) {
+                *this.next_out += 1;
+                return Poll::Ready(Some(res));
+            }
+            if let Poll::Ready(Some((idx, res))) = Pin::new(&mut *this.queue).poll_next(cx) {
+                if !*this.ordered || idx == *this.next_out {
+                    *this.next_out += 1;
+                    return Poll::Ready(Some(res));
+                }
+                this.done.insert(idx, res);
+                continue;
+            }
+
+            if *this.eof {
+                return if this.queue.is_empty() && this.done.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Pending
+                };
+            }
+
+            // next request
+            match this.service.poll_ready(cx) {
+                Poll::Ready(Ok(())) => (),
+                Poll::Ready(Err(e)) => {
+                    *this.eof = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(req)) => {
+                    this.queue.push(Indexed {
+                        idx: *this.next_in,
+                        fut: this.service.call(req),
+                    });
+                    *this.next_in += 1;
+                }
+                Poll::Ready(None) => *this.eof = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[pin_project::pin_project]
+struct Indexed<F> {
+    idx: usize,
+    #[pin]
+    fut: F,
+}
+
+impl<F: Future> Future for Indexed<F> {
+    type Output = (usize, F::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let idx = *this.idx;
+        this.fut.poll(cx).map(|res| (idx, res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{FutureExt, LocalBoxFuture};
+    use futures_util::stream::{self, StreamExt};
+
+    use super::*;
+    use crate::ServiceExt;
+
+    /// Responds with request after `req` milliseconds, fails for `0`
+    struct Srv(Rc<Cell<usize>>, usize);
+
+    impl Service for Srv {
+        type Request = u64;
+        type Response = u64;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<u64, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() < self.1 {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, req: u64) -> Self::Future {
+            let cnt = self.0.clone();
+            cnt.set(cnt.get() + 1);
+            actix_rt::time::delay_for(Duration::from_millis(req))
+                .map(move |_| {
+                    cnt.set(cnt.get() - 1);
+                    if req == 0 {
+                        Err(())
+                    } else {
+                        Ok(req)
+                    }
+                })
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_ordered() {
+        let srv = Srv(Rc::new(Cell::new(0)), 10);
+        let res: Vec<_> = call_all(srv, stream::iter(vec![30, 10, 20]))
+            .collect()
+            .await;
+        assert_eq!(res, vec![Ok(30), Ok(10), Ok(20)]);
+    }
+
+    #[actix_rt::test]
+    async fn test_unordered() {
+        let srv = Srv(Rc::new(Cell::new(0)), 10);
+        let res: Vec<_> = srv
+            .call_all(stream::iter(vec![30, 10, 0, 20]))
+            .unordered()
+            .collect()
+            .await;
+        assert_eq!(res, vec![Err(()), Ok(10), Ok(20), Ok(30)]);
+    }
+
+    #[actix_rt::test]
+    async fn test_backpressure() {
+        let cnt = Rc::new(Cell::new(0));
+        let max = Rc::new(Cell::new(0));
+        let max2 = max.clone();
+        let cnt2 = cnt.clone();
+        let requests = stream::iter(vec![10u64; 6]).inspect(move |_| {
+            max2.set(std::cmp::max(max2.get(), cnt2.get()));
+        });
+
+        let res: Vec<_> = call_all(Srv(cnt, 2), requests).collect().await;
+        assert_eq!(res.len(), 6);
+        assert!(res.iter().all(|r| r.is_ok()));
+        assert_eq!(max.get(), 1);
+    }
+}
@@ -0,0 +1,362 @@
+//! Service that stops calling inner service after repeated failures.
+//!
+//! Circuit breaker counts consecutive failed calls. Once the number of failures
+//! reaches the threshold, circuit opens and calls are rejected with
+//! `CircuitBreakerError::Open` without touching inner service. After reset
+//! timeout, circuit becomes half-open and allows one trial call, success closes
+//! circuit, failure opens it again.
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use std::{fmt, time};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Circuit breaker error
+#[derive(Debug, PartialEq)]
+pub enum CircuitBreakerError<E> {
+    /// Service error
+    Service(E),
+    /// Circuit is open, request has been rejected
+    Open,
+}
+
+impl<E> From<E> for CircuitBreakerError<E> {
+    fn from(err: E) -> Self {
+        CircuitBreakerError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitBreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitBreakerError::Service(e) => e.fmt(f),
+            CircuitBreakerError::Open => write!(f, "Circuit is open"),
+        }
+    }
+}
+
+/// Circuit state
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CircuitState {
+    /// Calls are passed to inner service
+    Closed,
+    /// Calls are rejected
+    Open,
+    /// One trial call is passed to inner service
+    HalfOpen,
+}
+
+/// Circuit breaker for services.
+pub struct CircuitBreaker<E = ()> {
+    threshold: usize,
+    reset_timeout: time::Duration,
+    _t: PhantomData<E>,
+}
+
+impl<E> CircuitBreaker<E> {
+    /// Open circuit after `threshold` consecutive failures, try to close it
+    /// after `reset_timeout`.
+    pub fn new(threshold: usize, reset_timeout: time::Duration) -> Self {
+        CircuitBreaker {
+            threshold,
+            reset_timeout,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for CircuitBreaker<E> {
+    fn clone(&self) -> Self {
+        CircuitBreaker::new(self.threshold, self.reset_timeout)
+    }
+}
+
+impl<S, E> Transform<S> for CircuitBreaker<E>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CircuitBreakerError<S::Error>;
+    type InitError = E;
+    type Transform = CircuitBreakerService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CircuitBreakerService::new(
+            self.threshold,
+            self.reset_timeout,
+            service,
+        ))
+    }
+}
+
+struct Inner {
+    threshold: usize,
+    reset_timeout: time::Duration,
+    state: Cell<CircuitState>,
+    failures: Cell<usize>,
+    opened: Cell<Instant>,
+    trial: Cell<bool>,
+}
+
+impl Inner {
+    fn state(&self) -> CircuitState {
+        if self.state.get() == CircuitState::Open
+            && self.opened.get().elapsed() >= self.reset_timeout
+        {
+            self.state.set(CircuitState::HalfOpen);
+            self.trial.set(false);
+        }
+        self.state.get()
+    }
+
+    fn success(&self) {
+        self.failures.set(0);
+        self.trial.set(false);
+        self.state.set(CircuitState::Closed);
+    }
+
+    fn failure(&self) {
+        let failures = self.failures.get() + 1;
+        self.failures.set(failures);
+        self.trial.set(false);
+
+        if self.state.get() == CircuitState::HalfOpen || failures >= self.threshold {
+            self.state.set(CircuitState::Open);
+            self.opened.set(Instant::now());
+        }
+    }
+}
+
+/// Circuit breaker for services.
+///
+/// Service is always ready while circuit is open, calls get rejected
+/// with `CircuitBreakerError::Open`.
+pub struct CircuitBreakerService<S> {
+    service: S,
+    inner: Rc<Inner>,
+}
+
+impl<S> CircuitBreakerService<S>
+where
+    S: Service,
+{
+    pub fn new<U>(threshold: usize, reset_timeout: time::Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        CircuitBreakerService {
+            service: service.into_service(),
+            inner: Rc::new(Inner {
+                threshold,
+                reset_timeout,
+                state: Cell::new(CircuitState::Closed),
+                failures: Cell::new(0),
+                opened: Cell::new(Instant::now()),
+                trial: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Current circuit state
+    pub fn state(&self) -> CircuitState {
+        self.inner.state()
+    }
+}
+
+impl<S> Service for CircuitBreakerService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = CircuitBreakerError<S::Error>;
+    type Future = CircuitBreakerResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.inner.state() {
+            CircuitState::Open => Poll::Ready(Ok(())),
+            CircuitState::HalfOpen if self.inner.trial.get() => Poll::Ready(Ok(())),
+            _ => self
+                .service
+                .poll_ready(cx)
+                .map_err(CircuitBreakerError::Service),
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let mut trial = None;
+        let fut = match self.inner.state() {
+            CircuitState::Open => None,
+            CircuitState::HalfOpen if self.inner.trial.get() => None,
+            CircuitState::HalfOpen => {
+                self.inner.trial.set(true);
+                trial = Some(TrialGuard(self.inner.clone()));
+                Some(self.service.call(req))
+            }
+            CircuitState::Closed => Some(self.service.call(req)),
+        };
+
+        CircuitBreakerResponse {
+            fut,
+            trial,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// `CircuitBreakerService` response future
+#[pin_project::pin_project]
+pub struct CircuitBreakerResponse<S: Service> {
+    #[pin]
+    fut: Option<S::Future>,
+    trial: Option<TrialGuard>,
+    inner: Rc<Inner>,
+}
+
+/// Releases half-open trial if trial call get dropped before completion.
+struct TrialGuard(Rc<Inner>);
+
+impl Drop for TrialGuard {
+    fn drop(&mut self) {
+        self.0.trial.set(false);
+    }
+}
+
+impl<S> Future for CircuitBreakerResponse<S>
+where
+    S: Service,
+{
+    type Output = Result<S::Response, CircuitBreakerError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.as_pin_mut() {
+            Some(fut) => match fut.poll(cx) {
+                Poll::Ready(Ok(res)) => {
+                    *this.trial = None;
+                    this.inner.success();
+                    Poll::Ready(Ok(res))
+                }
+                Poll::Ready(Err(e)) => {
+                    *this.trial = None;
+                    this.inner.failure();
+                    Poll::Ready(Err(CircuitBreakerError::Service(e)))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            None => Poll::Ready(Err(CircuitBreakerError::Open)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{err, lazy, ok, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    #[derive(Clone)]
+    struct Srv(Rc<Cell<bool>>, Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.1.set(self.1.get() + 1);
+            if self.0.get() {
+                ok(())
+            } else {
+                err(())
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_circuit_breaker() {
+        let healthy = Rc::new(Cell::new(false));
+        let calls = Rc::new(Cell::new(0));
+        let mut srv = CircuitBreakerService::new(
+            2,
+            Duration::from_millis(50),
+            Srv(healthy.clone(), calls.clone()),
+        );
+
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Open);
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Open));
+        assert_eq!(calls.get(), 2);
+
+        // failed trial call opens circuit again
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        assert_eq!(srv.state(), CircuitState::HalfOpen);
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.state(), CircuitState::Open);
+        assert_eq!(calls.get(), 3);
+
+        // successful trial call closes circuit
+        healthy.set(true);
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(srv.state(), CircuitState::Closed);
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[actix_rt::test]
+    async fn test_half_open_single_trial() {
+        let calls = Rc::new(Cell::new(0));
+        let mut srv = CircuitBreakerService::new(
+            1,
+            Duration::from_millis(10),
+            Srv(Rc::new(Cell::new(true)), calls.clone()),
+        );
+        srv.inner.failure();
+        actix_rt::time::delay_for(Duration::from_millis(20)).await;
+
+        let trial = srv.call(());
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Open));
+        assert_eq!(trial.await, Ok(()));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let factory = apply(
+            CircuitBreaker::new(1, Duration::from_secs(10)),
+            fn_factory(|| ok::<_, ()>(Srv(Rc::new(Cell::new(false)), Rc::new(Cell::new(0))))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Service(())));
+        assert_eq!(srv.call(()).await, Err(CircuitBreakerError::Open));
+    }
+}
@@ -0,0 +1,116 @@
+use std::marker::PhantomData;
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, ServiceFactory};
+
+/// Create service factory that produces clones of provided service
+///
+/// Factory accepts any config, config value is ignored.
+///
+/// ```rust
+/// use actix_service::{clone_factory, fn_service, ServiceFactory};
+/// use futures_util::future::ok;
+///
+/// # async fn test() {
+/// let srv = fn_service(|req: usize| ok::<_, ()>(req + 1));
+/// let factory = clone_factory::<_, _, usize, ()>(srv);
+///
+/// let mut srv = factory.new_service(10).await.unwrap();
+/// # }
+/// ```
+pub fn clone_factory<S, U, C, E>(service: U) -> CloneFactory<S, C, E>
+where
+    S: Service + Clone,
+    U: IntoService<S>,
+{
+    CloneFactory::new(service.into_service())
+}
+
+/// `clone_factory()` service factory
+pub struct CloneFactory<S, C = (), E = ()> {
+    service: S,
+    _t: PhantomData<(C, E)>,
+}
+
+impl<S, C, E> CloneFactory<S, C, E>
+where
+    S: Service + Clone,
+{
+    /// Create new `CloneFactory` for provided service
+    pub(crate) fn new(service: S) -> Self {
+        CloneFactory {
+            service,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S: Clone, C, E> Clone for CloneFactory<S, C, E> {
+    fn clone(&self) -> Self {
+        CloneFactory {
+            service: self.service.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, C, E> ServiceFactory for CloneFactory<S, C, E>
+where
+    S: Service + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+
+    type Config = C;
+    type Service = S;
+    type InitError = E;
+    type Future = Ready<Result<S, E>>;
+
+    fn new_service(&self, _: C) -> Self::Future {
+        ok(self.service.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{ok, Ready};
+
+    use super::*;
+    use crate::ServiceExt;
+
+    /// Client that shares state between clones
+    #[derive(Clone)]
+    struct Client(Rc<Cell<usize>>);
+
+    impl Service for Client {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            ok(self.0.get())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_clone_factory() {
+        let factory = Client(Rc::new(Cell::new(0))).clone_factory::<&'static str, ()>();
+
+        let mut srv1 = factory.new_service("cfg").await.unwrap();
+        let mut srv2 = factory.clone().new_service("cfg").await.unwrap();
+        assert_eq!(srv1.call(()).await, Ok(1));
+        assert_eq!(srv2.call(()).await, Ok(2));
+    }
+}
@@ -0,0 +1,275 @@
+//! Service that coalesces identical concurrent requests.
+//!
+//! Requests are identified by a key extracted from the request. While a call
+//! for some key is in flight, requests with the same key do not reach inner
+//! service, they wait for the in-flight call and receive a clone of its
+//! result. Once the call completes, next request with the key issues a new
+//! call.
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_util::future::{ok, FutureExt, Ready, Shared};
+
+use crate::{IntoService, Service, Transform};
+
+/// Coalesces identical concurrent requests.
+pub struct Coalesce<F, E = ()> {
+    key: F,
+    _t: PhantomData<E>,
+}
+
+impl<F, E> Coalesce<F, E> {
+    /// Coalesce requests by key returned by `key` function.
+    pub fn new(key: F) -> Self {
+        Coalesce {
+            key,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, E> Clone for Coalesce<F, E> {
+    fn clone(&self) -> Self {
+        Coalesce::new(self.key.clone())
+    }
+}
+
+impl<S, F, K, E> Transform<S> for Coalesce<F, E>
+where
+    S: Service,
+    S::Response: Clone,
+    S::Error: Clone,
+    F: Fn(&S::Request) -> K + Clone,
+    K: Hash + Eq + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = CoalesceService<S, F, K>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CoalesceService::new(self.key.clone(), service))
+    }
+}
+
+type Flights<S, K> = Rc<RefCell<Inflight<S, K>>>;
+
+struct Inflight<S: Service, K> {
+    next_id: usize,
+    calls: HashMap<K, (usize, Shared<S::Future>)>,
+}
+
+/// Coalesces identical concurrent requests.
+pub struct CoalesceService<S: Service, F, K> {
+    service: S,
+    key: F,
+    flights: Flights<S, K>,
+}
+
+impl<S, F, K> CoalesceService<S, F, K>
+where
+    S: Service,
+    S::Response: Clone,
+    S::Error: Clone,
+    F: Fn(&S::Request) -> K,
+    K: Hash + Eq + Clone,
+{
+    /// Coalesce requests by key returned by `key` function.
+    pub fn new<U>(key: F, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        CoalesceService {
+            key,
+            service: service.into_service(),
+            flights: Rc::new(RefCell::new(Inflight {
+                next_id: 0,
+                calls: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Number of in-flight calls to inner service.
+    pub fn in_flight(&self) -> usize {
+        self.flights.borrow().calls.len()
+    }
+}
+
+impl<S, F, K> Service for CoalesceService<S, F, K>
+where
+    S: Service,
+    S::Response: Clone,
+    S::Error: Clone,
+    F: Fn(&S::Request) -> K,
+    K: Hash + Eq + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CoalesceResponse<S, K>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let key = (self.key)(&req);
+
+        let (id, fut) = {
+            let mut flights = self.flights.borrow_mut();
+            match flights.calls.get(&key) {
+                Some((id, fut)) => (*id, fut.clone()),
+                None => {
+                    let id = flights.next_id;
+                    flights.next_id = id.wrapping_add(1);
+                    let fut = self.service.call(req).shared();
+                    flights.calls.insert(key.clone(), (id, fut.clone()));
+                    (id, fut)
+                }
+            }
+        };
+
+        CoalesceResponse {
+            id,
+            fut,
+            key: Some(key),
+            flights: self.flights.clone(),
+        }
+    }
+}
+
+/// `CoalesceService` response future
+#[pin_project::pin_project]
+pub struct CoalesceResponse<S: Service, K> {
+    id: usize,
+    fut: Shared<S::Future>,
+    key: Option<K>,
+    flights: Flights<S, K>,
+}
+
+impl<S, K> Future for CoalesceResponse<S, K>
+where
+    S: Service,
+    S::Response: Clone,
+    S::Error: Clone,
+    K: Hash + Eq,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = futures_util::ready!(Pin::new(&mut *this.fut).poll(cx));
+        if let Some(key) = this.key.take() {
+            let mut flights = this.flights.borrow_mut();
+            // key could belong to a newer call already
+            if flights.calls.get(&key).map(|(id, _)| *id) == Some(*this.id) {
+                flights.calls.remove(&key);
+            }
+        }
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use actix_rt::time::delay_for;
+    use futures_util::future::{join_all, LocalBoxFuture};
+
+    use super::*;
+    use crate::{apply, fn_factory, ServiceFactory};
+
+    /// Responds after 10 milliseconds, counts calls
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = (u32, &'static str);
+        type Response = String;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<String, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: (u32, &'static str)) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            let res = if req.0 == 0 {
+                Err(())
+            } else {
+                Ok(format!("{}-{}", req.0, self.0.get()))
+            };
+            delay_for(Duration::from_millis(10))
+                .map(move |_| res)
+                .boxed_local()
+        }
+    }
+
+    fn key(req: &(u32, &'static str)) -> u32 {
+        req.0
+    }
+
+    #[actix_rt::test]
+    async fn test_coalesce() {
+        let calls = Rc::new(Cell::new(0));
+        let mut srv = CoalesceService::new(key, Srv(calls.clone()));
+
+        let futs = vec![
+            srv.call((1, "a")),
+            srv.call((2, "a")),
+            srv.call((1, "b")),
+            srv.call((0, "a")),
+            srv.call((0, "b")),
+        ];
+        assert_eq!(srv.in_flight(), 3);
+
+        let res = join_all(futs).await;
+        assert_eq!(
+            res,
+            vec![
+                Ok("1-1".to_string()),
+                Ok("2-2".to_string()),
+                Ok("1-1".to_string()),
+                Err(()),
+                Err(())
+            ]
+        );
+        assert_eq!(calls.get(), 3);
+        assert_eq!(srv.in_flight(), 0);
+
+        // completed calls are not reused
+        assert_eq!(srv.call((1, "a")).await, Ok("1-4".to_string()));
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let factory = apply(
+            Coalesce::new(key),
+            fn_factory(move || ok::<_, ()>(Srv(calls2.clone()))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+
+        let res = join_all(vec![srv.call((1, "")), srv.call((1, ""))]).await;
+        assert_eq!(res, vec![Ok("1-1".to_string()), Ok("1-1".to_string())]);
+        assert_eq!(calls.get(), 1);
+    }
+}
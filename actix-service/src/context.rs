@@ -0,0 +1,404 @@
+//! Request context carried alongside requests.
+//!
+//! `WithContext<R>` wraps a request together with `Extensions`, a typed map
+//! of values that chained services could read and amend without changing
+//! request type. `Deadline` combinator fails calls once the deadline stored
+//! in the context passes, so timeout budget is shared by the whole chain.
+//! Deadlines are available with `rt` feature enabled.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "rt")]
+use std::future::Future;
+#[cfg(feature = "rt")]
+use std::marker::PhantomData;
+#[cfg(feature = "rt")]
+use std::pin::Pin;
+#[cfg(feature = "rt")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "rt")]
+use actix_rt::time::{delay_until, Delay, Instant};
+#[cfg(feature = "rt")]
+use futures_util::future::{ok, Ready};
+
+#[cfg(feature = "rt")]
+use crate::timeout::TimeoutError;
+#[cfg(feature = "rt")]
+use crate::{IntoService, Service, Transform};
+
+/// A type map of request extensions.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Extensions {
+    /// Create an empty `Extensions`.
+    pub fn new() -> Self {
+        Extensions::default()
+    }
+
+    /// Insert a value, previous value of the same type is returned.
+    pub fn insert<T: 'static>(&mut self, val: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(val))
+            .and_then(|item| item.downcast().ok().map(|item| *item))
+    }
+
+    /// Check if extensions contain a value of type `T`.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Get a reference to a value of type `T`.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|item| item.downcast_ref())
+    }
+
+    /// Get a mutable reference to a value of type `T`.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|item| item.downcast_mut())
+    }
+
+    /// Remove a value of type `T`.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|item| item.downcast().ok().map(|item| *item))
+    }
+
+    /// Clear all values.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "rt")]
+/// Deadline stored in request extensions
+#[derive(Clone, Copy, Debug)]
+struct RequestDeadline(Instant);
+
+/// Request with context.
+#[derive(Debug)]
+pub struct WithContext<R> {
+    req: R,
+    extensions: Extensions,
+}
+
+impl<R> WithContext<R> {
+    /// Wrap request with empty context.
+    pub fn new(req: R) -> Self {
+        WithContext {
+            req,
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// Wrap request with provided extensions.
+    pub fn with_extensions(req: R, extensions: Extensions) -> Self {
+        WithContext { req, extensions }
+    }
+
+    /// Get reference to the request.
+    pub fn request(&self) -> &R {
+        &self.req
+    }
+
+    /// Get mutable reference to the request.
+    pub fn request_mut(&mut self) -> &mut R {
+        &mut self.req
+    }
+
+    /// Get reference to request extensions.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get mutable reference to request extensions.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Request deadline, if set.
+    #[cfg(feature = "rt")]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.extensions.get::<RequestDeadline>().map(|d| d.0)
+    }
+
+    /// Set request deadline.
+    ///
+    /// Deadline can only be shortened, if context already has an earlier
+    /// deadline it is kept.
+    #[cfg(feature = "rt")]
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        match self.deadline() {
+            Some(current) if current <= deadline => (),
+            _ => {
+                self.extensions.insert(RequestDeadline(deadline));
+            }
+        }
+    }
+
+    /// Replace request keeping the context.
+    pub fn map<T, F>(self, f: F) -> WithContext<T>
+    where
+        F: FnOnce(R) -> T,
+    {
+        WithContext {
+            req: f(self.req),
+            extensions: self.extensions,
+        }
+    }
+
+    /// Split into request and extensions.
+    pub fn into_parts(self) -> (R, Extensions) {
+        (self.req, self.extensions)
+    }
+
+    /// Unwrap request, context is dropped.
+    pub fn into_inner(self) -> R {
+        self.req
+    }
+}
+
+impl<R> From<R> for WithContext<R> {
+    fn from(req: R) -> Self {
+        WithContext::new(req)
+    }
+}
+
+#[cfg(feature = "rt")]
+/// Fails calls with `TimeoutError::Timeout` once request deadline passes.
+///
+/// Requests without deadline are passed to inner service as is.
+pub struct Deadline<E = ()>(PhantomData<E>);
+
+#[cfg(feature = "rt")]
+impl<E> Deadline<E> {
+    pub fn new() -> Self {
+        Deadline(PhantomData)
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<E> Default for Deadline<E> {
+    fn default() -> Self {
+        Deadline::new()
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<E> Clone for Deadline<E> {
+    fn clone(&self) -> Self {
+        Deadline::new()
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<S, R, E> Transform<S> for Deadline<E>
+where
+    S: Service<Request = WithContext<R>>,
+{
+    type Request = WithContext<R>;
+    type Response = S::Response;
+    type Error = TimeoutError<S::Error>;
+    type InitError = E;
+    type Transform = DeadlineService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(DeadlineService::new(service))
+    }
+}
+
+#[cfg(feature = "rt")]
+/// Fails calls with `TimeoutError::Timeout` once request deadline passes.
+#[derive(Debug, Clone)]
+pub struct DeadlineService<S> {
+    service: S,
+}
+
+#[cfg(feature = "rt")]
+impl<S> DeadlineService<S> {
+    pub fn new<U, R>(service: U) -> Self
+    where
+        S: Service<Request = WithContext<R>>,
+        U: IntoService<S>,
+    {
+        DeadlineService {
+            service: service.into_service(),
+        }
+    }
+}
+
+#[cfg(feature = "rt")]
+impl<S, R> Service for DeadlineService<S>
+where
+    S: Service<Request = WithContext<R>>,
+{
+    type Request = WithContext<R>;
+    type Response = S::Response;
+    type Error = TimeoutError<S::Error>;
+    type Future = DeadlineServiceResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx).map_err(TimeoutError::Service)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: WithContext<R>) -> Self::Future {
+        match req.deadline() {
+            Some(deadline) if deadline <= Instant::now() => DeadlineServiceResponse {
+                fut: None,
+                delay: None,
+            },
+            deadline => DeadlineServiceResponse {
+                delay: deadline.map(delay_until),
+                fut: Some(self.service.call(req)),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "rt")]
+/// `DeadlineService` response future
+#[pin_project::pin_project]
+pub struct DeadlineServiceResponse<S: Service> {
+    #[pin]
+    fut: Option<S::Future>,
+    delay: Option<Delay>,
+}
+
+#[cfg(feature = "rt")]
+impl<S: Service> Future for DeadlineServiceResponse<S> {
+    type Output = Result<S::Response, TimeoutError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        // deadline has passed before call
+        let fut = match this.fut.as_pin_mut() {
+            Some(fut) => fut,
+            None => return Poll::Ready(Err(TimeoutError::Timeout)),
+        };
+
+        if let Poll::Ready(res) = fut.poll(cx) {
+            return Poll::Ready(res.map_err(TimeoutError::Service));
+        }
+
+        match this.delay {
+            Some(delay) => match Pin::new(delay).poll(cx) {
+                Poll::Ready(_) => Poll::Ready(Err(TimeoutError::Timeout)),
+                Poll::Pending => Poll::Pending,
+            },
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "rt")]
+    use std::task::{Context, Poll};
+    #[cfg(feature = "rt")]
+    use std::time::Duration;
+
+    #[cfg(feature = "rt")]
+    use actix_rt::time::delay_for;
+    #[cfg(feature = "rt")]
+    use futures_util::future::{FutureExt, LocalBoxFuture};
+
+    use super::*;
+
+    #[test]
+    fn test_extensions() {
+        let mut ext = Extensions::new();
+        assert_eq!(ext.insert(5i32), None);
+        assert_eq!(ext.insert(6i32), Some(5));
+        ext.insert("str");
+        assert!(ext.contains::<i32>());
+        assert_eq!(ext.get::<&'static str>(), Some(&"str"));
+
+        *ext.get_mut::<i32>().unwrap() += 1;
+        assert_eq!(ext.remove::<i32>(), Some(7));
+        assert!(!ext.contains::<i32>());
+        assert_eq!(ext.get::<u8>(), None);
+    }
+
+    #[cfg(feature = "rt")]
+    #[actix_rt::test]
+    async fn test_set_deadline() {
+        let now = Instant::now();
+        let mut req = WithContext::new(());
+        assert!(req.deadline().is_none());
+
+        req.set_deadline(now + Duration::from_secs(1));
+        req.set_deadline(now + Duration::from_secs(2));
+        assert_eq!(req.deadline(), Some(now + Duration::from_secs(1)));
+
+        let req = req.map(|_| 1);
+        assert_eq!(req.deadline(), Some(now + Duration::from_secs(1)));
+        assert_eq!(*req.request(), 1);
+    }
+
+    /// Responds after `req` milliseconds
+    #[cfg(feature = "rt")]
+    struct SleepService;
+
+    #[cfg(feature = "rt")]
+    impl Service for SleepService {
+        type Request = WithContext<u64>;
+        type Response = u64;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<u64, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: WithContext<u64>) -> Self::Future {
+            let ms = req.into_inner();
+            delay_for(Duration::from_millis(ms))
+                .map(move |_| Ok(ms))
+                .boxed_local()
+        }
+    }
+
+    #[cfg(feature = "rt")]
+    #[actix_rt::test]
+    async fn test_deadline() {
+        let mut srv = DeadlineService::new(SleepService);
+
+        // no deadline
+        assert_eq!(srv.call(WithContext::new(20)).await, Ok(20));
+
+        let mut req = WithContext::new(10);
+        req.set_deadline(Instant::now() + Duration::from_millis(100));
+        assert_eq!(srv.call(req).await, Ok(10));
+
+        let mut req = WithContext::new(100);
+        req.set_deadline(Instant::now() + Duration::from_millis(10));
+        assert_eq!(srv.call(req).await, Err(TimeoutError::Timeout));
+
+        // deadline already passed
+        let mut req = WithContext::new(0);
+        req.set_deadline(Instant::now() - Duration::from_millis(1));
+        assert_eq!(srv.call(req).await, Err(TimeoutError::Timeout));
+    }
+}
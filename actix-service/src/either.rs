@@ -0,0 +1,176 @@
+//! One of two services or service factories of different types.
+//!
+//! `Either` is useful for conditional construction of pipelines, i.e.
+//! `if tls { Either::Left(a) } else { Either::Right(b) }` produces single
+//! concrete type without boxing. Both variants must have the same request,
+//! response and error types.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::{Service, ServiceFactory};
+
+/// Service or service factory that is one of two types.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A, B> Service for Either<A, B>
+where
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = EitherResponse<A::Future, B::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Either::Left(srv) => srv.poll_ready(cx),
+            Either::Right(srv) => srv.poll_ready(cx),
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        match self {
+            Either::Left(srv) => srv.poll_shutdown(cx, is_error),
+            Either::Right(srv) => srv.poll_shutdown(cx, is_error),
+        }
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        match self {
+            Either::Left(srv) => EitherResponse::Left(srv.call(req)),
+            Either::Right(srv) => EitherResponse::Right(srv.call(req)),
+        }
+    }
+}
+
+impl<A, B> ServiceFactory for Either<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Config = A::Config;
+    type Service = Either<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = EitherServiceFactoryResponse<A, B>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        match self {
+            Either::Left(factory) => EitherServiceFactoryResponse {
+                fut: EitherResponse::Left(factory.new_service(cfg)),
+            },
+            Either::Right(factory) => EitherServiceFactoryResponse {
+                fut: EitherResponse::Right(factory.new_service(cfg)),
+            },
+        }
+    }
+}
+
+/// Future that is one of two futures with the same output.
+#[pin_project::pin_project(project = EitherResponseProj)]
+pub enum EitherResponse<A, B> {
+    Left(#[pin] A),
+    Right(#[pin] B),
+}
+
+impl<A, B> Future for EitherResponse<A, B>
+where
+    A: Future,
+    B: Future<Output = A::Output>,
+{
+    type Output = A::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EitherResponseProj::Left(fut) => fut.poll(cx),
+            EitherResponseProj::Right(fut) => fut.poll(cx),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct EitherServiceFactoryResponse<A: ServiceFactory, B: ServiceFactory> {
+    #[pin]
+    fut: EitherResponse<A::Future, B::Future>,
+}
+
+impl<A, B> Future for EitherServiceFactoryResponse<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory<InitError = A::InitError>,
+{
+    type Output = Result<Either<A::Service, B::Service>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = match self.project().fut.project() {
+            EitherResponseProj::Left(fut) => Either::Left(futures_util::ready!(fut.poll(cx))?),
+            EitherResponseProj::Right(fut) => {
+                Either::Right(futures_util::ready!(fut.poll(cx))?)
+            }
+        };
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Poll;
+
+    use futures_util::future::{lazy, ok};
+
+    use super::*;
+    use crate::{fn_factory, into_service};
+
+    #[actix_rt::test]
+    async fn test_service() {
+        for &left in &[true, false] {
+            let mut srv = if left {
+                Either::Left(into_service(|req: usize| ok::<_, ()>(req + 1)))
+            } else {
+                Either::Right(
+                    into_service(|req: usize| ok::<_, ()>(req * 10)).map(|res| res - 1),
+                )
+            };
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+            assert_eq!(srv.call(1).await, if left { Ok(2) } else { Ok(9) });
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_factory() {
+        for &left in &[true, false] {
+            let factory = if left {
+                Either::Left(fn_factory(|| {
+                    ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req + 1)))
+                }))
+            } else {
+                Either::Right(fn_factory(|| {
+                    ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req * 10)))
+                }))
+            };
+
+            let mut srv = factory.new_service(()).await.unwrap();
+            if left {
+                assert_eq!(srv.call(2).await, Ok(3));
+            } else {
+                assert!(matches!(srv, Either::Right(_)));
+                assert_eq!(srv.call(2).await, Ok(20));
+            }
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::stream::Stream;
+
+use crate::and_then::{AndThenService, AndThenServiceFactory, AndThenSplitServiceFactory};
+use crate::and_then_apply_fn::{AndThenApplyFn, AndThenApplyFnFactory};
+use crate::apply::{apply_fn, apply_fn_factory, Apply, ApplyServiceFactory};
+use crate::cached_readiness::CachedReadiness;
+use crate::call_all::{call_all, CallAll};
+use crate::circuit_breaker::CircuitBreakerService;
+use crate::clone_factory::CloneFactory;
+#[cfg(feature = "rt")]
+use crate::context::{DeadlineService, WithContext};
+use crate::filter::FilterService;
+#[cfg(feature = "rt")]
+use crate::hedge::HedgeService;
+use crate::inspect::{Inspect, InspectErr, InspectResponse};
+use crate::limit::ConcurrencyLimitService;
+use crate::load_shed::LoadShedService;
+use crate::map_config::{MapConfig, UnitConfig};
+use crate::map_request::{MapRequest, MapRequestServiceFactory};
+use crate::on_shutdown::{Dispose, OnShutdown};
+use crate::oneshot::{oneshot, Oneshot};
+use crate::or_else::{OrElseService, OrElseServiceFactory};
+use crate::race::{RaceService, RaceServiceFactory};
+#[cfg(feature = "rt")]
+use crate::rate_limit::RateLimitService;
+#[cfg(feature = "rt")]
+use crate::retry::{Policy, RetryService};
+use crate::stream::{AndThenStream, MapStream};
+use crate::then::{ThenService, ThenServiceFactory};
+#[cfg(feature = "rt")]
+use crate::timeout::TimeoutService;
+use crate::{IntoService, IntoServiceFactory, Service, ServiceFactory};
+
+/// An extension trait for `Service`s that provides a variety of convenient
+/// combinators.
+///
+/// `map` and `map_err` are defined on the `Service` trait itself.
+pub trait ServiceExt: Service {
+    /// Call another service after call to this one has resolved successfully.
+    ///
+    /// Result of the call to the first service is used as an input parameter
+    /// for the second service's call.
+    fn and_then<F, U>(self, service: F) -> AndThenService<Self, U>
+    where
+        Self: Sized,
+        F: IntoService<U>,
+        U: Service<Request = Self::Response, Error = Self::Error>,
+    {
+        AndThenService::new(self, service.into_service())
+    }
+
+    /// Apply function to specified service and use it as a next service in
+    /// chain.
+    fn and_then_apply_fn<U, I, F, Fut, Res, Err>(
+        self,
+        service: I,
+        f: F,
+    ) -> AndThenApplyFn<Self, U, F, Fut, Res, Err>
+    where
+        Self: Sized,
+        I: IntoService<U>,
+        U: Service,
+        F: FnMut(Self::Response, &mut U) -> Fut,
+        Fut: Future<Output = Result<Res, Err>>,
+        Err: From<Self::Error> + From<U::Error>,
+    {
+        AndThenApplyFn::new(self, service.into_service(), f)
+    }
+
+    /// Chain on a computation for when a call to the service finished,
+    /// passing the result of the call to the next service `U`.
+    ///
+    /// Unlike `and_then`, next service is called for failed calls too, so it
+    /// could be used for error recovery or logging.
+    fn then<F, U>(self, service: F) -> ThenService<Self, U>
+    where
+        Self: Sized,
+        F: IntoService<U>,
+        U: Service<Request = Result<Self::Response, Self::Error>, Error = Self::Error>,
+    {
+        ThenService::new(self, service.into_service())
+    }
+
+    /// Call fallback service with original request if call to this service
+    /// fails.
+    fn or_else<F, U>(self, service: F) -> OrElseService<Self, U>
+    where
+        Self: Sized,
+        Self::Request: Clone,
+        F: IntoService<U>,
+        U: Service<Request = Self::Request, Response = Self::Response, Error = Self::Error>,
+    {
+        OrElseService::new(self, service.into_service())
+    }
+
+    /// Call this and another service with the same request concurrently,
+    /// resolve with first successful response.
+    fn race<F, U>(self, service: F) -> RaceService<Self, U>
+    where
+        Self: Sized,
+        Self::Request: Clone,
+        F: IntoService<U>,
+        U: Service<Request = Self::Request, Response = Self::Response, Error = Self::Error>,
+    {
+        RaceService::new(self, service.into_service())
+    }
+
+    /// Wrap this service with a function that receives request and
+    /// a mutable reference to the service.
+    ///
+    /// Method-style version of `apply_fn(service, f)`.
+    fn apply_fn<F, R, In, Out>(self, f: F) -> Apply<Self, F, R, In, Out, Self::Error>
+    where
+        Self: Sized,
+        F: FnMut(In, &mut Self) -> R,
+        R: Future<Output = Result<Out, Self::Error>>,
+    {
+        apply_fn(self, f)
+    }
+
+    /// Map this service's request to a different type, returning a new
+    /// service of the resulting request type.
+    ///
+    /// This function is similar to `map`, but converts incoming requests
+    /// before they reach this service.
+    fn map_request<F, Req>(self, f: F) -> MapRequest<Self, F, Req>
+    where
+        Self: Sized,
+        F: FnMut(Req) -> Self::Request,
+    {
+        MapRequest::new(self, f)
+    }
+
+    /// Observe requests before they are passed to this service.
+    fn inspect<F>(self, f: F) -> Inspect<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Request),
+    {
+        Inspect::new(self, f)
+    }
+
+    /// Observe successful responses of this service.
+    fn inspect_response<F>(self, f: F) -> InspectResponse<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Response) + Clone,
+    {
+        InspectResponse::new(self, f)
+    }
+
+    /// Observe errors of this service, including readiness errors.
+    fn inspect_err<F>(self, f: F) -> InspectErr<Self, F>
+    where
+        Self: Sized,
+        F: Fn(&Self::Error) + Clone,
+    {
+        InspectErr::new(self, f)
+    }
+
+    /// Call this service with every request from the stream, returns
+    /// stream of responses.
+    fn call_all<St>(self, stream: St) -> CallAll<Self, St>
+    where
+        Self: Sized,
+        St: Stream<Item = Self::Request>,
+    {
+        call_all(self, stream)
+    }
+
+    /// Wait for this service to become ready and call it with the request,
+    /// consuming the service.
+    fn oneshot(self, req: Self::Request) -> Oneshot<Self>
+    where
+        Self: Sized,
+    {
+        oneshot(self, req)
+    }
+
+    /// Limit number of in-flight calls to this service.
+    fn concurrency_limit(self, max: usize) -> ConcurrencyLimitService<Self>
+    where
+        Self: Sized,
+    {
+        ConcurrencyLimitService::new(max, self)
+    }
+
+    /// Check requests with a predicate before calling this service.
+    ///
+    /// Requests rejected by the predicate resolve with predicate's error
+    /// without calling this service.
+    fn filter<F>(self, predicate: F) -> FilterService<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Request) -> Result<(), Self::Error>,
+    {
+        FilterService::new(predicate, self)
+    }
+
+    /// Map items of a streamed response to a different type.
+    fn map_stream<F, T>(self, f: F) -> MapStream<Self, F, T>
+    where
+        Self: Sized,
+        Self::Response: Stream,
+        F: FnMut(<Self::Response as Stream>::Item) -> T + Clone,
+    {
+        MapStream::new(self, f)
+    }
+
+    /// Call another service for each item of a streamed response.
+    ///
+    /// Response of resulting service is a stream of responses of `service`,
+    /// items are processed one at a time in order of the stream.
+    fn and_then_stream<F, U>(self, service: F) -> AndThenStream<Self, U>
+    where
+        Self: Sized,
+        Self::Response: Stream<Item = Result<U::Request, Self::Error>>,
+        F: IntoService<U>,
+        U: Service<Error = Self::Error>,
+    {
+        AndThenStream::new(self, service.into_service())
+    }
+
+    /// Run async cleanup once this service is shut down.
+    ///
+    /// Cleanup future is created on the first `poll_shutdown` call after
+    /// this service completed its own shutdown, shutdown of resulting service
+    /// completes once cleanup future resolves.
+    fn on_shutdown<F, Fut>(self, f: F) -> OnShutdown<Self, F, Fut>
+    where
+        Self: Sized,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        OnShutdown::new(self, f)
+    }
+
+    /// Shut this service down, returned future drives `poll_shutdown` until
+    /// it is complete.
+    ///
+    /// Services holding connections or other resources should be disposed
+    /// before they are dropped.
+    fn dispose(&mut self) -> Dispose<'_, Self>
+    where
+        Self: Sized,
+    {
+        Dispose::new(self, false)
+    }
+
+    /// Remember successful readiness check of this service until the next
+    /// call, repeated `poll_ready` calls do not poll this service.
+    fn cached_readiness(self) -> CachedReadiness<Self>
+    where
+        Self: Sized,
+    {
+        CachedReadiness::new(self)
+    }
+
+    /// Reject calls with `LoadShedError::Overloaded` instead of waiting
+    /// while this service is not ready.
+    fn load_shed(self) -> LoadShedService<Self>
+    where
+        Self: Sized,
+    {
+        LoadShedService::new(self)
+    }
+
+    /// Reject calls without touching this service after `threshold`
+    /// consecutive failures, until `reset_timeout` passes.
+    fn circuit_breaker(
+        self,
+        threshold: usize,
+        reset_timeout: Duration,
+    ) -> CircuitBreakerService<Self>
+    where
+        Self: Sized,
+    {
+        CircuitBreakerService::new(threshold, reset_timeout, self)
+    }
+
+    #[cfg(feature = "rt")]
+    /// Allow `num` calls per `per` duration, `poll_ready` returns `Pending`
+    /// while rate limit is exceeded.
+    fn rate_limit(self, num: u64, per: Duration) -> RateLimitService<Self>
+    where
+        Self: Sized,
+    {
+        RateLimitService::new(num, per, self)
+    }
+
+    #[cfg(feature = "rt")]
+    /// Retry failed calls according to the policy.
+    fn retry<P>(self, policy: P) -> RetryService<Self, P>
+    where
+        Self: Sized,
+        Self::Request: Clone,
+        P: Policy<Self::Error>,
+    {
+        RetryService::new(policy, self)
+    }
+
+    #[cfg(feature = "rt")]
+    /// Issue second call with the same request if first one does not
+    /// complete within `delay`, resolve with whichever completes first.
+    fn hedge(self, delay: Duration) -> HedgeService<Self>
+    where
+        Self: Sized,
+        Self::Request: Clone,
+    {
+        HedgeService::new(delay, self)
+    }
+
+    /// Convert this service to a service factory that produces clones of it
+    /// for any config.
+    ///
+    /// Method-style version of `clone_factory(service)`.
+    fn clone_factory<C, E>(self) -> CloneFactory<Self, C, E>
+    where
+        Self: Sized + Clone,
+    {
+        CloneFactory::new(self)
+    }
+
+    #[cfg(feature = "rt")]
+    /// Fail calls that do not complete within `timeout` with
+    /// `TimeoutError::Timeout`.
+    fn timeout(self, timeout: Duration) -> TimeoutService<Self>
+    where
+        Self: Sized,
+    {
+        TimeoutService::new(timeout, self)
+    }
+
+    #[cfg(feature = "rt")]
+    /// Fail calls with `TimeoutError::Timeout` once deadline stored in
+    /// request context passes.
+    fn deadline<R>(self) -> DeadlineService<Self>
+    where
+        Self: Sized + Service<Request = WithContext<R>>,
+    {
+        DeadlineService::new(self)
+    }
+}
+
+impl<S: Service> ServiceExt for S {}
+
+/// An extension trait for `ServiceFactory`s that provides a variety of
+/// convenient combinators.
+///
+/// `map`, `map_err` and `map_init_err` are defined on the `ServiceFactory`
+/// trait itself.
+pub trait ServiceFactoryExt: ServiceFactory {
+    /// Call another service after call to this one has resolved successfully.
+    fn and_then<F, U>(self, factory: F) -> AndThenServiceFactory<Self, U>
+    where
+        Self: Sized,
+        Self::Config: Clone,
+        F: IntoServiceFactory<U>,
+        U: ServiceFactory<
+            Config = Self::Config,
+            Request = Self::Response,
+            Error = Self::Error,
+            InitError = Self::InitError,
+        >,
+    {
+        AndThenServiceFactory::new(self, factory.into_factory())
+    }
+
+    /// Call another service after call to this one has resolved successfully,
+    /// factories could have different config types.
+    ///
+    /// Resulting factory accepts `(Self::Config, U::Config)` config tuple,
+    /// each factory gets its own part. Use `map_config` on the result to
+    /// derive both configs from a single value.
+    fn and_then_split<F, U>(self, factory: F) -> AndThenSplitServiceFactory<Self, U>
+    where
+        Self: Sized,
+        F: IntoServiceFactory<U>,
+        U: ServiceFactory<
+            Request = Self::Response,
+            Error = Self::Error,
+            InitError = Self::InitError,
+        >,
+    {
+        AndThenSplitServiceFactory::new(self, factory.into_factory())
+    }
+
+    /// Apply function to specified service and use it as a next service in
+    /// chain.
+    fn and_then_apply_fn<U, I, F, Fut, Res, Err>(
+        self,
+        factory: I,
+        f: F,
+    ) -> AndThenApplyFnFactory<Self, U, F, Fut, Res, Err>
+    where
+        Self: Sized,
+        Self::Config: Clone,
+        I: IntoServiceFactory<U>,
+        U: ServiceFactory<Config = Self::Config, InitError = Self::InitError>,
+        F: FnMut(Self::Response, &mut U::Service) -> Fut + Clone,
+        Fut: Future<Output = Result<Res, Err>>,
+        Err: From<Self::Error> + From<U::Error>,
+    {
+        AndThenApplyFnFactory::new(self, factory.into_factory(), f)
+    }
+
+    /// Chain on a computation for when a call to the service finished,
+    /// passing the result of the call to the next service `U`.
+    fn then<F, U>(self, factory: F) -> ThenServiceFactory<Self, U>
+    where
+        Self: Sized,
+        Self::Config: Clone,
+        F: IntoServiceFactory<U>,
+        U: ServiceFactory<
+            Config = Self::Config,
+            Request = Result<Self::Response, Self::Error>,
+            Error = Self::Error,
+            InitError = Self::InitError,
+        >,
+    {
+        ThenServiceFactory::new(self, factory.into_factory())
+    }
+
+    /// Call fallback service with original request if call to this service
+    /// fails.
+    fn or_else<F, U>(self, factory: F) -> OrElseServiceFactory<Self, U>
+    where
+        Self: Sized,
+        Self::Config: Clone,
+        Self::Request: Clone,
+        F: IntoServiceFactory<U>,
+        U: ServiceFactory<
+            Config = Self::Config,
+            Request = Self::Request,
+            Response = Self::Response,
+            Error = Self::Error,
+            InitError = Self::InitError,
+        >,
+    {
+        OrElseServiceFactory::new(self, factory.into_factory())
+    }
+
+    /// Call this and another service with the same request concurrently,
+    /// resolve with first successful response.
+    fn race<F, U>(self, factory: F) -> RaceServiceFactory<Self, U>
+    where
+        Self: Sized,
+        Self::Config: Clone,
+        Self::Request: Clone,
+        F: IntoServiceFactory<U>,
+        U: ServiceFactory<
+            Config = Self::Config,
+            Request = Self::Request,
+            Response = Self::Response,
+            Error = Self::Error,
+            InitError = Self::InitError,
+        >,
+    {
+        RaceServiceFactory::new(self, factory.into_factory())
+    }
+
+    /// Wrap services produced by this factory with a function that receives
+    /// request and a mutable reference to the service.
+    ///
+    /// Method-style version of `apply_fn_factory(factory, f)`.
+    fn apply_fn<F, R, In, Out>(
+        self,
+        f: F,
+    ) -> ApplyServiceFactory<Self, F, R, In, Out, Self::Error>
+    where
+        Self: Sized,
+        F: FnMut(In, &mut Self::Service) -> R + Clone,
+        R: Future<Output = Result<Out, Self::Error>>,
+    {
+        apply_fn_factory(self, f)
+    }
+
+    /// Map request of services produced by this factory to a different type.
+    fn map_request<F, Req>(self, f: F) -> MapRequestServiceFactory<Self, F, Req>
+    where
+        Self: Sized,
+        F: FnMut(Req) -> Self::Request + Clone,
+    {
+        MapRequestServiceFactory::new(self, f)
+    }
+
+    /// Map config argument to a config of this factory.
+    ///
+    /// Method-style version of `map_config(factory, f)`.
+    fn map_config<F, C>(self, f: F) -> MapConfig<Self, F, C>
+    where
+        Self: Sized,
+        F: Fn(C) -> Self::Config,
+    {
+        MapConfig::new(self, f)
+    }
+
+    /// Accept any config argument and create services with unit config.
+    ///
+    /// Method-style version of `unit_config(factory)`.
+    fn unit_config<C>(self) -> UnitConfig<Self, C>
+    where
+        Self: Sized + ServiceFactory<Config = ()>,
+    {
+        UnitConfig::new(self)
+    }
+}
+
+impl<T: ServiceFactory> ServiceFactoryExt for T {}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "rt")]
+    use std::time::Duration;
+
+    #[cfg(feature = "rt")]
+    use futures_util::future::err;
+    use futures_util::future::{ok, ready};
+
+    use super::*;
+    #[cfg(feature = "rt")]
+    use crate::retry::Backoff;
+    use crate::{fn_factory, fn_service, into_service};
+
+    #[actix_rt::test]
+    async fn test_service_ext() {
+        let mut srv = into_service(|req: usize| ok::<_, ()>(req + 1))
+            .and_then(fn_service(|req: usize| ok(req * 2)))
+            .map(|res| res.to_string())
+            .apply_fn(|req: String, srv| srv.call(req.parse().unwrap()));
+        assert_eq!(srv.call("1".to_string()).await, Ok("4".to_string()));
+    }
+
+    #[cfg(feature = "rt")]
+    #[actix_rt::test]
+    async fn test_service_ext_then() {
+        let mut srv = into_service(|req: usize| if req > 0 { ok(req) } else { err(()) })
+            .then(fn_service(|res: Result<usize, ()>| ok(res.unwrap_or(10))))
+            .retry(Backoff::fixed(Duration::from_millis(1)));
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(0).await, Ok(10));
+    }
+
+    #[actix_rt::test]
+    async fn test_factory_ext() {
+        let factory = fn_factory(|| ok::<_, ()>(fn_service(|req: usize| ok(req + 1))))
+            .and_then(fn_service(|req: usize| ok(req * 2)))
+            .then(fn_service(|res: Result<usize, ()>| ready(res)))
+            .apply_fn(|req: usize, srv| srv.call(req + 1))
+            .map_err(|_| "error");
+
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(6));
+    }
+
+    #[actix_rt::test]
+    async fn test_smart_pointers() {
+        let srv = into_service(|req: usize| ok::<_, ()>(req + 1));
+
+        let mut boxed = Box::new(srv.clone()).map(|res| res * 2);
+        assert_eq!(boxed.call(1).await, Ok(4));
+
+        let mut shared = std::rc::Rc::new(std::cell::RefCell::new(srv.clone()))
+            .and_then(into_service(|req: usize| ok(req * 3)));
+        assert_eq!(shared.call(1).await, Ok(6));
+
+        let mut inner = srv;
+        let mut by_ref = (&mut inner).map_err(|_| "error");
+        assert_eq!(by_ref.call(1).await, Ok(2));
+    }
+}
@@ -0,0 +1,195 @@
+//! Service that checks requests with a predicate before calling inner service.
+//!
+//! Predicate either accepts request or returns rejection error, rejected
+//! requests never reach inner service. Rejection error has the same type as
+//! inner service error, so filter does not change service signature.
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Checks requests with a predicate before passing them to inner service.
+pub struct Filter<F, E = ()> {
+    predicate: F,
+    _t: PhantomData<E>,
+}
+
+impl<F, E> Filter<F, E> {
+    /// Create filter with specified predicate.
+    pub fn new(predicate: F) -> Self {
+        Filter {
+            predicate,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, E> Clone for Filter<F, E> {
+    fn clone(&self) -> Self {
+        Filter::new(self.predicate.clone())
+    }
+}
+
+impl<S, F, E> Transform<S> for Filter<F, E>
+where
+    S: Service,
+    F: FnMut(&S::Request) -> Result<(), S::Error> + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = FilterService<S, F>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(FilterService::new(self.predicate.clone(), service))
+    }
+}
+
+/// Checks requests with a predicate before passing them to inner service.
+///
+/// If predicate returns error, call resolves with that error immediately.
+#[derive(Clone)]
+pub struct FilterService<S, F> {
+    service: S,
+    predicate: F,
+}
+
+impl<S, F> FilterService<S, F>
+where
+    S: Service,
+    F: FnMut(&S::Request) -> Result<(), S::Error>,
+{
+    pub fn new<U>(predicate: F, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        FilterService {
+            predicate,
+            service: service.into_service(),
+        }
+    }
+}
+
+impl<S, F> Service for FilterService<S, F>
+where
+    S: Service,
+    F: FnMut(&S::Request) -> Result<(), S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FilterResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        match (self.predicate)(&req) {
+            Ok(()) => FilterResponse {
+                fut: Some(self.service.call(req)),
+                err: None,
+            },
+            Err(e) => FilterResponse {
+                fut: None,
+                err: Some(e),
+            },
+        }
+    }
+}
+
+/// `FilterService` response future
+#[pin_project::pin_project]
+pub struct FilterResponse<S: Service> {
+    #[pin]
+    fut: Option<S::Future>,
+    err: Option<S::Error>,
+}
+
+impl<S> Future for FilterResponse<S>
+where
+    S: Service,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        match this.fut.as_pin_mut() {
+            Some(fut) => fut.poll(cx),
+            None => Poll::Ready(Err(this.err.take().expect("Polled after completion"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{ok, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = &'static str;
+        type Future = Ready<Result<usize, &'static str>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            ok(req)
+        }
+    }
+
+    fn max_size(req: &usize) -> Result<(), &'static str> {
+        if *req <= 10 {
+            Ok(())
+        } else {
+            Err("too large")
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_filter() {
+        let calls = Rc::new(Cell::new(0));
+        let mut srv = FilterService::new(max_size, Srv(calls.clone()));
+
+        assert_eq!(srv.call(5).await, Ok(5));
+        assert_eq!(srv.call(11).await, Err("too large"));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let factory = apply(
+            Filter::new(max_size),
+            fn_factory(move || ok::<_, ()>(Srv(calls2.clone()))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(10).await, Ok(10));
+        assert_eq!(srv.call(20).await, Err("too large"));
+        assert_eq!(calls.get(), 1);
+    }
+}
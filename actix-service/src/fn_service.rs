@@ -17,6 +17,41 @@ where
     FnServiceFactory::new(f)
 }
 
+/// Create `ServiceFactory` for function that can act as a `Service` and
+/// owns mutable state
+///
+/// Function receives mutable reference to the state with each request.
+/// Services created by the factory start with a clone of the initial state.
+///
+/// # Example
+///
+/// ```rust
+/// use actix_service::{fn_service_with_state, Service};
+/// use futures_util::future::ok;
+///
+/// #[actix_rt::main]
+/// async fn main() {
+///     let mut counter = fn_service_with_state(0, |n: usize, total: &mut usize| {
+///         *total += n;
+///         ok::<_, ()>(*total)
+///     });
+///
+///     assert_eq!(counter.call(1).await, Ok(1));
+///     assert_eq!(counter.call(2).await, Ok(3));
+/// }
+/// ```
+pub fn fn_service_with_state<F, St, Fut, Req, Res, Err, Cfg>(
+    state: St,
+    f: F,
+) -> FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: FnMut(Req, &mut St) -> Fut + Clone,
+    St: Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    FnStateServiceFactory::new(state, f)
+}
+
 /// Create `ServiceFactory` for function that can produce services
 ///
 /// # Example
@@ -239,6 +274,143 @@ where
     }
 }
 
+pub struct FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: FnMut(Req, &mut St) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    f: F,
+    state: St,
+    _t: PhantomData<Req>,
+}
+
+impl<F, St, Fut, Req, Res, Err> FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: FnMut(Req, &mut St) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    pub(crate) fn new(state: St, f: F) -> Self {
+        Self {
+            f,
+            state,
+            _t: PhantomData,
+        }
+    }
+
+    /// Get reference to the state
+    pub fn state(&self) -> &St {
+        &self.state
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err> Clone for FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: FnMut(Req, &mut St) -> Fut + Clone,
+    St: Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.state.clone(), self.f.clone())
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err> Service for FnStateService<F, St, Fut, Req, Res, Err>
+where
+    F: FnMut(Req, &mut St) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = Fut;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        (self.f)(req, &mut self.state)
+    }
+}
+
+pub struct FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: FnMut(Req, &mut St) -> Fut,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    f: F,
+    state: St,
+    _t: PhantomData<(Req, Cfg)>,
+}
+
+impl<F, St, Fut, Req, Res, Err, Cfg> FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: FnMut(Req, &mut St) -> Fut + Clone,
+    St: Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    fn new(state: St, f: F) -> Self {
+        FnStateServiceFactory {
+            f,
+            state,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err, Cfg> Clone
+    for FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: FnMut(Req, &mut St) -> Fut + Clone,
+    St: Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    fn clone(&self) -> Self {
+        Self::new(self.state.clone(), self.f.clone())
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err> Service for FnStateServiceFactory<F, St, Fut, Req, Res, Err, ()>
+where
+    F: FnMut(Req, &mut St) -> Fut + Clone,
+    St: Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+    type Future = Fut;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        (self.f)(req, &mut self.state)
+    }
+}
+
+impl<F, St, Fut, Req, Res, Err, Cfg> ServiceFactory
+    for FnStateServiceFactory<F, St, Fut, Req, Res, Err, Cfg>
+where
+    F: FnMut(Req, &mut St) -> Fut + Clone,
+    St: Clone,
+    Fut: Future<Output = Result<Res, Err>>,
+{
+    type Request = Req;
+    type Response = Res;
+    type Error = Err;
+
+    type Config = Cfg;
+    type Service = FnStateService<F, St, Fut, Req, Res, Err>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: Cfg) -> Self::Future {
+        ok(FnStateService::new(self.state.clone(), self.f.clone()))
+    }
+}
+
 /// Convert `Fn(&Config) -> Future<Service>` fn to NewService
 pub struct FnServiceConfig<F, Fut, Cfg, Srv, Err>
 where
@@ -400,4 +572,37 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), ("srv", 1));
     }
+
+    #[actix_rt::test]
+    async fn test_fn_factory_with_config_async() {
+        let new_srv = fn_factory_with_config(|cfg: usize| async move {
+            if cfg == 0 {
+                return Err("zero");
+            }
+            Ok(fn_service(move |n: usize| ok::<_, ()>(n * cfg)))
+        });
+
+        assert!(new_srv.new_service(0).await.is_err());
+
+        let mut srv = new_srv.clone().new_service(2).await.unwrap();
+        assert_eq!(srv.call(3).await, Ok(6));
+    }
+
+    #[actix_rt::test]
+    async fn test_fn_service_with_state() {
+        let new_srv = fn_service_with_state(Vec::new(), |req: usize, seen: &mut Vec<usize>| {
+            seen.push(req);
+            ok::<_, ()>(seen.len())
+        });
+
+        let mut srv = new_srv.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(2).await, Ok(2));
+        assert_eq!(srv.state(), &vec![1, 2]);
+
+        // each service owns its own copy of state
+        let mut srv = new_srv.new_service(()).await.unwrap();
+        assert_eq!(srv.call(3).await, Ok(1));
+    }
 }
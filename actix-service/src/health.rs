@@ -0,0 +1,251 @@
+//! Health-check gated readiness.
+//!
+//! `HealthCheck` periodically runs user supplied async probe. While probe
+//! fails, wrapped service is reported as not ready, so balancers and
+//! dispatchers skip it. Optionally, after a number of consecutive failures
+//! readiness check fails with `HealthCheckError::Unhealthy`.
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use actix_rt::time::delay_for;
+
+use crate::{IntoService, Service};
+
+/// Health-checked service error
+#[derive(Debug, PartialEq)]
+pub enum HealthCheckError<E> {
+    /// Service error
+    Service(E),
+    /// Health probe failed max number of times in a row
+    Unhealthy,
+}
+
+impl<E> From<E> for HealthCheckError<E> {
+    fn from(err: E) -> Self {
+        HealthCheckError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for HealthCheckError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthCheckError::Service(e) => e.fmt(f),
+            HealthCheckError::Unhealthy => write!(f, "Service is unhealthy"),
+        }
+    }
+}
+
+struct State {
+    failures: Cell<usize>,
+    max_failures: Cell<Option<usize>>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl State {
+    fn wake(&self) {
+        if let Some(waker) = self.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Service with readiness gated by health probe.
+///
+/// Probe is called right away and then every `interval`, it resolves to
+/// `true` if inner service is healthy. Probe task stops once service is
+/// dropped. Service must be created within an arbiter.
+pub struct HealthCheck<S> {
+    service: S,
+    state: Rc<State>,
+}
+
+impl<S: Service> HealthCheck<S> {
+    pub fn new<U, F, Fut>(service: U, probe: F, interval: Duration) -> Self
+    where
+        U: IntoService<S>,
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = bool> + 'static,
+    {
+        let state = Rc::new(State {
+            failures: Cell::new(0),
+            max_failures: Cell::new(None),
+            waker: RefCell::new(None),
+        });
+        actix_rt::spawn(run_probe(Rc::downgrade(&state), probe, interval));
+
+        HealthCheck {
+            service: service.into_service(),
+            state,
+        }
+    }
+
+    /// Fail readiness check with `HealthCheckError::Unhealthy` after `max`
+    /// consecutive probe failures.
+    ///
+    /// By default readiness check stays pending while probe fails.
+    pub fn max_failures(self, max: usize) -> Self {
+        self.state.max_failures.set(Some(max));
+        self
+    }
+
+    /// Returns `true` if last probe succeeded.
+    pub fn is_healthy(&self) -> bool {
+        self.state.failures.get() == 0
+    }
+
+    /// Number of consecutive probe failures.
+    pub fn failures(&self) -> usize {
+        self.state.failures.get()
+    }
+}
+
+async fn run_probe<F, Fut>(state: Weak<State>, probe: F, interval: Duration)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    loop {
+        let healthy = probe().await;
+
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+        if healthy {
+            if state.failures.get() != 0 {
+                state.failures.set(0);
+                state.wake();
+            }
+        } else {
+            state.failures.set(state.failures.get() + 1);
+            if state.max_failures.get() == Some(state.failures.get()) {
+                state.wake();
+            }
+        }
+        drop(state);
+
+        delay_for(interval).await;
+    }
+}
+
+impl<S: Service> Service for HealthCheck<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = HealthCheckError<S::Error>;
+    type Future = HealthCheckResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let failures = self.state.failures.get();
+        if failures == 0 {
+            return self
+                .service
+                .poll_ready(cx)
+                .map_err(HealthCheckError::Service);
+        }
+
+        match self.state.max_failures.get() {
+            Some(max) if failures >= max => Poll::Ready(Err(HealthCheckError::Unhealthy)),
+            _ => {
+                *self.state.waker.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        HealthCheckResponse {
+            fut: self.service.call(req),
+        }
+    }
+}
+
+/// `HealthCheck` response future
+#[pin_project::pin_project]
+pub struct HealthCheckResponse<S: Service> {
+    #[pin]
+    fut: S::Future,
+}
+
+impl<S: Service> Future for HealthCheckResponse<S> {
+    type Output = Result<S::Response, HealthCheckError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project()
+            .fut
+            .poll(cx)
+            .map_err(HealthCheckError::Service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::Poll;
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, ready};
+
+    use super::*;
+    use crate::into_service;
+
+    #[actix_rt::test]
+    async fn test_health_check() {
+        let healthy = Rc::new(Cell::new(true));
+        let healthy2 = healthy.clone();
+        let mut srv = HealthCheck::new(
+            into_service(|req: usize| ok::<_, ()>(req)),
+            move || ready(healthy2.get()),
+            Duration::from_millis(10),
+        );
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(1));
+
+        healthy.set(false);
+        delay_for(Duration::from_millis(25)).await;
+        assert!(!srv.is_healthy());
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        healthy.set(true);
+        delay_for(Duration::from_millis(25)).await;
+        assert!(srv.is_healthy());
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_max_failures() {
+        let healthy = Rc::new(Cell::new(false));
+        let healthy2 = healthy.clone();
+        let mut srv = HealthCheck::new(
+            into_service(|req: usize| ok::<_, ()>(req)),
+            move || ready(healthy2.get()),
+            Duration::from_millis(10),
+        )
+        .max_failures(3);
+
+        delay_for(Duration::from_millis(5)).await;
+        assert_eq!(srv.failures(), 1);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        delay_for(Duration::from_millis(30)).await;
+        assert_eq!(
+            lazy(|cx| srv.poll_ready(cx)).await,
+            Poll::Ready(Err(HealthCheckError::Unhealthy))
+        );
+
+        // service recovers once probe succeeds
+        healthy.set(true);
+        delay_for(Duration::from_millis(20)).await;
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+}
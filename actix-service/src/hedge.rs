@@ -0,0 +1,393 @@
+//! Service that issues additional call if first one is too slow.
+//!
+//! If the first call does not complete within the delay, request is sent
+//! to inner service once more and whichever call completes first wins, the
+//! other one gets dropped. Delay is either fixed, or a latency percentile
+//! of recent successful calls.
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_rt::time::{delay_for, Delay, Instant};
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Number of recent latencies used for percentile calculation.
+const HISTORY_SIZE: usize = 128;
+
+/// Min number of recorded latencies required before percentile is used.
+const MIN_HISTORY: usize = 10;
+
+#[derive(Clone, Copy, Debug)]
+enum Threshold {
+    Fixed(Duration),
+    Percentile(f64, Duration),
+}
+
+/// Hedges slow calls to inner service.
+pub struct Hedge<E = ()> {
+    threshold: Threshold,
+    _t: PhantomData<E>,
+}
+
+impl<E> Hedge<E> {
+    /// Issue second call if first one does not complete within `delay`.
+    pub fn new(delay: Duration) -> Self {
+        Hedge {
+            threshold: Threshold::Fixed(delay),
+            _t: PhantomData,
+        }
+    }
+
+    /// Issue second call if first one is slower than `percentile` of recent
+    /// calls, i.e. `0.9` for p90 latency.
+    ///
+    /// `initial` delay is used until there are enough recorded calls.
+    pub fn percentile(percentile: f64, initial: Duration) -> Self {
+        Hedge {
+            threshold: Threshold::Percentile(percentile, initial),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for Hedge<E> {
+    fn clone(&self) -> Self {
+        Hedge {
+            threshold: self.threshold,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, E> Transform<S> for Hedge<E>
+where
+    S: Service,
+    S::Request: Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = HedgeService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(HedgeService::with_threshold(self.threshold, service))
+    }
+}
+
+struct Inner<S> {
+    service: RefCell<S>,
+    threshold: Threshold,
+    history: RefCell<History>,
+}
+
+/// Recent latencies, in order of arrival and sorted
+#[derive(Default)]
+struct History {
+    recent: VecDeque<Duration>,
+    sorted: Vec<Duration>,
+}
+
+impl History {
+    fn push(&mut self, latency: Duration) {
+        if self.recent.len() >= HISTORY_SIZE {
+            if let Some(oldest) = self.recent.pop_front() {
+                if let Ok(idx) = self.sorted.binary_search(&oldest) {
+                    self.sorted.remove(idx);
+                }
+            }
+        }
+        self.recent.push_back(latency);
+        let idx = match self.sorted.binary_search(&latency) {
+            Ok(idx) | Err(idx) => idx,
+        };
+        self.sorted.insert(idx, latency);
+    }
+}
+
+impl<S> Inner<S> {
+    fn delay(&self) -> Duration {
+        match self.threshold {
+            Threshold::Fixed(delay) => delay,
+            Threshold::Percentile(percentile, initial) => {
+                let sorted = &self.history.borrow().sorted;
+                if sorted.len() < MIN_HISTORY {
+                    return initial;
+                }
+                let idx = ((sorted.len() - 1) as f64 * percentile).round() as usize;
+                sorted[std::cmp::min(idx, sorted.len() - 1)]
+            }
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        if let Threshold::Percentile(..) = self.threshold {
+            self.history.borrow_mut().push(latency);
+        }
+    }
+}
+
+/// Hedges slow calls to inner service.
+///
+/// Hedged call is issued only if inner service is ready once the delay
+/// passes, otherwise the call is not hedged.
+pub struct HedgeService<S> {
+    inner: Rc<Inner<S>>,
+}
+
+impl<S> HedgeService<S>
+where
+    S: Service,
+    S::Request: Clone,
+{
+    /// Issue second call if first one does not complete within `delay`.
+    pub fn new<U>(delay: Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Self::with_threshold(Threshold::Fixed(delay), service)
+    }
+
+    /// Issue second call if first one is slower than `percentile` of recent
+    /// calls.
+    pub fn percentile<U>(percentile: f64, initial: Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Self::with_threshold(Threshold::Percentile(percentile, initial), service)
+    }
+
+    fn with_threshold<U>(threshold: Threshold, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        HedgeService {
+            inner: Rc::new(Inner {
+                threshold,
+                service: RefCell::new(service.into_service()),
+                history: RefCell::new(History::default()),
+            }),
+        }
+    }
+}
+
+impl<S> Service for HedgeService<S>
+where
+    S: Service,
+    S::Request: Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = HedgeResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.service.borrow_mut().poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let fut = self.inner.service.borrow_mut().call(req.clone());
+        HedgeResponse {
+            first: Some(fut),
+            second: None,
+            delay: Some(delay_for(self.inner.delay())),
+            req: Some(req),
+            start: Instant::now(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// `HedgeService` response future
+#[pin_project::pin_project]
+pub struct HedgeResponse<S: Service> {
+    #[pin]
+    first: Option<S::Future>,
+    #[pin]
+    second: Option<S::Future>,
+    delay: Option<Delay>,
+    req: Option<S::Request>,
+    start: Instant,
+    inner: Rc<Inner<S>>,
+}
+
+impl<S: Service> Future for HedgeResponse<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.first.as_mut().as_pin_mut() {
+            if let Poll::Ready(res) = fut.poll(cx) {
+                if res.is_ok() {
+                    this.inner.record(this.start.elapsed());
+                }
+                this.second.set(None);
+                return Poll::Ready(res);
+            }
+        }
+
+        if let Some(delay) = this.delay {
+            if Pin::new(delay).poll(cx).is_ready() {
+                *this.delay = None;
+                let req = this.req.take().unwrap();
+                let mut service = this.inner.service.borrow_mut();
+                if let Poll::Ready(Ok(())) = service.poll_ready(cx) {
+                    this.second.set(Some(service.call(req)));
+                }
+            }
+        }
+
+        if let Some(fut) = this.second.as_mut().as_pin_mut() {
+            if let Poll::Ready(res) = fut.poll(cx) {
+                this.first.set(None);
+                return Poll::Ready(res);
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{ok, FutureExt, LocalBoxFuture};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    /// First call is slow, following calls are fast
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            let n = self.0.get() + 1;
+            self.0.set(n);
+            let delay = if n == 1 { 200 } else { 10 };
+            delay_for(Duration::from_millis(delay))
+                .map(move |_| Ok(n))
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_hedge() {
+        let calls = Rc::new(Cell::new(0));
+        let mut srv = HedgeService::new(Duration::from_millis(20), Srv(calls.clone()));
+
+        // second call wins
+        assert_eq!(srv.call(()).await, Ok(2));
+        assert_eq!(calls.get(), 2);
+
+        // fast call is not hedged
+        assert_eq!(srv.call(()).await, Ok(3));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn test_percentile() {
+        let srv = HedgeService::percentile(0.5, Duration::from_millis(5), Srv(Rc::default()));
+        assert_eq!(srv.inner.delay(), Duration::from_millis(5));
+
+        for i in 1..=MIN_HISTORY as u64 {
+            srv.inner.record(Duration::from_millis(i * 10));
+        }
+        assert_eq!(srv.inner.delay(), Duration::from_millis(60));
+    }
+
+    /// Slow service that is ready for a single call in flight
+    struct Busy(Rc<Cell<usize>>, Rc<Cell<bool>>);
+
+    impl Service for Busy {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.1.get() {
+                Poll::Pending
+            } else {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            self.1.set(true);
+            let busy = self.1.clone();
+            delay_for(Duration::from_millis(50))
+                .map(move |_| {
+                    busy.set(false);
+                    Ok(1)
+                })
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_not_ready() {
+        let calls = Rc::new(Cell::new(0));
+        let busy = Busy(calls.clone(), Rc::default());
+        let mut srv = HedgeService::new(Duration::from_millis(10), busy);
+
+        // hedge is skipped, inner service is not ready
+        assert_eq!(srv.call(()).await, Ok(1));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_percentile_window() {
+        let srv = HedgeService::percentile(0.5, Duration::from_millis(5), Srv(Rc::default()));
+        for i in 0..HISTORY_SIZE as u64 {
+            srv.inner.record(Duration::from_millis(1000 - i));
+        }
+        // oldest, slowest latencies are evicted
+        for _ in 0..HISTORY_SIZE {
+            srv.inner.record(Duration::from_millis(10));
+        }
+        assert_eq!(srv.inner.delay(), Duration::from_millis(10));
+
+        let history = srv.inner.history.borrow();
+        assert_eq!(history.sorted.len(), HISTORY_SIZE);
+        assert_eq!(history.recent.len(), HISTORY_SIZE);
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let calls = Rc::new(Cell::new(0));
+        let calls2 = calls.clone();
+        let factory = apply(
+            Hedge::new(Duration::from_millis(20)),
+            fn_factory(move || ok::<_, ()>(Srv(calls2.clone()))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(()).await, Ok(2));
+    }
+}
@@ -0,0 +1,225 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::Service;
+
+/// Service for the `inspect` combinator, observing requests before they are
+/// passed to the inner service.
+///
+/// This is created by the `ServiceExt::inspect` method.
+#[derive(Clone)]
+pub struct Inspect<A, F> {
+    service: A,
+    f: F,
+}
+
+impl<A, F> Inspect<A, F> {
+    /// Create new `Inspect` combinator
+    pub(crate) fn new(service: A, f: F) -> Self
+    where
+        A: Service,
+        F: Fn(&A::Request),
+    {
+        Self { service, f }
+    }
+}
+
+impl<A, F> Service for Inspect<A, F>
+where
+    A: Service,
+    F: Fn(&A::Request),
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = A::Future;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        (self.f)(&req);
+        self.service.call(req)
+    }
+}
+
+/// Service for the `inspect_response` combinator, observing successful
+/// responses of the inner service.
+///
+/// This is created by the `ServiceExt::inspect_response` method.
+#[derive(Clone)]
+pub struct InspectResponse<A, F> {
+    service: A,
+    f: F,
+}
+
+impl<A, F> InspectResponse<A, F> {
+    /// Create new `InspectResponse` combinator
+    pub(crate) fn new(service: A, f: F) -> Self
+    where
+        A: Service,
+        F: Fn(&A::Response) + Clone,
+    {
+        Self { service, f }
+    }
+}
+
+impl<A, F> Service for InspectResponse<A, F>
+where
+    A: Service,
+    F: Fn(&A::Response) + Clone,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = InspectResponseFuture<A, F>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        InspectResponseFuture {
+            fut: self.service.call(req),
+            f: self.f.clone(),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct InspectResponseFuture<A: Service, F> {
+    #[pin]
+    fut: A::Future,
+    f: F,
+}
+
+impl<A, F> Future for InspectResponseFuture<A, F>
+where
+    A: Service,
+    F: Fn(&A::Response),
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = futures_util::ready!(this.fut.poll(cx));
+        if let Ok(ref res) = res {
+            (this.f)(res);
+        }
+        Poll::Ready(res)
+    }
+}
+
+/// Service for the `inspect_err` combinator, observing errors of the inner
+/// service.
+///
+/// This is created by the `ServiceExt::inspect_err` method.
+#[derive(Clone)]
+pub struct InspectErr<A, F> {
+    service: A,
+    f: F,
+}
+
+impl<A, F> InspectErr<A, F> {
+    /// Create new `InspectErr` combinator
+    pub(crate) fn new(service: A, f: F) -> Self
+    where
+        A: Service,
+        F: Fn(&A::Error) + Clone,
+    {
+        Self { service, f }
+    }
+}
+
+impl<A, F> Service for InspectErr<A, F>
+where
+    A: Service,
+    F: Fn(&A::Error) + Clone,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = InspectErrFuture<A, F>;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx).map_err(|e| {
+            (self.f)(&e);
+            e
+        })
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        InspectErrFuture {
+            fut: self.service.call(req),
+            f: self.f.clone(),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct InspectErrFuture<A: Service, F> {
+    #[pin]
+    fut: A::Future,
+    f: F,
+}
+
+impl<A, F> Future for InspectErrFuture<A, F>
+where
+    A: Service,
+    F: Fn(&A::Error),
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = futures_util::ready!(this.fut.poll(cx));
+        if let Err(ref err) = res {
+            (this.f)(err);
+        }
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::task::Poll;
+
+    use futures_util::future::{lazy, ready};
+
+    use crate::{into_service, Service, ServiceExt};
+
+    #[actix_rt::test]
+    async fn test_inspect() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (log1, log2, log3) = (log.clone(), log.clone(), log.clone());
+
+        let mut srv =
+            into_service(|req: usize| ready(if req > 0 { Ok(req * 2) } else { Err(req) }))
+                .inspect(move |req| log1.borrow_mut().push(format!("req {}", req)))
+                .inspect_response(move |res| log2.borrow_mut().push(format!("res {}", res)))
+                .inspect_err(move |err| log3.borrow_mut().push(format!("err {}", err)));
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(srv.call(0).await, Err(0));
+        assert_eq!(*log.borrow(), vec!["req 1", "res 2", "req 0", "err 0"]);
+    }
+}
@@ -0,0 +1,175 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoServiceFactory, Service, ServiceFactory};
+
+/// Create service factory that defers construction of inner service until
+/// the service is polled for readiness first time.
+///
+/// Inner service is cached once created. If construction fails, error is
+/// returned from `poll_ready` and construction starts again on next call.
+pub fn lazy<T, U>(factory: U) -> Lazy<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+    U: IntoServiceFactory<T>,
+{
+    Lazy {
+        factory: Rc::new(factory.into_factory()),
+    }
+}
+
+/// `lazy()` service factory
+pub struct Lazy<T> {
+    factory: Rc<T>,
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Lazy {
+            factory: self.factory.clone(),
+        }
+    }
+}
+
+impl<T> ServiceFactory for Lazy<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+
+    type Config = T::Config;
+    type Service = LazyService<T>;
+    type InitError = T::InitError;
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, cfg: T::Config) -> Self::Future {
+        ok(LazyService {
+            factory: self.factory.clone(),
+            cfg,
+            service: None,
+            fut: None,
+        })
+    }
+}
+
+/// Service that creates inner service on first readiness check.
+pub struct LazyService<T: ServiceFactory> {
+    factory: Rc<T>,
+    cfg: T::Config,
+    service: Option<T::Service>,
+    fut: Option<Pin<Box<T::Future>>>,
+}
+
+impl<T: ServiceFactory> LazyService<T> {
+    /// Returns `true` if inner service has been created.
+    pub fn is_initialized(&self) -> bool {
+        self.service.is_some()
+    }
+}
+
+impl<T> Service for LazyService<T>
+where
+    T: ServiceFactory,
+    T::Config: Clone,
+    T::Error: From<T::InitError>,
+{
+    type Request = T::Request;
+    type Response = T::Response;
+    type Error = T::Error;
+    type Future = <T::Service as Service>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.service.is_none() {
+            let factory = &self.factory;
+            let cfg = &self.cfg;
+            let fut = self
+                .fut
+                .get_or_insert_with(|| Box::pin(factory.new_service(cfg.clone())));
+
+            let res = futures_util::ready!(fut.as_mut().poll(cx));
+            self.fut = None;
+            self.service = Some(res?);
+        }
+        self.service.as_mut().unwrap().poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        match self.service {
+            Some(ref mut srv) => srv.poll_shutdown(cx, is_error),
+            None => Poll::Ready(()),
+        }
+    }
+
+    fn call(&mut self, req: T::Request) -> Self::Future {
+        self.service
+            .as_mut()
+            .expect("LazyService called before it is ready")
+            .call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::Poll;
+
+    use futures_util::future::{lazy as lazy_fut, ok, ready};
+
+    use super::*;
+    use crate::{fn_factory, into_service};
+
+    #[actix_rt::test]
+    async fn test_lazy() {
+        let created = Rc::new(Cell::new(0));
+        let created2 = created.clone();
+        let factory = lazy(fn_factory(move || {
+            created2.set(created2.get() + 1);
+            ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req + 1)))
+        }));
+
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert!(!srv.is_initialized());
+        assert_eq!(created.get(), 0);
+
+        assert_eq!(lazy_fut(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert!(srv.is_initialized());
+        assert_eq!(srv.call(1).await, Ok(2));
+
+        assert_eq!(lazy_fut(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(created.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_init_error() {
+        let attempts = Rc::new(Cell::new(0));
+        let attempts2 = attempts.clone();
+        let factory = lazy(fn_factory(move || {
+            attempts2.set(attempts2.get() + 1);
+            let srv = into_service(|req: usize| ok::<_, ()>(req));
+            ready(if attempts2.get() == 1 {
+                Err(())
+            } else {
+                Ok(srv)
+            })
+        }));
+        let mut srv = factory.new_service(()).await.unwrap();
+
+        assert_eq!(
+            lazy_fut(|cx| srv.poll_ready(cx)).await,
+            Poll::Ready(Err(()))
+        );
+        assert_eq!(lazy_fut(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(attempts.get(), 2);
+    }
+}
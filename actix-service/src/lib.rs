@@ -11,22 +11,78 @@ mod and_then;
 mod and_then_apply_fn;
 mod apply;
 mod apply_cfg;
+pub mod balance;
+#[cfg(feature = "rt")]
+pub mod batch;
 pub mod boxed;
+pub mod cache;
+mod cached_readiness;
+mod call_all;
+pub mod circuit_breaker;
+mod clone_factory;
+pub mod coalesce;
+pub mod context;
+pub mod either;
+mod ext;
+pub mod filter;
 mod fn_service;
+#[cfg(feature = "rt")]
+pub mod health;
+#[cfg(feature = "rt")]
+pub mod hedge;
+mod inspect;
+mod lazy;
+pub mod limit;
+pub mod load_shed;
 mod map;
 mod map_config;
 mod map_err;
 mod map_init_err;
+mod map_request;
+pub mod metrics;
+mod on_shutdown;
+mod oneshot;
+mod or_else;
 mod pipeline;
+pub mod priority;
+mod race;
+#[cfg(feature = "rt")]
+pub mod rate_limit;
+pub mod reserve;
+#[cfg(feature = "rt")]
+pub mod retry;
+pub mod shared;
+#[cfg(feature = "rt")]
+pub mod spawn;
+mod stack;
+pub mod steer;
+mod stream;
 mod then;
+#[cfg(feature = "rt")]
+pub mod timeout;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "tracing")]
+pub mod trace;
 mod transform;
 mod transform_err;
 
 pub use self::apply::{apply_fn, apply_fn_factory};
-pub use self::apply_cfg::{apply_cfg, apply_cfg_factory};
-pub use self::fn_service::{fn_factory, fn_factory_with_config, fn_service};
-pub use self::map_config::{map_config, unit_config};
+pub use self::apply_cfg::{
+    apply_cfg, apply_cfg_factory, apply_cfg_factory_once, apply_cfg_once,
+};
+pub use self::call_all::call_all;
+pub use self::clone_factory::clone_factory;
+pub use self::ext::{ServiceExt, ServiceFactoryExt};
+pub use self::fn_service::{
+    fn_factory, fn_factory_with_config, fn_service, fn_service_with_state,
+};
+pub use self::lazy::lazy;
+pub use self::map_config::{map_config, map_config_service, unit_config};
+pub use self::oneshot::oneshot;
 pub use self::pipeline::{pipeline, pipeline_factory, Pipeline, PipelineFactory};
+#[cfg(feature = "rt")]
+pub use self::spawn::spawn_service;
 pub use self::transform::{apply, Transform};
 
 /// An asynchronous function from `Request` to a `Response`.
@@ -96,6 +152,20 @@ pub trait Service {
     /// 2. In case of chained services, `.poll_ready()` get called for all services at once.
     fn poll_ready(&mut self, ctx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>>;
 
+    /// Shutdown service.
+    ///
+    /// Returns `Ready` when the service is properly shut down and could be
+    /// dropped. Dispatchers call this method before dropping the service, so
+    /// service could flush buffers or finish in-flight work. `is_error` is
+    /// `true` if dispatcher is stopping because of an error.
+    ///
+    /// This method might be called again after it returned `Ready`. Default
+    /// implementation returns `Ready` immediately.
+    fn poll_shutdown(&mut self, ctx: &mut task::Context<'_>, is_error: bool) -> Poll<()> {
+        let _ = (ctx, is_error);
+        Poll::Ready(())
+    }
+
     /// Process the request and return the response asynchronously.
     ///
     /// This function is expected to be callable off task. As such,
@@ -210,7 +280,7 @@ pub trait ServiceFactory {
 
 impl<'a, S> Service for &'a mut S
 where
-    S: Service + 'a,
+    S: Service + ?Sized + 'a,
 {
     type Request = S::Request;
     type Response = S::Response;
@@ -221,6 +291,10 @@ where
         (**self).poll_ready(ctx)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, request: Self::Request) -> S::Future {
         (**self).call(request)
     }
@@ -239,6 +313,10 @@ where
         (**self).poll_ready(ctx)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, request: Self::Request) -> S::Future {
         (**self).call(request)
     }
@@ -257,14 +335,18 @@ where
         self.borrow_mut().poll_ready(ctx)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.borrow_mut().poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, request: Self::Request) -> S::Future {
         self.borrow_mut().call(request)
     }
 }
 
-impl<S> Service for Rc<RefCell<S>>
+impl<S> Service for Rc<S>
 where
-    S: Service,
+    S: shared::SharedService + ?Sized,
 {
     type Request = S::Request;
     type Response = S::Response;
@@ -272,11 +354,15 @@ where
     type Future = S::Future;
 
     fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.borrow_mut().poll_ready(ctx)
+        (**self).poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
     }
 
     fn call(&mut self, request: Self::Request) -> S::Future {
-        (&mut (**self).borrow_mut()).call(request)
+        (**self).call(request)
     }
 }
 
@@ -360,17 +446,39 @@ where
 }
 
 pub mod dev {
-    pub use crate::and_then::{AndThenService, AndThenServiceFactory};
+    pub use crate::and_then::{
+        AndThenService, AndThenServiceFactory, AndThenSplitServiceFactory,
+    };
     pub use crate::and_then_apply_fn::{AndThenApplyFn, AndThenApplyFnFactory};
     pub use crate::apply::{Apply, ApplyServiceFactory};
-    pub use crate::apply_cfg::{ApplyConfigService, ApplyConfigServiceFactory};
+    pub use crate::apply_cfg::{
+        ApplyConfigOnceService, ApplyConfigOnceServiceFactory, ApplyConfigService,
+        ApplyConfigServiceFactory,
+    };
+    pub use crate::cached_readiness::CachedReadiness;
+    pub use crate::call_all::CallAll;
+    pub use crate::clone_factory::CloneFactory;
     pub use crate::fn_service::{
-        FnService, FnServiceConfig, FnServiceFactory, FnServiceNoConfig,
+        FnService, FnServiceConfig, FnServiceFactory, FnServiceNoConfig, FnStateService,
+        FnStateServiceFactory,
     };
+    pub use crate::inspect::{
+        Inspect, InspectErr, InspectErrFuture, InspectResponse, InspectResponseFuture,
+    };
+    pub use crate::lazy::{Lazy, LazyService};
     pub use crate::map::{Map, MapServiceFactory};
-    pub use crate::map_config::{MapConfig, UnitConfig};
+    pub use crate::map_config::{MapConfig, MapConfigService, UnitConfig};
     pub use crate::map_err::{MapErr, MapErrServiceFactory};
     pub use crate::map_init_err::MapInitErr;
+    pub use crate::map_request::{MapRequest, MapRequestServiceFactory};
+    pub use crate::on_shutdown::{Dispose, OnShutdown};
+    pub use crate::oneshot::Oneshot;
+    pub use crate::or_else::{OrElseService, OrElseServiceFactory};
+    pub use crate::race::{RaceService, RaceServiceFactory};
+    pub use crate::stack::{Stack, StackFuture};
+    pub use crate::stream::{
+        AndThenStream, AndThenStreamFuture, AndThenStreamResponse, MapStream, MapStreamFuture,
+    };
     pub use crate::then::{ThenService, ThenServiceFactory};
     pub use crate::transform::ApplyTransform;
     pub use crate::transform_err::TransformMapInitErr;
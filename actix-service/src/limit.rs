@@ -0,0 +1,336 @@
+//! Service that limits number of in-flight calls.
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Counting semaphore for single threaded services.
+///
+/// Semaphore could be cloned, permits are shared across all clones.
+#[derive(Clone)]
+pub struct Semaphore(Rc<SemaphoreInner>);
+
+struct SemaphoreInner {
+    permits: Cell<isize>,
+    waiters: RefCell<Vec<Waker>>,
+}
+
+impl Semaphore {
+    /// Create semaphore with specified number of permits.
+    pub fn new(permits: usize) -> Self {
+        Semaphore(Rc::new(SemaphoreInner {
+            permits: Cell::new(permits as isize),
+            waiters: RefCell::new(Vec::new()),
+        }))
+    }
+
+    /// Number of currently available permits.
+    pub fn available(&self) -> usize {
+        std::cmp::max(self.0.permits.get(), 0) as usize
+    }
+
+    /// Acquire permit. If no permits are available, current task
+    /// get notified when permit is released.
+    pub fn poll_acquire(&self, cx: &mut Context<'_>) -> Poll<Permit> {
+        match self.try_acquire() {
+            Some(permit) => Poll::Ready(permit),
+            None => {
+                let mut waiters = self.0.waiters.borrow_mut();
+                if !waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                    waiters.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Acquire permit if one is available.
+    pub fn try_acquire(&self) -> Option<Permit> {
+        let permits = self.0.permits.get();
+        if permits > 0 {
+            self.0.permits.set(permits - 1);
+            Some(Permit(self.0.clone()))
+        } else {
+            None
+        }
+    }
+
+    /// Acquire permit ignoring the limit.
+    ///
+    /// Permit is accounted on release, so semaphore never hands out more
+    /// than configured number of permits afterwards.
+    fn force_acquire(&self) -> Permit {
+        self.0.permits.set(self.0.permits.get() - 1);
+        Permit(self.0.clone())
+    }
+}
+
+/// Semaphore permit, released on drop.
+pub struct Permit(Rc<SemaphoreInner>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.permits.set(self.0.permits.get() + 1);
+        for waker in self.0.waiters.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Limits number of in-flight calls to inner service.
+///
+/// Services created by `ConcurrencyLimit::new()` transform have their
+/// own limits, `ConcurrencyLimit::shared()` shares one limit between all
+/// services created by the transform.
+pub struct ConcurrencyLimit<E = ()> {
+    max: usize,
+    shared: Option<Semaphore>,
+    _t: PhantomData<E>,
+}
+
+impl<E> ConcurrencyLimit<E> {
+    /// Limit in-flight calls for each service separately.
+    pub fn new(max: usize) -> Self {
+        ConcurrencyLimit {
+            max,
+            shared: None,
+            _t: PhantomData,
+        }
+    }
+
+    /// Limit in-flight calls for all services created by this transform.
+    pub fn shared(max: usize) -> Self {
+        ConcurrencyLimit {
+            max,
+            shared: Some(Semaphore::new(max)),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<E> Clone for ConcurrencyLimit<E> {
+    fn clone(&self) -> Self {
+        ConcurrencyLimit {
+            max: self.max,
+            shared: self.shared.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, E> Transform<S> for ConcurrencyLimit<E>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = ConcurrencyLimitService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let semaphore = match self.shared {
+            Some(ref semaphore) => semaphore.clone(),
+            None => Semaphore::new(self.max),
+        };
+        ok(ConcurrencyLimitService::with_semaphore(semaphore, service))
+    }
+}
+
+/// Limits number of in-flight calls to inner service.
+///
+/// Permit is reserved by successful `poll_ready` call and is held
+/// until response future completes or get dropped.
+pub struct ConcurrencyLimitService<S> {
+    service: S,
+    semaphore: Semaphore,
+    permit: Option<Permit>,
+}
+
+impl<S> ConcurrencyLimitService<S>
+where
+    S: Service,
+{
+    pub fn new<U>(max: usize, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Self::with_semaphore(Semaphore::new(max), service)
+    }
+
+    /// Create service that uses provided semaphore for limiting calls.
+    pub fn with_semaphore<U>(semaphore: Semaphore, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        ConcurrencyLimitService {
+            semaphore,
+            permit: None,
+            service: service.into_service(),
+        }
+    }
+}
+
+impl<S> Clone for ConcurrencyLimitService<S>
+where
+    S: Clone,
+{
+    fn clone(&self) -> Self {
+        ConcurrencyLimitService {
+            service: self.service.clone(),
+            semaphore: self.semaphore.clone(),
+            permit: None,
+        }
+    }
+}
+
+impl<S> Service for ConcurrencyLimitService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ConcurrencyLimitResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permit.is_none() {
+            match self.semaphore.poll_acquire(cx) {
+                Poll::Ready(permit) => self.permit = Some(permit),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let permit = match self.permit.take() {
+            Some(permit) => permit,
+            None => self.semaphore.force_acquire(),
+        };
+        ConcurrencyLimitResponse {
+            fut: self.service.call(req),
+            _permit: permit,
+        }
+    }
+}
+
+/// `ConcurrencyLimitService` response future
+#[pin_project::pin_project]
+pub struct ConcurrencyLimitResponse<S: Service> {
+    #[pin]
+    fut: S::Future,
+    _permit: Permit,
+}
+
+impl<S: Service> Future for ConcurrencyLimitResponse<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().fut.poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, FutureExt, LocalBoxFuture};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    struct SleepService(Duration);
+
+    impl Service for SleepService {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            actix_rt::time::delay_for(self.0)
+                .then(|_| ok::<_, ()>(()))
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_service() {
+        let mut srv = ConcurrencyLimitService::new(1, SleepService(Duration::from_millis(50)));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+
+        let res = srv.call(());
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        let _ = res.await;
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_reserved_permit() {
+        let semaphore = Semaphore::new(1);
+        let sleep = Duration::from_millis(50);
+        let mut srv1 =
+            ConcurrencyLimitService::with_semaphore(semaphore.clone(), SleepService(sleep));
+        let mut srv2 =
+            ConcurrencyLimitService::with_semaphore(semaphore.clone(), SleepService(sleep));
+
+        assert_eq!(lazy(|cx| srv1.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Pending);
+        assert_eq!(semaphore.available(), 0);
+
+        srv1.call(()).await.unwrap();
+        assert_eq!(semaphore.available(), 1);
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_shared_transform() {
+        let wait_time = Duration::from_millis(50);
+        let factory = apply(
+            ConcurrencyLimit::shared(1),
+            fn_factory(move || ok::<_, ()>(SleepService(wait_time))),
+        );
+
+        let mut srv1 = factory.new_service(()).await.unwrap();
+        let mut srv2 = factory.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv1.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        let res = srv1.call(());
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Pending);
+
+        let _ = res.await;
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let wait_time = Duration::from_millis(50);
+        let factory = apply(
+            ConcurrencyLimit::new(1),
+            fn_factory(move || ok::<_, ()>(SleepService(wait_time))),
+        );
+
+        let mut srv1 = factory.new_service(()).await.unwrap();
+        let mut srv2 = factory.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv1.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        let _res = srv1.call(());
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+}
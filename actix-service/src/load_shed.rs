@@ -0,0 +1,212 @@
+//! Service that rejects requests when inner service is not ready.
+//!
+//! Instead of waiting for inner service readiness, `LoadShedService` is always
+//! ready and fails calls with `LoadShedError::Overloaded` while inner service
+//! is at capacity.
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Load shedding error
+#[derive(Debug, PartialEq)]
+pub enum LoadShedError<E> {
+    /// Service error
+    Service(E),
+    /// Service is not ready, request has been rejected
+    Overloaded,
+}
+
+impl<E> From<E> for LoadShedError<E> {
+    fn from(err: E) -> Self {
+        LoadShedError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for LoadShedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadShedError::Service(e) => e.fmt(f),
+            LoadShedError::Overloaded => write!(f, "Service is overloaded"),
+        }
+    }
+}
+
+/// Rejects requests when inner service is not ready.
+pub struct LoadShed<E = ()>(PhantomData<E>);
+
+impl<E> LoadShed<E> {
+    pub fn new() -> Self {
+        LoadShed(PhantomData)
+    }
+}
+
+impl<E> Default for LoadShed<E> {
+    fn default() -> Self {
+        LoadShed::new()
+    }
+}
+
+impl<E> Clone for LoadShed<E> {
+    fn clone(&self) -> Self {
+        LoadShed::new()
+    }
+}
+
+impl<S, E> Transform<S> for LoadShed<E>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = LoadShedError<S::Error>;
+    type InitError = E;
+    type Transform = LoadShedService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(LoadShedService::new(service))
+    }
+}
+
+/// Rejects requests when inner service is not ready.
+#[derive(Debug, Clone)]
+pub struct LoadShedService<S> {
+    service: S,
+    ready: bool,
+}
+
+impl<S> LoadShedService<S>
+where
+    S: Service,
+{
+    pub fn new<U>(service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        LoadShedService {
+            service: service.into_service(),
+            ready: false,
+        }
+    }
+}
+
+impl<S> Service for LoadShedService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = LoadShedError<S::Error>;
+    type Future = LoadShedServiceResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.service.poll_ready(cx) {
+            Poll::Ready(Ok(())) => self.ready = true,
+            Poll::Ready(Err(e)) => {
+                self.ready = false;
+                return Poll::Ready(Err(LoadShedError::Service(e)));
+            }
+            Poll::Pending => self.ready = false,
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        if self.ready {
+            self.ready = false;
+            LoadShedServiceResponse {
+                fut: Some(self.service.call(req)),
+            }
+        } else {
+            LoadShedServiceResponse { fut: None }
+        }
+    }
+}
+
+/// `LoadShedService` response future
+#[pin_project::pin_project]
+pub struct LoadShedServiceResponse<S: Service> {
+    #[pin]
+    fut: Option<S::Future>,
+}
+
+impl<S> Future for LoadShedServiceResponse<S>
+where
+    S: Service,
+{
+    type Output = Result<S::Response, LoadShedError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().fut.as_pin_mut() {
+            Some(fut) => fut.poll(cx).map_err(LoadShedError::Service),
+            None => Poll::Ready(Err(LoadShedError::Overloaded)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{lazy, ok, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    struct Srv(bool);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0 {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_ready() {
+        let mut srv = LoadShedService::new(Srv(true));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Ok(()));
+    }
+
+    #[actix_rt::test]
+    async fn test_overloaded() {
+        let mut srv = LoadShedService::new(Srv(false));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Err(LoadShedError::Overloaded));
+
+        // call without poll_ready is rejected as well
+        let mut srv = LoadShedService::new(Srv(true));
+        assert_eq!(srv.call(()).await, Err(LoadShedError::Overloaded));
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let factory = apply(LoadShed::new(), fn_factory(|| ok::<_, ()>(Srv(false))));
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Err(LoadShedError::Overloaded));
+    }
+}
@@ -57,6 +57,10 @@ where
         self.service.poll_ready(ctx)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, req: A::Request) -> Self::Future {
         MapFuture::new(self.service.call(req), self.f.clone())
     }
@@ -1,6 +1,10 @@
+use std::future::Future;
 use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
 
-use super::{IntoServiceFactory, ServiceFactory};
+use super::{IntoServiceFactory, Service, ServiceFactory};
 
 /// Adapt external config argument to a config for provided service factory
 ///
@@ -15,6 +19,27 @@ where
     MapConfig::new(factory.into_factory(), f)
 }
 
+/// Adapt external config argument to a config for provided service factory,
+/// config is produced by a service created by `mapper` factory
+///
+/// Mapper service is created for each `new_service` call, and called with
+/// external config argument.
+pub fn map_config_service<T, M, C, U1, U2>(factory: U1, mapper: U2) -> MapConfigService<T, M, C>
+where
+    T: ServiceFactory,
+    M: ServiceFactory<
+        Config = (),
+        Request = C,
+        Response = T::Config,
+        Error = T::InitError,
+        InitError = T::InitError,
+    >,
+    U1: IntoServiceFactory<T>,
+    U2: IntoServiceFactory<M>,
+{
+    MapConfigService::new(factory.into_factory(), mapper.into_factory())
+}
+
 /// Replace config with unit
 pub fn unit_config<T, U, C>(factory: U) -> UnitConfig<T, C>
 where
@@ -79,6 +104,123 @@ where
     }
 }
 
+/// `map_config_service()` adapter service factory
+pub struct MapConfigService<A, M, C>(Rc<(A, M)>, PhantomData<C>);
+
+impl<A, M, C> MapConfigService<A, M, C> {
+    /// Create new `MapConfigService` combinator
+    pub(crate) fn new(a: A, m: M) -> Self
+    where
+        A: ServiceFactory,
+        M: ServiceFactory<
+            Config = (),
+            Request = C,
+            Response = A::Config,
+            Error = A::InitError,
+            InitError = A::InitError,
+        >,
+    {
+        Self(Rc::new((a, m)), PhantomData)
+    }
+}
+
+impl<A, M, C> Clone for MapConfigService<A, M, C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone(), PhantomData)
+    }
+}
+
+impl<A, M, C> ServiceFactory for MapConfigService<A, M, C>
+where
+    A: ServiceFactory,
+    M: ServiceFactory<
+        Config = (),
+        Request = C,
+        Response = A::Config,
+        Error = A::InitError,
+        InitError = A::InitError,
+    >,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Config = C;
+    type Service = A::Service;
+    type InitError = A::InitError;
+    type Future = MapConfigServiceResponse<A, M, C>;
+
+    fn new_service(&self, cfg: C) -> Self::Future {
+        MapConfigServiceResponse {
+            store: self.0.clone(),
+            state: State::CreateMapper(self.0.as_ref().1.new_service(()), Some(cfg)),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct MapConfigServiceResponse<A, M, C>
+where
+    A: ServiceFactory,
+    M: ServiceFactory,
+{
+    store: Rc<(A, M)>,
+    #[pin]
+    state: State<A, M, C>,
+}
+
+#[pin_project::pin_project(project = StateProj)]
+enum State<A, M, C>
+where
+    A: ServiceFactory,
+    M: ServiceFactory,
+{
+    CreateMapper(#[pin] M::Future, Option<C>),
+    MapperReady(Option<M::Service>, Option<C>),
+    MapConfig(M::Service, #[pin] <M::Service as Service>::Future),
+    CreateService(#[pin] A::Future),
+}
+
+impl<A, M, C> Future for MapConfigServiceResponse<A, M, C>
+where
+    A: ServiceFactory,
+    M: ServiceFactory<
+        Config = (),
+        Request = C,
+        Response = A::Config,
+        Error = A::InitError,
+        InitError = A::InitError,
+    >,
+{
+    type Output = Result<A::Service, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::CreateMapper(fut, cfg) => {
+                    let mapper = futures_util::ready!(fut.poll(cx))?;
+                    let cfg = cfg.take();
+                    this.state.set(State::MapperReady(Some(mapper), cfg));
+                }
+                StateProj::MapperReady(mapper, cfg) => {
+                    futures_util::ready!(mapper.as_mut().unwrap().poll_ready(cx))?;
+                    let mut mapper = mapper.take().unwrap();
+                    let fut = mapper.call(cfg.take().unwrap());
+                    this.state.set(State::MapConfig(mapper, fut));
+                }
+                StateProj::MapConfig(_, fut) => {
+                    let cfg = futures_util::ready!(fut.poll(cx))?;
+                    let fut = this.store.0.new_service(cfg);
+                    this.state.set(State::CreateService(fut));
+                }
+                StateProj::CreateService(fut) => return fut.poll(cx),
+            }
+        }
+    }
+}
+
 /// `unit_config()` config combinator
 pub struct UnitConfig<A, C> {
     a: A,
@@ -124,3 +266,61 @@ where
         self.a.new_service(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{err, ok};
+
+    use super::*;
+    use crate::{fn_factory_with_config, fn_service, into_service, ServiceFactoryExt};
+
+    fn factory() -> impl ServiceFactory<
+        Config = usize,
+        Request = usize,
+        Response = usize,
+        Error = (),
+        InitError = (),
+    > {
+        fn_factory_with_config(|cfg: usize| {
+            ok::<_, ()>(into_service(move |req: usize| ok::<_, ()>(req * cfg)))
+        })
+    }
+
+    #[actix_rt::test]
+    async fn test_map_config() {
+        let factory = factory().map_config(|cfg: &'static str| cfg.len());
+        let mut srv = factory.new_service("abc").await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(6));
+    }
+
+    #[actix_rt::test]
+    async fn test_unit_config() {
+        let factory = unit_config(fn_service(|req: usize| ok::<_, ()>(req + 1)));
+        let mut srv = factory.new_service(10usize).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(2));
+
+        let factory = fn_factory_with_config(|_: ()| {
+            ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req)))
+        })
+        .unit_config();
+        let mut srv = factory.new_service("cfg").await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(1));
+    }
+
+    #[actix_rt::test]
+    async fn test_map_config_service() {
+        let mapper = fn_service(|cfg: &'static str| {
+            if cfg.is_empty() {
+                err(())
+            } else {
+                ok(cfg.len())
+            }
+        });
+        let factory = map_config_service(factory(), mapper);
+
+        assert!(factory.new_service("").await.is_err());
+
+        let mut srv = factory.new_service("abcd").await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(8));
+    }
+}
@@ -58,6 +58,10 @@ where
         self.service.poll_ready(ctx).map_err(&self.f)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, req: A::Request) -> Self::Future {
         MapErrFuture::new(self.service.call(req), self.f.clone())
     }
@@ -93,3 +93,28 @@ where
         this.fut.poll(cx).map_err(this.f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{ok, ready};
+
+    use crate::{
+        fn_factory_with_config, into_service, pipeline_factory, Service, ServiceFactory,
+    };
+
+    #[actix_rt::test]
+    async fn test_map_init_err() {
+        let factory = fn_factory_with_config(|fail: bool| {
+            let srv = into_service(|req: usize| ok::<_, ()>(req + 1));
+            ready(if fail { Err("init") } else { Ok(srv) })
+        });
+
+        let factory = pipeline_factory(factory.map_init_err(|e| e.len()));
+        assert_eq!(factory.new_service(true).await.err(), Some(4));
+        let mut srv = factory.new_service(false).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(2));
+
+        let factory = factory.map_init_err(|_| "error");
+        assert_eq!(factory.new_service(true).await.err(), Some("error"));
+    }
+}
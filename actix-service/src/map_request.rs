@@ -0,0 +1,206 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{Service, ServiceFactory};
+
+/// Service for the `map_request` combinator, changing the type of a service's
+/// request.
+///
+/// This is created by the `ServiceExt::map_request` method.
+pub struct MapRequest<A, F, Req> {
+    service: A,
+    f: F,
+    _t: PhantomData<fn(Req)>,
+}
+
+impl<A, F, Req> MapRequest<A, F, Req> {
+    /// Create new `MapRequest` combinator
+    pub(crate) fn new(service: A, f: F) -> Self
+    where
+        A: Service,
+        F: FnMut(Req) -> A::Request,
+    {
+        Self {
+            service,
+            f,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<A, F, Req> Clone for MapRequest<A, F, Req>
+where
+    A: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        MapRequest {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<A, F, Req> Service for MapRequest<A, F, Req>
+where
+    A: Service,
+    F: FnMut(Req) -> A::Request,
+{
+    type Request = Req;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = A::Future;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.service.call((self.f)(req))
+    }
+}
+
+/// `MapRequest` new service combinator
+pub struct MapRequestServiceFactory<A, F, Req> {
+    a: A,
+    f: F,
+    r: PhantomData<fn(Req)>,
+}
+
+impl<A, F, Req> MapRequestServiceFactory<A, F, Req> {
+    /// Create new `MapRequest` new service instance
+    pub(crate) fn new(a: A, f: F) -> Self
+    where
+        A: ServiceFactory,
+        F: FnMut(Req) -> A::Request,
+    {
+        Self {
+            a,
+            f,
+            r: PhantomData,
+        }
+    }
+}
+
+impl<A, F, Req> Clone for MapRequestServiceFactory<A, F, Req>
+where
+    A: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            f: self.f.clone(),
+            r: PhantomData,
+        }
+    }
+}
+
+impl<A, F, Req> ServiceFactory for MapRequestServiceFactory<A, F, Req>
+where
+    A: ServiceFactory,
+    F: FnMut(Req) -> A::Request + Clone,
+{
+    type Request = Req;
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Config = A::Config;
+    type Service = MapRequest<A::Service, F, Req>;
+    type InitError = A::InitError;
+    type Future = MapRequestServiceFuture<A, F, Req>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        MapRequestServiceFuture {
+            fut: self.a.new_service(cfg),
+            f: Some(self.f.clone()),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct MapRequestServiceFuture<A, F, Req>
+where
+    A: ServiceFactory,
+    F: FnMut(Req) -> A::Request,
+{
+    #[pin]
+    fut: A::Future,
+    f: Option<F>,
+    _t: PhantomData<fn(Req)>,
+}
+
+impl<A, F, Req> Future for MapRequestServiceFuture<A, F, Req>
+where
+    A: ServiceFactory,
+    F: FnMut(Req) -> A::Request,
+{
+    type Output = Result<MapRequest<A::Service, F, Req>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(svc) = this.fut.poll(cx)? {
+            Poll::Ready(Ok(MapRequest::new(svc, this.f.take().unwrap())))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::{lazy, ok, Ready};
+
+    use super::*;
+    use crate::{IntoServiceFactory, Service, ServiceExt, ServiceFactoryExt};
+
+    struct Srv;
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            ok(req * 2)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_poll_ready() {
+        let mut srv = Srv.map_request(|req: String| req.len());
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_call() {
+        let mut srv = Srv.map_request(|req: String| req.parse().unwrap());
+        let res = srv.call("21".to_string()).await;
+        assert_eq!(res, Ok(42));
+    }
+
+    #[actix_rt::test]
+    async fn test_new_service() {
+        let new_srv = (|| ok::<_, ()>(Srv))
+            .into_factory()
+            .map_request(|req: String| req.len());
+        let mut srv = new_srv.new_service(&()).await.unwrap();
+        let res = srv.call("ok".to_string()).await;
+        assert_eq!(res, Ok(4));
+    }
+}
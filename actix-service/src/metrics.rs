@@ -0,0 +1,396 @@
+//! Service that records latency, error and in-flight metrics of calls.
+//!
+//! Metrics are reported to a `Recorder`. `Stats` recorder keeps counters in
+//! memory, `MetricsRecorder` reports to the `metrics` crate facade and is
+//! available with `metrics` feature enabled. The `metrics` crate requires
+//! Rust 1.71.1, newer than minimum supported version of actix-service.
+use std::cell::Cell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Receiver of call metrics.
+pub trait Recorder {
+    /// Call to inner service has been issued.
+    fn started(&self);
+
+    /// Call has completed.
+    fn finished(&self, latency: Duration, is_error: bool);
+
+    /// Response future has been dropped before completion.
+    fn cancelled(&self, latency: Duration) {
+        let _ = latency;
+    }
+}
+
+impl<R: Recorder> Recorder for Rc<R> {
+    fn started(&self) {
+        (**self).started()
+    }
+
+    fn finished(&self, latency: Duration, is_error: bool) {
+        (**self).finished(latency, is_error)
+    }
+
+    fn cancelled(&self, latency: Duration) {
+        (**self).cancelled(latency)
+    }
+}
+
+/// Records metrics of calls to inner service.
+pub struct Metrics<R, E = ()> {
+    recorder: R,
+    _t: PhantomData<E>,
+}
+
+impl<R, E> Metrics<R, E> {
+    /// Report metrics of all created services to `recorder`.
+    pub fn new(recorder: R) -> Self {
+        Metrics {
+            recorder,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<R: Clone, E> Clone for Metrics<R, E> {
+    fn clone(&self) -> Self {
+        Metrics::new(self.recorder.clone())
+    }
+}
+
+impl<S, R, E> Transform<S> for Metrics<R, E>
+where
+    S: Service,
+    R: Recorder + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = MetricsService<S, R>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(MetricsService::new(self.recorder.clone(), service))
+    }
+}
+
+/// Records metrics of calls to inner service.
+pub struct MetricsService<S, R> {
+    service: S,
+    recorder: R,
+}
+
+impl<S, R> MetricsService<S, R>
+where
+    S: Service,
+    R: Recorder + Clone,
+{
+    pub fn new<U>(recorder: R, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        MetricsService {
+            recorder,
+            service: service.into_service(),
+        }
+    }
+
+    /// Get reference to the recorder
+    pub fn recorder(&self) -> &R {
+        &self.recorder
+    }
+}
+
+impl<S: Clone, R: Clone> Clone for MetricsService<S, R> {
+    fn clone(&self) -> Self {
+        MetricsService {
+            service: self.service.clone(),
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl<S, R> Service for MetricsService<S, R>
+where
+    S: Service,
+    R: Recorder + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MetricsResponse<S, R>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.recorder.started();
+        MetricsResponse {
+            fut: self.service.call(req),
+            guard: CallGuard {
+                recorder: Some(self.recorder.clone()),
+                start: Instant::now(),
+            },
+        }
+    }
+}
+
+/// `MetricsService` response future
+#[pin_project::pin_project]
+pub struct MetricsResponse<S: Service, R: Recorder> {
+    #[pin]
+    fut: S::Future,
+    guard: CallGuard<R>,
+}
+
+impl<S, R> Future for MetricsResponse<S, R>
+where
+    S: Service,
+    R: Recorder,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = futures_util::ready!(this.fut.poll(cx));
+        this.guard.finish(res.is_err());
+        Poll::Ready(res)
+    }
+}
+
+/// Reports cancelled call if response future gets dropped
+struct CallGuard<R: Recorder> {
+    recorder: Option<R>,
+    start: Instant,
+}
+
+impl<R: Recorder> CallGuard<R> {
+    fn finish(&mut self, is_error: bool) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finished(self.start.elapsed(), is_error);
+        }
+    }
+}
+
+impl<R: Recorder> Drop for CallGuard<R> {
+    fn drop(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.cancelled(self.start.elapsed());
+        }
+    }
+}
+
+/// Recorder that keeps call statistics in memory.
+///
+/// Clones share the same statistics.
+#[derive(Clone, Debug, Default)]
+pub struct Stats(Rc<StatsInner>);
+
+#[derive(Debug, Default)]
+struct StatsInner {
+    calls: Cell<u64>,
+    errors: Cell<u64>,
+    cancelled: Cell<u64>,
+    in_flight: Cell<usize>,
+    latency: Cell<Duration>,
+    max_latency: Cell<Duration>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats::default()
+    }
+
+    /// Number of completed calls, including failed ones.
+    pub fn calls(&self) -> u64 {
+        self.0.calls.get()
+    }
+
+    /// Number of failed calls.
+    pub fn errors(&self) -> u64 {
+        self.0.errors.get()
+    }
+
+    /// Number of calls cancelled before completion.
+    pub fn cancelled(&self) -> u64 {
+        self.0.cancelled.get()
+    }
+
+    /// Number of calls in progress.
+    pub fn in_flight(&self) -> usize {
+        self.0.in_flight.get()
+    }
+
+    /// Mean latency of completed calls.
+    pub fn mean_latency(&self) -> Duration {
+        match self.calls() {
+            0 => Duration::from_secs(0),
+            n => Duration::from_nanos((self.0.latency.get().as_nanos() / n as u128) as u64),
+        }
+    }
+
+    /// Max latency of completed calls.
+    pub fn max_latency(&self) -> Duration {
+        self.0.max_latency.get()
+    }
+}
+
+impl Recorder for Stats {
+    fn started(&self) {
+        self.0.in_flight.set(self.0.in_flight.get() + 1);
+    }
+
+    fn finished(&self, latency: Duration, is_error: bool) {
+        let inner = &self.0;
+        inner.in_flight.set(inner.in_flight.get() - 1);
+        inner.calls.set(inner.calls.get() + 1);
+        if is_error {
+            inner.errors.set(inner.errors.get() + 1);
+        }
+        inner.latency.set(inner.latency.get() + latency);
+        if latency > inner.max_latency.get() {
+            inner.max_latency.set(latency);
+        }
+    }
+
+    fn cancelled(&self, _: Duration) {
+        self.0.in_flight.set(self.0.in_flight.get() - 1);
+        self.0.cancelled.set(self.0.cancelled.get() + 1);
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub use self::recorder::MetricsRecorder;
+
+#[cfg(feature = "metrics")]
+mod recorder {
+    use std::borrow::Cow;
+    use std::time::Duration;
+
+    use super::Recorder;
+
+    /// Recorder that reports to the `metrics` crate facade.
+    ///
+    /// Reported metrics are `{name}_calls_total` and `{name}_errors_total`
+    /// counters, `{name}_latency_seconds` histogram and `{name}_in_flight`
+    /// gauge.
+    #[derive(Clone, Debug)]
+    pub struct MetricsRecorder {
+        calls: String,
+        errors: String,
+        latency: String,
+        in_flight: String,
+    }
+
+    impl MetricsRecorder {
+        /// Report metrics with `name` prefix
+        pub fn new<N: Into<Cow<'static, str>>>(name: N) -> Self {
+            let name = name.into();
+            MetricsRecorder {
+                calls: format!("{}_calls_total", name),
+                errors: format!("{}_errors_total", name),
+                latency: format!("{}_latency_seconds", name),
+                in_flight: format!("{}_in_flight", name),
+            }
+        }
+    }
+
+    impl Recorder for MetricsRecorder {
+        fn started(&self) {
+            metrics_crate::gauge!(self.in_flight.clone()).increment(1.0);
+        }
+
+        fn finished(&self, latency: Duration, is_error: bool) {
+            metrics_crate::gauge!(self.in_flight.clone()).decrement(1.0);
+            metrics_crate::counter!(self.calls.clone()).increment(1);
+            if is_error {
+                metrics_crate::counter!(self.errors.clone()).increment(1);
+            }
+            metrics_crate::histogram!(self.latency.clone()).record(latency.as_secs_f64());
+        }
+
+        fn cancelled(&self, _: Duration) {
+            metrics_crate::gauge!(self.in_flight.clone()).decrement(1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, FutureExt, LocalBoxFuture};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    /// Responds after `req` milliseconds, fails for odd request
+    struct Srv;
+
+    impl Service for Srv {
+        type Request = u64;
+        type Response = u64;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<u64, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: u64) -> Self::Future {
+            actix_rt::time::delay_for(Duration::from_millis(req))
+                .map(move |_| if req & 1 == 0 { Ok(req) } else { Err(()) })
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_stats() {
+        let stats = Stats::new();
+        let mut srv = MetricsService::new(stats.clone(), Srv);
+
+        assert_eq!(srv.call(10).await, Ok(10));
+        assert_eq!(srv.call(1).await, Err(()));
+        assert_eq!(stats.calls(), 2);
+        assert_eq!(stats.errors(), 1);
+        assert!(stats.max_latency() >= Duration::from_millis(10));
+        assert!(stats.mean_latency() >= Duration::from_millis(5));
+
+        let mut fut = srv.call(20);
+        let _ = lazy(|cx| Pin::new(&mut fut).poll(cx)).await;
+        assert_eq!(stats.in_flight(), 1);
+        drop(fut);
+        assert_eq!(stats.in_flight(), 0);
+        assert_eq!(stats.cancelled(), 1);
+        assert_eq!(stats.calls(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let stats = Stats::new();
+        let factory = apply(Metrics::new(stats.clone()), fn_factory(|| ok::<_, ()>(Srv)));
+        let mut srv1 = factory.new_service(()).await.unwrap();
+        let mut srv2 = factory.new_service(()).await.unwrap();
+
+        let _ = srv1.call(2).await;
+        let _ = srv2.call(4).await;
+        assert_eq!(stats.calls(), 2);
+        assert_eq!(stats.errors(), 0);
+    }
+}
@@ -0,0 +1,124 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::Service;
+
+/// Service for the `on_shutdown` combinator, running async cleanup once the
+/// inner service is shut down.
+///
+/// This is created by the `ServiceExt::on_shutdown` method.
+pub struct OnShutdown<A, F, Fut> {
+    service: A,
+    f: Option<F>,
+    fut: Option<Pin<Box<Fut>>>,
+}
+
+impl<A, F, Fut> OnShutdown<A, F, Fut> {
+    /// Create new `OnShutdown` combinator
+    pub(crate) fn new(service: A, f: F) -> Self
+    where
+        A: Service,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        Self {
+            service,
+            f: Some(f),
+            fut: None,
+        }
+    }
+}
+
+impl<A, F, Fut> Service for OnShutdown<A, F, Fut>
+where
+    A: Service,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = A::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        futures_util::ready!(self.service.poll_shutdown(cx, is_error));
+
+        if let Some(f) = self.f.take() {
+            self.fut = Some(Box::pin(f()));
+        }
+        if let Some(ref mut fut) = self.fut {
+            futures_util::ready!(fut.as_mut().poll(cx));
+            self.fut = None;
+        }
+        Poll::Ready(())
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+/// Future for the `dispose` method, drives service shutdown to completion.
+///
+/// This is created by the `ServiceExt::dispose` method.
+pub struct Dispose<'a, S> {
+    service: &'a mut S,
+    is_error: bool,
+}
+
+impl<'a, S> Dispose<'a, S> {
+    pub(crate) fn new(service: &'a mut S, is_error: bool) -> Self {
+        Self { service, is_error }
+    }
+}
+
+impl<'a, S: Service> Future for Dispose<'a, S> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let is_error = self.is_error;
+        self.service.poll_shutdown(cx, is_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use actix_rt::time::delay_for;
+    use futures_util::future::ok;
+
+    use crate::{into_service, Service, ServiceExt};
+
+    #[actix_rt::test]
+    async fn test_on_shutdown() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (log1, log2) = (log.clone(), log.clone());
+
+        let mut srv = into_service(|req: usize| ok::<_, ()>(req))
+            .on_shutdown(move || async move {
+                delay_for(Duration::from_millis(10)).await;
+                log1.borrow_mut().push("inner");
+            })
+            .on_shutdown(move || async move {
+                log2.borrow_mut().push("outer");
+            });
+
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert!(log.borrow().is_empty());
+
+        srv.dispose().await;
+        assert_eq!(*log.borrow(), vec!["inner", "outer"]);
+
+        // cleanup runs once
+        srv.dispose().await;
+        assert_eq!(log.borrow().len(), 2);
+    }
+}
@@ -0,0 +1,116 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{IntoService, Service};
+
+/// Wait for service readiness, call it with the request and resolve with
+/// the response.
+///
+/// Service is consumed, it is kept alive until the response is ready.
+pub fn oneshot<T, S>(service: T, req: S::Request) -> Oneshot<S>
+where
+    T: IntoService<S>,
+    S: Service,
+{
+    Oneshot {
+        state: State::NotReady(Some((service.into_service(), req))),
+    }
+}
+
+/// Future for the `oneshot` helper.
+#[pin_project::pin_project]
+pub struct Oneshot<S: Service> {
+    #[pin]
+    state: State<S>,
+}
+
+#[pin_project::pin_project(project = StateProj)]
+enum State<S: Service> {
+    NotReady(Option<(S, S::Request)>),
+    Called(S, #[pin] S::Future),
+    Done,
+}
+
+impl<S: Service> Future for Oneshot<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::NotReady(item) => {
+                    match item.as_mut().unwrap().0.poll_ready(cx) {
+                        Poll::Ready(Ok(())) => (),
+                        Poll::Ready(Err(e)) => {
+                            this.state.set(State::Done);
+                            return Poll::Ready(Err(e));
+                        }
+                        Poll::Pending => return Poll::Pending,
+                    }
+                    let (mut srv, req) = item.take().unwrap();
+                    let fut = srv.call(req);
+                    this.state.set(State::Called(srv, fut));
+                }
+                StateProj::Called(_, fut) => {
+                    let res = futures_util::ready!(fut.poll(cx));
+                    this.state.set(State::Done);
+                    return Poll::Ready(res);
+                }
+                StateProj::Done => panic!("Oneshot polled after completion"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{ok, Ready};
+
+    use super::*;
+    use crate::ServiceExt;
+
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            // ready on second poll
+            self.0.set(self.0.get() + 1);
+            if self.0.get() > 1 {
+                Poll::Ready(Ok(()))
+            } else {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            ok(req + 1)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_oneshot() {
+        let cnt = Rc::new(Cell::new(0));
+        assert_eq!(oneshot(Srv(cnt.clone()), 1).await, Ok(2));
+        assert_eq!(cnt.get(), 2);
+
+        assert_eq!(Srv(Rc::new(Cell::new(0))).oneshot(2).await, Ok(3));
+    }
+
+    #[actix_rt::test]
+    async fn test_oneshot_fn() {
+        let res = oneshot(crate::fn_service(|req: usize| ok::<_, ()>(req * 2)), 2).await;
+        assert_eq!(res, Ok(4));
+    }
+}
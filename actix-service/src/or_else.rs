@@ -0,0 +1,337 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use super::{Service, ServiceFactory};
+
+/// Service for the `or_else` combinator, calling fallback service with
+/// original request if first service fails.
+///
+/// This is created by the `ServiceExt::or_else` method.
+pub struct OrElseService<A, B>(Rc<RefCell<A>>, Rc<RefCell<B>>);
+
+impl<A, B> OrElseService<A, B> {
+    /// Create new `OrElse` combinator
+    pub(crate) fn new(a: A, b: B) -> Self
+    where
+        A: Service,
+        A::Request: Clone,
+        B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+    {
+        Self(Rc::new(RefCell::new(a)), Rc::new(RefCell::new(b)))
+    }
+}
+
+impl<A, B> Clone for OrElseService<A, B> {
+    fn clone(&self) -> Self {
+        OrElseService(self.0.clone(), self.1.clone())
+    }
+}
+
+impl<A, B> Service for OrElseService<A, B>
+where
+    A: Service,
+    A::Request: Clone,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = OrElseServiceResponse<A, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let not_ready = { !self.0.borrow_mut().poll_ready(cx)?.is_ready() };
+        if !self.1.borrow_mut().poll_ready(cx)?.is_ready() || not_ready {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let a = self.0.borrow_mut().poll_shutdown(cx, is_error).is_ready();
+        let b = self.1.borrow_mut().poll_shutdown(cx, is_error).is_ready();
+        if a && b {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        OrElseServiceResponse {
+            state: State::A(
+                self.0.borrow_mut().call(req.clone()),
+                Some((req, self.1.clone())),
+            ),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct OrElseServiceResponse<A, B>
+where
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    #[pin]
+    state: State<A, B>,
+}
+
+#[pin_project::pin_project(project = StateProj)]
+enum State<A, B>
+where
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    A(#[pin] A::Future, Option<(A::Request, Rc<RefCell<B>>)>),
+    B(#[pin] B::Future),
+    Empty,
+}
+
+impl<A, B> Future for OrElseServiceResponse<A, B>
+where
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.as_mut().project();
+
+        match this.state.as_mut().project() {
+            StateProj::A(fut, b) => match fut.poll(cx) {
+                Poll::Ready(Ok(res)) => {
+                    this.state.set(State::Empty);
+                    Poll::Ready(Ok(res))
+                }
+                Poll::Ready(Err(_)) => {
+                    let (req, b) = b.take().unwrap();
+                    this.state.set(State::Empty); // drop fut A
+                    let fut = b.borrow_mut().call(req);
+                    this.state.set(State::B(fut));
+                    self.poll(cx)
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            StateProj::B(fut) => fut.poll(cx).map(|r| {
+                this.state.set(State::Empty);
+                r
+            }),
+            StateProj::Empty => {
+                panic!("future must not be polled after it returned `Poll::Ready`")
+            }
+        }
+    }
+}
+
+/// `.or_else()` service factory combinator
+pub struct OrElseServiceFactory<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> OrElseServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    A::Config: Clone,
+    A::Request: Clone,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    /// Create new `OrElseFactory` combinator
+    pub(crate) fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<A, B> ServiceFactory for OrElseServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    A::Config: Clone,
+    A::Request: Clone,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Config = A::Config;
+    type Service = OrElseService<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = OrElseServiceFactoryResponse<A, B>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        OrElseServiceFactoryResponse {
+            fut_a: self.a.new_service(cfg.clone()),
+            fut_b: self.b.new_service(cfg),
+            a: None,
+            b: None,
+        }
+    }
+}
+
+impl<A, B> Clone for OrElseServiceFactory<A, B>
+where
+    A: Clone,
+    B: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct OrElseServiceFactoryResponse<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory,
+{
+    #[pin]
+    fut_a: A::Future,
+    #[pin]
+    fut_b: B::Future,
+
+    a: Option<A::Service>,
+    b: Option<B::Service>,
+}
+
+impl<A, B> Future for OrElseServiceFactoryResponse<A, B>
+where
+    A: ServiceFactory,
+    A::Request: Clone,
+    B: ServiceFactory<
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Output = Result<OrElseService<A::Service, B::Service>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.a.is_none() {
+            if let Poll::Ready(service) = this.fut_a.poll(cx)? {
+                *this.a = Some(service);
+            }
+        }
+        if this.b.is_none() {
+            if let Poll::Ready(service) = this.fut_b.poll(cx)? {
+                *this.b = Some(service);
+            }
+        }
+        if this.a.is_some() && this.b.is_some() {
+            Poll::Ready(Ok(OrElseService::new(
+                this.a.take().unwrap(),
+                this.b.take().unwrap(),
+            )))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{err, lazy, ok, ready, Ready};
+
+    use crate::{fn_factory, pipeline, pipeline_factory, Service, ServiceFactory};
+
+    struct Primary(Rc<Cell<usize>>);
+
+    impl Service for Primary {
+        type Request = &'static str;
+        type Response = &'static str;
+        type Error = ();
+        type Future = Ready<Result<Self::Response, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.0.set(self.0.get() + 1);
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            if req == "cached" {
+                ok("primary")
+            } else {
+                err(())
+            }
+        }
+    }
+
+    struct Fallback(Rc<Cell<usize>>);
+
+    impl Service for Fallback {
+        type Request = &'static str;
+        type Response = &'static str;
+        type Error = ();
+        type Future = Ready<Result<Self::Response, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.0.set(self.0.get() + 1);
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            if req == "missing" {
+                err(())
+            } else {
+                ok("fallback")
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_poll_ready() {
+        let cnt = Rc::new(Cell::new(0));
+        let mut srv = pipeline(Primary(cnt.clone())).or_else(Fallback(cnt.clone()));
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+        assert_eq!(cnt.get(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_call() {
+        let cnt = Rc::new(Cell::new(0));
+        let mut srv = pipeline(Primary(cnt.clone())).or_else(Fallback(cnt));
+        assert_eq!(srv.call("cached").await, Ok("primary"));
+        assert_eq!(srv.call("origin").await, Ok("fallback"));
+        assert_eq!(srv.call("missing").await, Err(()));
+    }
+
+    #[actix_rt::test]
+    async fn test_new_service() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+        let new_srv = pipeline_factory(fn_factory(move || {
+            ready(Ok::<_, ()>(Primary(cnt2.clone())))
+        }))
+        .or_else(move || ready(Ok(Fallback(cnt.clone()))));
+
+        let mut srv = new_srv.new_service(()).await.unwrap();
+        assert_eq!(srv.call("cached").await, Ok("primary"));
+        assert_eq!(srv.call("origin").await, Ok("fallback"));
+    }
+}
@@ -3,9 +3,11 @@ use std::task::{Context, Poll};
 
 use crate::and_then::{AndThenService, AndThenServiceFactory};
 use crate::and_then_apply_fn::{AndThenApplyFn, AndThenApplyFnFactory};
+use crate::boxed::{self, BoxService, BoxServiceFactory};
 use crate::map::{Map, MapServiceFactory};
 use crate::map_err::{MapErr, MapErrServiceFactory};
 use crate::map_init_err::MapInitErr;
+use crate::or_else::{OrElseService, OrElseServiceFactory};
 use crate::then::{ThenService, ThenServiceFactory};
 use crate::{IntoService, IntoServiceFactory, Service, ServiceFactory};
 
@@ -95,6 +97,23 @@ impl<T: Service> Pipeline<T> {
         }
     }
 
+    /// Call fallback service with original request if call to this service
+    /// fails.
+    ///
+    /// Note that this function consumes the receiving pipeline and returns a
+    /// wrapped version of it.
+    pub fn or_else<F, U>(self, service: F) -> Pipeline<OrElseService<T, U>>
+    where
+        Self: Sized,
+        T::Request: Clone,
+        F: IntoService<U>,
+        U: Service<Request = T::Request, Response = T::Response, Error = T::Error>,
+    {
+        Pipeline {
+            service: OrElseService::new(self.service, service.into_service()),
+        }
+    }
+
     /// Map this service's output to a different type, returning a new service
     /// of the resulting type.
     ///
@@ -131,6 +150,22 @@ impl<T: Service> Pipeline<T> {
             service: MapErr::new(self.service, f),
         }
     }
+
+    /// Erase type of the pipeline built so far.
+    ///
+    /// Pipeline stays composable, but nested combinator types of previous
+    /// stages are replaced with a boxed service. This reduces compile times
+    /// and binary size of deeply nested pipelines at the cost of dynamic
+    /// dispatch and an allocation per call.
+    pub fn boxed(self) -> Pipeline<BoxService<T::Request, T::Response, T::Error>>
+    where
+        T: 'static,
+        T::Future: 'static,
+    {
+        Pipeline {
+            service: boxed::service(self.service),
+        }
+    }
 }
 
 impl<T> Clone for Pipeline<T>
@@ -155,6 +190,11 @@ impl<T: Service> Service for Pipeline<T> {
         self.service.poll_ready(ctx)
     }
 
+    #[inline]
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(ctx, is_error)
+    }
+
     #[inline]
     fn call(&mut self, req: T::Request) -> Self::Future {
         self.service.call(req)
@@ -231,6 +271,27 @@ impl<T: ServiceFactory> PipelineFactory<T> {
         }
     }
 
+    /// Create `NewService` to call fallback service with original request
+    /// if call to this service fails.
+    pub fn or_else<F, U>(self, factory: F) -> PipelineFactory<OrElseServiceFactory<T, U>>
+    where
+        Self: Sized,
+        T::Config: Clone,
+        T::Request: Clone,
+        F: IntoServiceFactory<U>,
+        U: ServiceFactory<
+            Config = T::Config,
+            Request = T::Request,
+            Response = T::Response,
+            Error = T::Error,
+            InitError = T::InitError,
+        >,
+    {
+        PipelineFactory {
+            factory: OrElseServiceFactory::new(self.factory, factory.into_factory()),
+        }
+    }
+
     /// Map this service's output to a different type, returning a new service
     /// of the resulting type.
     pub fn map<F, R>(self, f: F) -> PipelineFactory<MapServiceFactory<T, F, R>>
@@ -264,6 +325,29 @@ impl<T: ServiceFactory> PipelineFactory<T> {
             factory: MapInitErr::new(self.factory, f),
         }
     }
+
+    /// Erase type of the pipeline factory built so far.
+    ///
+    /// Same as `Pipeline::boxed`, both the factory and services it creates
+    /// are boxed.
+    pub fn boxed(
+        self,
+    ) -> PipelineFactory<
+        BoxServiceFactory<T::Config, T::Request, T::Response, T::Error, T::InitError>,
+    >
+    where
+        T: 'static,
+        T::Request: 'static,
+        T::Response: 'static,
+        T::Service: 'static,
+        T::Future: 'static,
+        T::Error: 'static,
+        T::InitError: 'static,
+    {
+        PipelineFactory {
+            factory: boxed::factory(self.factory),
+        }
+    }
 }
 
 impl<T> Clone for PipelineFactory<T>
@@ -0,0 +1,332 @@
+//! Priority queueing for services.
+//!
+//! `Prioritized` accepts requests tagged with a priority and holds them in a
+//! bounded queue. Once the inner service is ready, queued requests are
+//! passed to it highest priority first, requests with equal priority are
+//! passed in order of arrival.
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::{IntoService, Service};
+
+struct Slot<S: Service> {
+    result: Option<Result<S::Future, S::Error>>,
+    waker: Option<Waker>,
+}
+
+struct Entry<S: Service, P> {
+    priority: P,
+    seq: u64,
+    req: S::Request,
+    slot: Rc<RefCell<Slot<S>>>,
+}
+
+impl<S: Service, P: Ord> PartialEq for Entry<S, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.seq == other.seq
+    }
+}
+
+impl<S: Service, P: Ord> Eq for Entry<S, P> {}
+
+impl<S: Service, P: Ord> PartialOrd for Entry<S, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S: Service, P: Ord> Ord for Entry<S, P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // earlier requests go first within the same priority
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct Inner<S: Service, P> {
+    service: RefCell<S>,
+    capacity: usize,
+    queue: RefCell<BinaryHeap<Entry<S, P>>>,
+    seq: Cell<u64>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl<S: Service, P: Ord> Inner<S, P> {
+    /// Pass queued requests to the inner service while it is ready.
+    fn dispatch(&self, cx: &mut Context<'_>) {
+        loop {
+            let mut queue = self.queue.borrow_mut();
+
+            // skip requests whose response futures are dropped
+            while queue
+                .peek()
+                .map(|entry| Rc::strong_count(&entry.slot) == 1)
+                .unwrap_or(false)
+            {
+                queue.pop();
+            }
+            if queue.is_empty() {
+                break;
+            }
+
+            let res = match self.service.borrow_mut().poll_ready(cx) {
+                Poll::Ready(res) => res,
+                Poll::Pending => break,
+            };
+            let entry = queue.pop().unwrap();
+            drop(queue);
+
+            let Entry { req, slot, .. } = entry;
+            let result = res.map(|_| self.service.borrow_mut().call(req));
+            let mut slot = slot.borrow_mut();
+            slot.result = Some(result);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+            if let Some(waker) = self.waker.borrow_mut().take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Wake one of queued requests, so that the queue keeps being dispatched.
+    fn wake_next(&self) {
+        for entry in self.queue.borrow().iter() {
+            if let Some(waker) = entry.slot.borrow_mut().waker.take() {
+                waker.wake();
+                return;
+            }
+        }
+    }
+}
+
+/// Priority queueing service.
+///
+/// Requests are `(priority, request)` pairs. Service is ready while there is
+/// space in the queue. Queued requests are passed to the inner service
+/// highest priority first, as soon as it becomes ready. Clones share the
+/// queue and the inner service.
+pub struct Prioritized<S: Service, P> {
+    inner: Rc<Inner<S, P>>,
+}
+
+impl<S: Service, P: Ord> Prioritized<S, P> {
+    /// Create new `Prioritized` service with queue of provided capacity.
+    pub fn new<U>(capacity: usize, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Prioritized {
+            inner: Rc::new(Inner {
+                service: RefCell::new(service.into_service()),
+                capacity: std::cmp::max(capacity, 1),
+                queue: RefCell::new(BinaryHeap::new()),
+                seq: Cell::new(0),
+                waker: RefCell::new(None),
+            }),
+        }
+    }
+
+    /// Number of queued requests.
+    pub fn queued(&self) -> usize {
+        self.inner.queue.borrow().len()
+    }
+}
+
+impl<S: Service, P> Clone for Prioritized<S, P> {
+    fn clone(&self) -> Self {
+        Prioritized {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: Service, P: Ord> Service for Prioritized<S, P> {
+    type Request = (P, S::Request);
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = PrioritizedResponse<S, P>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.dispatch(cx);
+
+        if self.inner.queue.borrow().len() < self.inner.capacity {
+            Poll::Ready(Ok(()))
+        } else {
+            *self.inner.waker.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.service.borrow_mut().poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, (priority, req): (P, S::Request)) -> Self::Future {
+        let seq = self.inner.seq.get();
+        self.inner.seq.set(seq.wrapping_add(1));
+
+        let slot = Rc::new(RefCell::new(Slot {
+            result: None,
+            waker: None,
+        }));
+        self.inner.queue.borrow_mut().push(Entry {
+            priority,
+            seq,
+            req,
+            slot: slot.clone(),
+        });
+
+        PrioritizedResponse {
+            queued: Some(Queued {
+                inner: self.inner.clone(),
+                slot,
+            }),
+            fut: None,
+        }
+    }
+}
+
+/// Request waiting in the queue
+struct Queued<S: Service, P: Ord> {
+    inner: Rc<Inner<S, P>>,
+    slot: Rc<RefCell<Slot<S>>>,
+}
+
+impl<S: Service, P: Ord> Drop for Queued<S, P> {
+    fn drop(&mut self) {
+        // this request could be the one driving the queue
+        if Rc::strong_count(&self.slot) > 1 {
+            self.inner.wake_next();
+        }
+    }
+}
+
+/// `Prioritized` response future
+#[pin_project::pin_project]
+pub struct PrioritizedResponse<S: Service, P: Ord> {
+    queued: Option<Queued<S, P>>,
+    #[pin]
+    fut: Option<S::Future>,
+}
+
+impl<S: Service, P: Ord> Future for PrioritizedResponse<S, P> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+            return fut.poll(cx);
+        }
+
+        let queued = this
+            .queued
+            .as_ref()
+            .expect("PrioritizedResponse polled after completion");
+        queued.inner.dispatch(cx);
+
+        let result = {
+            let mut slot = queued.slot.borrow_mut();
+            match slot.result.take() {
+                Some(result) => result,
+                None => {
+                    slot.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        *this.queued = None;
+
+        match result {
+            Ok(fut) => {
+                this.fut.set(Some(fut));
+                this.fut.as_pin_mut().unwrap().poll(cx)
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{join_all, lazy, ok, Ready};
+
+    use super::*;
+
+    /// Ready only while `ready` flag is set, records requests
+    struct Srv(Rc<Cell<bool>>, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Service for Srv {
+        type Request = &'static str;
+        type Response = &'static str;
+        type Error = ();
+        type Future = Ready<Result<&'static str, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            self.1.borrow_mut().push(req);
+            ok(req)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_priority() {
+        let ready = Rc::new(Cell::new(false));
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = Prioritized::new(10, Srv(ready.clone(), log.clone()));
+
+        let futs = vec![
+            srv.call((1, "low1")),
+            srv.call((5, "high")),
+            srv.call((1, "low2")),
+            srv.call((3, "mid")),
+        ];
+        let mut dropped = srv.call((4, "dropped"));
+        assert!(lazy(|cx| Pin::new(&mut dropped).poll(cx))
+            .await
+            .is_pending());
+        drop(dropped);
+        assert_eq!(srv.queued(), 5);
+
+        ready.set(true);
+        let res = join_all(futs).await;
+        assert_eq!(res, vec![Ok("low1"), Ok("high"), Ok("low2"), Ok("mid")]);
+        assert_eq!(*log.borrow(), vec!["high", "mid", "low1", "low2"]);
+        assert_eq!(srv.queued(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_capacity() {
+        let ready = Rc::new(Cell::new(false));
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut srv = Prioritized::new(2, Srv(ready.clone(), log));
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        let fut1 = srv.call((1, "a"));
+        let fut2 = srv.call((2, "b"));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        ready.set(true);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(fut1.await, Ok("a"));
+        assert_eq!(fut2.await, Ok("b"));
+    }
+}
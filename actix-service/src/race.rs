@@ -0,0 +1,343 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{Service, ServiceFactory};
+
+/// Service for the `race` combinator, calling two services with the same
+/// request and resolving with first successful response.
+///
+/// Response future of the slower service gets dropped. If both calls fail,
+/// error of the call that failed last is returned.
+///
+/// This is created by the `ServiceExt::race` method.
+pub struct RaceService<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> RaceService<A, B> {
+    /// Create new `Race` combinator
+    pub(crate) fn new(a: A, b: B) -> Self
+    where
+        A: Service,
+        A::Request: Clone,
+        B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+    {
+        Self { a, b }
+    }
+}
+
+impl<A, B> Clone for RaceService<A, B>
+where
+    A: Clone,
+    B: Clone,
+{
+    fn clone(&self) -> Self {
+        RaceService {
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
+
+impl<A, B> Service for RaceService<A, B>
+where
+    A: Service,
+    A::Request: Clone,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+    type Future = RaceServiceResponse<A, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let not_ready = !self.a.poll_ready(cx)?.is_ready();
+        if !self.b.poll_ready(cx)?.is_ready() || not_ready {
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let a = self.a.poll_shutdown(cx, is_error).is_ready();
+        let b = self.b.poll_shutdown(cx, is_error).is_ready();
+        if a && b {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        RaceServiceResponse {
+            fut_a: Some(self.a.call(req.clone())),
+            fut_b: Some(self.b.call(req)),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct RaceServiceResponse<A, B>
+where
+    A: Service,
+    B: Service,
+{
+    #[pin]
+    fut_a: Option<A::Future>,
+    #[pin]
+    fut_b: Option<B::Future>,
+}
+
+impl<A, B> Future for RaceServiceResponse<A, B>
+where
+    A: Service,
+    B: Service<Request = A::Request, Response = A::Response, Error = A::Error>,
+{
+    type Output = Result<A::Response, A::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Some(fut) = this.fut_a.as_mut().as_pin_mut() {
+            match fut.poll(cx) {
+                Poll::Ready(Ok(res)) => {
+                    this.fut_b.set(None);
+                    return Poll::Ready(Ok(res));
+                }
+                Poll::Ready(Err(e)) => {
+                    this.fut_a.set(None);
+                    if this.fut_b.is_none() {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                Poll::Pending => (),
+            }
+        }
+
+        if let Some(fut) = this.fut_b.as_mut().as_pin_mut() {
+            match fut.poll(cx) {
+                Poll::Ready(Ok(res)) => {
+                    this.fut_a.set(None);
+                    return Poll::Ready(Ok(res));
+                }
+                Poll::Ready(Err(e)) => {
+                    this.fut_b.set(None);
+                    if this.fut_a.is_none() {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                Poll::Pending => (),
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+/// `.race()` service factory combinator
+pub struct RaceServiceFactory<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> RaceServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    A::Config: Clone,
+    A::Request: Clone,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    /// Create new `RaceFactory` combinator
+    pub(crate) fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<A, B> ServiceFactory for RaceServiceFactory<A, B>
+where
+    A: ServiceFactory,
+    A::Config: Clone,
+    A::Request: Clone,
+    B: ServiceFactory<
+        Config = A::Config,
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Request = A::Request;
+    type Response = A::Response;
+    type Error = A::Error;
+
+    type Config = A::Config;
+    type Service = RaceService<A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = RaceServiceFactoryResponse<A, B>;
+
+    fn new_service(&self, cfg: A::Config) -> Self::Future {
+        RaceServiceFactoryResponse {
+            fut_a: self.a.new_service(cfg.clone()),
+            fut_b: self.b.new_service(cfg),
+            a: None,
+            b: None,
+        }
+    }
+}
+
+impl<A, B> Clone for RaceServiceFactory<A, B>
+where
+    A: Clone,
+    B: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct RaceServiceFactoryResponse<A, B>
+where
+    A: ServiceFactory,
+    B: ServiceFactory,
+{
+    #[pin]
+    fut_a: A::Future,
+    #[pin]
+    fut_b: B::Future,
+
+    a: Option<A::Service>,
+    b: Option<B::Service>,
+}
+
+impl<A, B> Future for RaceServiceFactoryResponse<A, B>
+where
+    A: ServiceFactory,
+    A::Request: Clone,
+    B: ServiceFactory<
+        Request = A::Request,
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+{
+    type Output = Result<RaceService<A::Service, B::Service>, A::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if this.a.is_none() {
+            if let Poll::Ready(service) = this.fut_a.poll(cx)? {
+                *this.a = Some(service);
+            }
+        }
+        if this.b.is_none() {
+            if let Poll::Ready(service) = this.fut_b.poll(cx)? {
+                *this.b = Some(service);
+            }
+        }
+        if this.a.is_some() && this.b.is_some() {
+            Poll::Ready(Ok(RaceService::new(
+                this.a.take().unwrap(),
+                this.b.take().unwrap(),
+            )))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, ready, FutureExt, LocalBoxFuture};
+
+    use crate::{fn_factory, Service, ServiceExt, ServiceFactory, ServiceFactoryExt};
+
+    /// Responds with its name after delay, fails for `0` request.
+    #[derive(Clone)]
+    struct Srv(&'static str, u64, Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = u64;
+        type Response = &'static str;
+        type Error = &'static str;
+        type Future = LocalBoxFuture<'static, Result<&'static str, &'static str>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: u64) -> Self::Future {
+            let name = self.0;
+            let done = self.2.clone();
+            let delay = if req == 0 { 0 } else { self.1 };
+            actix_rt::time::delay_for(Duration::from_millis(delay))
+                .map(move |_| {
+                    done.set(done.get() + 1);
+                    if req == 0 || req == delay {
+                        Err(name)
+                    } else {
+                        Ok(name)
+                    }
+                })
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_poll_ready() {
+        let done = Rc::new(Cell::new(0));
+        let mut srv = Srv("a", 10, done.clone()).race(Srv("b", 20, done));
+        let res = lazy(|cx| srv.poll_ready(cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    async fn test_first_success() {
+        let done = Rc::new(Cell::new(0));
+        let mut srv = Srv("slow", 50, done.clone()).race(Srv("fast", 10, done.clone()));
+        assert_eq!(srv.call(1).await, Ok("fast"));
+        // slow call has been cancelled
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        assert_eq!(done.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_failed_call() {
+        let done = Rc::new(Cell::new(0));
+        // fast service fails for request equal to its delay
+        let mut srv = Srv("slow", 30, done.clone()).race(Srv("fast", 10, done.clone()));
+        assert_eq!(srv.call(10).await, Ok("slow"));
+
+        // both calls fail
+        assert_eq!(srv.call(0).await, Err("fast"));
+    }
+
+    #[actix_rt::test]
+    async fn test_new_service() {
+        let done = Rc::new(Cell::new(0));
+        let done2 = done.clone();
+        let factory = fn_factory(move || ready(Ok::<_, ()>(Srv("a", 30, done2.clone()))))
+            .race(fn_factory(move || ok(Srv("b", 10, done.clone()))));
+
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok("b"));
+    }
+}
@@ -0,0 +1,355 @@
+//! Service that limits rate of requests with token bucket.
+//!
+//! Bucket holds up to `burst` tokens and is refilled with `num` tokens every
+//! `per` duration. Every call consumes one token. By default, `poll_ready`
+//! returns `Pending` until token is available, so dispatchers naturally
+//! backpressure. In rejecting mode service stays ready and calls fail with
+//! `RateLimitError::Limited` while bucket is empty.
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, time};
+
+use actix_rt::time::{delay_until, Delay, Instant};
+use futures_util::future::{ok, Ready};
+
+use crate::{IntoService, Service, Transform};
+
+/// Rate limit error
+#[derive(Debug, PartialEq)]
+pub enum RateLimitError<E> {
+    /// Service error
+    Service(E),
+    /// Rate limit exceeded, request has been rejected
+    Limited,
+}
+
+impl<E> From<E> for RateLimitError<E> {
+    fn from(err: E) -> Self {
+        RateLimitError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RateLimitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::Service(e) => e.fmt(f),
+            RateLimitError::Limited => write!(f, "Rate limit exceeded"),
+        }
+    }
+}
+
+/// Token bucket rate limiter for services.
+pub struct RateLimit<E = ()> {
+    num: u64,
+    per: time::Duration,
+    burst: u64,
+    reject: bool,
+    _t: PhantomData<E>,
+}
+
+impl<E> RateLimit<E> {
+    /// Allow `num` requests per `per` duration.
+    ///
+    /// Burst size defaults to `num`.
+    pub fn new(num: u64, per: time::Duration) -> Self {
+        RateLimit {
+            num,
+            per,
+            burst: num,
+            reject: false,
+            _t: PhantomData,
+        }
+    }
+
+    /// Set max number of requests that could be handled at once.
+    pub fn burst(mut self, burst: u64) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// Reject requests instead of delaying readiness.
+    pub fn reject(mut self) -> Self {
+        self.reject = true;
+        self
+    }
+}
+
+impl<E> Clone for RateLimit<E> {
+    fn clone(&self) -> Self {
+        RateLimit {
+            num: self.num,
+            per: self.per,
+            burst: self.burst,
+            reject: self.reject,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, E> Transform<S> for RateLimit<E>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = RateLimitError<S::Error>;
+    type InitError = E;
+    type Transform = RateLimitService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let mut srv = RateLimitService::new(self.num, self.per, service).burst(self.burst);
+        if self.reject {
+            srv = srv.reject();
+        }
+        ok(srv)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    num: u64,
+    per: time::Duration,
+    burst: u64,
+    tokens: u64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        if self.tokens >= self.burst {
+            self.last = now;
+            return;
+        }
+        let per = self.per.as_nanos();
+        let elapsed = now.duration_since(self.last).as_nanos();
+        let new = elapsed * u128::from(self.num) / per;
+        if new > 0 {
+            let tokens = u128::from(self.tokens) + new;
+            if tokens >= u128::from(self.burst) {
+                self.tokens = self.burst;
+                self.last = now;
+            } else {
+                self.tokens = tokens as u64;
+                let nanos = new * per / u128::from(self.num);
+                self.last += time::Duration::from_nanos(nanos as u64);
+            }
+        }
+    }
+
+    fn next_token(&self) -> Instant {
+        let nanos = self.per.as_nanos() / u128::from(self.num);
+        self.last + time::Duration::from_nanos(nanos as u64)
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        self.refill(Instant::now());
+        if self.tokens > 0 {
+            self.tokens -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Token bucket rate limiter for services.
+pub struct RateLimitService<S> {
+    service: S,
+    bucket: Bucket,
+    reject: bool,
+    delay: Option<Delay>,
+}
+
+impl<S> RateLimitService<S>
+where
+    S: Service,
+{
+    /// Allow `num` requests per `per` duration.
+    pub fn new<U>(num: u64, per: time::Duration, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        assert!(num > 0, "Rate must be greater than zero");
+        RateLimitService {
+            service: service.into_service(),
+            bucket: Bucket {
+                num,
+                per,
+                burst: num,
+                tokens: num,
+                last: Instant::now(),
+            },
+            reject: false,
+            delay: None,
+        }
+    }
+
+    /// Set max number of requests that could be handled at once.
+    pub fn burst(mut self, burst: u64) -> Self {
+        self.bucket.burst = burst;
+        self.bucket.tokens = burst;
+        self
+    }
+
+    /// Reject requests instead of delaying readiness.
+    pub fn reject(mut self) -> Self {
+        self.reject = true;
+        self
+    }
+}
+
+impl<S> Service for RateLimitService<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = RateLimitError<S::Error>;
+    type Future = RateLimitResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.reject {
+            loop {
+                self.bucket.refill(Instant::now());
+                if self.bucket.tokens > 0 {
+                    self.delay = None;
+                    break;
+                }
+
+                let deadline = self.bucket.next_token();
+                let delay = self.delay.get_or_insert_with(|| delay_until(deadline));
+                if delay.deadline() != deadline {
+                    delay.reset(deadline);
+                }
+                if Pin::new(delay).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+        }
+        self.service.poll_ready(cx).map_err(RateLimitError::Service)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let fut = if self.bucket.try_acquire() || !self.reject {
+            Some(self.service.call(req))
+        } else {
+            None
+        };
+        RateLimitResponse { fut }
+    }
+}
+
+/// `RateLimitService` response future
+#[pin_project::pin_project]
+pub struct RateLimitResponse<S: Service> {
+    #[pin]
+    fut: Option<S::Future>,
+}
+
+impl<S> Future for RateLimitResponse<S>
+where
+    S: Service,
+{
+    type Output = Result<S::Response, RateLimitError<S::Error>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().fut.as_pin_mut() {
+            Some(fut) => fut.poll(cx).map_err(RateLimitError::Service),
+            None => Poll::Ready(Err(RateLimitError::Limited)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, poll_fn, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    struct Srv;
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ok(())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_delay() {
+        let mut srv = RateLimitService::new(1, Duration::from_millis(50), Srv).burst(2);
+
+        for _ in 0..2 {
+            assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+            assert_eq!(srv.call(()).await, Ok(()));
+        }
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+
+        let start = Instant::now();
+        assert_eq!(poll_fn(|cx| srv.poll_ready(cx)).await, Ok(()));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(srv.call(()).await, Ok(()));
+    }
+
+    #[actix_rt::test]
+    async fn test_reject() {
+        let mut srv = RateLimitService::new(1, Duration::from_millis(50), Srv).reject();
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(()).await, Err(RateLimitError::Limited));
+
+        actix_rt::time::delay_for(Duration::from_millis(60)).await;
+        assert_eq!(srv.call(()).await, Ok(()));
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let factory = apply(
+            RateLimit::new(1, Duration::from_secs(10)).reject(),
+            fn_factory(|| ok::<_, ()>(Srv)),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(srv.call(()).await, Err(RateLimitError::Limited));
+    }
+
+    #[test]
+    fn test_refill() {
+        let now = Instant::now();
+        let mut bucket = Bucket {
+            num: 10,
+            per: Duration::from_secs(1),
+            burst: 5,
+            tokens: 0,
+            last: now,
+        };
+        bucket.refill(now + Duration::from_millis(250));
+        assert_eq!(bucket.tokens, 2);
+        assert_eq!(bucket.last, now + Duration::from_millis(200));
+        assert_eq!(bucket.next_token(), now + Duration::from_millis(300));
+
+        bucket.refill(now + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 5);
+    }
+}
@@ -0,0 +1,236 @@
+//! Explicit readiness reservation for shared services.
+//!
+//! When multiple tasks share a service, readiness observed by one task could
+//! be consumed by a call from another one. `Reserve` turns readiness into a
+//! `Permit`: while a permit is held no other task could observe readiness
+//! of the inner service, and the holder is guaranteed that the next call is
+//! issued against a ready service. Permit is released on call, explicit
+//! `release` or drop.
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+use crate::{IntoService, Service};
+
+struct Inner<S> {
+    service: RefCell<S>,
+    reserved: Cell<bool>,
+    waiters: RefCell<Vec<Waker>>,
+}
+
+impl<S: Service> Inner<S> {
+    fn poll_reserve(&self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        if self.reserved.get() {
+            let mut waiters = self.waiters.borrow_mut();
+            if !waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                waiters.push(cx.waker().clone());
+            }
+            return Poll::Pending;
+        }
+        futures_util::ready!(self.service.borrow_mut().poll_ready(cx))?;
+        self.reserved.set(true);
+        Poll::Ready(Ok(()))
+    }
+
+    fn release(&self) {
+        self.reserved.set(false);
+        for waker in self.waiters.borrow_mut().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Shared service with readiness reservation.
+///
+/// Clones share the inner service. `Reserve` implements `Service` itself,
+/// successful `poll_ready` holds a permit until the next `call`.
+pub struct Reserve<S: Service> {
+    inner: Rc<Inner<S>>,
+    permit: Option<Permit<S>>,
+}
+
+impl<S: Service> Reserve<S> {
+    pub fn new<U>(service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Reserve {
+            inner: Rc::new(Inner {
+                service: RefCell::new(service.into_service()),
+                reserved: Cell::new(false),
+                waiters: RefCell::new(Vec::new()),
+            }),
+            permit: None,
+        }
+    }
+
+    /// Wait for inner service readiness and reserve it for the next call.
+    pub fn reserve(&self) -> ReserveFuture<S> {
+        ReserveFuture {
+            inner: Some(self.inner.clone()),
+        }
+    }
+
+    /// Returns `true` if some handle holds a permit.
+    pub fn is_reserved(&self) -> bool {
+        self.inner.reserved.get()
+    }
+}
+
+impl<S: Service> Clone for Reserve<S> {
+    fn clone(&self) -> Self {
+        Reserve {
+            inner: self.inner.clone(),
+            permit: None,
+        }
+    }
+}
+
+impl<S: Service> Service for Reserve<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permit.is_none() {
+            futures_util::ready!(self.inner.poll_reserve(cx))?;
+            self.permit = Some(Permit {
+                inner: Some(self.inner.clone()),
+            });
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.service.borrow_mut().poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.permit
+            .take()
+            .expect("Reserve::call() called without readiness check")
+            .call(req)
+    }
+}
+
+/// `Reserve::reserve()` future
+pub struct ReserveFuture<S: Service> {
+    inner: Option<Rc<Inner<S>>>,
+}
+
+impl<S: Service> Future for ReserveFuture<S> {
+    type Output = Result<Permit<S>, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = futures_util::ready!(self
+            .inner
+            .as_ref()
+            .expect("ReserveFuture polled after completion")
+            .poll_reserve(cx));
+        let inner = self.inner.take().unwrap();
+        Poll::Ready(res.map(|_| Permit { inner: Some(inner) }))
+    }
+}
+
+/// Reserved readiness of a shared service.
+///
+/// Permit is released on call, explicit `release` or drop.
+pub struct Permit<S: Service> {
+    inner: Option<Rc<Inner<S>>>,
+}
+
+impl<S: Service> Permit<S> {
+    /// Call reserved service.
+    pub fn call(mut self, req: S::Request) -> S::Future {
+        let inner = self.inner.take().unwrap();
+        let fut = inner.service.borrow_mut().call(req);
+        inner.release();
+        fut
+    }
+
+    /// Release permit without calling the service.
+    pub fn release(self) {}
+}
+
+impl<S: Service> Drop for Permit<S> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            inner.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{lazy, ok, Ready};
+
+    use super::*;
+
+    /// Ready only while `ready` flag is set, counts calls
+    struct Srv(Rc<Cell<bool>>, usize);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            self.1 += 1;
+            ok(self.1)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_permit() {
+        let ready = Rc::new(Cell::new(true));
+        let srv = Reserve::new(Srv(ready.clone(), 0));
+
+        let permit = srv.reserve().await.unwrap();
+        assert!(srv.is_reserved());
+
+        // other tasks wait for the permit
+        let mut fut = srv.reserve();
+        assert!(lazy(|cx| Pin::new(&mut fut).poll(cx)).await.is_pending());
+
+        assert_eq!(permit.call(()).await, Ok(1));
+        let permit = fut.await.unwrap();
+        permit.release();
+        assert!(!srv.is_reserved());
+
+        ready.set(false);
+        let mut fut = srv.reserve();
+        assert!(lazy(|cx| Pin::new(&mut fut).poll(cx)).await.is_pending());
+        assert!(!srv.is_reserved());
+    }
+
+    #[actix_rt::test]
+    async fn test_service() {
+        let ready = Rc::new(Cell::new(true));
+        let mut srv1 = Reserve::new(Srv(ready, 0));
+        let mut srv2 = srv1.clone();
+
+        assert_eq!(lazy(|cx| srv1.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        // readiness is held by first handle
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Pending);
+
+        assert_eq!(srv1.call(()).await, Ok(1));
+        assert_eq!(lazy(|cx| srv2.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv2.call(()).await, Ok(2));
+    }
+}
@@ -0,0 +1,425 @@
+//! Service that retries failed requests according to a policy.
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_rt::time::{delay_for, Delay};
+use futures_util::future::{ok, Ready};
+use futures_util::ready;
+
+use crate::{IntoService, Service, Transform};
+
+/// Retry policy
+///
+/// Policy decides if failed request has to be retried and how long
+/// to wait before next attempt.
+pub trait Policy<E> {
+    /// Check if request should be retried after `attempt` failed attempts.
+    ///
+    /// Returns delay before next attempt. `None` stops retrying, the last
+    /// error is returned to the caller.
+    fn retry(&self, err: &E, attempt: usize) -> Option<Duration>;
+}
+
+impl<E, F> Policy<E> for F
+where
+    F: Fn(&E, usize) -> Option<Duration>,
+{
+    fn retry(&self, err: &E, attempt: usize) -> Option<Duration> {
+        (self)(err, attempt)
+    }
+}
+
+/// Fixed or exponential backoff policy.
+///
+/// By default request is attempted 3 times, exponential backoff is capped
+/// at 30 seconds.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    delay: Duration,
+    factor: u32,
+    max_delay: Duration,
+    max_attempts: usize,
+    jitter: bool,
+}
+
+impl Backoff {
+    /// Wait same amount of time before each attempt.
+    pub fn fixed(delay: Duration) -> Self {
+        Backoff {
+            delay,
+            factor: 1,
+            max_delay: delay,
+            max_attempts: 3,
+            jitter: false,
+        }
+    }
+
+    /// Double delay after each failed attempt, starting from `delay`.
+    pub fn exponential(delay: Duration) -> Self {
+        Backoff {
+            delay,
+            factor: 2,
+            max_delay: Duration::from_secs(30),
+            max_attempts: 3,
+            jitter: false,
+        }
+    }
+
+    /// Set max number of attempts, including first call.
+    pub fn max_attempts(mut self, max: usize) -> Self {
+        self.max_attempts = max;
+        self
+    }
+
+    /// Set upper limit for delay between attempts.
+    pub fn max_delay(mut self, max: Duration) -> Self {
+        self.max_delay = max;
+        self
+    }
+
+    /// Randomize delay between half of computed delay and full delay.
+    pub fn jitter(mut self, enabled: bool) -> Self {
+        self.jitter = enabled;
+        self
+    }
+
+    /// Retry only errors for which predicate returns `true`.
+    pub fn retry_if<F, E>(self, f: F) -> RetryIf<Self, F>
+    where
+        F: Fn(&E) -> bool,
+    {
+        RetryIf { policy: self, f }
+    }
+
+    fn delay(&self, attempt: usize) -> Duration {
+        let delay = self
+            .factor
+            .checked_pow(attempt.saturating_sub(1) as u32)
+            .and_then(|n| self.delay.checked_mul(n))
+            .map(|d| std::cmp::min(d, self.max_delay))
+            .unwrap_or(self.max_delay);
+
+        if self.jitter {
+            let half = delay / 2;
+            let nanos = (delay - half).as_nanos() as u64;
+            if nanos > 0 {
+                let rnd = RandomState::new().build_hasher().finish();
+                return half + Duration::from_nanos(rnd % nanos);
+            }
+        }
+        delay
+    }
+}
+
+impl<E> Policy<E> for Backoff {
+    fn retry(&self, _: &E, attempt: usize) -> Option<Duration> {
+        if attempt < self.max_attempts {
+            Some(self.delay(attempt))
+        } else {
+            None
+        }
+    }
+}
+
+/// Policy that classifies errors before delegating to inner policy.
+///
+/// This is created by the `Backoff::retry_if` method.
+#[derive(Debug, Clone)]
+pub struct RetryIf<P, F> {
+    policy: P,
+    f: F,
+}
+
+impl<P, F, E> Policy<E> for RetryIf<P, F>
+where
+    P: Policy<E>,
+    F: Fn(&E) -> bool,
+{
+    fn retry(&self, err: &E, attempt: usize) -> Option<Duration> {
+        if (self.f)(err) {
+            self.policy.retry(err, attempt)
+        } else {
+            None
+        }
+    }
+}
+
+/// Retries failed requests.
+///
+/// Request type must be `Clone`, every attempt receives a copy of
+/// the original request.
+pub struct Retry<P, E = ()> {
+    policy: Rc<P>,
+    _t: PhantomData<E>,
+}
+
+impl<P, E> Retry<P, E> {
+    pub fn new(policy: P) -> Self {
+        Retry {
+            policy: Rc::new(policy),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<P, E> Clone for Retry<P, E> {
+    fn clone(&self) -> Self {
+        Retry {
+            policy: self.policy.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, P, E> Transform<S> for Retry<P, E>
+where
+    S: Service,
+    S::Request: Clone,
+    P: Policy<S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = RetryService<S, P>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RetryService {
+            service: Rc::new(RefCell::new(service)),
+            policy: self.policy.clone(),
+        })
+    }
+}
+
+/// Retries failed requests.
+pub struct RetryService<S, P> {
+    service: Rc<RefCell<S>>,
+    policy: Rc<P>,
+}
+
+impl<S, P> RetryService<S, P>
+where
+    S: Service,
+    S::Request: Clone,
+    P: Policy<S::Error>,
+{
+    pub fn new<U>(policy: P, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        RetryService {
+            service: Rc::new(RefCell::new(service.into_service())),
+            policy: Rc::new(policy),
+        }
+    }
+}
+
+impl<S, P> Clone for RetryService<S, P> {
+    fn clone(&self) -> Self {
+        RetryService {
+            service: self.service.clone(),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl<S, P> Service for RetryService<S, P>
+where
+    S: Service,
+    S::Request: Clone,
+    P: Policy<S::Error>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = RetryServiceResponse<S, P>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.borrow_mut().poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.borrow_mut().poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let fut = self.service.borrow_mut().call(req.clone());
+        RetryServiceResponse {
+            req,
+            attempt: 0,
+            service: self.service.clone(),
+            policy: self.policy.clone(),
+            state: State::Call(fut),
+        }
+    }
+}
+
+/// `RetryService` response future
+#[pin_project::pin_project]
+pub struct RetryServiceResponse<S: Service, P> {
+    req: S::Request,
+    attempt: usize,
+    service: Rc<RefCell<S>>,
+    policy: Rc<P>,
+    #[pin]
+    state: State<S>,
+}
+
+#[pin_project::pin_project(project = StateProj)]
+enum State<S: Service> {
+    Call(#[pin] S::Future),
+    Delay(Delay),
+    Ready,
+}
+
+impl<S, P> Future for RetryServiceResponse<S, P>
+where
+    S: Service,
+    S::Request: Clone,
+    P: Policy<S::Error>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.state.as_mut().project() {
+                StateProj::Call(fut) => match ready!(fut.poll(cx)) {
+                    Ok(res) => return Poll::Ready(Ok(res)),
+                    Err(e) => {
+                        *this.attempt += 1;
+                        match this.policy.retry(&e, *this.attempt) {
+                            Some(delay) if delay == Duration::from_secs(0) => {
+                                this.state.set(State::Ready)
+                            }
+                            Some(delay) => this.state.set(State::Delay(delay_for(delay))),
+                            None => return Poll::Ready(Err(e)),
+                        }
+                    }
+                },
+                StateProj::Delay(delay) => {
+                    ready!(Pin::new(delay).poll(cx));
+                    this.state.set(State::Ready);
+                }
+                StateProj::Ready => {
+                    let mut srv = this.service.borrow_mut();
+                    ready!(srv.poll_ready(cx))?;
+                    let fut = srv.call(this.req.clone());
+                    drop(srv);
+                    this.state.set(State::Call(fut));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{err, ok, Ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    #[derive(Clone)]
+    struct Srv(Rc<Cell<usize>>, usize);
+
+    impl Service for Srv {
+        type Request = ();
+        type Response = usize;
+        type Error = usize;
+        type Future = Ready<Result<usize, usize>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            let n = self.0.get() + 1;
+            self.0.set(n);
+            if n < self.1 {
+                err(n)
+            } else {
+                ok(n)
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_retry() {
+        let cnt = Rc::new(Cell::new(0));
+        let policy = Backoff::fixed(Duration::from_millis(10));
+        let mut srv = RetryService::new(policy, Srv(cnt.clone(), 3));
+        assert_eq!(srv.call(()).await, Ok(3));
+        assert_eq!(cnt.get(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn test_max_attempts() {
+        let cnt = Rc::new(Cell::new(0));
+        let policy = Backoff::exponential(Duration::from_millis(5)).max_attempts(2);
+        let mut srv = RetryService::new(policy, Srv(cnt.clone(), 5));
+        assert_eq!(srv.call(()).await, Err(2));
+        assert_eq!(cnt.get(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_retry_if() {
+        let cnt = Rc::new(Cell::new(0));
+        let policy = Backoff::fixed(Duration::from_millis(5))
+            .max_attempts(5)
+            .retry_if(|e: &usize| *e < 2);
+        let mut srv = RetryService::new(policy, Srv(cnt.clone(), 5));
+        assert_eq!(srv.call(()).await, Err(2));
+        assert_eq!(cnt.get(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let cnt = Rc::new(Cell::new(0));
+        let cnt2 = cnt.clone();
+        let factory = apply(
+            Retry::new(|_: &usize, attempt| {
+                if attempt < 4 {
+                    Some(Duration::from_millis(0))
+                } else {
+                    None
+                }
+            }),
+            fn_factory(move || ok::<_, ()>(Srv(cnt2.clone(), 4))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(()).await, Ok(4));
+        assert_eq!(cnt.get(), 4);
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::exponential(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(350));
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(200));
+        assert_eq!(backoff.delay(3), Duration::from_millis(350));
+        assert_eq!(backoff.delay(100), Duration::from_millis(350));
+
+        let backoff = backoff.jitter(true);
+        for attempt in 1..5 {
+            let delay = backoff.delay(attempt);
+            assert!(delay <= Duration::from_millis(350));
+            assert!(delay >= Duration::from_millis(50));
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Services that could be called through shared reference.
+//!
+//! `Service::call` takes `&mut self`, so a service used from several places
+//! has to be wrapped into `Rc<RefCell<..>>`, and a call to a busy service can
+//! not be started while another call is being issued. `SharedService` is the
+//! same contract with `&self` receivers, interior mutability is left to the
+//! implementer.
+//!
+//! Any `SharedService` could be used as regular `Service` with
+//! `SharedServiceAdapter`, and any regular service becomes `SharedService`
+//! when wrapped into `RefCell`. `Shared` wraps regular service into
+//! `Rc<RefCell<..>>`, so it could be cloned and handed to many owners.
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::{IntoService, Service};
+
+/// An asynchronous function from `Request` to a `Response`, callable through
+/// shared reference.
+pub trait SharedService {
+    /// Requests handled by the service.
+    type Request;
+
+    /// Responses given by the service.
+    type Response;
+
+    /// Errors produced by the service.
+    type Error;
+
+    /// The future response value.
+    type Future: Future<Output = Result<Self::Response, Self::Error>>;
+
+    /// Returns `Ready` when the service is able to process requests.
+    ///
+    /// Same rules as for `Service::poll_ready` apply.
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>>;
+
+    /// Shutdown service.
+    ///
+    /// Same rules as for `Service::poll_shutdown` apply.
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let _ = (ctx, is_error);
+        Poll::Ready(())
+    }
+
+    /// Process the request and return the response asynchronously.
+    fn call(&self, req: Self::Request) -> Self::Future;
+}
+
+impl<'a, S> SharedService for &'a S
+where
+    S: SharedService + ?Sized + 'a,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&self, req: Self::Request) -> S::Future {
+        (**self).call(req)
+    }
+}
+
+impl<S> SharedService for Box<S>
+where
+    S: SharedService + ?Sized,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&self, req: Self::Request) -> S::Future {
+        (**self).call(req)
+    }
+}
+
+impl<S> SharedService for Rc<S>
+where
+    S: SharedService + ?Sized,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&self, req: Self::Request) -> S::Future {
+        (**self).call(req)
+    }
+}
+
+impl<S> SharedService for Arc<S>
+where
+    S: SharedService + ?Sized,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        (**self).poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        (**self).poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&self, req: Self::Request) -> S::Future {
+        (**self).call(req)
+    }
+}
+
+/// Regular service behind `RefCell`, calls borrow inner service for the
+/// duration of `poll_ready` and `call`.
+impl<S> SharedService for RefCell<S>
+where
+    S: Service,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.borrow_mut().poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.borrow_mut().poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&self, req: Self::Request) -> S::Future {
+        self.borrow_mut().call(req)
+    }
+}
+
+/// Convert `SharedService` to a regular `Service`.
+pub fn from_shared<S>(service: S) -> SharedServiceAdapter<S>
+where
+    S: SharedService,
+{
+    SharedServiceAdapter(service)
+}
+
+/// Adapter that implements `Service` for `SharedService`.
+///
+/// Adapter is cloneable if inner service is, so `Rc<S>` or `Arc<S>` based
+/// services could be handed to many owners without additional wrappers.
+#[derive(Clone, Debug)]
+pub struct SharedServiceAdapter<S>(S);
+
+impl<S> SharedServiceAdapter<S> {
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.0
+    }
+
+    /// Consume adapter and return inner service
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S> Service for SharedServiceAdapter<S>
+where
+    S: SharedService,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.0.poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: Self::Request) -> S::Future {
+        self.0.call(req)
+    }
+}
+
+/// Cloneable service that shares inner service between its clones.
+///
+/// Readiness checks and calls of all clones go to the same inner service,
+/// one at a time.
+pub struct Shared<S>(Rc<RefCell<S>>);
+
+impl<S: Service> Shared<S> {
+    /// Wrap service into `Shared`
+    pub fn new<U>(service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        Shared(Rc::new(RefCell::new(service.into_service())))
+    }
+}
+
+impl<S> Clone for Shared<S> {
+    fn clone(&self) -> Self {
+        Shared(self.0.clone())
+    }
+}
+
+impl<S: Service> Service for Shared<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.borrow_mut().poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.0.borrow_mut().poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&mut self, req: Self::Request) -> S::Future {
+        self.0.borrow_mut().call(req)
+    }
+}
+
+impl<S: Service> SharedService for Shared<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&self, ctx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.borrow_mut().poll_ready(ctx)
+    }
+
+    fn poll_shutdown(&self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.0.borrow_mut().poll_shutdown(ctx, is_error)
+    }
+
+    fn call(&self, req: Self::Request) -> S::Future {
+        self.0.borrow_mut().call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{join, lazy, ok, Ready};
+
+    use super::*;
+    use crate::Service;
+
+    #[derive(Default)]
+    struct Counter(Cell<usize>);
+
+    impl SharedService for Counter {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&self, req: usize) -> Self::Future {
+            self.0.set(self.0.get() + req);
+            ok(self.0.get())
+        }
+    }
+
+    struct Srv;
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            ok(req * 2)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_shared_calls() {
+        let srv = Counter::default();
+        let (r1, r2) = join(srv.call(1), srv.call(2)).await;
+        assert_eq!((r1, r2), (Ok(1), Ok(3)));
+
+        let srv = Rc::new(srv);
+        let srv2 = srv.clone();
+        assert_eq!(srv2.call(3).await, Ok(6));
+        assert_eq!(srv.0.get(), 6);
+    }
+
+    #[actix_rt::test]
+    async fn test_ref_cell() {
+        let srv = RefCell::new(Srv);
+        assert_eq!(
+            lazy(|cx| SharedService::poll_ready(&srv, cx)).await,
+            Poll::Ready(Ok(()))
+        );
+        assert_eq!(SharedService::call(&srv, 2).await, Ok(4));
+    }
+
+    #[actix_rt::test]
+    async fn test_adapter() {
+        let counter = Rc::new(Counter::default());
+        let mut srv = from_shared(counter.clone()).map(|res| res * 10);
+        let mut srv2 = from_shared(counter.clone());
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(10));
+        assert_eq!(srv2.call(1).await, Ok(2));
+    }
+
+    #[actix_rt::test]
+    async fn test_rc_service() {
+        let counter = Rc::new(Counter::default());
+        let mut srv = counter.clone().map(|res| res * 10);
+        assert_eq!(srv.call(1).await, Ok(10));
+
+        let mut srv = Rc::new(RefCell::new(Srv));
+        assert_eq!(Service::call(&mut srv, 2).await, Ok(4));
+        assert_eq!(counter.0.get(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_shared_wrapper() {
+        struct Cnt(usize);
+
+        impl Service for Cnt {
+            type Request = ();
+            type Response = usize;
+            type Error = ();
+            type Future = Ready<Result<usize, ()>>;
+
+            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, _: ()) -> Self::Future {
+                self.0 += 1;
+                ok(self.0)
+            }
+        }
+
+        let mut srv = Shared::new(Cnt(0));
+        let mut srv2 = srv.clone();
+        assert_eq!(
+            lazy(|cx| Service::poll_ready(&mut srv2, cx)).await,
+            Poll::Ready(Ok(()))
+        );
+        assert_eq!(Service::call(&mut srv, ()).await, Ok(1));
+        assert_eq!(Service::call(&mut srv2, ()).await, Ok(2));
+        assert_eq!(SharedService::call(&srv, ()).await, Ok(3));
+    }
+}
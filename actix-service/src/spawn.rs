@@ -0,0 +1,219 @@
+//! Run service on current arbiter behind a `Send` handle.
+//!
+//! `spawn_service` moves service, which may be `!Send`, to a task on the
+//! current arbiter. Returned `SpawnedService` handle is cloneable and is
+//! `Send` if request, response and error types are, calls are forwarded to
+//! the service over a channel.
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_channel::{mpsc, oneshot};
+use futures_util::future::poll_fn;
+use futures_util::stream::StreamExt;
+
+use crate::{IntoService, Service};
+
+/// Number of calls buffered in the channel before handle is not ready.
+const BUFFER_SIZE: usize = 32;
+
+type Message<Req, Res, Err> = (Req, oneshot::Sender<Result<Res, Err>>);
+
+/// Spawned service error
+#[derive(Debug, PartialEq)]
+pub enum SpawnError<E> {
+    /// Service error
+    Service(E),
+    /// Service task has stopped
+    Closed,
+}
+
+impl<E> From<E> for SpawnError<E> {
+    fn from(err: E) -> Self {
+        SpawnError::Service(err)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for SpawnError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::Service(e) => e.fmt(f),
+            SpawnError::Closed => write!(f, "Spawned service is closed"),
+        }
+    }
+}
+
+/// Move service to a task on current arbiter and return handle to it.
+///
+/// Service task stops once all handles are dropped, pending calls get
+/// completed before that. Inner service is shutdown on stop.
+///
+/// This function panics if it is called outside of arbiter.
+pub fn spawn_service<S, U>(service: U) -> SpawnedService<S::Request, S::Response, S::Error>
+where
+    S: Service + 'static,
+    S::Future: 'static,
+    U: IntoService<S>,
+{
+    let mut service = service.into_service();
+    let (tx, mut rx) = mpsc::channel::<Message<S::Request, S::Response, S::Error>>(BUFFER_SIZE);
+
+    actix_rt::spawn(async move {
+        while let Some((req, tx)) = rx.next().await {
+            if let Err(e) = poll_fn(|cx| service.poll_ready(cx)).await {
+                let _ = tx.send(Err(e));
+                continue;
+            }
+            let fut = service.call(req);
+            actix_rt::spawn(async move {
+                let _ = tx.send(fut.await);
+            });
+        }
+        poll_fn(|cx| service.poll_shutdown(cx, false)).await;
+    });
+
+    SpawnedService { tx }
+}
+
+/// Handle to a service spawned with `spawn_service`.
+pub struct SpawnedService<Req, Res, Err> {
+    tx: mpsc::Sender<Message<Req, Res, Err>>,
+}
+
+impl<Req, Res, Err> SpawnedService<Req, Res, Err> {
+    /// Returns `true` if service task has stopped.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+impl<Req, Res, Err> Clone for SpawnedService<Req, Res, Err> {
+    fn clone(&self) -> Self {
+        SpawnedService {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<Req, Res, Err> Service for SpawnedService<Req, Res, Err> {
+    type Request = Req;
+    type Response = Res;
+    type Error = SpawnError<Err>;
+    type Future = SpawnResponse<Res, Err>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.tx.poll_ready(cx).map_err(|_| SpawnError::Closed)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let (tx, rx) = oneshot::channel();
+        let rx = match self.tx.start_send((req, tx)) {
+            Ok(_) => Some(rx),
+            Err(_) => None,
+        };
+        SpawnResponse { rx }
+    }
+}
+
+/// `SpawnedService` response future
+pub struct SpawnResponse<Res, Err> {
+    rx: Option<oneshot::Receiver<Result<Res, Err>>>,
+}
+
+impl<Res, Err> Future for SpawnResponse<Res, Err> {
+    type Output = Result<Res, SpawnError<Err>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.rx {
+            Some(ref mut rx) => match futures_util::ready!(Pin::new(rx).poll(cx)) {
+                Ok(res) => Poll::Ready(res.map_err(SpawnError::Service)),
+                Err(_) => Poll::Ready(Err(SpawnError::Closed)),
+            },
+            None => Poll::Ready(Err(SpawnError::Closed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ready, Ready};
+
+    use super::*;
+
+    /// `!Send` service that counts calls
+    struct Srv(Rc<Cell<usize>>);
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = usize;
+        type Error = ();
+        type Future = Ready<Result<usize, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            ready(if req > 0 { Ok(req * 2) } else { Err(()) })
+        }
+    }
+
+    fn is_send<T: Send>(_: &T) {}
+
+    #[actix_rt::test]
+    async fn test_spawn_service() {
+        let cnt = Rc::new(Cell::new(0));
+        let mut srv = spawn_service(Srv(cnt.clone()));
+        is_send(&srv);
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(2));
+        assert_eq!(srv.clone().call(2).await, Ok(4));
+        assert_eq!(srv.call(0).await, Err(SpawnError::Service(())));
+        assert_eq!(cnt.get(), 3);
+        assert!(!srv.is_closed());
+    }
+
+    struct ShutdownSrv(Rc<Cell<bool>>);
+
+    impl Service for ShutdownSrv {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = Ready<Result<(), ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(&mut self, _: &mut Context<'_>, _: bool) -> Poll<()> {
+            self.0.set(true);
+            Poll::Ready(())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            ready(Ok(()))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_shutdown() {
+        let shutdown = Rc::new(Cell::new(false));
+        let mut srv = spawn_service(ShutdownSrv(shutdown.clone()));
+        let mut srv2 = srv.clone();
+        assert_eq!(srv.call(()).await, Ok(()));
+        assert_eq!(srv2.call(()).await, Ok(()));
+
+        drop(srv);
+        drop(srv2);
+        actix_rt::time::delay_for(Duration::from_millis(10)).await;
+        assert!(shutdown.get());
+    }
+}
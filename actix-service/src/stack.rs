@@ -0,0 +1,182 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use super::Transform;
+
+/// Transform composed of two transforms, `Outer` transform wraps services
+/// produced by `Inner` transform.
+///
+/// Stack is a transform itself, so middleware chains could be built and
+/// reused independently of the final service factory.
+///
+/// ```rust,ignore
+/// let middleware = Stack::new(Timeout::new(timeout), ConcurrencyLimit::new(100))
+///     .and_then(LoadShed::new());
+///
+/// let factory = apply(middleware.clone(), fn_factory(...));
+/// ```
+pub struct Stack<Outer, Inner> {
+    outer: Rc<Outer>,
+    inner: Inner,
+}
+
+impl<Outer, Inner> Stack<Outer, Inner> {
+    /// Create new `Stack` transform
+    pub fn new(outer: Outer, inner: Inner) -> Self {
+        Stack {
+            outer: Rc::new(outer),
+            inner,
+        }
+    }
+
+    /// Apply `outer` transform to services produced by this stack.
+    pub fn and_then<T>(self, outer: T) -> Stack<T, Self> {
+        Stack::new(outer, self)
+    }
+}
+
+impl<Outer, Inner> Clone for Stack<Outer, Inner>
+where
+    Inner: Clone,
+{
+    fn clone(&self) -> Self {
+        Stack {
+            outer: self.outer.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Outer, Inner, S> Transform<S> for Stack<Outer, Inner>
+where
+    Inner: Transform<S>,
+    Outer: Transform<Inner::Transform, InitError = Inner::InitError>,
+{
+    type Request = Outer::Request;
+    type Response = Outer::Response;
+    type Error = Outer::Error;
+    type Transform = Outer::Transform;
+    type InitError = Outer::InitError;
+    type Future = StackFuture<Outer, Inner, S>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        StackFuture {
+            outer: self.outer.clone(),
+            state: StackFutureState::Inner(self.inner.new_transform(service)),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct StackFuture<Outer, Inner, S>
+where
+    Inner: Transform<S>,
+    Outer: Transform<Inner::Transform, InitError = Inner::InitError>,
+{
+    outer: Rc<Outer>,
+    #[pin]
+    state: StackFutureState<Outer, Inner, S>,
+}
+
+#[pin_project::pin_project(project = StackFutureStateProj)]
+enum StackFutureState<Outer, Inner, S>
+where
+    Inner: Transform<S>,
+    Outer: Transform<Inner::Transform, InitError = Inner::InitError>,
+{
+    Inner(#[pin] Inner::Future),
+    Outer(#[pin] Outer::Future),
+}
+
+impl<Outer, Inner, S> Future for StackFuture<Outer, Inner, S>
+where
+    Inner: Transform<S>,
+    Outer: Transform<Inner::Transform, InitError = Inner::InitError>,
+{
+    type Output = Result<Outer::Transform, Outer::InitError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        loop {
+            match this.state.as_mut().project() {
+                StackFutureStateProj::Inner(fut) => {
+                    let srv = futures_util::ready!(fut.poll(cx))?;
+                    let fut = this.outer.new_transform(srv);
+                    this.state.set(StackFutureState::Outer(fut));
+                }
+                StackFutureStateProj::Outer(fut) => return fut.poll(cx),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rt"))]
+mod tests {
+    use std::task::Poll;
+    use std::time::Duration;
+
+    use futures_util::future::{lazy, ok, FutureExt, LocalBoxFuture};
+
+    use super::*;
+    use crate::limit::ConcurrencyLimit;
+    use crate::timeout::{Timeout, TimeoutError};
+    use crate::{apply, fn_factory, Service, ServiceFactory};
+
+    struct Srv;
+
+    impl Service for Srv {
+        type Request = u64;
+        type Response = u64;
+        type Error = ();
+        type Future = LocalBoxFuture<'static, Result<u64, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: u64) -> Self::Future {
+            actix_rt::time::delay_for(Duration::from_millis(req))
+                .map(move |_| Ok(req))
+                .boxed_local()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_stack() {
+        let stack = Stack::new(
+            Timeout::<()>::new(Duration::from_millis(20)),
+            ConcurrencyLimit::new(1),
+        );
+
+        let factory = apply(stack.clone(), fn_factory(|| ok::<_, ()>(Srv)));
+        let mut srv = factory.new_service(()).await.unwrap();
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        let fut = srv.call(50);
+        // limit is applied to inner service
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+        assert_eq!(fut.await, Err(TimeoutError::Timeout));
+
+        // stack is reusable
+        let factory = apply(stack, fn_factory(|| ok::<_, ()>(Srv)));
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(1).await, Ok(1));
+    }
+
+    #[actix_rt::test]
+    async fn test_and_then() {
+        let stack = Stack::new(
+            ConcurrencyLimit::<()>::new(1),
+            Timeout::new(Duration::from_millis(20)),
+        )
+        .and_then(Timeout::new(Duration::from_millis(100)));
+        let mut srv = stack.new_transform(Srv).await.unwrap();
+        assert_eq!(
+            srv.call(30).await,
+            Err(TimeoutError::Service(TimeoutError::Timeout))
+        );
+    }
+}
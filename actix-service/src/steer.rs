@@ -0,0 +1,163 @@
+//! Service that routes requests to one of the inner services.
+//!
+//! Picker function selects index of inner service for each request. Steer
+//! service is ready only when all inner services are ready, so any service
+//! could be picked for the next call.
+use std::task::{Context, Poll};
+
+use crate::Service;
+
+/// Routes each request to one of the inner services selected by picker.
+///
+/// ```rust,ignore
+/// let mut srv = Steer::new(vec![api, static_files], |req: &Request| {
+///     if req.path().starts_with("/api") { 0 } else { 1 }
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Steer<S, F> {
+    services: Vec<S>,
+    picker: F,
+    ready: usize,
+}
+
+impl<S, F> Steer<S, F>
+where
+    S: Service,
+    F: Fn(&S::Request) -> usize,
+{
+    /// Create new `Steer` service.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `services` is empty.
+    pub fn new(services: Vec<S>, picker: F) -> Self {
+        assert!(!services.is_empty(), "At least one service is required");
+        Steer {
+            services,
+            picker,
+            ready: 0,
+        }
+    }
+
+    /// Inner services.
+    pub fn services(&self) -> &[S] {
+        &self.services
+    }
+}
+
+impl<S, F> Service for Steer<S, F>
+where
+    S: Service,
+    F: Fn(&S::Request) -> usize,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // services that have already reported readiness are not polled again
+        while self.ready < self.services.len() {
+            match self.services[self.ready].poll_ready(cx)? {
+                Poll::Ready(()) => self.ready += 1,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let mut ready = true;
+        for srv in self.services.iter_mut() {
+            ready &= srv.poll_shutdown(cx, is_error).is_ready();
+        }
+        if ready {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if picker returns index greater than number of services.
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let idx = (self.picker)(&req);
+        assert!(
+            idx < self.services.len(),
+            "Picker returned out of range index {}",
+            idx
+        );
+        self.ready = 0;
+        self.services[idx].call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{lazy, ok, Ready};
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct Srv(usize, Rc<Cell<bool>>);
+
+    impl Service for Srv {
+        type Request = usize;
+        type Response = (usize, usize);
+        type Error = ();
+        type Future = Ready<Result<Self::Response, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.1.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, req: usize) -> Self::Future {
+            ok((self.0, req))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_steer() {
+        let ready = Rc::new(Cell::new(true));
+        let mut srv = Steer::new(
+            vec![Srv(0, ready.clone()), Srv(1, ready.clone())],
+            |req: &usize| req % 2,
+        );
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(4).await, Ok((0, 4)));
+        assert_eq!(srv.call(7).await, Ok((1, 7)));
+    }
+
+    #[actix_rt::test]
+    async fn test_readiness() {
+        let ready1 = Rc::new(Cell::new(true));
+        let ready2 = Rc::new(Cell::new(false));
+        let mut srv = Steer::new(
+            vec![Srv(0, ready1.clone()), Srv(1, ready2.clone())],
+            |_: &usize| 0,
+        );
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Pending);
+        ready2.set(true);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+    }
+
+    #[actix_rt::test]
+    #[should_panic]
+    async fn test_out_of_range() {
+        let ready = Rc::new(Cell::new(true));
+        let mut srv = Steer::new(vec![Srv(0, ready)], |_: &usize| 1);
+        drop(srv.call(1));
+    }
+}
@@ -0,0 +1,290 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use futures_util::stream::{self, Stream, StreamExt};
+
+use super::Service;
+
+/// Service for the `map_stream` combinator, changing the type of items of a
+/// streamed response.
+///
+/// This is created by the `ServiceExt::map_stream` method.
+pub struct MapStream<A, F, T> {
+    service: A,
+    f: F,
+    _t: PhantomData<T>,
+}
+
+impl<A, F, T> MapStream<A, F, T> {
+    /// Create new `MapStream` combinator
+    pub(crate) fn new(service: A, f: F) -> Self
+    where
+        A: Service,
+        A::Response: Stream,
+        F: FnMut(<A::Response as Stream>::Item) -> T,
+    {
+        Self {
+            service,
+            f,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<A, F, T> Clone for MapStream<A, F, T>
+where
+    A: Clone,
+    F: Clone,
+{
+    fn clone(&self) -> Self {
+        MapStream {
+            service: self.service.clone(),
+            f: self.f.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<A, F, T> Service for MapStream<A, F, T>
+where
+    A: Service,
+    A::Response: Stream,
+    F: FnMut(<A::Response as Stream>::Item) -> T + Clone,
+{
+    type Request = A::Request;
+    type Response = stream::Map<A::Response, F>;
+    type Error = A::Error;
+    type Future = MapStreamFuture<A, F, T>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        MapStreamFuture {
+            fut: self.service.call(req),
+            f: Some(self.f.clone()),
+            _t: PhantomData,
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct MapStreamFuture<A, F, T>
+where
+    A: Service,
+    A::Response: Stream,
+    F: FnMut(<A::Response as Stream>::Item) -> T,
+{
+    #[pin]
+    fut: A::Future,
+    f: Option<F>,
+    _t: PhantomData<T>,
+}
+
+impl<A, F, T> Future for MapStreamFuture<A, F, T>
+where
+    A: Service,
+    A::Response: Stream,
+    F: FnMut(<A::Response as Stream>::Item) -> T,
+{
+    type Output = Result<stream::Map<A::Response, F>, A::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let stream = futures_util::ready!(this.fut.poll(cx))?;
+        Poll::Ready(Ok(stream.map(this.f.take().unwrap())))
+    }
+}
+
+/// Service for the `and_then_stream` combinator, calling another service for
+/// each item of a streamed response.
+///
+/// This is created by the `ServiceExt::and_then_stream` method.
+pub struct AndThenStream<A, B> {
+    a: A,
+    b: Rc<RefCell<B>>,
+}
+
+impl<A, B> AndThenStream<A, B> {
+    /// Create new `AndThenStream` combinator
+    pub(crate) fn new(a: A, b: B) -> Self
+    where
+        A: Service,
+        A::Response: Stream<Item = Result<B::Request, A::Error>>,
+        B: Service<Error = A::Error>,
+    {
+        Self {
+            a,
+            b: Rc::new(RefCell::new(b)),
+        }
+    }
+}
+
+impl<A: Clone, B> Clone for AndThenStream<A, B> {
+    fn clone(&self) -> Self {
+        AndThenStream {
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
+
+impl<A, B> Service for AndThenStream<A, B>
+where
+    A: Service,
+    A::Response: Stream<Item = Result<B::Request, A::Error>>,
+    B: Service<Error = A::Error>,
+{
+    type Request = A::Request;
+    type Response = AndThenStreamResponse<A::Response, B>;
+    type Error = A::Error;
+    type Future = AndThenStreamFuture<A, B>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.a.poll_ready(cx)
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let a = self.a.poll_shutdown(cx, is_error).is_ready();
+        let b = self.b.borrow_mut().poll_shutdown(cx, is_error).is_ready();
+        if a && b {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&mut self, req: A::Request) -> Self::Future {
+        AndThenStreamFuture {
+            fut: self.a.call(req),
+            b: Some(self.b.clone()),
+        }
+    }
+}
+
+#[pin_project::pin_project]
+pub struct AndThenStreamFuture<A, B>
+where
+    A: Service,
+    B: Service,
+{
+    #[pin]
+    fut: A::Future,
+    b: Option<Rc<RefCell<B>>>,
+}
+
+impl<A, B> Future for AndThenStreamFuture<A, B>
+where
+    A: Service,
+    A::Response: Stream<Item = Result<B::Request, A::Error>>,
+    B: Service<Error = A::Error>,
+{
+    type Output = Result<AndThenStreamResponse<A::Response, B>, A::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let stream = futures_util::ready!(this.fut.poll(cx))?;
+        Poll::Ready(Ok(AndThenStreamResponse {
+            stream,
+            b: this.b.take().unwrap(),
+            item: None,
+            fut: None,
+        }))
+    }
+}
+
+/// Stream of responses of the second service of `and_then_stream` combinator
+///
+/// Items are processed one at a time, in order of the inner stream. Stream
+/// errors are passed through.
+#[pin_project::pin_project]
+pub struct AndThenStreamResponse<St, B: Service> {
+    #[pin]
+    stream: St,
+    b: Rc<RefCell<B>>,
+    item: Option<B::Request>,
+    #[pin]
+    fut: Option<B::Future>,
+}
+
+impl<St, B> Stream for AndThenStreamResponse<St, B>
+where
+    St: Stream<Item = Result<B::Request, B::Error>>,
+    B: Service,
+{
+    type Item = Result<B::Response, B::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(fut) = this.fut.as_mut().as_pin_mut() {
+                let res = futures_util::ready!(fut.poll(cx));
+                this.fut.set(None);
+                return Poll::Ready(Some(res));
+            }
+
+            if this.item.is_none() {
+                match futures_util::ready!(this.stream.as_mut().poll_next(cx)) {
+                    Some(Ok(item)) => *this.item = Some(item),
+                    Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                    None => return Poll::Ready(None),
+                }
+            }
+
+            let mut b = this.b.borrow_mut();
+            if let Err(e) = futures_util::ready!(b.poll_ready(cx)) {
+                *this.item = None;
+                return Poll::Ready(Some(Err(e)));
+            }
+            let fut = b.call(this.item.take().unwrap());
+            drop(b);
+            this.fut.set(Some(fut));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::ok;
+    use futures_util::stream::{self, StreamExt};
+
+    use crate::{into_service, Service, ServiceExt};
+
+    #[actix_rt::test]
+    async fn test_map_stream() {
+        let mut srv = into_service(|n: usize| ok::<_, ()>(stream::iter(0..n)))
+            .map_stream(|item| item * 2);
+
+        let res: Vec<_> = srv.call(3).await.unwrap().collect().await;
+        assert_eq!(res, vec![0, 2, 4]);
+    }
+
+    #[actix_rt::test]
+    async fn test_and_then_stream() {
+        let mut srv = into_service(|n: usize| {
+            ok::<_, &'static str>(stream::iter(0..n).map(|i| {
+                if i == 2 {
+                    Err("err")
+                } else {
+                    Ok(i)
+                }
+            }))
+        })
+        .and_then_stream(into_service(|i: usize| ok(i + 10)));
+
+        let res: Vec<_> = srv.call(4).await.unwrap().collect().await;
+        assert_eq!(res, vec![Ok(10), Ok(11), Err("err"), Ok(13)]);
+    }
+}
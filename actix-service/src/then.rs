@@ -57,6 +57,16 @@ where
         }
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let a = self.a.poll_shutdown(ctx, is_error).is_ready();
+        let b = self.b.borrow_mut().poll_shutdown(ctx, is_error).is_ready();
+        if a && b {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
     fn call(&mut self, req: A::Request) -> Self::Future {
         ThenServiceResponse {
             state: State::A(self.a.call(req), Some(self.b.clone())),
@@ -347,4 +357,21 @@ mod tests {
         assert!(res.is_ok());
         assert_eq!(res.unwrap(), (("srv2", "err")));
     }
+
+    #[actix_rt::test]
+    async fn test_logging_stage() {
+        let log = Rc::new(std::cell::RefCell::new(Vec::new()));
+        let log2 = log.clone();
+        let mut srv = pipeline(Srv1(Rc::new(Cell::new(0)))).then(crate::into_service(
+            move |res: Result<&'static str, ()>| {
+                log2.borrow_mut().push(res.is_ok());
+                ready(res)
+            },
+        ));
+
+        assert_eq!(srv.call(Ok("srv1")).await, Ok("srv1"));
+        // error is passed through unchanged
+        assert_eq!(srv.call(Err("srv1")).await, Err(()));
+        assert_eq!(*log.borrow(), vec![true, false]);
+    }
 }
@@ -0,0 +1,150 @@
+//! Interoperability with `tower-service`.
+//!
+//! `from_tower` turns `tower_service::Service` into actix `Service`, so
+//! middleware from tower ecosystem could be used in actix pipelines.
+//! `into_tower` converts in the other direction.
+use std::marker::PhantomData;
+use std::task::{Context, Poll};
+
+use crate::{IntoService, Service};
+
+/// Convert `tower_service::Service` to actix `Service`
+pub fn from_tower<S, Req>(service: S) -> FromTower<S, Req>
+where
+    S: tower_service::Service<Req>,
+{
+    FromTower {
+        service,
+        _t: PhantomData,
+    }
+}
+
+/// Convert actix `Service` to `tower_service::Service`
+pub fn into_tower<T, S>(service: T) -> IntoTower<S>
+where
+    T: IntoService<S>,
+    S: Service,
+{
+    IntoTower(service.into_service())
+}
+
+/// Actix service that wraps tower service.
+pub struct FromTower<S, Req> {
+    service: S,
+    _t: PhantomData<fn(Req)>,
+}
+
+impl<S, Req> FromTower<S, Req> {
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// Consume adapter and return inner service
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+}
+
+impl<S: Clone, Req> Clone for FromTower<S, Req> {
+    fn clone(&self) -> Self {
+        FromTower {
+            service: self.service.clone(),
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, Req> Service for FromTower<S, Req>
+where
+    S: tower_service::Service<Req>,
+{
+    type Request = Req;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.service.call(req)
+    }
+}
+
+/// Tower service that wraps actix service.
+#[derive(Clone)]
+pub struct IntoTower<S>(S);
+
+impl<S> IntoTower<S> {
+    /// Get reference to inner service
+    pub fn get_ref(&self) -> &S {
+        &self.0
+    }
+
+    /// Consume adapter and return inner service
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<S: Service> tower_service::Service<S::Request> for IntoTower<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        self.0.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{lazy, ok, Ready};
+
+    use super::*;
+    use crate::{into_service, ServiceExt};
+
+    /// Tower service that is generic over request type
+    struct Echo;
+
+    impl<R> tower_service::Service<R> for Echo {
+        type Response = R;
+        type Error = ();
+        type Future = Ready<Result<R, ()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: R) -> Self::Future {
+            ok(req)
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_from_tower() {
+        let mut srv = from_tower::<_, usize>(Echo).map(|res| res * 2);
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(2).await, Ok(4));
+    }
+
+    #[actix_rt::test]
+    async fn test_into_tower() {
+        let mut srv = into_tower(into_service(|req: usize| ok::<_, ()>(req + 1)));
+        let res = lazy(|cx| tower_service::Service::poll_ready(&mut srv, cx)).await;
+        assert_eq!(res, Poll::Ready(Ok(())));
+        assert_eq!(tower_service::Service::call(&mut srv, 1).await, Ok(2));
+
+        // round trip
+        let mut srv = from_tower(srv).concurrency_limit(1);
+        assert_eq!(srv.call(2).await, Ok(3));
+    }
+}
@@ -0,0 +1,204 @@
+//! Tracing span per call.
+//!
+//! `Traced` opens a span for each call. The span is entered while inner
+//! service handles the call and every time response future is polled, so
+//! events emitted by inner service and its future belong to the span.
+//! Readiness waits and completion status are recorded as events.
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use futures_util::future::{ok, Ready};
+use tracing_crate as tracing;
+use tracing_crate::Span;
+
+use crate::{IntoService, Service, Transform};
+
+/// Opens a tracing span for each call.
+pub struct Traced<F, E = ()> {
+    make_span: F,
+    _t: PhantomData<E>,
+}
+
+impl<F, E> Traced<F, E> {
+    /// Create spans with `make_span` function.
+    pub fn new(make_span: F) -> Self {
+        Traced {
+            make_span,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, E> Clone for Traced<F, E> {
+    fn clone(&self) -> Self {
+        Traced::new(self.make_span.clone())
+    }
+}
+
+impl<S, F, E> Transform<S> for Traced<F, E>
+where
+    S: Service,
+    F: Fn(&S::Request) -> Span + Clone,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type InitError = E;
+    type Transform = TracedService<S, F>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TracedService::new(self.make_span.clone(), service))
+    }
+}
+
+/// Opens a tracing span for each call.
+///
+/// Span is created by `make_span` function in the context of the caller,
+/// so caller's current span becomes span's parent.
+pub struct TracedService<S, F> {
+    service: S,
+    make_span: F,
+    not_ready: Option<Instant>,
+}
+
+impl<S, F> TracedService<S, F>
+where
+    S: Service,
+    F: Fn(&S::Request) -> Span,
+{
+    /// Create spans with `make_span` function.
+    pub fn new<U>(make_span: F, service: U) -> Self
+    where
+        U: IntoService<S>,
+    {
+        TracedService {
+            make_span,
+            service: service.into_service(),
+            not_ready: None,
+        }
+    }
+}
+
+impl<S: Clone, F: Clone> Clone for TracedService<S, F> {
+    fn clone(&self) -> Self {
+        TracedService {
+            service: self.service.clone(),
+            make_span: self.make_span.clone(),
+            not_ready: None,
+        }
+    }
+}
+
+impl<S, F> Service for TracedService<S, F>
+where
+    S: Service,
+    F: Fn(&S::Request) -> Span,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TracedResponse<S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.service.poll_ready(cx) {
+            Poll::Pending => {
+                if self.not_ready.is_none() {
+                    tracing::trace!("service is not ready");
+                    self.not_ready = Some(Instant::now());
+                }
+                Poll::Pending
+            }
+            Poll::Ready(res) => {
+                if let Some(start) = self.not_ready.take() {
+                    tracing::trace!(
+                        wait_us = start.elapsed().as_micros() as u64,
+                        "service is ready"
+                    );
+                }
+                Poll::Ready(res)
+            }
+        }
+    }
+
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.service.poll_shutdown(cx, is_error)
+    }
+
+    fn call(&mut self, req: S::Request) -> Self::Future {
+        let span = (self.make_span)(&req);
+        let fut = {
+            let _enter = span.enter();
+            self.service.call(req)
+        };
+
+        TracedResponse { fut, span }
+    }
+}
+
+/// `TracedService` response future
+#[pin_project::pin_project]
+pub struct TracedResponse<S: Service> {
+    #[pin]
+    fut: S::Future,
+    span: Span,
+}
+
+impl<S: Service> Future for TracedResponse<S> {
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let _enter = this.span.enter();
+
+        let res = futures_util::ready!(this.fut.poll(cx));
+        match res {
+            Ok(_) => tracing::debug!(status = "ok", "call completed"),
+            Err(_) => tracing::debug!(status = "error", "call completed"),
+        }
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::task::Poll;
+
+    use futures_util::future::{lazy, ready};
+
+    use super::*;
+    use crate::{apply, fn_factory, into_service, ServiceFactory};
+
+    #[actix_rt::test]
+    async fn test_traced() {
+        let spans = Rc::new(RefCell::new(Vec::new()));
+        let spans2 = spans.clone();
+        let mut srv = TracedService::new(
+            move |req: &usize| {
+                spans2.borrow_mut().push(*req);
+                tracing::info_span!("call", req = *req)
+            },
+            into_service(|req: usize| ready(if req > 0 { Ok(req) } else { Err(()) })),
+        );
+
+        assert_eq!(lazy(|cx| srv.poll_ready(cx)).await, Poll::Ready(Ok(())));
+        assert_eq!(srv.call(1).await, Ok(1));
+        assert_eq!(srv.call(0).await, Err(()));
+        assert_eq!(*spans.borrow(), vec![1, 0]);
+    }
+
+    #[actix_rt::test]
+    async fn test_transform() {
+        let factory = apply(
+            Traced::new(|_: &usize| Span::none()),
+            fn_factory(|| ok::<_, ()>(into_service(|req: usize| ok::<_, ()>(req * 2)))),
+        );
+        let mut srv = factory.new_service(()).await.unwrap();
+        assert_eq!(srv.call(2).await, Ok(4));
+    }
+}
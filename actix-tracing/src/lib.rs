@@ -38,6 +38,10 @@ where
         self.inner.poll_ready(ctx)
     }
 
+    fn poll_shutdown(&mut self, ctx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        self.inner.poll_shutdown(ctx, is_error)
+    }
+
     fn call(&mut self, req: Self::Request) -> Self::Future {
         let span = (self.make_span)(&req);
         let _enter = span.as_ref().map(|s| s.enter());
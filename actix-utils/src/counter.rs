@@ -12,7 +12,7 @@ pub struct Counter(Rc<CounterInner>);
 
 struct CounterInner {
     count: Cell<usize>,
-    capacity: usize,
+    capacity: Cell<usize>,
     task: LocalWaker,
 }
 
@@ -20,7 +20,7 @@ impl Counter {
     /// Create `Counter` instance and set max value.
     pub fn new(capacity: usize) -> Self {
         Counter(Rc::new(CounterInner {
-            capacity,
+            capacity: Cell::new(capacity),
             count: Cell::new(0),
             task: LocalWaker::new(),
         }))
@@ -41,6 +41,18 @@ impl Counter {
     pub fn total(&self) -> usize {
         self.0.count.get()
     }
+
+    /// Change max value.
+    ///
+    /// Acquired counts are kept if new value is lower than total number of
+    /// acquired counts, counter stays at capacity until enough counts are
+    /// released.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.0.capacity.set(capacity);
+        if self.0.count.get() < capacity {
+            self.0.task.wake();
+        }
+    }
 }
 
 pub struct CounterGuard(Rc<CounterInner>);
@@ -68,13 +80,13 @@ impl CounterInner {
     fn dec(&self) {
         let num = self.count.get();
         self.count.set(num - 1);
-        if num == self.capacity {
+        if num == self.capacity.get() {
             self.task.wake();
         }
     }
 
     fn available(&self, cx: &mut task::Context<'_>) -> bool {
-        if self.count.get() < self.capacity {
+        if self.count.get() < self.capacity.get() {
             true
         } else {
             self.task.register(cx.waker());
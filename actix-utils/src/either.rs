@@ -45,6 +45,16 @@ where
         }
     }
 
+    fn poll_shutdown(&mut self, cx: &mut Context<'_>, is_error: bool) -> Poll<()> {
+        let left = self.left.poll_shutdown(cx, is_error).is_ready();
+        let right = self.right.poll_shutdown(cx, is_error).is_ready();
+        if left && right {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
     fn call(&mut self, req: either::Either<A::Request, B::Request>) -> Self::Future {
         match req {
             either::Either::Left(req) => future::Either::Left(self.left.call(req)),
@@ -6,7 +6,7 @@ use std::{fmt, mem};
 
 use actix_codec::{AsyncRead, AsyncWrite, Decoder, Encoder, Framed};
 use actix_service::{IntoService, Service};
-use futures::{Future, FutureExt, Stream};
+use futures::{Future, FutureExt, Stream, StreamExt};
 use log::debug;
 
 use crate::mpsc;
@@ -76,29 +76,30 @@ where
     <U as Encoder>::Error: std::fmt::Debug,
 {
     service: S,
-    state: State<S, U>,
+    state: State<S::Error, U>,
     framed: Framed<T, U>,
+    result: Option<Result<(), DispatcherError<S::Error, U>>>,
     rx: mpsc::Receiver<Result<Message<<U as Encoder>::Item>, S::Error>>,
     tx: mpsc::Sender<Result<Message<<U as Encoder>::Item>, S::Error>>,
 }
 
-enum State<S: Service, U: Encoder + Decoder> {
+enum State<E, U: Encoder + Decoder> {
     Processing,
-    Error(DispatcherError<S::Error, U>),
-    FramedError(DispatcherError<S::Error, U>),
+    Error(DispatcherError<E, U>),
+    FramedError(DispatcherError<E, U>),
     FlushAndStop,
     Stopping,
 }
 
-impl<S: Service, U: Encoder + Decoder> State<S, U> {
-    fn take_error(&mut self) -> DispatcherError<S::Error, U> {
+impl<E, U: Encoder + Decoder> State<E, U> {
+    fn take_error(&mut self) -> DispatcherError<E, U> {
         match mem::replace(self, State::Processing) {
             State::Error(err) => err,
             _ => panic!(),
         }
     }
 
-    fn take_framed_error(&mut self) -> DispatcherError<S::Error, U> {
+    fn take_framed_error(&mut self) -> DispatcherError<E, U> {
         match mem::replace(self, State::Processing) {
             State::FramedError(err) => err,
             _ => panic!(),
@@ -124,6 +125,7 @@ where
             tx,
             service: service.into_service(),
             state: State::Processing,
+            result: None,
         }
     }
 
@@ -140,6 +142,7 @@ where
             tx,
             service: service.into_service(),
             state: State::Processing,
+            result: None,
         }
     }
 
@@ -220,49 +223,175 @@ where
         <U as Encoder>::Item: 'static,
         <U as Encoder>::Error: std::fmt::Debug,
     {
+        poll_write(&mut self.framed, &mut self.rx, &mut self.state, cx)
+    }
+}
+
+impl<S, T, U> Future for Dispatcher<S, T, U>
+where
+    S: Service<Request = Request<U>, Response = Response<U>>,
+    S::Error: 'static,
+    S::Future: 'static,
+    T: AsyncRead + AsyncWrite,
+    U: Decoder + Encoder,
+    <U as Encoder>::Item: 'static,
+    <U as Encoder>::Error: std::fmt::Debug,
+    <U as Decoder>::Error: std::fmt::Debug,
+{
+    type Output = Result<(), DispatcherError<S::Error, U>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
-            while !self.framed.is_write_buf_full() {
-                match Pin::new(&mut self.rx).poll_next(cx) {
-                    Poll::Ready(Some(Ok(Message::Item(msg)))) => {
-                        if let Err(err) = self.framed.write(msg) {
-                            self.state = State::FramedError(DispatcherError::Encoder(err));
-                            return true;
-                        }
-                    }
-                    Poll::Ready(Some(Ok(Message::Close))) => {
-                        self.state = State::FlushAndStop;
-                        return true;
+            let this = self.as_mut().project();
+
+            return match this.state {
+                State::Processing => {
+                    if self.poll_read(cx) || self.poll_write(cx) {
+                        continue;
+                    } else {
+                        Poll::Pending
                     }
-                    Poll::Ready(Some(Err(err))) => {
-                        self.state = State::Error(DispatcherError::Service(err));
-                        return true;
+                }
+                _ => {
+                    if this.result.is_none() {
+                        let res = futures::ready!(poll_stopped(this.framed, this.state, cx));
+                        *this.result = Some(res);
                     }
-                    Poll::Ready(None) | Poll::Pending => break,
+
+                    // let service release its resources
+                    let is_error = this
+                        .result
+                        .as_ref()
+                        .map(|res| res.is_err())
+                        .unwrap_or(false);
+                    futures::ready!(this.service.poll_shutdown(cx, is_error));
+                    Poll::Ready(this.result.take().unwrap())
                 }
-            }
+            };
+        }
+    }
+}
 
-            if !self.framed.is_write_buf_empty() {
-                match self.framed.flush(cx) {
-                    Poll::Pending => break,
-                    Poll::Ready(Ok(_)) => (),
-                    Poll::Ready(Err(err)) => {
-                        debug!("Error sending data: {:?}", err);
-                        self.state = State::FramedError(DispatcherError::Encoder(err));
-                        return true;
-                    }
+/// Framed dispatcher for services with streamed responses.
+///
+/// Same as `Dispatcher`, but service response is a stream of frames, which
+/// get written to the framed object as they are produced. Frames of
+/// concurrently processed requests may be interleaved. Stream error stops
+/// the dispatcher.
+#[pin_project::pin_project]
+pub struct StreamDispatcher<S, T, U>
+where
+    S: Service<Request = Request<U>>,
+    S::Response: Stream<Item = Result<Response<U>, S::Error>> + 'static,
+    S::Error: 'static,
+    S::Future: 'static,
+    T: AsyncRead + AsyncWrite,
+    U: Encoder + Decoder,
+    <U as Encoder>::Item: 'static,
+    <U as Encoder>::Error: std::fmt::Debug,
+{
+    service: S,
+    state: State<S::Error, U>,
+    framed: Framed<T, U>,
+    result: Option<Result<(), DispatcherError<S::Error, U>>>,
+    rx: mpsc::Receiver<Result<Message<<U as Encoder>::Item>, S::Error>>,
+    tx: mpsc::Sender<Result<Message<<U as Encoder>::Item>, S::Error>>,
+}
+
+impl<S, T, U> StreamDispatcher<S, T, U>
+where
+    S: Service<Request = Request<U>>,
+    S::Response: Stream<Item = Result<Response<U>, S::Error>> + 'static,
+    S::Error: 'static,
+    S::Future: 'static,
+    T: AsyncRead + AsyncWrite,
+    U: Decoder + Encoder,
+    <U as Encoder>::Item: 'static,
+    <U as Encoder>::Error: std::fmt::Debug,
+{
+    pub fn new<F: IntoService<S>>(framed: Framed<T, U>, service: F) -> Self {
+        let (tx, rx) = mpsc::channel();
+        StreamDispatcher {
+            framed,
+            rx,
+            tx,
+            service: service.into_service(),
+            state: State::Processing,
+            result: None,
+        }
+    }
+
+    /// Get sink
+    pub fn get_sink(&self) -> mpsc::Sender<Result<Message<<U as Encoder>::Item>, S::Error>> {
+        self.tx.clone()
+    }
+
+    /// Get reference to a service wrapped by `StreamDispatcher` instance.
+    pub fn get_ref(&self) -> &S {
+        &self.service
+    }
+
+    /// Get reference to a framed instance wrapped by `StreamDispatcher`
+    /// instance.
+    pub fn get_framed(&self) -> &Framed<T, U> {
+        &self.framed
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>) -> bool {
+        loop {
+            match self.service.poll_ready(cx) {
+                Poll::Ready(Ok(_)) => {
+                    let item = match self.framed.next_item(cx) {
+                        Poll::Ready(Some(Ok(el))) => el,
+                        Poll::Ready(Some(Err(err))) => {
+                            self.state = State::FramedError(DispatcherError::Decoder(err));
+                            return true;
+                        }
+                        Poll::Pending => return false,
+                        Poll::Ready(None) => {
+                            self.state = State::Stopping;
+                            return true;
+                        }
+                    };
+
+                    let tx = self.tx.clone();
+                    let fut = self.service.call(item);
+                    actix_rt::spawn(async move {
+                        match fut.await {
+                            Ok(stream) => {
+                                futures::pin_mut!(stream);
+                                while let Some(item) = stream.next().await {
+                                    let is_err = item.is_err();
+                                    if tx.send(item.map(Message::Item)).is_err() || is_err {
+                                        break;
+                                    }
+                                }
+                            }
+                            Err(err) => {
+                                let _ = tx.send(Err(err));
+                            }
+                        }
+                    });
+                }
+                Poll::Pending => return false,
+                Poll::Ready(Err(err)) => {
+                    self.state = State::Error(DispatcherError::Service(err));
+                    return true;
                 }
-            } else {
-                break;
             }
         }
+    }
 
-        false
+    /// write to framed object
+    fn poll_write(&mut self, cx: &mut Context<'_>) -> bool {
+        poll_write(&mut self.framed, &mut self.rx, &mut self.state, cx)
     }
 }
 
-impl<S, T, U> Future for Dispatcher<S, T, U>
+impl<S, T, U> Future for StreamDispatcher<S, T, U>
 where
-    S: Service<Request = Request<U>, Response = Response<U>>,
+    S: Service<Request = Request<U>>,
+    S::Response: Stream<Item = Result<Response<U>, S::Error>> + 'static,
     S::Error: 'static,
     S::Future: 'static,
     T: AsyncRead + AsyncWrite,
@@ -285,32 +414,112 @@ where
                         Poll::Pending
                     }
                 }
-                State::Error(_) => {
-                    // flush write buffer
-                    if !self.framed.is_write_buf_empty() {
-                        if let Poll::Pending = self.framed.flush(cx) {
-                            return Poll::Pending;
-                        }
+                _ => {
+                    if this.result.is_none() {
+                        let res = futures::ready!(poll_stopped(this.framed, this.state, cx));
+                        *this.result = Some(res);
                     }
-                    Poll::Ready(Err(self.state.take_error()))
+
+                    // let service release its resources
+                    let is_error = this
+                        .result
+                        .as_ref()
+                        .map(|res| res.is_err())
+                        .unwrap_or(false);
+                    futures::ready!(this.service.poll_shutdown(cx, is_error));
+                    Poll::Ready(this.result.take().unwrap())
                 }
-                State::FlushAndStop => {
-                    if !this.framed.is_write_buf_empty() {
-                        match this.framed.flush(cx) {
-                            Poll::Ready(Err(err)) => {
-                                debug!("Error sending data: {:?}", err);
-                                Poll::Ready(Ok(()))
-                            }
-                            Poll::Pending => Poll::Pending,
-                            Poll::Ready(Ok(_)) => Poll::Ready(Ok(())),
-                        }
-                    } else {
+            };
+        }
+    }
+}
+
+/// write to framed object
+fn poll_write<T, U, E>(
+    framed: &mut Framed<T, U>,
+    rx: &mut mpsc::Receiver<Result<Message<<U as Encoder>::Item>, E>>,
+    state: &mut State<E, U>,
+    cx: &mut Context<'_>,
+) -> bool
+where
+    T: AsyncRead + AsyncWrite,
+    U: Decoder + Encoder,
+    <U as Encoder>::Error: std::fmt::Debug,
+{
+    loop {
+        while !framed.is_write_buf_full() {
+            match Pin::new(&mut *rx).poll_next(cx) {
+                Poll::Ready(Some(Ok(Message::Item(msg)))) => {
+                    if let Err(err) = framed.write(msg) {
+                        *state = State::FramedError(DispatcherError::Encoder(err));
+                        return true;
+                    }
+                }
+                Poll::Ready(Some(Ok(Message::Close))) => {
+                    *state = State::FlushAndStop;
+                    return true;
+                }
+                Poll::Ready(Some(Err(err))) => {
+                    *state = State::Error(DispatcherError::Service(err));
+                    return true;
+                }
+                Poll::Ready(None) | Poll::Pending => break,
+            }
+        }
+
+        if !framed.is_write_buf_empty() {
+            match framed.flush(cx) {
+                Poll::Pending => break,
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(err)) => {
+                    debug!("Error sending data: {:?}", err);
+                    *state = State::FramedError(DispatcherError::Encoder(err));
+                    return true;
+                }
+            }
+        } else {
+            break;
+        }
+    }
+
+    false
+}
+
+/// complete dispatcher that is not processing anymore
+fn poll_stopped<T, U, E>(
+    framed: &mut Framed<T, U>,
+    state: &mut State<E, U>,
+    cx: &mut Context<'_>,
+) -> Poll<Result<(), DispatcherError<E, U>>>
+where
+    T: AsyncRead + AsyncWrite,
+    U: Decoder + Encoder,
+    <U as Encoder>::Error: std::fmt::Debug,
+{
+    match state {
+        State::Processing => Poll::Pending,
+        State::Error(_) => {
+            // flush write buffer
+            if !framed.is_write_buf_empty() && framed.flush(cx).is_pending() {
+                return Poll::Pending;
+            }
+            Poll::Ready(Err(state.take_error()))
+        }
+        State::FlushAndStop => {
+            if !framed.is_write_buf_empty() {
+                match framed.flush(cx) {
+                    Poll::Ready(Err(err)) => {
+                        debug!("Error sending data: {:?}", err);
                         Poll::Ready(Ok(()))
                     }
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Ok(_)) => Poll::Ready(Ok(())),
                 }
-                State::FramedError(_) => Poll::Ready(Err(this.state.take_framed_error())),
-                State::Stopping => Poll::Ready(Ok(())),
-            };
+            } else {
+                Poll::Ready(Ok(()))
+            }
         }
+        State::FramedError(_) => Poll::Ready(Err(state.take_framed_error())),
+        State::Stopping => Poll::Ready(Ok(())),
     }
 }